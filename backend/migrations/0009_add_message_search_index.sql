@@ -0,0 +1,5 @@
+-- Full-text search on message content
+-- Dùng cấu hình 'simple' vì nội dung chủ yếu là tiếng Việt (không có stemmer sẵn trong Postgres)
+CREATE INDEX idx_messages_content_search ON messages
+    USING GIN (to_tsvector('simple', COALESCE(content, '')))
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,20 @@
+-- Thread stats on root messages (maintained alongside reply inserts)
+ALTER TABLE messages ADD COLUMN reply_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE messages ADD COLUMN last_reply_at TIMESTAMP WITH TIME ZONE;
+
+-- Backfill from existing replies
+UPDATE messages root
+SET reply_count = stats.reply_count,
+    last_reply_at = stats.last_reply_at
+FROM (
+    SELECT reply_to_id, COUNT(*) AS reply_count, MAX(created_at) AS last_reply_at
+    FROM messages
+    WHERE reply_to_id IS NOT NULL
+      AND deleted_at IS NULL
+    GROUP BY reply_to_id
+) stats
+WHERE root.id = stats.reply_to_id;
+
+-- Index for loading a thread page
+CREATE INDEX idx_messages_reply_to ON messages(reply_to_id, created_at)
+    WHERE reply_to_id IS NOT NULL AND deleted_at IS NULL;
@@ -0,0 +1,7 @@
+-- Thời điểm participant đánh dấu đã xem tới last_seen_message_id
+ALTER TABLE participants ADD COLUMN last_seen_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE participants
+SET last_seen_at = m.created_at
+FROM messages m
+WHERE m.id = participants.last_seen_message_id;
@@ -0,0 +1,34 @@
+-- Add scheduled_message_status enum
+CREATE TYPE scheduled_message_status AS ENUM (
+    'pending',
+    'processing',
+    'sent',
+    'canceled',
+    'failed'
+);
+
+-- Create scheduled_messages table
+CREATE TABLE scheduled_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    sender_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    type message_type NOT NULL DEFAULT 'text',
+    content TEXT,
+    file_url TEXT,
+    reply_to_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+    scheduled_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    status scheduled_message_status NOT NULL DEFAULT 'pending',
+    claimed_at TIMESTAMP WITH TIME ZONE,
+    message_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Create indexes for scheduled_messages
+CREATE INDEX idx_scheduled_messages_due ON scheduled_messages(scheduled_at)
+    WHERE status IN ('pending', 'processing');
+CREATE INDEX idx_scheduled_messages_sender ON scheduled_messages(sender_id, scheduled_at);
+
+CREATE TRIGGER update_scheduled_messages_updated_at BEFORE UPDATE ON scheduled_messages
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,12 @@
+-- Per-conversation TTL for disappearing messages (NULL = tắt)
+ALTER TABLE conversations
+    ADD COLUMN message_ttl_seconds INTEGER
+    CHECK (message_ttl_seconds IS NULL OR message_ttl_seconds > 0);
+
+-- Thời điểm tin nhắn hết hạn, được tính lúc gửi từ TTL hiện tại của conversation
+ALTER TABLE messages ADD COLUMN expires_at TIMESTAMP WITH TIME ZONE;
+
+-- Index cho reaper quét tin nhắn đã hết hạn
+CREATE INDEX idx_messages_expires_at
+    ON messages(expires_at)
+    WHERE expires_at IS NOT NULL AND deleted_at IS NULL;
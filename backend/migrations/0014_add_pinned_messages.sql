@@ -0,0 +1,11 @@
+-- Create pinned_messages table
+CREATE TABLE pinned_messages (
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    pinned_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    pinned_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (conversation_id, message_id)
+);
+
+-- Create indexes for pinned_messages
+CREATE INDEX idx_pinned_messages_conversation ON pinned_messages(conversation_id, pinned_at DESC);
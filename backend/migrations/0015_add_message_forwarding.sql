@@ -0,0 +1,3 @@
+-- Reference to the original message for forwarded copies
+ALTER TABLE messages
+    ADD COLUMN forwarded_from_message_id UUID REFERENCES messages(id) ON DELETE SET NULL;
@@ -0,0 +1,11 @@
+-- Create message_edits table (prior revisions of edited messages)
+CREATE TABLE message_edits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    content TEXT,
+    edited_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    edited_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Create indexes for message_edits
+CREATE INDEX idx_message_edits_message ON message_edits(message_id, edited_at);
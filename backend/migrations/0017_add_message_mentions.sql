@@ -0,0 +1,13 @@
+-- Create message_mentions table
+CREATE TABLE message_mentions (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, user_id)
+);
+
+-- Create indexes for message_mentions
+CREATE INDEX idx_message_mentions_user ON message_mentions(user_id, created_at DESC);
+
+-- Số lần được nhắc tới chưa đọc, reset cùng unread_count
+ALTER TABLE participants ADD COLUMN mention_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,11 @@
+-- Participant roles for group permissions
+CREATE TYPE participant_role AS ENUM ('owner', 'admin', 'member');
+
+ALTER TABLE participants ADD COLUMN role participant_role NOT NULL DEFAULT 'member';
+
+-- Backfill: người tạo nhóm trở thành owner
+UPDATE participants p
+SET role = 'owner'
+FROM group_conversations g
+WHERE g.conversation_id = p.conversation_id
+  AND g.created_by = p.user_id;
@@ -0,0 +1,23 @@
+-- Add approval setting to group_conversations
+ALTER TABLE group_conversations ADD COLUMN join_approval_required BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Create join_request_status enum
+CREATE TYPE join_request_status AS ENUM ('pending', 'approved', 'rejected');
+
+-- Create group_join_requests table
+CREATE TABLE group_join_requests (
+    id UUID PRIMARY KEY,
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    message TEXT,
+    status join_request_status NOT NULL DEFAULT 'pending',
+    reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Create indexes for group_join_requests
+CREATE UNIQUE INDEX idx_group_join_requests_pending ON group_join_requests(conversation_id, user_id)
+    WHERE status = 'pending';
+CREATE INDEX idx_group_join_requests_conversation ON group_join_requests(conversation_id, created_at)
+    WHERE status = 'pending';
@@ -0,0 +1,8 @@
+-- Add per-participant conversation preferences
+ALTER TABLE participants ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE participants ADD COLUMN muted_until TIMESTAMP WITH TIME ZONE;
+ALTER TABLE participants ADD COLUMN pinned_at TIMESTAMP WITH TIME ZONE;
+
+-- Create indexes for conversation list ordering
+CREATE INDEX idx_participants_user_pinned ON participants(user_id, archived, pinned_at DESC NULLS LAST)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,4 @@
+-- Keyset pagination (created_at, id) cho lịch sử tin nhắn
+DROP INDEX IF EXISTS idx_message_conversation;
+CREATE INDEX idx_message_conversation ON messages(conversation_id, created_at DESC, id DESC)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,23 @@
+ALTER TYPE message_type ADD VALUE IF NOT EXISTS 'poll';
+
+-- Bình chọn gắn 1-1 với tin nhắn loại poll, phương án lưu theo thứ tự (option_index = vị trí trong mảng)
+CREATE TABLE polls (
+    message_id UUID PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    question VARCHAR(300) NOT NULL,
+    options TEXT[] NOT NULL,
+    multiple_choice BOOLEAN NOT NULL DEFAULT FALSE,
+    closes_at TIMESTAMP WITH TIME ZONE,
+    closed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT polls_option_count CHECK (cardinality(options) BETWEEN 2 AND 10)
+);
+
+CREATE TABLE poll_votes (
+    message_id UUID NOT NULL REFERENCES polls(message_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    option_index SMALLINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, user_id, option_index)
+);
+
+CREATE INDEX idx_poll_votes_user ON poll_votes(user_id);
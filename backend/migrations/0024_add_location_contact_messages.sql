@@ -0,0 +1,17 @@
+ALTER TYPE message_type ADD VALUE IF NOT EXISTS 'location';
+ALTER TYPE message_type ADD VALUE IF NOT EXISTS 'contact';
+
+-- Dữ liệu có cấu trúc của tin nhắn vị trí / danh thiếp, không nhồi vào content
+ALTER TABLE messages
+    ADD COLUMN location_latitude DOUBLE PRECISION,
+    ADD COLUMN location_longitude DOUBLE PRECISION,
+    ADD COLUMN location_label VARCHAR(200),
+    ADD COLUMN location_live_until TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN contact_user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD CONSTRAINT messages_location_range CHECK (
+        (location_latitude IS NULL AND location_longitude IS NULL)
+        OR (
+            location_latitude BETWEEN -90 AND 90
+            AND location_longitude BETWEEN -180 AND 180
+        )
+    );
@@ -0,0 +1,14 @@
+-- Nhật ký sự kiện bảo mật của tài khoản (phát hiện dùng lại refresh token, ...)
+CREATE TYPE security_event_type AS ENUM ('refresh_token_reuse');
+
+CREATE TABLE security_events (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    event_type security_event_type NOT NULL,
+    session_id UUID REFERENCES user_sessions(id) ON DELETE SET NULL,
+    user_agent VARCHAR(512),
+    ip_address VARCHAR(64),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_security_events_user ON security_events(user_id, created_at DESC);
@@ -0,0 +1,21 @@
+-- Xác thực email và token dùng một lần (đặt lại mật khẩu, xác thực email)
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TYPE user_token_purpose AS ENUM ('password_reset', 'email_verification');
+
+CREATE TABLE user_tokens (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose user_token_purpose NOT NULL,
+    -- SHA-256 (hex) của token gửi qua email, không lưu token gốc
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- Địa chỉ email mà token được gửi tới
+    email VARCHAR(255),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_user_tokens_user_purpose ON user_tokens(user_id, purpose) WHERE used_at IS NULL;
+
+ALTER TYPE security_event_type ADD VALUE 'password_reset';
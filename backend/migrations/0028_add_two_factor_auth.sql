@@ -0,0 +1,22 @@
+-- Xác thực hai lớp (TOTP) và mã khôi phục dùng một lần
+ALTER TABLE users
+    -- Secret base32, có giá trị nhưng totp_enabled_at NULL nghĩa là đang đăng ký, chưa xác nhận
+    ADD COLUMN totp_secret VARCHAR(64),
+    ADD COLUMN totp_enabled_at TIMESTAMP WITH TIME ZONE,
+    -- Bước thời gian của mã TOTP dùng gần nhất, chặn dùng lại cùng một mã
+    ADD COLUMN totp_last_step BIGINT;
+
+CREATE TABLE user_recovery_codes (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Hash argon2 của mã khôi phục
+    code_hash TEXT NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_user_recovery_codes_user ON user_recovery_codes(user_id) WHERE used_at IS NULL;
+
+ALTER TYPE security_event_type ADD VALUE 'two_factor_enabled';
+ALTER TYPE security_event_type ADD VALUE 'two_factor_disabled';
+ALTER TYPE security_event_type ADD VALUE 'recovery_code_used';
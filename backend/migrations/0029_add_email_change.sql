@@ -0,0 +1,5 @@
+-- Đổi email cần xác nhận qua địa chỉ mới, ghi nhận đổi mật khẩu / email vào nhật ký bảo mật
+ALTER TYPE user_token_purpose ADD VALUE 'email_change';
+
+ALTER TYPE security_event_type ADD VALUE 'password_changed';
+ALTER TYPE security_event_type ADD VALUE 'email_changed';
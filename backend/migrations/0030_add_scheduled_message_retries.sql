@@ -0,0 +1,4 @@
+-- Lỗi tạm thời khi gửi tin nhắn hẹn giờ được thử lại với backoff
+ALTER TABLE scheduled_messages
+    ADD COLUMN attempts INT NOT NULL DEFAULT 0,
+    ADD COLUMN next_attempt_at TIMESTAMP WITH TIME ZONE;
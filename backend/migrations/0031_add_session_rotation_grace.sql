@@ -0,0 +1,4 @@
+-- Nhớ refresh token vừa bị xoay vòng để chấp nhận request refresh đồng thời từ nhiều tab
+ALTER TABLE user_sessions
+    ADD COLUMN previous_jti UUID,
+    ADD COLUMN rotated_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,2 @@
+-- Ghi nhận mỗi lần refresh token vừa xoay vòng được chấp nhận trong thời gian ân hạn
+ALTER TYPE security_event_type ADD VALUE 'refresh_token_grace_use';
@@ -0,0 +1,121 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::ENV;
+use crate::api::error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Tách khoá ký cursor khỏi các chữ ký khác dùng chung SECRET_KEY (JWT, ...)
+const CURSOR_DOMAIN: &[u8] = b"cursor.v1|";
+
+/// Cursor phân trang dùng chung cho mọi danh sách: vị trí keyset (timestamp, id) của phần tử cuối trang
+///
+/// Danh sách không chỉ sắp theo thời gian đặt thêm `rank` là khóa sắp xếp đứng trước (timestamp, id).
+/// Client chỉ nhận chuỗi opaque đã ký HMAC, sửa bất kỳ ký tự nào đều bị từ chối.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: Uuid,
+    pub rank: Option<String>,
+}
+
+impl Cursor {
+    pub fn new(timestamp: DateTime<Utc>, id: Uuid) -> Self {
+        Self {
+            timestamp,
+            id,
+            rank: None,
+        }
+    }
+
+    pub fn ranked(rank: impl Into<String>, timestamp: DateTime<Utc>, id: Uuid) -> Self {
+        Self {
+            timestamp,
+            id,
+            rank: Some(rank.into()),
+        }
+    }
+
+    /// Payload `{timestamp micro giây}.{id}[.{rank}]`, rank đứng cuối nên được phép chứa dấu chấm
+    pub fn encode(&self) -> String {
+        let mut payload = format!("{}.{}", self.timestamp.timestamp_micros(), self.id);
+        if let Some(rank) = &self.rank {
+            payload.push('.');
+            payload.push_str(rank);
+        }
+        sign(&payload)
+    }
+
+    /// Decode cursor của danh sách sắp theo thời gian (không chấp nhận cursor có rank)
+    pub fn decode(cursor: &str) -> Result<Self, error::SystemError> {
+        Self::decode_payload(cursor)
+            .filter(|c| c.rank.is_none())
+            .ok_or_else(invalid_cursor)
+    }
+
+    /// Decode cursor của danh sách có rank (bắt buộc có rank)
+    pub fn decode_ranked(cursor: &str) -> Result<Self, error::SystemError> {
+        Self::decode_payload(cursor)
+            .filter(|c| c.rank.is_some())
+            .ok_or_else(invalid_cursor)
+    }
+
+    /// Decode cursor tuỳ chọn lấy từ query string
+    pub fn parse(cursor: Option<&str>) -> Result<Option<Self>, error::SystemError> {
+        cursor.map(Self::decode).transpose()
+    }
+
+    /// Decode cursor có rank tuỳ chọn lấy từ query string
+    pub fn parse_ranked(cursor: Option<&str>) -> Result<Option<Self>, error::SystemError> {
+        cursor.map(Self::decode_ranked).transpose()
+    }
+
+    fn decode_payload(cursor: &str) -> Option<Self> {
+        let payload = verify(cursor)?;
+        let mut parts = payload.splitn(3, '.');
+
+        Some(Self {
+            timestamp: DateTime::from_timestamp_micros(parts.next()?.parse().ok()?)?,
+            id: parts.next()?.parse().ok()?,
+            rank: parts.next().map(str::to_string),
+        })
+    }
+}
+
+pub fn invalid_cursor() -> error::SystemError {
+    error::SystemError::bad_request("Định dạng danh sách phân trang (cursor) không hợp lệ")
+}
+
+/// Ký payload thành `{base64url(payload)}.{base64url(hmac)}`
+fn sign(payload: &str) -> String {
+    let tag = mac(payload.as_bytes()).finalize().into_bytes();
+
+    format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(payload),
+        URL_SAFE_NO_PAD.encode(tag)
+    )
+}
+
+/// Kiểm tra chữ ký (so sánh constant-time) và trả lại payload gốc
+fn verify(token: &str) -> Option<String> {
+    let (payload, tag) = token.split_once('.')?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+
+    mac(&payload).verify_slice(&tag).ok()?;
+
+    String::from_utf8(payload).ok()
+}
+
+fn mac(payload: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(ENV.jwt_secret.as_bytes())
+        .expect("HMAC nhận khoá với độ dài bất kỳ");
+    mac.update(CURSOR_DOMAIN);
+    mac.update(payload);
+    mac
+}
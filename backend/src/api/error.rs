@@ -229,4 +229,16 @@ impl SystemError {
     pub fn internal_error(msg: impl Into<Cow<'static, str>>) -> Self {
         Self::InternalError(msg.into())
     }
+
+    /// Lỗi hạ tầng (DB, Redis, IO...) có thể tự hết khi thử lại, khác với lỗi nghiệp vụ
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::IOError(_)
+                | Self::DatabaseError(_)
+                | Self::PoolGet(_)
+                | Self::RedisError(_)
+                | Self::InternalError(_)
+        )
+    }
 }
@@ -1,3 +1,4 @@
+pub mod cursor;
 pub mod error;
 pub mod messages;
 pub mod success;
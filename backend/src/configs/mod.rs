@@ -3,6 +3,8 @@ use sqlx::{PgPool, postgres::PgPoolOptions};
 
 use crate::{ENV, api::error};
 
+pub mod mailer;
+
 #[async_trait::async_trait]
 pub trait CacheStore {
     async fn get<T>(&self, key: &str) -> Result<Option<T>, error::SystemError>
@@ -19,6 +21,9 @@ pub trait CacheStore {
         T: serde::Serialize + Send + Sync;
 
     async fn delete(&self, key: &str) -> Result<(), error::SystemError>;
+
+    /// Tăng bộ đếm nguyên tử, TTL chỉ đặt ở lần tăng đầu tiên (cửa sổ cố định). Trả về giá trị mới.
+    async fn incr(&self, key: &str, expiration: usize) -> Result<i64, error::SystemError>;
 }
 
 pub async fn connect_database() -> Result<PgPool, error::SystemError> {
@@ -100,6 +105,23 @@ impl RedisCache {
         Ok(())
     }
 
+    pub async fn incr(&self, key: &str, expiration: usize) -> Result<i64, error::SystemError> {
+        let mut conn = self.pool.get().await?;
+
+        let (count,): (i64,) = deadpool_redis::redis::pipe()
+            .atomic()
+            .incr(key, 1)
+            .cmd("EXPIRE")
+            .arg(key)
+            .arg(expiration)
+            .arg("NX")
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(count)
+    }
+
     /// Expose Redis pool cho PresenceService
     pub fn get_pool(&self) -> &deadpool_redis::Pool {
         &self.pool
@@ -130,4 +152,8 @@ impl CacheStore for RedisCache {
     async fn delete(&self, key: &str) -> Result<(), error::SystemError> {
         RedisCache::delete(self, key).await
     }
+
+    async fn incr(&self, key: &str, expiration: usize) -> Result<i64, error::SystemError> {
+        RedisCache::incr(self, key, expiration).await
+    }
 }
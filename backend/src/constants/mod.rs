@@ -1,3 +1,5 @@
+use std::net::IpAddr;
+
 pub struct Env {
     pub jwt_secret: String,
     pub access_token_expiration: u64,
@@ -8,6 +10,12 @@ pub struct Env {
     pub frontend_url: String,
     pub ip: String,
     pub port: u16,
+    pub message_edit_window: u64,
+    pub smtp_url: Option<String>,
+    pub mail_from: String,
+    pub mail_dir: String,
+    /// Reverse proxy được tin để đọc IP client từ `X-Forwarded-For`
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 pub(crate) fn compute_cookie_secure(cookie_secure: Option<&str>, app_env: Option<&str>) -> bool {
@@ -21,6 +29,20 @@ pub(crate) fn compute_cookie_secure(cookie_secure: Option<&str>, app_env: Option
         .unwrap_or(false)
 }
 
+pub(crate) fn parse_trusted_proxies(value: Option<&str>) -> Vec<IpAddr> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<IpAddr>()
+                .expect("TRUSTED_PROXIES must be a comma-separated list of IP addresses")
+        })
+        .collect()
+}
+
 impl Env {
     fn new() -> Self {
         let jwt_secret = std::env::var("SECRET_KEY")
@@ -52,6 +74,16 @@ impl Env {
             .unwrap_or_else(|_| "8080".to_string())
             .parse::<u16>()
             .expect("PORT must be a valid u16 integer");
+        let message_edit_window = std::env::var("MESSAGE_EDIT_WINDOW")
+            .unwrap_or_else(|_| "900".to_string())
+            .parse::<u64>()
+            .expect("MESSAGE_EDIT_WINDOW must be a valid u64 integer");
+        let smtp_url = std::env::var("SMTP_URL").ok().filter(|url| !url.is_empty());
+        let mail_from = std::env::var("MAIL_FROM")
+            .unwrap_or_else(|_| "AppChat <no-reply@appchat.local>".to_string());
+        let mail_dir = std::env::var("MAIL_DIR").unwrap_or_else(|_| "./mails".to_string());
+        let trusted_proxies =
+            parse_trusted_proxies(std::env::var("TRUSTED_PROXIES").ok().as_deref());
         Env {
             jwt_secret,
             access_token_expiration,
@@ -62,6 +94,11 @@ impl Env {
             frontend_url,
             ip,
             port,
+            message_edit_window,
+            smtp_url,
+            mail_from,
+            mail_dir,
+            trusted_proxies,
         }
     }
 }
@@ -74,7 +111,7 @@ impl Default for Env {
 
 #[cfg(test)]
 mod tests {
-    use super::compute_cookie_secure;
+    use super::{compute_cookie_secure, parse_trusted_proxies};
 
     #[test]
     fn test_compute_cookie_secure_prefers_cookie_secure_true_values() {
@@ -97,4 +134,14 @@ mod tests {
         assert!(!compute_cookie_secure(None, Some("development")));
         assert!(!compute_cookie_secure(None, None));
     }
+
+    #[test]
+    fn test_parse_trusted_proxies_defaults_to_none() {
+        assert!(parse_trusted_proxies(None).is_empty());
+        assert!(parse_trusted_proxies(Some("")).is_empty());
+        assert_eq!(
+            parse_trusted_proxies(Some("10.0.0.1, ::1,")),
+            vec!["10.0.0.1".parse::<std::net::IpAddr>().unwrap(), "::1".parse().unwrap()]
+        );
+    }
 }
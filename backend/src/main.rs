@@ -9,7 +9,7 @@ use actix_web::{
 use std::sync::{Arc, LazyLock};
 
 use crate::{
-    configs::{RedisCache, connect_database},
+    configs::{RedisCache, connect_database, mailer::build_mailer},
     middlewares::{authentication, authorization},
     modules::{
         call::{
@@ -23,9 +23,16 @@ use crate::{
             },
             service::ConversationService,
         },
+        draft::service::DraftService,
         file_upload::{repository_pg::FilePgRepository, service::FileUploadService},
         friend::{repository_pg::FriendRepositoryPg, service::FriendService},
-        message::{repository_pg::MessageRepositoryPg, service::MessageService},
+        message::{
+            reaper::spawn_reaper, repository_pg::MessageRepositoryPg, service::MessageService,
+        },
+        scheduled_message::{
+            dispatcher::spawn_dispatcher, repository_pg::ScheduledMessagePgRepository,
+            service::ScheduledMessageService,
+        },
         user::{repository_pg::UserRepositoryPg, schema::UserRole, service::UserService},
         websocket::{
             handler::websocket_handler, presence::PresenceService, server::WebSocketServer,
@@ -103,8 +110,16 @@ async fn main() -> std::io::Result<()> {
     let last_message_repo = LastMessagePgRepository::default();
     let file_repo = FilePgRepository::new(db_pool.clone());
     let ws_server = Arc::new(WebSocketServer::new());
-    let user_service =
-        UserService::with_dependencies(Arc::new(user_repo.clone()), Arc::new(redis_pool.clone()));
+    let mailer = build_mailer().map_err(|_| {
+        eprintln!("Mailer configuration error");
+        std::io::Error::other("Mailer configuration error")
+    })?;
+    let user_service = UserService::with_dependencies(
+        Arc::new(user_repo.clone()),
+        Arc::new(redis_pool.clone()),
+        ws_server.clone(),
+        mailer,
+    );
     let friend_service = FriendService::with_dependencies(
         Arc::new(friend_repo.clone()),
         Arc::new(user_repo.clone()),
@@ -121,9 +136,20 @@ async fn main() -> std::io::Result<()> {
         Arc::new(message_repo),
         Arc::new(participant_repo),
         Arc::new(last_message_repo),
+        Arc::new(redis_pool.clone()),
+        ws_server.clone(),
+    );
+    let draft_service = DraftService::with_dependencies(
+        Arc::new(conversation_repo.clone()),
         Arc::new(redis_pool),
         ws_server.clone(),
     );
+    let scheduled_message_service = ScheduledMessageService::with_dependencies(
+        Arc::new(ScheduledMessagePgRepository::new(db_pool.clone())),
+        Arc::new(conversation_repo.clone()),
+    );
+    spawn_dispatcher(scheduled_message_service.clone(), message_service.clone());
+    spawn_reaper(message_service.clone());
     
     // Call module
     let call_repo = Arc::new(CallPgRepository::new(db_pool.clone()));
@@ -164,6 +190,8 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(db_pool.clone()))
             .app_data(web::Data::new(conversation_service.clone()))
             .app_data(web::Data::new(message_service.clone()))
+            .app_data(web::Data::new(scheduled_message_service.clone()))
+            .app_data(web::Data::new(draft_service.clone()))
             .app_data(web::Data::new(ws_server.clone())) // WebSocket server
             .app_data(web::Data::new(presence_service.clone())) // Presence service
             .app_data(web::Data::new(friend_repo.clone())) // Friend repo for WS presence
@@ -190,6 +218,8 @@ async fn main() -> std::io::Result<()> {
                             .configure(modules::friend::route::configure)
                             .configure(modules::conversation::route::configure)
                             .configure(modules::message::route::configure)
+                            .configure(modules::scheduled_message::route::configure)
+                            .configure(modules::draft::route::configure)
                             .configure(modules::file_upload::route::configure::<FilePgRepository>)
                                 .configure(modules::call::route::configure),
                     ),
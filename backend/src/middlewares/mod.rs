@@ -10,7 +10,13 @@ use std::rc::Rc;
 use std::time::Instant;
 use uuid::Uuid;
 
-use crate::{ENV, METRICS, api::error, modules::user::schema::UserRole, observability::RequestContext, utils::Claims};
+use crate::{
+    ENV, METRICS,
+    api::error,
+    modules::user::schema::UserRole,
+    observability::RequestContext,
+    utils::{Claims, TypeClaims},
+};
 
 pub async fn request_context<B>(
     req: ServiceRequest,
@@ -81,6 +87,11 @@ where
     let claims = Claims::decode(token, ENV.jwt_secret.as_ref())
         .map_err(|_| error::Error::forbidden("Token không hợp lệ hoặc đã hết hạn"))?;
 
+    // Refresh token / challenge token 2FA không được dùng thay access token
+    if claims._type != Some(TypeClaims::AccessToken) {
+        return Err(error::Error::forbidden("Token không hợp lệ hoặc đã hết hạn").into());
+    }
+
     req.extensions_mut().insert(claims);
 
     next.call(req).await
@@ -1,10 +1,9 @@
 use actix_web::{HttpRequest, web};
-use chrono::{DateTime, Utc};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    api::{error, success},
+    api::{cursor::Cursor, error, success},
     middlewares::get_extensions,
     modules::{
         call::{
@@ -104,13 +103,7 @@ pub async fn get_call_history(
 ) -> Result<success::Success<CallHistoryResponse>, error::Error> {
     let user_id = get_extensions::<Claims>(&req)?.sub;
 
-    let cursor = query
-        .cursor
-        .as_deref()
-        .map(DateTime::parse_from_rfc3339)
-        .transpose()
-        .map_err(|_| error::Error::bad_request("Cursor không hợp lệ"))?
-        .map(|dt| dt.with_timezone(&Utc));
+    let cursor = Cursor::parse(query.cursor.as_deref())?;
 
     let data = call_handler
         .call_service
@@ -1,8 +1,7 @@
-use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use crate::{
-    api::error,
+    api::{cursor::Cursor, error},
     modules::{
         call::{
             model::CallWithDetails,
@@ -52,7 +51,7 @@ pub trait CallRepository {
         &self,
         user_id: Uuid,
         limit: i64,
-        cursor: Option<DateTime<Utc>>,
+        cursor: Option<&Cursor>,
     ) -> Result<Vec<CallWithDetails>, error::SystemError>;
 
     async fn create_call_message(
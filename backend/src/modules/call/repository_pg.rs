@@ -1,8 +1,7 @@
-use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use crate::{
-    api::error,
+    api::{cursor::Cursor, error},
     modules::{
         call::{
             model::CallWithDetails,
@@ -163,7 +162,7 @@ impl CallRepository for CallPgRepository {
         &self,
         user_id: Uuid,
         limit: i64,
-        cursor: Option<DateTime<Utc>>,
+        cursor: Option<&Cursor>,
     ) -> Result<Vec<CallWithDetails>, error::SystemError> {
         let rows = sqlx::query_as::<_, CallWithDetails>(
             r#"
@@ -188,14 +187,15 @@ impl CallRepository for CallPgRepository {
                   AND p.user_id = $1
                   AND p.deleted_at IS NULL
             )
-              AND ($3::timestamptz IS NULL OR c.created_at < $3)
-            ORDER BY c.created_at DESC
+              AND ($3::timestamptz IS NULL OR (c.created_at, c.id) < ($3, $4))
+            ORDER BY c.created_at DESC, c.id DESC
             LIMIT $2
             "#,
         )
         .bind(user_id)
-        .bind(limit)
-        .bind(cursor)
+        .bind(limit + 1)
+        .bind(cursor.map(|c| c.timestamp))
+        .bind(cursor.map(|c| c.id))
         .fetch_all(&self.pool)
         .await?;
 
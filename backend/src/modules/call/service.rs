@@ -1,9 +1,9 @@
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    api::error,
+    api::{cursor::Cursor, error},
     modules::{
         call::{
             model::{
@@ -285,14 +285,22 @@ where
         &self,
         user_id: Uuid,
         limit: i64,
-        cursor: Option<DateTime<Utc>>,
+        cursor: Option<Cursor>,
     ) -> Result<CallHistoryResponse, error::SystemError> {
         let safe_limit = limit.clamp(1, 50);
-        let calls = self.call_repo.get_user_calls(user_id, safe_limit, cursor).await?;
+        let mut calls = self
+            .call_repo
+            .get_user_calls(user_id, safe_limit, cursor.as_ref())
+            .await?;
 
-        let next_cursor = calls
-            .last()
-            .map(|call: &CallWithDetails| call.created_at.to_rfc3339());
+        let next_cursor = if calls.len() > safe_limit as usize {
+            calls.pop();
+            calls
+                .last()
+                .map(|call: &CallWithDetails| Cursor::new(call.created_at, call.id).encode())
+        } else {
+            None
+        };
 
         Ok(CallHistoryResponse {
             calls,
@@ -7,14 +7,22 @@ use crate::{
     modules::{
         conversation::{
             model::{
-                AddMemberRequest, ConversationDetail, MessageQueryRequest, NewConversation,
-                UpdateGroupRequest,
+                AddMemberRequest, ConversationDetail, ConversationListQuery,
+                ConversationListResponse, ConversationPreferences, CreateInviteLinkRequest, JoinRequestDetail,
+                MessageHistoryRequest, MessageQueryRequest, MessageSeenBy, NewConversation, PinnedMessage,
+                SubmitJoinRequest, TransferOwnershipRequest, UpdateConversationPreferencesRequest,
+                UpdateGroupRequest, UpdateJoinApprovalRequest, UpdateMessageTtlRequest,
             },
             repository_pg::{ConversationPgRepository, ParticipantPgRepository},
+            schema::{GroupInviteLinkEntity, GroupJoinRequestEntity, ParticipantRole},
             service::ConversationService,
         },
         friend::handle::FriendSvc,
-        message::{model::GetMessageResponse, repository_pg::MessageRepositoryPg},
+        message::{
+            handle::MessageSvc,
+            model::{GetMessageResponse, ThreadResponse},
+            repository_pg::MessageRepositoryPg,
+        },
     },
     utils::{Claims, ValidatedJson, ValidatedQuery},
 };
@@ -26,13 +34,17 @@ pub type ConversationSvc =
 #[get("")]
 pub async fn get_conversations(
     conversation_svc: web::Data<ConversationSvc>,
+    ValidatedQuery(query): ValidatedQuery<ConversationListQuery>,
     req: HttpRequest,
-) -> Result<success::Success<Vec<ConversationDetail>>, error::Error> {
+) -> Result<success::Success<ConversationListResponse>, error::Error> {
     let user_id = get_extensions::<Claims>(&req)?.sub;
 
-    let conversations = conversation_svc.get_by_user_id(user_id).await?;
+    let (conversations, cursor) = conversation_svc.get_by_user_id(user_id, query).await?;
 
-    Ok(success::Success::ok(Some(conversations))
+    Ok(success::Success::ok(Some(ConversationListResponse {
+        conversations,
+        cursor,
+    }))
         .message("Lấy danh sách cuộc trò chuyện thành công"))
 }
 
@@ -42,7 +54,7 @@ pub async fn get_messages(
     conversation_svc: web::Data<ConversationSvc>,
     conversation_id: web::Path<Uuid>,
     req: HttpRequest,
-    ValidatedQuery(query): ValidatedQuery<MessageQueryRequest>,
+    ValidatedQuery(query): ValidatedQuery<MessageHistoryRequest>,
 ) -> Result<success::Success<GetMessageResponse>, error::Error> {
     let user_id = get_extensions::<Claims>(&req)?.sub;
     let (_, is_member) = conversation_svc
@@ -55,13 +67,98 @@ pub async fn get_messages(
         ));
     }
 
-    let (messages, cursor) = conversation_svc
-        .get_message(*conversation_id, query.limit, query.cursor.clone())
+    let response = conversation_svc.get_message(*conversation_id, query).await?;
+    Ok(success::Success::ok(Some(response)).message("Lấy danh sách tin nhắn thành công"))
+}
+
+/// Lấy thread (tin nhắn gốc + các reply) trong một cuộc trò chuyện (có phân trang cursor)
+#[get("/{conversation_id}/messages/{message_id}/thread")]
+pub async fn get_thread(
+    conversation_svc: web::Data<ConversationSvc>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: HttpRequest,
+    ValidatedQuery(query): ValidatedQuery<MessageQueryRequest>,
+) -> Result<success::Success<ThreadResponse>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (conversation_id, message_id) = path.into_inner();
+
+    let (_, is_member) = conversation_svc
+        .get_conversation_and_check_membership(conversation_id, user_id)
+        .await?;
+
+    if !is_member {
+        return Err(error::Error::forbidden(
+            "Bạn không phải thành viên của cuộc trò chuyện này",
+        ));
+    }
+
+    let thread = conversation_svc
+        .get_thread(conversation_id, message_id, query.limit, query.cursor)
+        .await?;
+    Ok(success::Success::ok(Some(thread)).message("Lấy thread tin nhắn thành công"))
+}
+
+/// Lấy danh sách thành viên nhóm đã đọc tới một tin nhắn (kèm lần đọc gần nhất của mỗi người)
+#[get("/{conversation_id}/messages/{message_id}/seen-by")]
+pub async fn get_seen_by(
+    conversation_svc: web::Data<ConversationSvc>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<MessageSeenBy>>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (conversation_id, message_id) = path.into_inner();
+
+    let seen_by = conversation_svc
+        .get_seen_by(conversation_id, user_id, message_id)
+        .await?;
+    Ok(success::Success::ok(Some(seen_by)).message("Lấy danh sách đã xem thành công"))
+}
+
+/// Lấy danh sách tin nhắn đang được ghim
+#[get("/{conversation_id}/pins")]
+pub async fn get_pinned_messages(
+    conversation_svc: web::Data<ConversationSvc>,
+    conversation_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<PinnedMessage>>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let pins = conversation_svc
+        .get_pinned_messages(*conversation_id, user_id)
+        .await?;
+    Ok(success::Success::ok(Some(pins)).message("Lấy danh sách tin nhắn ghim thành công"))
+}
+
+/// Ghim tin nhắn
+#[post("/{conversation_id}/pins/{message_id}")]
+pub async fn pin_message(
+    conversation_svc: web::Data<ConversationSvc>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (conversation_id, message_id) = path.into_inner();
+
+    conversation_svc
+        .pin_message(conversation_id, user_id, message_id)
+        .await?;
+    Ok(success::Success::ok(None).message("Ghim tin nhắn thành công"))
+}
+
+/// Bỏ ghim tin nhắn
+#[delete("/{conversation_id}/pins/{message_id}")]
+pub async fn unpin_message(
+    conversation_svc: web::Data<ConversationSvc>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (conversation_id, message_id) = path.into_inner();
+
+    conversation_svc
+        .unpin_message(conversation_id, user_id, message_id)
         .await?;
-    Ok(
-        success::Success::ok(Some(GetMessageResponse { messages, cursor }))
-            .message("Lấy danh sách tin nhắn thành công"),
-    )
+    Ok(success::Success::no_content())
 }
 
 /// Tạo cuộc trò chuyện mới (Direct hoặc Group)
@@ -128,6 +225,41 @@ pub async fn update_group(
     Ok(success::Success::ok(None).message("Cập nhật thông tin nhóm thành công"))
 }
 
+/// Cập nhật tuỳ chỉnh cá nhân: lưu trữ, tắt thông báo, ghim cuộc trò chuyện
+#[patch("/{conversation_id}/preferences")]
+pub async fn update_preferences(
+    conversation_svc: web::Data<ConversationSvc>,
+    conversation_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<UpdateConversationPreferencesRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<ConversationPreferences>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let preferences = conversation_svc
+        .update_preferences(*conversation_id, user_id, body)
+        .await?;
+
+    Ok(success::Success::ok(Some(preferences)).message("Cập nhật tuỳ chỉnh cuộc trò chuyện thành công"))
+}
+
+/// Đặt TTL tin nhắn tự hủy cho cuộc trò chuyện
+#[patch("/{conversation_id}/message-ttl")]
+pub async fn update_message_ttl(
+    conversation_svc: web::Data<ConversationSvc>,
+    message_svc: web::Data<MessageSvc>,
+    conversation_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<UpdateMessageTtlRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    conversation_svc
+        .update_message_ttl(*conversation_id, user_id, body.message_ttl_seconds, &message_svc)
+        .await?;
+
+    Ok(success::Success::ok(None).message("Cập nhật tin nhắn tự hủy thành công"))
+}
+
 /// Thêm thành viên vào nhóm
 #[post("/{conversation_id}/members")]
 pub async fn add_member(
@@ -151,6 +283,208 @@ pub async fn add_member(
     Ok(success::Success::ok(None).message("Thêm thành viên vào nhóm thành công"))
 }
 
+/// Tạo link mời tham gia nhóm
+#[post("/{conversation_id}/invite-links")]
+pub async fn create_invite_link(
+    conversation_svc: web::Data<ConversationSvc>,
+    conversation_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<CreateInviteLinkRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<GroupInviteLinkEntity>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let invite = conversation_svc
+        .create_invite_link(*conversation_id, user_id, body.expires_in_seconds, body.max_uses)
+        .await?;
+
+    Ok(success::Success::created(Some(invite)).message("Tạo link mời thành công"))
+}
+
+/// Lấy danh sách link mời của nhóm
+#[get("/{conversation_id}/invite-links")]
+pub async fn get_invite_links(
+    conversation_svc: web::Data<ConversationSvc>,
+    conversation_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<GroupInviteLinkEntity>>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let invites = conversation_svc
+        .get_invite_links(*conversation_id, user_id)
+        .await?;
+
+    Ok(success::Success::ok(Some(invites)).message("Lấy danh sách link mời thành công"))
+}
+
+/// Thu hồi link mời
+#[delete("/{conversation_id}/invite-links/{invite_id}")]
+pub async fn revoke_invite_link(
+    conversation_svc: web::Data<ConversationSvc>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (conversation_id, invite_id) = path.into_inner();
+
+    conversation_svc
+        .revoke_invite_link(conversation_id, user_id, invite_id)
+        .await?;
+
+    Ok(success::Success::ok(None).message("Thu hồi link mời thành công"))
+}
+
+/// Tham gia nhóm bằng link mời
+#[post("/join/{token}")]
+pub async fn join_by_invite(
+    conversation_svc: web::Data<ConversationSvc>,
+    message_svc: web::Data<MessageSvc>,
+    token: web::Path<String>,
+    req: HttpRequest,
+) -> Result<success::Success<ConversationDetail>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let conversation = conversation_svc
+        .join_by_invite(&token, user_id, &message_svc)
+        .await?;
+
+    Ok(success::Success::ok(Some(conversation)).message("Tham gia nhóm thành công"))
+}
+
+/// Bật/tắt chế độ phê duyệt thành viên mới
+#[patch("/{conversation_id}/join-approval")]
+pub async fn update_join_approval(
+    conversation_svc: web::Data<ConversationSvc>,
+    conversation_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<UpdateJoinApprovalRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    conversation_svc
+        .update_join_approval(*conversation_id, user_id, body.join_approval_required)
+        .await?;
+
+    Ok(success::Success::ok(None).message("Cập nhật chế độ phê duyệt thành công"))
+}
+
+/// Gửi yêu cầu tham gia nhóm
+#[post("/{conversation_id}/join-requests")]
+pub async fn submit_join_request(
+    conversation_svc: web::Data<ConversationSvc>,
+    conversation_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<SubmitJoinRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<GroupJoinRequestEntity>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let request = conversation_svc
+        .submit_join_request(*conversation_id, user_id, body.message)
+        .await?;
+
+    Ok(success::Success::created(Some(request)).message("Đã gửi yêu cầu tham gia nhóm"))
+}
+
+/// Lấy danh sách yêu cầu tham gia đang chờ duyệt
+#[get("/{conversation_id}/join-requests")]
+pub async fn get_join_requests(
+    conversation_svc: web::Data<ConversationSvc>,
+    conversation_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<JoinRequestDetail>>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let requests = conversation_svc
+        .get_join_requests(*conversation_id, user_id)
+        .await?;
+
+    Ok(success::Success::ok(Some(requests)).message("Lấy danh sách yêu cầu tham gia thành công"))
+}
+
+/// Duyệt yêu cầu tham gia nhóm
+#[post("/{conversation_id}/join-requests/{request_id}/approve")]
+pub async fn approve_join_request(
+    conversation_svc: web::Data<ConversationSvc>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (conversation_id, request_id) = path.into_inner();
+
+    conversation_svc
+        .review_join_request(conversation_id, user_id, request_id, true)
+        .await?;
+
+    Ok(success::Success::ok(None).message("Đã duyệt yêu cầu tham gia"))
+}
+
+/// Từ chối yêu cầu tham gia nhóm
+#[post("/{conversation_id}/join-requests/{request_id}/reject")]
+pub async fn reject_join_request(
+    conversation_svc: web::Data<ConversationSvc>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (conversation_id, request_id) = path.into_inner();
+
+    conversation_svc
+        .review_join_request(conversation_id, user_id, request_id, false)
+        .await?;
+
+    Ok(success::Success::ok(None).message("Đã từ chối yêu cầu tham gia"))
+}
+
+/// Nâng thành viên lên quản trị viên
+#[post("/{conversation_id}/members/{target_user_id}/promote")]
+pub async fn promote_member(
+    conversation_svc: web::Data<ConversationSvc>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (conversation_id, target_user_id) = path.into_inner();
+
+    conversation_svc
+        .change_member_role(conversation_id, user_id, target_user_id, ParticipantRole::Admin)
+        .await?;
+
+    Ok(success::Success::ok(None).message("Đã nâng thành viên lên quản trị viên"))
+}
+
+/// Hạ quản trị viên xuống thành viên
+#[post("/{conversation_id}/members/{target_user_id}/demote")]
+pub async fn demote_member(
+    conversation_svc: web::Data<ConversationSvc>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (conversation_id, target_user_id) = path.into_inner();
+
+    conversation_svc
+        .change_member_role(conversation_id, user_id, target_user_id, ParticipantRole::Member)
+        .await?;
+
+    Ok(success::Success::ok(None).message("Đã hạ quản trị viên xuống thành viên"))
+}
+
+/// Chuyển quyền trưởng nhóm
+#[post("/{conversation_id}/transfer-ownership")]
+pub async fn transfer_ownership(
+    conversation_svc: web::Data<ConversationSvc>,
+    conversation_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<TransferOwnershipRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    conversation_svc
+        .transfer_ownership(*conversation_id, user_id, body.user_id)
+        .await?;
+
+    Ok(success::Success::ok(None).message("Chuyển quyền trưởng nhóm thành công"))
+}
+
 /// Xóa thành viên hoặc rời khỏi nhóm
 #[delete("/{conversation_id}/members/{target_user_id}")]
 pub async fn remove_member(
@@ -3,13 +3,18 @@ use sqlx::prelude::FromRow;
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::modules::conversation::schema::ConversationType;
+use crate::api::cursor::Cursor;
+use crate::modules::{
+    conversation::schema::{ConversationType, GroupJoinRequestEntity, ParticipantRole},
+    message::schema::MessageEntity,
+};
 
 #[derive(Debug, Clone, FromRow, Deserialize, Serialize)]
 pub struct GroupInfo {
     pub name: String,
     pub created_by: Uuid,
     pub avatar_url: Option<String>,
+    pub join_approval_required: bool,
 }
 
 #[derive(FromRow)]
@@ -17,16 +22,34 @@ pub struct ConversationRaw {
     pub id: Uuid,
     #[sqlx(rename = "type")]
     pub _type: ConversationType,
+    pub message_ttl_seconds: Option<i32>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 
     pub group_name: Option<String>,
     pub group_created_by: Option<Uuid>,
     pub group_avatar_url: Option<String>,
+    pub group_join_approval_required: Option<bool>,
 
     pub last_content: Option<String>,
     pub last_sender_id: Option<Uuid>,
     pub last_created_at: Option<chrono::DateTime<chrono::Utc>>,
+
+    // Tuỳ chỉnh của user đang xem (chỉ có trong danh sách conversation của user)
+    #[sqlx(default)]
+    pub archived: bool,
+    #[sqlx(default)]
+    pub muted_until: Option<chrono::DateTime<chrono::Utc>>,
+    #[sqlx(default)]
+    pub pinned_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Tuỳ chỉnh riêng của một participant với conversation (lưu trữ, tắt thông báo, ghim)
+#[derive(Debug, Clone, Default, FromRow, Deserialize, Serialize)]
+pub struct ConversationPreferences {
+    pub archived: bool,
+    pub muted_until: Option<chrono::DateTime<chrono::Utc>>,
+    pub pinned_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Clone, FromRow, Deserialize, Serialize)]
@@ -35,6 +58,8 @@ pub struct ParticipantRow {
     pub display_name: String,
     pub avatar_url: Option<String>,
     pub unread_count: i32,
+    pub mention_count: i32,
+    pub role: ParticipantRole,
     pub joined_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -50,21 +75,36 @@ pub struct ConversationRow {
     pub conversation_id: Uuid,
     #[sqlx(rename = "type")]
     pub _type: ConversationType,
+    pub message_ttl_seconds: Option<i32>,
     pub group_info: Option<GroupInfo>,
     pub last_message: Option<LastMessageRow>,
+    pub preferences: ConversationPreferences,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+impl ConversationRow {
+    /// Mốc sắp xếp danh sách: tin nhắn cuối, chưa có tin nhắn thì lấy updated_at
+    pub fn last_activity_at(&self) -> chrono::DateTime<chrono::Utc> {
+        self.last_message
+            .as_ref()
+            .map_or(self.updated_at, |message| message.created_at)
+    }
+}
+
 #[derive(Debug, Clone, FromRow, Deserialize, Serialize)]
 
 pub struct ConversationDetail {
     pub conversation_id: Uuid,
     #[sqlx(rename = "type")]
     pub _type: ConversationType,
+    pub message_ttl_seconds: Option<i32>,
     pub group_info: Option<GroupInfo>,
     pub last_message: Option<LastMessageRow>,
     pub participants: Vec<ParticipantRow>,
+    /// Tuỳ chỉnh của user đang xem (None với payload gửi chung cho cả nhóm)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferences: Option<ConversationPreferences>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -91,11 +131,43 @@ pub struct ParticipantDetailWithConversation {
     pub display_name: String,
     pub avatar_url: Option<String>,
     pub unread_count: i32,
+    pub mention_count: i32,
+    pub role: ParticipantRole,
     pub joined_at: chrono::DateTime<chrono::Utc>,
 
     pub conversation_id: Uuid,
 }
 
+/// Participant đã xem tới (hoặc qua) một tin nhắn
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct MessageSeenBy {
+    pub user_id: Uuid,
+    pub display_name: String,
+    pub avatar_url: Option<String>,
+    /// Lần đọc gần nhất của participant trong conversation (không phải lúc xem riêng tin này)
+    pub last_seen_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Tin nhắn được ghim trong conversation
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct PinnedMessage {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub message: MessageEntity,
+    pub pinned_by: Uuid,
+    pub pinned_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Yêu cầu tham gia nhóm kèm thông tin người gửi
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct JoinRequestDetail {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub request: GroupJoinRequestEntity,
+    pub display_name: String,
+    pub avatar_url: Option<String>,
+}
+
 #[allow(unused)]
 #[derive(Debug, Clone, FromRow)]
 pub struct NewLastMessage {
@@ -105,6 +177,74 @@ pub struct NewLastMessage {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Query lấy danh sách conversation (phân trang cursor)
+#[derive(Debug, Deserialize, Validate)]
+pub struct ConversationListQuery {
+    #[serde(default = "default_conversation_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: i32,
+    pub cursor: Option<String>,
+    /// true = chỉ lấy conversation đã lưu trữ, mặc định lấy các conversation chưa lưu trữ
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(rename = "type")]
+    pub _type: Option<ConversationType>,
+    /// Chỉ lấy conversation còn tin nhắn chưa đọc
+    #[serde(default)]
+    pub unread_only: bool,
+    /// Tìm theo tên nhóm hoặc tên hiển thị của người chat 1-1
+    #[validate(length(min = 1, max = 100, message = "Từ khóa tìm kiếm từ 1 đến 100 ký tự"))]
+    pub q: Option<String>,
+}
+
+fn default_conversation_limit() -> i32 {
+    30
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationListResponse {
+    pub conversations: Vec<ConversationDetail>,
+    pub cursor: Option<String>,
+}
+
+/// Điều kiện lọc danh sách conversation của user
+#[derive(Debug, Clone)]
+pub struct ConversationQuery {
+    pub user_id: Uuid,
+    pub archived: bool,
+    pub _type: Option<ConversationType>,
+    pub unread_only: bool,
+    pub search: Option<String>,
+    pub cursor: Option<Cursor>,
+}
+
+/// `Cursor::rank` của danh sách conversation: nhóm được ghim đứng trước nhóm còn lại
+///
+/// Trong mỗi nhóm sắp theo `pinned_at`/hoạt động gần nhất (tin nhắn cuối, chưa có thì updated_at), rồi theo id.
+pub const PINNED_RANK: &str = "pinned";
+pub const RECENT_RANK: &str = "recent";
+
+/// Cập nhật tuỳ chỉnh conversation, bỏ trống field nào thì giữ nguyên field đó
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct UpdateConversationPreferencesRequest {
+    pub archived: Option<bool>,
+    /// null = bật lại thông báo
+    #[serde(default, deserialize_with = "crate::utils::double_option")]
+    pub muted_until: Option<Option<chrono::DateTime<chrono::Utc>>>,
+    pub pinned: Option<bool>,
+}
+
+/// Query lấy lịch sử tin nhắn: `before`/`after` là cursor, `around` là id tin nhắn cần nhảy tới
+#[derive(Debug, Deserialize, Validate)]
+pub struct MessageHistoryRequest {
+    #[validate(range(min = 1, max = 50))]
+    pub limit: i32,
+    #[serde(alias = "cursor")]
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub around: Option<Uuid>,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct MessageQueryRequest {
     #[validate(range(min = 1, max = 50))]
@@ -120,7 +260,48 @@ pub struct UpdateGroupRequest {
     pub avatar_url: Option<Option<String>>,
 }
 
+/// Đặt TTL cho tin nhắn tự hủy (null = tắt)
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct UpdateMessageTtlRequest {
+    #[validate(range(
+        min = 5,
+        max = 2_592_000,
+        message = "TTL phải nằm trong khoảng 5 giây đến 30 ngày"
+    ))]
+    pub message_ttl_seconds: Option<i32>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Validate)]
 pub struct AddMemberRequest {
     pub user_id: Uuid,
 }
+
+/// Tạo link mời nhóm (bỏ trống = không giới hạn)
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct CreateInviteLinkRequest {
+    #[validate(range(
+        min = 60,
+        max = 2_592_000,
+        message = "Thời hạn link mời phải nằm trong khoảng 1 phút đến 30 ngày"
+    ))]
+    pub expires_in_seconds: Option<i64>,
+    #[validate(range(min = 1, max = 1000, message = "Số lượt dùng phải từ 1 đến 1000"))]
+    pub max_uses: Option<i32>,
+}
+
+/// Bật/tắt chế độ phê duyệt thành viên mới
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct UpdateJoinApprovalRequest {
+    pub join_approval_required: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct SubmitJoinRequest {
+    #[validate(length(max = 500, message = "Lời nhắn tối đa 500 ký tự"))]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct TransferOwnershipRequest {
+    pub user_id: Uuid,
+}
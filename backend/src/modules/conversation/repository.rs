@@ -4,10 +4,13 @@ use crate::{
     api::error,
     modules::conversation::{
         model::{
-            ConversationDetail, ConversationRow, NewLastMessage, NewParticipant,
-            ParticipantDetailWithConversation,
+            ConversationDetail, ConversationPreferences, ConversationQuery, ConversationRow, MessageSeenBy, NewLastMessage, NewParticipant,
+            JoinRequestDetail, ParticipantDetailWithConversation, PinnedMessage,
+        },
+        schema::{
+            ConversationEntity, ConversationType, GroupInviteLinkEntity, GroupJoinRequestEntity,
+            JoinRequestStatus, LastMessageEntity, ParticipantEntity, ParticipantRole,
         },
-        schema::{ConversationEntity, ConversationType, LastMessageEntity, ParticipantEntity},
     },
 };
 
@@ -60,9 +63,11 @@ pub trait ConversationRepository {
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 
+    /// Danh sách conversation của user theo keyset (ghim trước), lấy tối đa limit + 1 dòng
     async fn find_all_conversation_with_details_by_user<'e, E>(
         &self,
-        user_id: &Uuid,
+        query: &ConversationQuery,
+        limit: i32,
         tx: E,
     ) -> Result<Vec<ConversationRow>, error::SystemError>
     where
@@ -97,22 +102,221 @@ pub trait ConversationRepository {
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 
-    /// Lấy user_id của người tạo nhóm
-    async fn get_group_creator<'e, E>(
+    /// Đặt TTL tin nhắn tự hủy của conversation (None = tắt)
+    async fn update_message_ttl<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        message_ttl_seconds: Option<i32>,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Ghim tin nhắn, trả về false nếu tin nhắn đã được ghim trước đó
+    async fn pin_message<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        message_id: &Uuid,
+        pinned_by: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Bỏ ghim tin nhắn, trả về false nếu tin nhắn chưa được ghim
+    async fn unpin_message<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        message_id: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Khóa dòng conversation (FOR NO KEY UPDATE) để các thao tác đếm rồi ghi trên cùng
+    /// conversation chạy tuần tự
+    async fn lock_conversation<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn count_pinned_messages<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<i64, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Danh sách tin nhắn đang được ghim (mới ghim trước, bỏ qua tin đã xóa)
+    async fn find_pinned_messages<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<PinnedMessage>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Lấy vai trò của participant đang active (None nếu không phải thành viên)
+    async fn get_participant_role<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_id: &Uuid,
+        tx: E,
+    ) -> Result<Option<ParticipantRole>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Lấy vai trò và khóa dòng participant (FOR NO KEY UPDATE) tới hết transaction
+    ///
+    /// Dùng cho kiểm tra quyền để hai thao tác quản trị song song không cùng dựa trên vai trò cũ.
+    async fn lock_participant_role<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_id: &Uuid,
+        tx: E,
+    ) -> Result<Option<ParticipantRole>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Đổi vai trò participant đang active, trả về false nếu không tìm thấy
+    async fn update_participant_role<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_id: &Uuid,
+        role: ParticipantRole,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn create_invite_link<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        created_by: &Uuid,
+        token: &str,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+        max_uses: Option<i32>,
+        tx: E,
+    ) -> Result<GroupInviteLinkEntity, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Danh sách link mời chưa bị thu hồi của nhóm (mới nhất trước)
+    async fn find_invite_links<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<GroupInviteLinkEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Thu hồi link mời, trả về false nếu không tồn tại hoặc đã thu hồi
+    async fn revoke_invite_link<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        invite_id: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Lấy link mời còn hiệu lực theo token và khóa dòng (FOR UPDATE) để đếm lượt dùng
+    async fn find_active_invite_link<'e, E>(
+        &self,
+        token: &str,
+        tx: E,
+    ) -> Result<Option<GroupInviteLinkEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn increment_invite_use<'e, E>(
+        &self,
+        invite_id: &Uuid,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Nhóm có yêu cầu phê duyệt thành viên mới không (None nếu không phải nhóm)
+    async fn get_join_approval_required<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Option<bool>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn update_join_approval_required<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        join_approval_required: bool,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Tạo yêu cầu tham gia, trả về None nếu user đã có yêu cầu đang chờ
+    async fn create_join_request<'e, E>(
         &self,
         conversation_id: &Uuid,
+        user_id: &Uuid,
+        message: Option<&str>,
         tx: E,
-    ) -> Result<Option<Uuid>, error::SystemError>
+    ) -> Result<Option<GroupJoinRequestEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Danh sách yêu cầu đang chờ duyệt (cũ nhất trước)
+    async fn find_pending_join_requests<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<JoinRequestDetail>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Lấy yêu cầu đang chờ và khóa dòng (FOR UPDATE) để duyệt
+    async fn find_pending_join_request<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        request_id: &Uuid,
+        tx: E,
+    ) -> Result<Option<GroupJoinRequestEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn update_join_request_status<'e, E>(
+        &self,
+        request_id: &Uuid,
+        status: JoinRequestStatus,
+        reviewed_by: &Uuid,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Danh sách owner/admin đang active của nhóm
+    async fn find_admin_ids<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<Uuid>, error::SystemError>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 
     /// Thêm participant vào conversation (UPSERT – khôi phục nếu đã từng join)
+    ///
+    /// Trả về false nếu user đang là thành viên (giữ nguyên vai trò và trạng thái hiện tại).
     async fn add_participant<'e, E>(
         &self,
         conversation_id: &Uuid,
         user_id: &Uuid,
         tx: E,
-    ) -> Result<(), error::SystemError>
+    ) -> Result<bool, error::SystemError>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 
@@ -194,6 +398,59 @@ pub trait ParticipantRepository {
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 
+    /// Lấy danh sách participants (trừ người gửi) đã xem tới tin nhắn
+    async fn find_seen_by<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        message_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<MessageSeenBy>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Resolve @username/@all thành user_id của các participant đang active (trừ người gửi)
+    async fn find_mentioned_user_ids<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        sender_id: &Uuid,
+        usernames: &[String],
+        mention_all: bool,
+        tx: E,
+    ) -> Result<Vec<Uuid>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn increment_mention_count<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_ids: &[Uuid],
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Cập nhật tuỳ chỉnh của participant, trả về None nếu user không phải thành viên
+    async fn update_preferences<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_id: &Uuid,
+        archived: Option<bool>,
+        muted_until: Option<Option<chrono::DateTime<chrono::Utc>>>,
+        pinned: Option<bool>,
+        tx: E,
+    ) -> Result<Option<ConversationPreferences>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Danh sách participant đang tắt thông báo conversation
+    async fn find_muted_user_ids<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<Uuid>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
     /// Get unread counts for all participants in a conversation
     /// Returns a map of user_id -> unread_count
     async fn get_unread_counts<'e, E>(
@@ -214,4 +471,15 @@ pub trait LastMessageRepository {
     ) -> Result<LastMessageEntity, error::SystemError>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Tính lại last message từ tin nhắn mới nhất còn tồn tại (sau khi tin nhắn bị xóa hàng loạt)
+    ///
+    /// Conversation không còn tin nhắn nào giữ thời điểm cũ nhưng bỏ nội dung.
+    async fn refresh_last_messages<'e, E>(
+        &self,
+        conversation_ids: &[Uuid],
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 }
@@ -2,14 +2,16 @@ use sqlx::{FromRow, Row};
 use uuid::Uuid;
 
 use crate::modules::conversation::model::{
-    ConversationDetail, ConversationRaw, ConversationRow, GroupInfo, LastMessageRow,
-    NewLastMessage, NewParticipant, ParticipantDetailWithConversation, ParticipantRow,
+    ConversationDetail, ConversationPreferences, ConversationQuery, ConversationRaw, ConversationRow, GroupInfo, LastMessageRow,
+    JoinRequestDetail, MessageSeenBy, NewLastMessage, NewParticipant,
+    PINNED_RANK, ParticipantDetailWithConversation, ParticipantRow, PinnedMessage,
 };
 use crate::modules::conversation::repository::{
     ConversationRepository, LastMessageRepository, ParticipantRepository,
 };
 use crate::modules::conversation::schema::{
-    ConversationType, LastMessageEntity, ParticipantEntity,
+    ConversationType, GroupInviteLinkEntity, GroupJoinRequestEntity, JoinRequestStatus,
+    LastMessageEntity, ParticipantEntity, ParticipantRole,
 };
 use crate::{api::error, modules::conversation::schema::ConversationEntity};
 
@@ -60,12 +62,14 @@ impl ConversationRepository for ConversationPgRepository {
             SELECT
                 c.id,
                 c.type,
+                c.message_ttl_seconds,
                 c.created_at,
                 c.updated_at,
 
                 g.name AS group_name,
                 g.created_by AS group_created_by,
                 g.avatar_url AS group_avatar_url,
+                g.join_approval_required AS group_join_approval_required,
 
                 m.content AS last_content,
                 m.sender_id AS last_sender_id,
@@ -77,7 +81,8 @@ impl ConversationRepository for ConversationPgRepository {
                 SELECT content, sender_id, created_at
                 FROM messages
                 WHERE conversation_id = c.id
-                ORDER BY created_at DESC
+                  AND deleted_at IS NULL
+                ORDER BY created_at DESC, id DESC
                 LIMIT 1
             ) m ON true
             WHERE c.id = $1
@@ -101,6 +106,8 @@ impl ConversationRepository for ConversationPgRepository {
                 u.avatar_url,
                 u.avatar_id,
                 p.unread_count,
+                p.mention_count,
+                p.role,
                 p.joined_at
             FROM participants p
             JOIN users u ON u.id = p.user_id
@@ -114,6 +121,7 @@ impl ConversationRepository for ConversationPgRepository {
         let res = ConversationDetail {
             conversation_id: raw.id,
             _type: raw._type,
+            message_ttl_seconds: raw.message_ttl_seconds,
             created_at: raw.created_at,
             updated_at: raw.updated_at,
 
@@ -122,6 +130,7 @@ impl ConversationRepository for ConversationPgRepository {
                     name,
                     avatar_url: raw.group_avatar_url,
                     created_by,
+                    join_approval_required: raw.group_join_approval_required.unwrap_or(false),
                 }),
                 _ => None,
             },
@@ -136,6 +145,7 @@ impl ConversationRepository for ConversationPgRepository {
             },
 
             participants,
+            preferences: None,
         };
 
         Ok(Some(res))
@@ -221,12 +231,19 @@ impl ConversationRepository for ConversationPgRepository {
 
         sqlx::query(
             r#"
-            INSERT INTO participants (conversation_id, user_id, unread_count, joined_at)
-            SELECT $1, unnest($2::uuid[]), 0, NOW()
+            INSERT INTO participants (conversation_id, user_id, unread_count, joined_at, role)
+            SELECT
+                $1,
+                member_id,
+                0,
+                NOW(),
+                CASE WHEN member_id = $3 THEN 'owner' ELSE 'member' END::participant_role
+            FROM unnest($2::uuid[]) AS member_id
             "#,
         )
         .bind(conversation.id)
         .bind(unique_member_ids)
+        .bind(user_id)
         .execute(tx.as_mut())
         .await?;
 
@@ -274,17 +291,24 @@ impl ConversationRepository for ConversationPgRepository {
 
     async fn find_all_conversation_with_details_by_user<'e, E>(
         &self,
-        user_id: &Uuid,
+        query: &ConversationQuery,
+        limit: i32,
         tx: E,
     ) -> Result<Vec<ConversationRow>, error::SystemError>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>,
     {
+        let search_pattern = query
+            .search
+            .as_ref()
+            .map(|q| format!("%{}%", q.replace('%', "\\%").replace('_', "\\_")));
+
         let rows = sqlx::query_as::<_, ConversationRaw>(
             r#"
             SELECT
                 c.id,
                 c.type,
+                c.message_ttl_seconds,
                 c.created_at,
                 c.updated_at,
 
@@ -292,10 +316,15 @@ impl ConversationRepository for ConversationPgRepository {
                 g.avatar_url    AS group_avatar_url,
                 g.avatar_id     AS group_avatar_id,
                 g.created_by    AS group_created_by,
+                g.join_approval_required AS group_join_approval_required,
 
                 lm.content      AS last_content,
                 lm.sender_id    AS last_sender_id,
-                lm.created_at   AS last_created_at
+                lm.created_at   AS last_created_at,
+
+                p.archived,
+                p.muted_until,
+                p.pinned_at
 
             FROM conversations c
 
@@ -303,23 +332,63 @@ impl ConversationRepository for ConversationPgRepository {
                 ON p.conversation_id = c.id
             AND p.user_id = $1
             AND p.deleted_at IS NULL
+            AND p.archived = $2
 
             LEFT JOIN group_conversations g
                 ON g.conversation_id = c.id
 
+            -- Tin đã xoá / tự hủy không còn làm preview
             LEFT JOIN LATERAL (
                 SELECT content, sender_id, created_at
                 FROM messages m
                 WHERE m.conversation_id = c.id
-                ORDER BY created_at DESC
+                  AND m.deleted_at IS NULL
+                ORDER BY m.created_at DESC, m.id DESC
                 LIMIT 1
             ) lm ON TRUE
 
+            WHERE ($3::conversation_type IS NULL OR c.type = $3)
+            AND (NOT $4 OR p.unread_count > 0)
+            AND (
+                $5::text IS NULL
+                OR lower(g.name) LIKE lower($5)
+                OR (
+                    c.type = 'direct'
+                    AND EXISTS (
+                        SELECT 1
+                        FROM participants op
+                        JOIN users ou ON ou.id = op.user_id
+                        WHERE op.conversation_id = c.id
+                        AND op.user_id <> $1
+                        AND lower(ou.display_name) LIKE lower($5)
+                    )
+                )
+            )
+            AND (
+                $6::boolean IS NULL
+                OR (
+                    (p.pinned_at IS NOT NULL),
+                    COALESCE(p.pinned_at, lm.created_at, c.updated_at),
+                    c.id
+                ) < ($6, $7, $8)
+            )
+
             ORDER BY
-                COALESCE(lm.created_at, c.updated_at) DESC
+                (p.pinned_at IS NOT NULL) DESC,
+                COALESCE(p.pinned_at, lm.created_at, c.updated_at) DESC,
+                c.id DESC
+            LIMIT $9
             "#,
         )
-        .bind(user_id)
+        .bind(query.user_id)
+        .bind(query.archived)
+        .bind(query._type.as_ref())
+        .bind(query.unread_only)
+        .bind(search_pattern)
+        .bind(query.cursor.as_ref().map(|c| c.rank.as_deref() == Some(PINNED_RANK)))
+        .bind(query.cursor.as_ref().map(|c| c.timestamp))
+        .bind(query.cursor.as_ref().map(|c| c.id))
+        .bind(limit + 1)
         .fetch_all(tx)
         .await?;
 
@@ -331,6 +400,7 @@ impl ConversationRepository for ConversationPgRepository {
                         name,
                         avatar_url: r.group_avatar_url,
                         created_by,
+                        join_approval_required: r.group_join_approval_required.unwrap_or(false),
                     }),
                     _ => None,
                 };
@@ -347,10 +417,16 @@ impl ConversationRepository for ConversationPgRepository {
                 ConversationRow {
                     conversation_id: r.id,
                     _type: r._type,
+                    message_ttl_seconds: r.message_ttl_seconds,
                     created_at: r.created_at,
                     updated_at: r.updated_at,
                     group_info,
                     last_message,
+                    preferences: ConversationPreferences {
+                        archived: r.archived,
+                        muted_until: r.muted_until,
+                        pinned_at: r.pinned_at,
+                    },
                 }
             })
             .collect();
@@ -375,6 +451,7 @@ impl ConversationRepository for ConversationPgRepository {
                 FROM participants p
                 WHERE p.conversation_id = c.id
                 AND p.user_id = $2
+                AND p.deleted_at IS NULL
                 ) as is_member
             FROM conversations c
             WHERE c.id = $1
@@ -456,45 +533,535 @@ impl ConversationRepository for ConversationPgRepository {
         Ok(())
     }
 
-    async fn get_group_creator<'e, E>(
+    async fn update_message_ttl<'e, E>(
         &self,
         conversation_id: &Uuid,
+        message_ttl_seconds: Option<i32>,
         tx: E,
-    ) -> Result<Option<Uuid>, error::SystemError>
+    ) -> Result<(), error::SystemError>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>,
     {
-        let row = sqlx::query_scalar::<_, Uuid>(
-            "SELECT created_by FROM group_conversations WHERE conversation_id = $1",
+        sqlx::query(
+            r#"
+            UPDATE conversations
+            SET message_ttl_seconds = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
         )
         .bind(conversation_id)
+        .bind(message_ttl_seconds)
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn pin_message<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        message_id: &Uuid,
+        pinned_by: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO pinned_messages (conversation_id, message_id, pinned_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (conversation_id, message_id) DO NOTHING
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(message_id)
+        .bind(pinned_by)
+        .execute(tx)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn unpin_message<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        message_id: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query(
+            "DELETE FROM pinned_messages WHERE conversation_id = $1 AND message_id = $2",
+        )
+        .bind(conversation_id)
+        .bind(message_id)
+        .execute(tx)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn lock_conversation<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // NO KEY UPDATE không chặn các insert chỉ giữ KEY SHARE qua foreign key (gửi tin nhắn...)
+        sqlx::query("SELECT id FROM conversations WHERE id = $1 FOR NO KEY UPDATE")
+            .bind(conversation_id)
+            .fetch_optional(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn count_pinned_messages<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<i64, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM pinned_messages pm
+            JOIN messages m ON m.id = pm.message_id
+            WHERE pm.conversation_id = $1
+              AND m.deleted_at IS NULL
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_one(tx)
+        .await?;
+
+        Ok(count)
+    }
+
+    async fn find_pinned_messages<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<PinnedMessage>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let pins = sqlx::query_as::<_, PinnedMessage>(
+            r#"
+            SELECT m.*, pm.pinned_by, pm.pinned_at
+            FROM pinned_messages pm
+            JOIN messages m ON m.id = pm.message_id
+            WHERE pm.conversation_id = $1
+              AND m.deleted_at IS NULL
+            ORDER BY pm.pinned_at DESC
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(pins)
+    }
+
+    async fn get_participant_role<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_id: &Uuid,
+        tx: E,
+    ) -> Result<Option<ParticipantRole>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let role = sqlx::query_scalar::<_, ParticipantRole>(
+            r#"
+            SELECT role
+            FROM participants
+            WHERE conversation_id = $1
+              AND user_id = $2
+              AND deleted_at IS NULL
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(user_id)
         .fetch_optional(tx)
         .await?;
-        Ok(row)
+        Ok(role)
     }
 
-    async fn add_participant<'e, E>(
+    async fn lock_participant_role<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_id: &Uuid,
+        tx: E,
+    ) -> Result<Option<ParticipantRole>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let role = sqlx::query_scalar::<_, ParticipantRole>(
+            r#"
+            SELECT role
+            FROM participants
+            WHERE conversation_id = $1
+              AND user_id = $2
+              AND deleted_at IS NULL
+            FOR NO KEY UPDATE
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .fetch_optional(tx)
+        .await?;
+        Ok(role)
+    }
+
+    async fn update_participant_role<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_id: &Uuid,
+        role: ParticipantRole,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            UPDATE participants
+            SET role = $3
+            WHERE conversation_id = $1
+              AND user_id = $2
+              AND deleted_at IS NULL
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .bind(role)
+        .execute(tx)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn create_invite_link<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        created_by: &Uuid,
+        token: &str,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+        max_uses: Option<i32>,
+        tx: E,
+    ) -> Result<GroupInviteLinkEntity, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let invite = sqlx::query_as::<_, GroupInviteLinkEntity>(
+            r#"
+            INSERT INTO group_invite_links (id, conversation_id, token, created_by, expires_at, max_uses)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(conversation_id)
+        .bind(token)
+        .bind(created_by)
+        .bind(expires_at)
+        .bind(max_uses)
+        .fetch_one(tx)
+        .await?;
+
+        Ok(invite)
+    }
+
+    async fn find_invite_links<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<GroupInviteLinkEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let invites = sqlx::query_as::<_, GroupInviteLinkEntity>(
+            r#"
+            SELECT *
+            FROM group_invite_links
+            WHERE conversation_id = $1
+              AND revoked_at IS NULL
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(invites)
+    }
+
+    async fn revoke_invite_link<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        invite_id: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            UPDATE group_invite_links
+            SET revoked_at = NOW()
+            WHERE id = $1
+              AND conversation_id = $2
+              AND revoked_at IS NULL
+            "#,
+        )
+        .bind(invite_id)
+        .bind(conversation_id)
+        .execute(tx)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_active_invite_link<'e, E>(
+        &self,
+        token: &str,
+        tx: E,
+    ) -> Result<Option<GroupInviteLinkEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let invite = sqlx::query_as::<_, GroupInviteLinkEntity>(
+            r#"
+            SELECT *
+            FROM group_invite_links
+            WHERE token = $1
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+              AND (max_uses IS NULL OR use_count < max_uses)
+            FOR UPDATE
+            "#,
+        )
+        .bind(token)
+        .fetch_optional(tx)
+        .await?;
+
+        Ok(invite)
+    }
+
+    async fn increment_invite_use<'e, E>(
+        &self,
+        invite_id: &Uuid,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        sqlx::query("UPDATE group_invite_links SET use_count = use_count + 1 WHERE id = $1")
+            .bind(invite_id)
+            .execute(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_join_approval_required<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Option<bool>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let required = sqlx::query_scalar::<_, bool>(
+            "SELECT join_approval_required FROM group_conversations WHERE conversation_id = $1",
+        )
+        .bind(conversation_id)
+        .fetch_optional(tx)
+        .await?;
+
+        Ok(required)
+    }
+
+    async fn update_join_approval_required<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        join_approval_required: bool,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        sqlx::query(
+            "UPDATE group_conversations SET join_approval_required = $2 WHERE conversation_id = $1",
+        )
+        .bind(conversation_id)
+        .bind(join_approval_required)
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn create_join_request<'e, E>(
         &self,
         conversation_id: &Uuid,
         user_id: &Uuid,
+        message: Option<&str>,
+        tx: E,
+    ) -> Result<Option<GroupJoinRequestEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let request = sqlx::query_as::<_, GroupJoinRequestEntity>(
+            r#"
+            INSERT INTO group_join_requests (id, conversation_id, user_id, message)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (conversation_id, user_id) WHERE status = 'pending' DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(conversation_id)
+        .bind(user_id)
+        .bind(message)
+        .fetch_optional(tx)
+        .await?;
+
+        Ok(request)
+    }
+
+    async fn find_pending_join_requests<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<JoinRequestDetail>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let requests = sqlx::query_as::<_, JoinRequestDetail>(
+            r#"
+            SELECT r.*, u.display_name, u.avatar_url
+            FROM group_join_requests r
+            JOIN users u ON u.id = r.user_id
+            WHERE r.conversation_id = $1
+              AND r.status = 'pending'
+            ORDER BY r.created_at ASC
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(requests)
+    }
+
+    async fn find_pending_join_request<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        request_id: &Uuid,
+        tx: E,
+    ) -> Result<Option<GroupJoinRequestEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let request = sqlx::query_as::<_, GroupJoinRequestEntity>(
+            r#"
+            SELECT *
+            FROM group_join_requests
+            WHERE id = $1
+              AND conversation_id = $2
+              AND status = 'pending'
+            FOR UPDATE
+            "#,
+        )
+        .bind(request_id)
+        .bind(conversation_id)
+        .fetch_optional(tx)
+        .await?;
+
+        Ok(request)
+    }
+
+    async fn update_join_request_status<'e, E>(
+        &self,
+        request_id: &Uuid,
+        status: JoinRequestStatus,
+        reviewed_by: &Uuid,
         tx: E,
     ) -> Result<(), error::SystemError>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>,
     {
         sqlx::query(
+            r#"
+            UPDATE group_join_requests
+            SET status = $2, reviewed_by = $3, reviewed_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(request_id)
+        .bind(status)
+        .bind(reviewed_by)
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_admin_ids<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<Uuid>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT user_id
+            FROM participants
+            WHERE conversation_id = $1
+              AND deleted_at IS NULL
+              AND role IN ('owner', 'admin')
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(ids)
+    }
+
+    async fn add_participant<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_id: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // Chỉ reset dòng đã rời nhóm; thành viên hiện tại không bị hạ vai trò
+        let result = sqlx::query(
             r#"
             INSERT INTO participants (conversation_id, user_id, unread_count, joined_at)
             VALUES ($1, $2, 0, NOW())
             ON CONFLICT (conversation_id, user_id)
-            DO UPDATE SET deleted_at = NULL, joined_at = NOW(), unread_count = 0
+            DO UPDATE SET deleted_at = NULL, joined_at = NOW(), unread_count = 0, role = 'member'
+            WHERE participants.deleted_at IS NOT NULL
             "#,
         )
         .bind(conversation_id)
         .bind(user_id)
         .execute(tx)
         .await?;
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
     async fn remove_participant<'e, E>(
@@ -663,7 +1230,9 @@ impl ParticipantRepository for ParticipantPgRepository {
             r#"
             UPDATE participants
             SET last_seen_message_id = $1,
-                unread_count = 0
+                last_seen_at = NOW(),
+                unread_count = 0,
+                mention_count = 0
             WHERE conversation_id = $2
             AND user_id = $3
             AND deleted_at IS NULL
@@ -694,6 +1263,8 @@ impl ParticipantRepository for ParticipantPgRepository {
                 u.display_name,
                 u.avatar_url,
                 p.unread_count,
+                p.mention_count,
+                p.role,
                 p.joined_at
             FROM participants p
             JOIN users u ON u.id = p.user_id
@@ -708,6 +1279,168 @@ impl ParticipantRepository for ParticipantPgRepository {
         Ok(participants)
     }
 
+    async fn find_seen_by<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        message_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<MessageSeenBy>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // "Đã xem" = tin nhắn cuối đã xem của participant không cũ hơn tin nhắn đích
+        let seen_by = sqlx::query_as::<_, MessageSeenBy>(
+            r#"
+            SELECT
+                p.user_id,
+                u.display_name,
+                u.avatar_url,
+                p.last_seen_at
+            FROM messages target
+            JOIN participants p
+              ON p.conversation_id = target.conversation_id
+             AND p.deleted_at IS NULL
+             AND p.user_id <> target.sender_id
+            JOIN messages seen ON seen.id = p.last_seen_message_id
+            JOIN users u ON u.id = p.user_id
+            WHERE target.id = $2
+              AND target.conversation_id = $1
+              AND seen.created_at >= target.created_at
+            ORDER BY p.last_seen_at DESC NULLS LAST
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(message_id)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(seen_by)
+    }
+
+    async fn find_mentioned_user_ids<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        sender_id: &Uuid,
+        usernames: &[String],
+        mention_all: bool,
+        tx: E,
+    ) -> Result<Vec<Uuid>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // usernames đã được lowercase khi parse, khớp với unique index lower(username)
+        let user_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT p.user_id
+            FROM participants p
+            JOIN users u ON u.id = p.user_id
+            WHERE p.conversation_id = $1
+              AND p.deleted_at IS NULL
+              AND p.user_id <> $2
+              AND ($4 OR lower(u.username) = ANY($3))
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(sender_id)
+        .bind(usernames)
+        .bind(mention_all)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(user_ids)
+    }
+
+    async fn increment_mention_count<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_ids: &[Uuid],
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE participants
+            SET mention_count = mention_count + 1
+            WHERE conversation_id = $1
+              AND user_id = ANY($2)
+              AND deleted_at IS NULL
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(user_ids)
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_preferences<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        user_id: &Uuid,
+        archived: Option<bool>,
+        muted_until: Option<Option<chrono::DateTime<chrono::Utc>>>,
+        pinned: Option<bool>,
+        tx: E,
+    ) -> Result<Option<ConversationPreferences>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // Field None = giữ nguyên; ghim lại conversation đã ghim không đổi thứ tự
+        let preferences = sqlx::query_as::<_, ConversationPreferences>(
+            r#"
+            UPDATE participants
+            SET archived = COALESCE($3, archived),
+                muted_until = CASE WHEN $4 THEN $5 ELSE muted_until END,
+                pinned_at = CASE
+                    WHEN $6::boolean IS NULL THEN pinned_at
+                    WHEN $6 THEN COALESCE(pinned_at, NOW())
+                    ELSE NULL
+                END
+            WHERE conversation_id = $1
+              AND user_id = $2
+              AND deleted_at IS NULL
+            RETURNING archived, muted_until, pinned_at
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .bind(archived)
+        .bind(muted_until.is_some())
+        .bind(muted_until.flatten())
+        .bind(pinned)
+        .fetch_optional(tx)
+        .await?;
+
+        Ok(preferences)
+    }
+
+    async fn find_muted_user_ids<'e, E>(
+        &self,
+        conversation_id: &Uuid,
+        tx: E,
+    ) -> Result<Vec<Uuid>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let user_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT user_id
+            FROM participants
+            WHERE conversation_id = $1
+              AND deleted_at IS NULL
+              AND muted_until > NOW()
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(user_ids)
+    }
+
     async fn get_unread_counts<'e, E>(
         &self,
         conversation_id: &Uuid,
@@ -777,4 +1510,37 @@ impl LastMessageRepository for LastMessagePgRepository {
 
         Ok(res)
     }
+
+    async fn refresh_last_messages<'e, E>(
+        &self,
+        conversation_ids: &[Uuid],
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE last_messages lm
+            SET content = latest.content,
+                sender_id = COALESCE(latest.sender_id, lm.sender_id),
+                created_at = COALESCE(latest.created_at, lm.created_at)
+            FROM UNNEST($1::uuid[]) AS c(id)
+            LEFT JOIN LATERAL (
+                SELECT m.content, m.sender_id, m.created_at
+                FROM messages m
+                WHERE m.conversation_id = c.id
+                  AND m.deleted_at IS NULL
+                ORDER BY m.created_at DESC, m.id DESC
+                LIMIT 1
+            ) latest ON TRUE
+            WHERE lm.conversation_id = c.id
+            "#,
+        )
+        .bind(conversation_ids)
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
 }
@@ -5,12 +5,31 @@ use crate::modules::conversation::handle::*;
 pub fn configure(cfg: &mut ServiceConfig) {
     cfg.service(
         scope("/conversations")
+            .service(join_by_invite)
             .service(get_conversations)
             .service(get_messages)
+            .service(get_thread)
+            .service(get_seen_by)
+            .service(get_pinned_messages)
+            .service(pin_message)
+            .service(unpin_message)
             .service(mark_as_seen)
             .service(update_group)
+            .service(update_preferences)
+            .service(update_message_ttl)
             .service(add_member)
             .service(remove_member)
+            .service(promote_member)
+            .service(demote_member)
+            .service(transfer_ownership)
+            .service(create_invite_link)
+            .service(get_invite_links)
+            .service(revoke_invite_link)
+            .service(update_join_approval)
+            .service(submit_join_request)
+            .service(get_join_requests)
+            .service(approve_join_request)
+            .service(reject_join_request)
             .service(scope("").service(create_conversation)),
     );
 }
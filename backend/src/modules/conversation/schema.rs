@@ -10,11 +10,31 @@ pub enum ConversationType {
     Group,
 }
 
+/// Vai trò của participant trong nhóm (thứ tự khai báo = thứ tự quyền hạn)
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Serialize, Deserialize)]
+#[sqlx(type_name = "participant_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ParticipantRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Type, Serialize, Deserialize)]
+#[sqlx(type_name = "join_request_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum JoinRequestStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
 #[derive(Debug, Clone, FromRow)]
 pub struct ConversationEntity {
     pub id: Uuid,
     #[sqlx(rename = "type")]
     pub _type: ConversationType,
+    pub message_ttl_seconds: Option<i32>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -37,6 +57,34 @@ pub struct GroupConversationEntity {
     pub avatar_url: Option<String>,
 }
 
+/// Link mời tham gia nhóm
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct GroupInviteLinkEntity {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub token: String,
+    pub created_by: Uuid,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub max_uses: Option<i32>,
+    pub use_count: i32,
+    #[serde(skip_serializing)]
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Yêu cầu tham gia nhóm cần owner/admin phê duyệt
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct GroupJoinRequestEntity {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub user_id: Uuid,
+    pub message: Option<String>,
+    pub status: JoinRequestStatus,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Clone, FromRow)]
 pub struct LastMessageEntity {
     pub id: Uuid,
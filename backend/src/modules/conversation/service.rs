@@ -7,21 +7,48 @@ use std::{collections::HashMap, sync::Arc};
 use uuid::Uuid;
 
 use crate::{
-    api::error,
+    api::{
+        cursor::{self, Cursor},
+        error,
+    },
     modules::{
         conversation::{
-            model::{ConversationDetail, ParticipantDetailWithConversation, ParticipantRow},
-            repository::{ConversationRepository, ParticipantRepository},
-            schema::{ConversationEntity, ConversationType},
+            model::{
+                ConversationDetail, ConversationListQuery, PINNED_RANK, RECENT_RANK,
+                ConversationPreferences, ConversationQuery, JoinRequestDetail,
+                MessageHistoryRequest, MessageSeenBy,
+                ParticipantDetailWithConversation, ParticipantRow, PinnedMessage,
+                UpdateConversationPreferencesRequest,
+            },
+            repository::{ConversationRepository, LastMessageRepository, ParticipantRepository},
+            schema::{
+                ConversationEntity, ConversationType, GroupInviteLinkEntity,
+                GroupJoinRequestEntity, JoinRequestStatus, ParticipantRole,
+            },
+        },
+        message::{
+            model::{
+                GetMessageResponse, MessageQuery, MessageWithReactions,
+                PageDirection, ReactionSummary, ThreadResponse,
+            },
+            repository::MessageRepository,
+            schema::{ContactCard, MessageEntity},
+            service::MessageService,
         },
-        message::{model::MessageQuery, repository::MessageRepository, schema::MessageEntity},
         websocket::{
             message::{LastMessageInfo, SenderInfo, ServerMessage},
             server::WebSocketServer,
         },
     },
+    utils,
 };
 
+/// Số tin nhắn ghim tối đa trong một conversation
+pub const MAX_PINNED_MESSAGES: i64 = 10;
+
+/// Độ dài token của link mời nhóm
+const INVITE_TOKEN_LENGTH: usize = 22;
+
 /// ConversationService với generic repositories để dễ testing và decoupling
 #[derive(Clone)]
 pub struct ConversationService<R, P, L>
@@ -150,17 +177,50 @@ where
         Ok(conversation_detail)
     }
 
-    /// Lấy tất cả conversations của user
+    /// Lấy danh sách conversations của user (keyset pagination + bộ lọc)
+    ///
+    /// Trả về (conversations, next_cursor).
     pub async fn get_by_user_id(
         &self,
         user_id: Uuid,
-    ) -> Result<Vec<ConversationDetail>, error::SystemError> {
+        params: ConversationListQuery,
+    ) -> Result<(Vec<ConversationDetail>, Option<String>), error::SystemError> {
+        let cursor = Cursor::parse_ranked(params.cursor.as_deref())?;
+        if let Some(rank) = cursor.as_ref().and_then(|c| c.rank.as_deref())
+            && rank != PINNED_RANK
+            && rank != RECENT_RANK
+        {
+            return Err(cursor::invalid_cursor());
+        }
+
+        let query = ConversationQuery {
+            user_id,
+            archived: params.archived,
+            _type: params._type,
+            unread_only: params.unread_only,
+            search: params.q.map(|q| q.trim().to_string()).filter(|q| !q.is_empty()),
+            cursor,
+        };
+
         let pool = self.conversation_repo.get_pool();
-        let conversations = self
+        let mut conversations = self
             .conversation_repo
-            .find_all_conversation_with_details_by_user(&user_id, pool)
+            .find_all_conversation_with_details_by_user(&query, params.limit, pool)
             .await?;
 
+        let next_cursor = if conversations.len() > params.limit as usize {
+            conversations.pop();
+            conversations.last().map(|conv| {
+                let cursor = match conv.preferences.pinned_at {
+                    Some(pinned_at) => Cursor::ranked(PINNED_RANK, pinned_at, conv.conversation_id),
+                    None => Cursor::ranked(RECENT_RANK, conv.last_activity_at(), conv.conversation_id),
+                };
+                cursor.encode()
+            })
+        } else {
+            None
+        };
+
         let conversation_ids: Vec<Uuid> = conversations
             .iter()
             .map(|conv_row| conv_row.conversation_id)
@@ -192,6 +252,8 @@ where
                     display_name: p.display_name,
                     avatar_url: p.avatar_url,
                     unread_count: p.unread_count,
+                    mention_count: p.mention_count,
+                    role: p.role,
                     joined_at: p.joined_at,
                 })
                 .collect();
@@ -199,57 +261,264 @@ where
             ConversationDetail {
                 conversation_id: conv.conversation_id,
                 _type: conv._type,
+                message_ttl_seconds: conv.message_ttl_seconds,
                 group_info: conv.group_info,
                 last_message: conv.last_message,
                 participants,
+                preferences: Some(conv.preferences),
                 created_at: conv.created_at,
                 updated_at: conv.updated_at,
             }
         });
 
-        Ok(res.collect())
+        Ok((res.collect(), next_cursor))
     }
 
     /// Lấy messages của conversation với cursor-based pagination
+    ///
+    /// Hỗ trợ `before` (cũ hơn), `after` (mới hơn) và `around` (cửa sổ quanh một tin nhắn).
+    /// Tin nhắn luôn trả về theo thứ tự cũ -> mới.
     pub async fn get_message(
         &self,
         conversation_id: Uuid,
-        limit: i32,
-        cursor: Option<String>,
-    ) -> Result<(Vec<MessageEntity>, Option<String>), error::SystemError> {
-        let created_at = match cursor {
-            Some(c) => Some(
-                chrono::DateTime::parse_from_rfc3339(&c)
-                    .map_err(|_| {
-                        error::SystemError::bad_request(
-                            "Định dạng danh sách phân trang (cursor) không hợp lệ",
-                        )
-                    })?
-                    .with_timezone(&chrono::Utc),
+        params: MessageHistoryRequest,
+    ) -> Result<GetMessageResponse, error::SystemError> {
+        let modes = [params.before.is_some(), params.after.is_some(), params.around.is_some()];
+        if modes.iter().filter(|m| **m).count() > 1 {
+            return Err(error::SystemError::bad_request(
+                "Chỉ được dùng một trong các tham số before, after, around",
+            ));
+        }
+
+        let limit = params.limit;
+        let pool = self.message_repo.get_pool();
+
+        if let Some(message_id) = params.around {
+            return self.get_message_around(conversation_id, message_id, limit).await;
+        }
+
+        let (direction, cursor) = match (params.before, params.after) {
+            (_, Some(after)) => (PageDirection::After, Some(Cursor::decode(&after)?)),
+            (before, None) => (
+                PageDirection::Before,
+                Cursor::parse(before.as_deref())?,
             ),
-            None => None,
         };
+        let has_cursor = cursor.is_some();
 
         let mut messages = self
             .message_repo
             .find_by_query(
                 &MessageQuery {
                     conversation_id,
-                    created_at,
+                    cursor,
+                    direction,
                 },
                 limit,
-                self.message_repo.get_pool(),
+                pool,
+            )
+            .await?;
+
+        let has_more = messages.len() > limit as usize;
+        if has_more {
+            messages.pop();
+        }
+
+        // Trang `before` đang có thứ tự mới -> cũ
+        if direction == PageDirection::Before {
+            messages.reverse();
+        }
+
+        let first = messages.first().map(|m| Cursor::new(m.created_at, m.id));
+        let last = messages.last().map(|m| Cursor::new(m.created_at, m.id));
+
+        let (older, newer) = match direction {
+            PageDirection::Before => (has_more.then_some(first).flatten(), last.filter(|_| has_cursor)),
+            PageDirection::After => (first, has_more.then_some(last).flatten()),
+        };
+
+        Ok(GetMessageResponse {
+            messages: self.attach_reactions(messages).await?,
+            cursor: older.map(|c| c.encode()),
+            newer_cursor: newer.map(|c| c.encode()),
+        })
+    }
+
+    /// Helper: Cửa sổ tin nhắn quanh `message_id` (dùng khi nhảy tới kết quả tìm kiếm, reply, tin ghim)
+    async fn get_message_around(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        limit: i32,
+    ) -> Result<GetMessageResponse, error::SystemError> {
+        let pool = self.message_repo.get_pool();
+
+        let anchor = self
+            .message_repo
+            .find_by_id(&message_id, pool)
+            .await?
+            .filter(|m| m.conversation_id == conversation_id && m.deleted_at.is_none())
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy tin nhắn"))?;
+        let anchor_cursor = Cursor::new(anchor.created_at, anchor.id);
+
+        // Chia đều phần còn lại (trừ chính tin nhắn anchor) cho hai phía
+        let older_limit = (limit - 1) / 2;
+        let newer_limit = limit - 1 - older_limit;
+
+        let mut older = self
+            .message_repo
+            .find_by_query(
+                &MessageQuery {
+                    conversation_id,
+                    cursor: Some(anchor_cursor.clone()),
+                    direction: PageDirection::Before,
+                },
+                older_limit,
+                pool,
+            )
+            .await?;
+        let mut newer = self
+            .message_repo
+            .find_by_query(
+                &MessageQuery {
+                    conversation_id,
+                    cursor: Some(anchor_cursor),
+                    direction: PageDirection::After,
+                },
+                newer_limit,
+                pool,
             )
             .await?;
 
-        let next_cursor = if messages.len() > limit as usize {
-            messages.pop().map(|m| m.created_at)
+        let has_older = older.len() > older_limit as usize;
+        older.truncate(older_limit as usize);
+        let has_newer = newer.len() > newer_limit as usize;
+        newer.truncate(newer_limit as usize);
+
+        older.reverse();
+        let mut messages = older;
+        messages.push(anchor);
+        messages.append(&mut newer);
+
+        let cursor = has_older
+            .then(|| messages.first().map(|m| Cursor::new(m.created_at, m.id).encode()))
+            .flatten();
+        let newer_cursor = has_newer
+            .then(|| messages.last().map(|m| Cursor::new(m.created_at, m.id).encode()))
+            .flatten();
+
+        Ok(GetMessageResponse {
+            messages: self.attach_reactions(messages).await?,
+            cursor,
+            newer_cursor,
+        })
+    }
+
+    /// Lấy thread của một tin nhắn gốc: root + các reply (cũ -> mới, phân trang cursor)
+    pub async fn get_thread(
+        &self,
+        conversation_id: Uuid,
+        root_message_id: Uuid,
+        limit: i32,
+        cursor: Option<String>,
+    ) -> Result<ThreadResponse, error::SystemError> {
+        let after = Cursor::parse(cursor.as_deref())?;
+
+        let pool = self.message_repo.get_pool();
+
+        let root = self
+            .message_repo
+            .find_by_id(&root_message_id, pool)
+            .await?
+            .filter(|message| message.conversation_id == conversation_id)
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy tin nhắn"))?;
+
+        let mut replies = self
+            .message_repo
+            .find_thread_replies(&root_message_id, after.as_ref(), limit, pool)
+            .await?;
+
+        let next_cursor = if replies.len() > limit as usize {
+            replies.pop();
+            replies.last().map(|m| Cursor::new(m.created_at, m.id).encode())
         } else {
             None
         };
 
-        messages.reverse();
-        Ok((messages, next_cursor.map(|c| c.to_rfc3339())))
+        let mut messages = self.attach_reactions(vec![root]).await?;
+        let root = messages
+            .pop()
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy tin nhắn"))?;
+        let replies = self.attach_reactions(replies).await?;
+
+        Ok(ThreadResponse {
+            root,
+            replies,
+            cursor: next_cursor,
+        })
+    }
+
+    /// Helper: Gom reactions (và danh thiếp của tin nhắn contact) cho cả trang bằng một truy vấn để tránh N+1
+    async fn attach_reactions(
+        &self,
+        mut messages: Vec<MessageEntity>,
+    ) -> Result<Vec<MessageWithReactions>, error::SystemError> {
+        if messages.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.attach_contact_cards(&mut messages).await?;
+
+        let message_ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+        let mut reaction_map = self
+            .message_repo
+            .get_reaction_summaries(&message_ids, self.message_repo.get_pool())
+            .await?
+            .into_iter()
+            .fold(
+                HashMap::<Uuid, Vec<ReactionSummary>>::new(),
+                |mut acc, row| {
+                    acc.entry(row.message_id)
+                        .or_default()
+                        .push(ReactionSummary::from(row));
+                    acc
+                },
+            );
+
+        Ok(messages
+            .into_iter()
+            .map(|message| MessageWithReactions {
+                reactions: reaction_map.remove(&message.id).unwrap_or_default(),
+                message,
+            })
+            .collect())
+    }
+
+    /// Helper: Resolve `contact_user_id` thành `ContactCard` cho các tin nhắn danh thiếp
+    async fn attach_contact_cards(
+        &self,
+        messages: &mut [MessageEntity],
+    ) -> Result<(), error::SystemError> {
+        let contact_ids: Vec<Uuid> = messages.iter().filter_map(|m| m.contact_user_id).collect();
+        if contact_ids.is_empty() {
+            return Ok(());
+        }
+
+        let cards: HashMap<Uuid, ContactCard> = self
+            .message_repo
+            .find_contact_cards(&contact_ids, self.message_repo.get_pool())
+            .await?
+            .into_iter()
+            .map(|card| (card.id, card))
+            .collect();
+
+        for message in messages.iter_mut() {
+            message.contact = message
+                .contact_user_id
+                .and_then(|id| cards.get(&id).cloned());
+        }
+
+        Ok(())
     }
 
     /// Lấy participants của conversation
@@ -380,62 +649,97 @@ where
         Ok(())
     }
 
-    /// Cập nhật thông tin nhóm (tên, avatar) - Chỉ trưởng nhóm mới có quyền
-    pub async fn update_group_info(
+    /// Danh sách thành viên nhóm đã đọc tới một tin nhắn
+    ///
+    /// `last_seen_at` là lần đọc gần nhất của thành viên trong nhóm, không phải lúc xem riêng tin này.
+    pub async fn get_seen_by(
         &self,
         conversation_id: Uuid,
         user_id: Uuid,
-        name: Option<String>,
-        avatar_url: Option<Option<String>>,
-    ) -> Result<(), error::SystemError> {
-        let mut tx = self.conversation_repo.get_pool().begin().await?;
+        message_id: Uuid,
+    ) -> Result<Vec<MessageSeenBy>, error::SystemError> {
+        let pool = self.conversation_repo.get_pool();
 
-        // 1. Kiểm tra conversation-type và membership
-        let (conv, is_member) = self
+        let (conversation, is_member) = self
             .conversation_repo
-            .get_conversation_and_check_membership(&conversation_id, &user_id, tx.as_mut())
+            .get_conversation_and_check_membership(&conversation_id, &user_id, pool)
             .await?;
 
-        let conv = conv.ok_or_else(|| error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"))?;
-        if conv._type != ConversationType::Group {
-            return Err(error::SystemError::bad_request("Chỉ có thể cập nhật thông tin cho nhóm"));
-        }
+        let conversation = conversation
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"))?;
+
         if !is_member {
-            return Err(error::SystemError::forbidden("Bạn không phải thành viên của nhóm này"));
+            return Err(error::SystemError::forbidden(
+                "Bạn không phải thành viên của cuộc trò chuyện này",
+            ));
         }
 
-        // 2. Kiểm tra quyền trưởng nhóm (creator)
-        let creator_id = self
-            .conversation_repo
-            .get_group_creator(&conversation_id, tx.as_mut())
+        if conversation._type != ConversationType::Group {
+            return Err(error::SystemError::bad_request("Thao tác này chỉ áp dụng cho nhóm"));
+        }
+
+        self.message_repo
+            .find_by_id(&message_id, pool)
             .await?
-            .ok_or_else(|| error::SystemError::internal_error("Lỗi dữ liệu nhóm: thiếu thông tin người tạo"))?;
+            .filter(|message| message.conversation_id == conversation_id)
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy tin nhắn"))?;
 
-        if creator_id != user_id {
-            return Err(error::SystemError::forbidden("Chỉ trưởng nhóm mới có quyền thay đổi thông tin"));
-        }
+        self.participant_repo
+            .find_seen_by(&conversation_id, &message_id, pool)
+            .await
+    }
 
-        // 3. Thực hiện cập nhật
+    /// Ghim tin nhắn trong conversation
+    pub async fn pin_message(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.ensure_can_pin(conversation_id, user_id, &mut tx).await?;
+
+        self.message_repo
+            .find_by_id(&message_id, tx.as_mut())
+            .await?
+            .filter(|message| message.conversation_id == conversation_id)
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy tin nhắn"))?;
+
+        // Dưới READ COMMITTED hai request song song không thấy bản ghim của nhau,
+        // nên phải khóa conversation trước khi ghim và đếm
         self.conversation_repo
-            .update_group_info(
-                &conversation_id,
-                name.as_deref(),
-                avatar_url.as_ref().map(|opt| opt.as_deref()),
-                tx.as_mut(),
-            )
+            .lock_conversation(&conversation_id, tx.as_mut())
             .await?;
 
-        self.conversation_repo.update_timestamp(&conversation_id, tx.as_mut()).await?;
+        let pinned = self
+            .conversation_repo
+            .pin_message(&conversation_id, &message_id, &user_id, tx.as_mut())
+            .await?;
+
+        if !pinned {
+            return Err(error::SystemError::bad_request("Tin nhắn đã được ghim"));
+        }
+
+        let pin_count = self
+            .conversation_repo
+            .count_pinned_messages(&conversation_id, tx.as_mut())
+            .await?;
+
+        if pin_count > MAX_PINNED_MESSAGES {
+            return Err(error::SystemError::bad_request(format!(
+                "Chỉ có thể ghim tối đa {MAX_PINNED_MESSAGES} tin nhắn"
+            )));
+        }
 
         tx.commit().await?;
 
-        // 4. Broadcast WS tới tất cả thành viên trong nhóm
         self.ws_server.broadcast_to_room(
             conversation_id,
-            &ServerMessage::GroupUpdated {
+            &ServerMessage::MessagePinned {
                 conversation_id,
-                name,
-                avatar_url,
+                message_id,
+                pinned_by: user_id,
             },
             None,
         );
@@ -443,115 +747,955 @@ where
         Ok(())
     }
 
-    /// Thêm thành viên vào nhóm (Chỉ trưởng nhóm mới có quyền)
-    pub async fn add_member(
+    /// Bỏ ghim tin nhắn trong conversation
+    pub async fn unpin_message(
         &self,
         conversation_id: Uuid,
-        requester_id: Uuid,
-        new_user_id: Uuid,
-        is_friend: bool,
+        user_id: Uuid,
+        message_id: Uuid,
     ) -> Result<(), error::SystemError> {
-        if !is_friend {
-            return Err(error::SystemError::forbidden("Chỉ có thể thêm bạn bè vào nhóm"));
-        }
-
         let mut tx = self.conversation_repo.get_pool().begin().await?;
 
-        // 1. Kiểm tra quyền trưởng nhóm
-        let creator_id = self
+        self.ensure_can_pin(conversation_id, user_id, &mut tx).await?;
+
+        let unpinned = self
             .conversation_repo
-            .get_group_creator(&conversation_id, tx.as_mut())
-            .await?
-            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy nhóm hoặc không phải là nhóm"))?;
+            .unpin_message(&conversation_id, &message_id, tx.as_mut())
+            .await?;
 
-        if creator_id != requester_id {
-            return Err(error::SystemError::forbidden("Chỉ trưởng nhóm mới có quyền thêm thành viên"));
+        if !unpinned {
+            return Err(error::SystemError::not_found("Tin nhắn chưa được ghim"));
         }
 
-        // 2. Thêm thành viên (UPSERT)
-        self.conversation_repo
-            .add_participant(&conversation_id, &new_user_id, tx.as_mut())
-            .await?;
+        tx.commit().await?;
 
-        self.conversation_repo.update_timestamp(&conversation_id, tx.as_mut()).await?;
+        self.ws_server.broadcast_to_room(
+            conversation_id,
+            &ServerMessage::MessageUnpinned {
+                conversation_id,
+                message_id,
+                unpinned_by: user_id,
+            },
+            None,
+        );
 
-        // Lấy thông tin user mới để broadcast
-        let user_info = sqlx::query_as::<_, ParticipantRow>(
-            "SELECT display_name, avatar_url FROM users WHERE id = $1"
-        )
-        .bind(new_user_id)
-        .fetch_one(tx.as_mut())
-        .await
-        .map_err(|_| error::SystemError::not_found("Không tìm thấy người dùng được thêm"))?;
+        Ok(())
+    }
 
-        tx.commit().await?;
+    /// Danh sách tin nhắn đang được ghim
+    pub async fn get_pinned_messages(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<PinnedMessage>, error::SystemError> {
+        let pool = self.conversation_repo.get_pool();
 
-        // 3. Broadcast WS
-        let conversation_detail = self
+        let (conv, is_member) = self
             .conversation_repo
-            .find_one_conversation_detail(&conversation_id)
+            .get_conversation_and_check_membership(&conversation_id, &user_id, pool)
             .await?;
 
-        if let Some(conversation_detail) = conversation_detail {
-            let conversation_json = serde_json::to_value(&conversation_detail).map_err(|e| {
-                error::SystemError::internal_error(format!(
-                    "Lỗi khi xử lý dữ liệu cuộc trò chuyện: {}",
-                    e
-                ))
-            })?;
+        if conv.is_none() {
+            return Err(error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"));
+        }
+        if !is_member {
+            return Err(error::SystemError::forbidden(
+                "Bạn không phải thành viên của cuộc trò chuyện này",
+            ));
+        }
 
-            self.ws_server.send_to_users(
-                &[new_user_id],
-                &ServerMessage::NewGroup {
-                    conversation: conversation_json,
-                },
-            );
+        self.conversation_repo
+            .find_pinned_messages(&conversation_id, pool)
+            .await
+    }
+
+    /// Cập nhật tuỳ chỉnh riêng của user với conversation (lưu trữ, tắt thông báo, ghim)
+    pub async fn update_preferences(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        payload: UpdateConversationPreferencesRequest,
+    ) -> Result<ConversationPreferences, error::SystemError> {
+        if payload.archived.is_none() && payload.muted_until.is_none() && payload.pinned.is_none() {
+            return Err(error::SystemError::bad_request("Không có thông tin nào để cập nhật"));
         }
 
-        self.ws_server.broadcast_to_room(
-            conversation_id,
-            &ServerMessage::MemberAdded {
-                conversation_id,
-                user_id: new_user_id,
-                display_name: user_info.display_name,
-                avatar_url: user_info.avatar_url,
-            },
-            None,
-        );
+        if let Some(Some(muted_until)) = payload.muted_until
+            && muted_until <= chrono::Utc::now()
+        {
+            return Err(error::SystemError::bad_request(
+                "Thời điểm tắt thông báo phải ở tương lai",
+            ));
+        }
 
-        Ok(())
+        self.participant_repo
+            .update_preferences(
+                &conversation_id,
+                &user_id,
+                payload.archived,
+                payload.muted_until,
+                payload.pinned,
+                self.conversation_repo.get_pool(),
+            )
+            .await?
+            .ok_or_else(|| error::SystemError::forbidden("Bạn không phải thành viên của cuộc trò chuyện này"))
     }
 
-    /// Xóa thành viên hoặc tự rời nhóm
-    pub async fn remove_member(
+    /// Helper: Kiểm tra quyền ghim/bỏ ghim
+    ///
+    /// Nhóm: owner/admin. Chat 1-1: cả hai người.
+    async fn ensure_can_pin(
         &self,
         conversation_id: Uuid,
-        requester_id: Uuid,
-        target_user_id: Uuid,
+        user_id: Uuid,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<(), error::SystemError> {
-        let mut tx = self.conversation_repo.get_pool().begin().await?;
+        let (conv, is_member) = self
+            .conversation_repo
+            .get_conversation_and_check_membership(&conversation_id, &user_id, tx.as_mut())
+            .await?;
 
-        // 1. Kiểm tra conversation-type
-        let conv = self.conversation_repo.find_by_id(&conversation_id, tx.as_mut()).await?
-            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"))?;
+        let conv = conv.ok_or_else(|| error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"))?;
+        if !is_member {
+            return Err(error::SystemError::forbidden("Bạn không phải thành viên của cuộc trò chuyện này"));
+        }
 
-        if conv._type != ConversationType::Group {
-            return Err(error::SystemError::bad_request("Chỉ có thể rời nhóm hoặc xóa thành viên khỏi nhóm"));
+        if conv._type == ConversationType::Group {
+            self.require_group_role(
+                conversation_id,
+                user_id,
+                ParticipantRole::Admin,
+                "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền ghim tin nhắn",
+                tx,
+            )
+            .await?;
         }
 
-        // 2. Kiểm tra quyền
-        let creator_id = self
+        Ok(())
+    }
+
+    /// Helper: Kiểm tra quyền trong nhóm - điểm kiểm tra duy nhất cho mọi thao tác quản trị
+    ///
+    /// Trả về vai trò hiện tại của user nếu đạt `required` trở lên. Dòng participant của user
+    /// bị khóa tới hết transaction, nên vai trò không thể đổi giữa lúc kiểm tra và lúc ghi.
+    async fn require_group_role(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        required: ParticipantRole,
+        denied_message: &'static str,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<ParticipantRole, error::SystemError> {
+        let role = self
             .conversation_repo
-            .get_group_creator(&conversation_id, tx.as_mut())
+            .lock_participant_role(&conversation_id, &user_id, tx.as_mut())
             .await?
-            .ok_or_else(|| error::SystemError::internal_error("Lỗi dữ liệu nhóm"))?;
+            .ok_or_else(|| error::SystemError::forbidden("Bạn không phải thành viên của nhóm này"))?;
 
-        if requester_id != target_user_id && requester_id != creator_id {
-            return Err(error::SystemError::forbidden("Bạn không có quyền thực hiện hành động này"));
+        if role < required {
+            return Err(error::SystemError::forbidden(denied_message));
         }
 
-        if target_user_id == creator_id {
-            return Err(error::SystemError::bad_request("Không thể xóa trưởng nhóm."));
+        Ok(role)
+    }
+
+    /// Người có vai trò `actor` có được xóa người có vai trò `target` khỏi nhóm không
+    ///
+    /// Chỉ được tác động lên người có vai trò thấp hơn mình; owner không thể bị xóa.
+    pub(crate) fn can_remove_member(actor: ParticipantRole, target: ParticipantRole) -> bool {
+        actor >= ParticipantRole::Admin && actor > target
+    }
+
+    /// Helper: Lấy nhóm và đảm bảo conversation là group
+    async fn require_group(
+        &self,
+        conversation_id: Uuid,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<ConversationEntity, error::SystemError> {
+        let conv = self
+            .conversation_repo
+            .find_by_id(&conversation_id, tx.as_mut())
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"))?;
+
+        if conv._type != ConversationType::Group {
+            return Err(error::SystemError::bad_request("Thao tác này chỉ áp dụng cho nhóm"));
+        }
+
+        Ok(conv)
+    }
+
+    /// Cập nhật thông tin nhóm (tên, avatar) - Owner/admin mới có quyền
+    pub async fn update_group_info(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        name: Option<String>,
+        avatar_url: Option<Option<String>>,
+    ) -> Result<(), error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        // 1. Kiểm tra conversation-type và membership
+        let (conv, is_member) = self
+            .conversation_repo
+            .get_conversation_and_check_membership(&conversation_id, &user_id, tx.as_mut())
+            .await?;
+
+        let conv = conv.ok_or_else(|| error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"))?;
+        if conv._type != ConversationType::Group {
+            return Err(error::SystemError::bad_request("Chỉ có thể cập nhật thông tin cho nhóm"));
+        }
+        if !is_member {
+            return Err(error::SystemError::forbidden("Bạn không phải thành viên của nhóm này"));
+        }
+
+        // 2. Kiểm tra quyền owner/admin
+        self.require_group_role(
+            conversation_id,
+            user_id,
+            ParticipantRole::Admin,
+            "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền thay đổi thông tin",
+            &mut tx,
+        )
+        .await?;
+
+        // 3. Thực hiện cập nhật
+        self.conversation_repo
+            .update_group_info(
+                &conversation_id,
+                name.as_deref(),
+                avatar_url.as_ref().map(|opt| opt.as_deref()),
+                tx.as_mut(),
+            )
+            .await?;
+
+        self.conversation_repo.update_timestamp(&conversation_id, tx.as_mut()).await?;
+
+        tx.commit().await?;
+
+        // 4. Broadcast WS tới tất cả thành viên trong nhóm
+        self.ws_server.broadcast_to_room(
+            conversation_id,
+            &ServerMessage::GroupUpdated {
+                conversation_id,
+                name,
+                avatar_url,
+            },
+            None,
+        );
+
+        Ok(())
+    }
+
+    /// Đặt TTL tin nhắn tự hủy cho conversation
+    ///
+    /// Nhóm: owner/admin. Chat 1-1: bất kỳ ai trong hai người.
+    /// TTL chỉ áp dụng cho tin nhắn gửi sau thời điểm thay đổi.
+    /// System message thông báo được ghi cùng transaction với thay đổi TTL.
+    pub async fn update_message_ttl<MM, MC, MP, ML>(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        message_ttl_seconds: Option<i32>,
+        message_service: &MessageService<MM, MC, MP, ML>,
+    ) -> Result<(), error::SystemError>
+    where
+        MM: MessageRepository + Send + Sync,
+        MC: ConversationRepository + Send + Sync,
+        MP: ParticipantRepository + Send + Sync,
+        ML: LastMessageRepository + Send + Sync,
+    {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let (conv, is_member) = self
+            .conversation_repo
+            .get_conversation_and_check_membership(&conversation_id, &user_id, tx.as_mut())
+            .await?;
+
+        let conv = conv.ok_or_else(|| error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"))?;
+        if !is_member {
+            return Err(error::SystemError::forbidden("Bạn không phải thành viên của cuộc trò chuyện này"));
+        }
+
+        if conv._type == ConversationType::Group {
+            self.require_group_role(
+                conversation_id,
+                user_id,
+                ParticipantRole::Admin,
+                "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền thay đổi tin nhắn tự hủy",
+                &mut tx,
+            )
+            .await?;
+        }
+
+        if conv.message_ttl_seconds == message_ttl_seconds {
+            return Err(error::SystemError::bad_request("TTL không thay đổi"));
+        }
+
+        self.conversation_repo
+            .update_message_ttl(&conversation_id, message_ttl_seconds, tx.as_mut())
+            .await?;
+
+        let pending = message_service
+            .insert_system_message(
+                user_id,
+                conversation_id,
+                Self::describe_message_ttl(message_ttl_seconds),
+                &mut tx,
+            )
+            .await?;
+
+        tx.commit().await?;
+
+        message_service.publish_message(pending).await;
+
+        Ok(())
+    }
+
+    /// Nội dung system message thông báo thay đổi TTL
+    pub(crate) fn describe_message_ttl(message_ttl_seconds: Option<i32>) -> String {
+        let Some(secs) = message_ttl_seconds else {
+            return "Đã tắt tin nhắn tự hủy".to_string();
+        };
+
+        let duration = match secs {
+            s if s % 86_400 == 0 => format!("{} ngày", s / 86_400),
+            s if s % 3_600 == 0 => format!("{} giờ", s / 3_600),
+            s if s % 60 == 0 => format!("{} phút", s / 60),
+            s => format!("{s} giây"),
+        };
+
+        format!("Tin nhắn mới sẽ tự hủy sau {duration}")
+    }
+
+    /// Đổi vai trò thành viên giữa admin và member (chỉ owner)
+    pub async fn change_member_role(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+        target_user_id: Uuid,
+        new_role: ParticipantRole,
+    ) -> Result<(), error::SystemError> {
+        if new_role == ParticipantRole::Owner {
+            return Err(error::SystemError::bad_request(
+                "Dùng chức năng chuyển quyền trưởng nhóm để đặt owner",
+            ));
+        }
+
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.require_group(conversation_id, &mut tx).await?;
+        self.require_group_role(
+            conversation_id,
+            requester_id,
+            ParticipantRole::Owner,
+            "Chỉ trưởng nhóm mới có quyền phân quyền quản trị viên",
+            &mut tx,
+        )
+        .await?;
+
+        let current_role = self
+            .conversation_repo
+            .get_participant_role(&conversation_id, &target_user_id, tx.as_mut())
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Người dùng không phải thành viên của nhóm"))?;
+
+        if current_role == ParticipantRole::Owner {
+            return Err(error::SystemError::bad_request("Không thể đổi vai trò của trưởng nhóm"));
+        }
+        if current_role == new_role {
+            return Err(error::SystemError::bad_request("Thành viên đã có vai trò này"));
+        }
+
+        self.conversation_repo
+            .update_participant_role(&conversation_id, &target_user_id, new_role, tx.as_mut())
+            .await?;
+
+        tx.commit().await?;
+
+        self.notify_role_changed(conversation_id, target_user_id, new_role);
+
+        Ok(())
+    }
+
+    /// Chuyển quyền trưởng nhóm; owner cũ trở thành admin
+    pub async fn transfer_ownership(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+        new_owner_id: Uuid,
+    ) -> Result<(), error::SystemError> {
+        if requester_id == new_owner_id {
+            return Err(error::SystemError::bad_request("Bạn đã là trưởng nhóm"));
+        }
+
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.require_group(conversation_id, &mut tx).await?;
+        self.require_group_role(
+            conversation_id,
+            requester_id,
+            ParticipantRole::Owner,
+            "Chỉ trưởng nhóm mới có quyền chuyển quyền trưởng nhóm",
+            &mut tx,
+        )
+        .await?;
+
+        let promoted = self
+            .conversation_repo
+            .update_participant_role(&conversation_id, &new_owner_id, ParticipantRole::Owner, tx.as_mut())
+            .await?;
+
+        if !promoted {
+            return Err(error::SystemError::not_found("Người dùng không phải thành viên của nhóm"));
+        }
+
+        self.conversation_repo
+            .update_participant_role(&conversation_id, &requester_id, ParticipantRole::Admin, tx.as_mut())
+            .await?;
+
+        tx.commit().await?;
+
+        self.notify_role_changed(conversation_id, new_owner_id, ParticipantRole::Owner);
+        self.notify_role_changed(conversation_id, requester_id, ParticipantRole::Admin);
+
+        Ok(())
+    }
+
+    /// Helper: Thông báo vai trò thành viên thay đổi tới cả nhóm
+    fn notify_role_changed(&self, conversation_id: Uuid, user_id: Uuid, role: ParticipantRole) {
+        self.ws_server.broadcast_to_room(
+            conversation_id,
+            &ServerMessage::MemberRoleChanged {
+                conversation_id,
+                user_id,
+                role,
+            },
+            None,
+        );
+    }
+
+    /// Thêm thành viên vào nhóm (owner/admin)
+    pub async fn add_member(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+        new_user_id: Uuid,
+        is_friend: bool,
+    ) -> Result<(), error::SystemError> {
+        if !is_friend {
+            return Err(error::SystemError::forbidden("Chỉ có thể thêm bạn bè vào nhóm"));
+        }
+
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        // 1. Kiểm tra quyền owner/admin
+        self.require_group(conversation_id, &mut tx).await?;
+        self.require_group_role(
+            conversation_id,
+            requester_id,
+            ParticipantRole::Admin,
+            "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền thêm thành viên",
+            &mut tx,
+        )
+        .await?;
+
+        // 2. Thêm thành viên (UPSERT)
+        let (display_name, avatar_url) = self
+            .admit_member(conversation_id, new_user_id, &mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        // 3. Broadcast WS
+        self.notify_member_added(conversation_id, new_user_id, display_name, avatar_url)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Tạo link mời nhóm (owner/admin)
+    pub async fn create_invite_link(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+        expires_in_seconds: Option<i64>,
+        max_uses: Option<i32>,
+    ) -> Result<GroupInviteLinkEntity, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.require_group(conversation_id, &mut tx).await?;
+        self.require_group_role(
+            conversation_id,
+            requester_id,
+            ParticipantRole::Admin,
+            "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền tạo link mời",
+            &mut tx,
+        )
+        .await?;
+
+        let expires_at =
+            expires_in_seconds.map(|secs| chrono::Utc::now() + chrono::Duration::seconds(secs));
+        let token = utils::generate_random_token(INVITE_TOKEN_LENGTH);
+
+        let invite = self
+            .conversation_repo
+            .create_invite_link(
+                &conversation_id,
+                &requester_id,
+                &token,
+                expires_at,
+                max_uses,
+                tx.as_mut(),
+            )
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(invite)
+    }
+
+    /// Danh sách link mời đang hoạt động của nhóm (owner/admin)
+    pub async fn get_invite_links(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+    ) -> Result<Vec<GroupInviteLinkEntity>, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.require_group_role(
+            conversation_id,
+            requester_id,
+            ParticipantRole::Admin,
+            "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền xem link mời",
+            &mut tx,
+        )
+        .await?;
+
+        let invites = self
+            .conversation_repo
+            .find_invite_links(&conversation_id, tx.as_mut())
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(invites)
+    }
+
+    /// Thu hồi link mời (owner/admin)
+    pub async fn revoke_invite_link(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+        invite_id: Uuid,
+    ) -> Result<(), error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.require_group_role(
+            conversation_id,
+            requester_id,
+            ParticipantRole::Admin,
+            "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền thu hồi link mời",
+            &mut tx,
+        )
+        .await?;
+
+        let revoked = self
+            .conversation_repo
+            .revoke_invite_link(&conversation_id, &invite_id, tx.as_mut())
+            .await?;
+
+        if !revoked {
+            return Err(error::SystemError::not_found("Không tìm thấy link mời"));
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Tham gia nhóm bằng link mời, trả về thông tin nhóm vừa tham gia
+    ///
+    /// Nhóm bật phê duyệt không nhận thành viên qua link; user phải gửi yêu cầu tham gia.
+    /// System message thông báo được ghi cùng transaction với việc thêm thành viên.
+    pub async fn join_by_invite<MM, MC, MP, ML>(
+        &self,
+        token: &str,
+        user_id: Uuid,
+        message_service: &MessageService<MM, MC, MP, ML>,
+    ) -> Result<ConversationDetail, error::SystemError>
+    where
+        MM: MessageRepository + Send + Sync,
+        MC: ConversationRepository + Send + Sync,
+        MP: ParticipantRepository + Send + Sync,
+        ML: LastMessageRepository + Send + Sync,
+    {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        // 1. Link phải còn hạn, chưa bị thu hồi và còn lượt (khóa dòng tới khi commit)
+        let invite = self
+            .conversation_repo
+            .find_active_invite_link(token, tx.as_mut())
+            .await?
+            .ok_or_else(|| {
+                error::SystemError::not_found("Link mời không tồn tại, đã hết hạn hoặc hết lượt dùng")
+            })?;
+        let conversation_id = invite.conversation_id;
+
+        // 2. Đã là thành viên thì không tốn lượt dùng
+        let current_role = self
+            .conversation_repo
+            .get_participant_role(&conversation_id, &user_id, tx.as_mut())
+            .await?;
+
+        if current_role.is_some() {
+            return Err(error::SystemError::bad_request("Bạn đã là thành viên của nhóm này"));
+        }
+
+        // 3. Link mời không được bỏ qua chế độ phê duyệt
+        let join_approval_required = self
+            .conversation_repo
+            .get_join_approval_required(&conversation_id, tx.as_mut())
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy nhóm"))?;
+
+        if join_approval_required {
+            return Err(error::SystemError::forbidden(
+                "Nhóm này cần được duyệt, vui lòng gửi yêu cầu tham gia",
+            ));
+        }
+
+        // 4. Thêm thành viên qua cùng luồng với add_member
+        self.conversation_repo
+            .increment_invite_use(&invite.id, tx.as_mut())
+            .await?;
+
+        let (display_name, avatar_url) =
+            self.admit_member(conversation_id, user_id, &mut tx).await?;
+
+        let pending = message_service
+            .insert_system_message(
+                user_id,
+                conversation_id,
+                "Đã tham gia nhóm bằng link mời".to_string(),
+                &mut tx,
+            )
+            .await?;
+
+        tx.commit().await?;
+
+        // 5. Broadcast WS: thành viên mới nhận nhóm trước rồi mới tới system message
+        let conversation = self
+            .notify_member_added(conversation_id, user_id, display_name, avatar_url)
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"))?;
+
+        message_service.publish_message(pending).await;
+
+        Ok(conversation)
+    }
+
+    /// Bật/tắt chế độ phê duyệt thành viên mới (owner/admin)
+    pub async fn update_join_approval(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+        join_approval_required: bool,
+    ) -> Result<(), error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.require_group(conversation_id, &mut tx).await?;
+        self.require_group_role(
+            conversation_id,
+            requester_id,
+            ParticipantRole::Admin,
+            "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền thay đổi chế độ phê duyệt",
+            &mut tx,
+        )
+        .await?;
+
+        self.conversation_repo
+            .update_join_approval_required(&conversation_id, join_approval_required, tx.as_mut())
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Gửi yêu cầu tham gia nhóm (chỉ với nhóm bật phê duyệt) và báo cho owner/admin
+    pub async fn submit_join_request(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        message: Option<String>,
+    ) -> Result<GroupJoinRequestEntity, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let join_approval_required = self
+            .conversation_repo
+            .get_join_approval_required(&conversation_id, tx.as_mut())
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy nhóm"))?;
+
+        if !join_approval_required {
+            return Err(error::SystemError::forbidden("Nhóm này không nhận yêu cầu tham gia"));
+        }
+
+        let current_role = self
+            .conversation_repo
+            .get_participant_role(&conversation_id, &user_id, tx.as_mut())
+            .await?;
+
+        if current_role.is_some() {
+            return Err(error::SystemError::bad_request("Bạn đã là thành viên của nhóm này"));
+        }
+
+        let request = self
+            .conversation_repo
+            .create_join_request(&conversation_id, &user_id, message.as_deref(), tx.as_mut())
+            .await?
+            .ok_or_else(|| {
+                error::SystemError::bad_request("Bạn đã gửi yêu cầu tham gia nhóm này, vui lòng chờ duyệt")
+            })?;
+
+        let admin_ids = self
+            .conversation_repo
+            .find_admin_ids(&conversation_id, tx.as_mut())
+            .await?;
+        let (display_name, avatar_url) = Self::find_member_profile(user_id, &mut tx).await?;
+
+        tx.commit().await?;
+
+        self.ws_server.send_to_users(
+            &admin_ids,
+            &ServerMessage::JoinRequestReceived {
+                conversation_id,
+                request_id: request.id,
+                user_id,
+                display_name,
+                avatar_url,
+                message: request.message.clone(),
+            },
+        );
+
+        Ok(request)
+    }
+
+    /// Danh sách yêu cầu tham gia đang chờ duyệt (owner/admin)
+    pub async fn get_join_requests(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+    ) -> Result<Vec<JoinRequestDetail>, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.require_group_role(
+            conversation_id,
+            requester_id,
+            ParticipantRole::Admin,
+            "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền xem yêu cầu tham gia",
+            &mut tx,
+        )
+        .await?;
+
+        let requests = self
+            .conversation_repo
+            .find_pending_join_requests(&conversation_id, tx.as_mut())
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(requests)
+    }
+
+    /// Duyệt hoặc từ chối yêu cầu tham gia (owner/admin)
+    ///
+    /// Duyệt sẽ thêm thành viên trong cùng transaction và broadcast MemberAdded như add_member.
+    /// Nếu user đã vào nhóm bằng đường khác thì yêu cầu chỉ được đóng lại, không thêm lần nữa.
+    pub async fn review_join_request(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+        request_id: Uuid,
+        approve: bool,
+    ) -> Result<(), error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.require_group_role(
+            conversation_id,
+            requester_id,
+            ParticipantRole::Admin,
+            "Chỉ trưởng nhóm hoặc quản trị viên mới có quyền duyệt yêu cầu tham gia",
+            &mut tx,
+        )
+        .await?;
+
+        let request = self
+            .conversation_repo
+            .find_pending_join_request(&conversation_id, &request_id, tx.as_mut())
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy yêu cầu tham gia đang chờ"))?;
+
+        let status = if approve {
+            JoinRequestStatus::Approved
+        } else {
+            JoinRequestStatus::Rejected
+        };
+
+        self.conversation_repo
+            .update_join_request_status(&request.id, status, &requester_id, tx.as_mut())
+            .await?;
+
+        if !approve {
+            tx.commit().await?;
+            return Ok(());
+        }
+
+        let current_role = self
+            .conversation_repo
+            .get_participant_role(&conversation_id, &request.user_id, tx.as_mut())
+            .await?;
+
+        if current_role.is_some() {
+            tx.commit().await?;
+            return Ok(());
+        }
+
+        let (display_name, avatar_url) = self
+            .admit_member(conversation_id, request.user_id, &mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        self.notify_member_added(conversation_id, request.user_id, display_name, avatar_url)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Helper: Thêm participant trong transaction đang mở (add_member, link mời, duyệt yêu cầu)
+    ///
+    /// Trả về tên hiển thị và avatar của user mới để broadcast sau khi commit.
+    async fn admit_member(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(String, Option<String>), error::SystemError> {
+        let added = self
+            .conversation_repo
+            .add_participant(&conversation_id, &user_id, tx.as_mut())
+            .await?;
+
+        if !added {
+            return Err(error::SystemError::bad_request("Người dùng đã là thành viên của nhóm"));
+        }
+
+        self.conversation_repo.update_timestamp(&conversation_id, tx.as_mut()).await?;
+
+        Self::find_member_profile(user_id, tx).await
+    }
+
+    /// Helper: Lấy tên hiển thị và avatar của user
+    async fn find_member_profile(
+        user_id: Uuid,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(String, Option<String>), error::SystemError> {
+        sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT display_name, avatar_url FROM users WHERE id = $1",
+        )
+        .bind(user_id)
+        .fetch_one(tx.as_mut())
+        .await
+        .map_err(|_| error::SystemError::not_found("Không tìm thấy người dùng"))
+    }
+
+    /// Helper: Gửi NewGroup cho thành viên mới và MemberAdded tới cả nhóm
+    ///
+    /// Trả về conversation detail đã gửi cho thành viên mới.
+    async fn notify_member_added(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        display_name: String,
+        avatar_url: Option<String>,
+    ) -> Result<Option<ConversationDetail>, error::SystemError> {
+        let conversation_detail = self
+            .conversation_repo
+            .find_one_conversation_detail(&conversation_id)
+            .await?;
+
+        if let Some(conversation_detail) = &conversation_detail {
+            let conversation_json = serde_json::to_value(conversation_detail).map_err(|e| {
+                error::SystemError::internal_error(format!(
+                    "Lỗi khi xử lý dữ liệu cuộc trò chuyện: {}",
+                    e
+                ))
+            })?;
+
+            self.ws_server.send_to_users(
+                &[user_id],
+                &ServerMessage::NewGroup {
+                    conversation: conversation_json,
+                },
+            );
+        }
+
+        self.ws_server.broadcast_to_room(
+            conversation_id,
+            &ServerMessage::MemberAdded {
+                conversation_id,
+                user_id,
+                display_name,
+                avatar_url,
+            },
+            None,
+        );
+
+        Ok(conversation_detail)
+    }
+
+    /// Xóa thành viên hoặc tự rời nhóm
+    pub async fn remove_member(
+        &self,
+        conversation_id: Uuid,
+        requester_id: Uuid,
+        target_user_id: Uuid,
+    ) -> Result<(), error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        // 1. Kiểm tra conversation-type
+        self.require_group(conversation_id, &mut tx).await?;
+
+        // 2. Kiểm tra quyền
+        let requester_role = self
+            .require_group_role(
+                conversation_id,
+                requester_id,
+                ParticipantRole::Member,
+                "Bạn không có quyền thực hiện hành động này",
+                &mut tx,
+            )
+            .await?;
+
+        if requester_id == target_user_id {
+            // Owner phải chuyển quyền trưởng nhóm trước khi rời
+            if requester_role == ParticipantRole::Owner {
+                return Err(error::SystemError::bad_request(
+                    "Trưởng nhóm cần chuyển quyền cho người khác trước khi rời nhóm",
+                ));
+            }
+        } else {
+            let target_role = self
+                .conversation_repo
+                .get_participant_role(&conversation_id, &target_user_id, tx.as_mut())
+                .await?
+                .ok_or_else(|| error::SystemError::not_found("Người dùng không phải thành viên của nhóm"))?;
+
+            if !Self::can_remove_member(requester_role, target_role) {
+                return Err(error::SystemError::forbidden("Bạn không có quyền thực hiện hành động này"));
+            }
         }
 
         // 3. Soft delete participant
@@ -0,0 +1,61 @@
+use actix_web::{HttpRequest, delete, get, put, web};
+use uuid::Uuid;
+
+use crate::{
+    api::{error, success},
+    middlewares::get_extensions,
+    modules::{
+        conversation::repository_pg::ConversationPgRepository,
+        draft::{
+            model::{Draft, SaveDraftRequest},
+            service::DraftService,
+        },
+    },
+    utils::{Claims, ValidatedJson},
+};
+
+pub type DraftSvc = DraftService<ConversationPgRepository>;
+
+/// Lấy bản nháp của user trong conversation
+#[get("/{conversation_id}")]
+pub async fn get_draft(
+    draft_service: web::Data<DraftSvc>,
+    conversation_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<Draft>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let draft = draft_service.get_draft(user_id, *conversation_id).await?;
+    Ok(success::Success::ok(draft).message("Lấy bản nháp thành công"))
+}
+
+/// Lưu bản nháp, đồng bộ tới các thiết bị khác của user
+#[put("/{conversation_id}")]
+pub async fn save_draft(
+    draft_service: web::Data<DraftSvc>,
+    conversation_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<SaveDraftRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<Draft>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let draft = draft_service
+        .save_draft(user_id, *conversation_id, body.content, body.reply_to_id, None)
+        .await?;
+    Ok(success::Success::ok(draft).message("Lưu bản nháp thành công"))
+}
+
+/// Xoá bản nháp
+#[delete("/{conversation_id}")]
+pub async fn delete_draft(
+    draft_service: web::Data<DraftSvc>,
+    conversation_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    draft_service
+        .delete_draft(user_id, *conversation_id, None)
+        .await?;
+    Ok(success::Success::no_content())
+}
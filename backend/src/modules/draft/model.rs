@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Bản nháp tin nhắn của user trong một conversation (chỉ lưu trên Redis)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Draft {
+    pub conversation_id: Uuid,
+    pub content: String,
+    pub reply_to_id: Option<Uuid>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Lưu bản nháp, content rỗng và không reply = xoá bản nháp
+#[derive(Debug, Deserialize, Validate)]
+pub struct SaveDraftRequest {
+    #[validate(length(max = 5000, message = "Content must be at most 5000 characters"))]
+    pub content: String,
+    #[serde(default)]
+    pub reply_to_id: Option<Uuid>,
+}
@@ -0,0 +1,12 @@
+use actix_web::web::{ServiceConfig, scope};
+
+use crate::modules::draft::handle::*;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/drafts")
+            .service(get_draft)
+            .service(save_draft)
+            .service(delete_draft),
+    );
+}
@@ -0,0 +1,206 @@
+/// Draft Service
+///
+/// Bản nháp tin nhắn theo từng user + conversation, lưu trên Redis qua `CacheStore`
+/// và đồng bộ real-time giữa các session (thiết bị) của cùng một user.
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use uuid::Uuid;
+
+use crate::{
+    api::error,
+    configs::{CacheStore, RedisCache},
+    modules::{
+        conversation::repository::ConversationRepository,
+        draft::model::Draft,
+        websocket::{message::ServerMessage, server::WebSocketServer},
+    },
+};
+
+/// Bản nháp không được cập nhật quá 30 ngày sẽ tự hết hạn
+pub const DRAFT_TTL: usize = 30 * 24 * 60 * 60;
+
+/// Giới hạn độ dài giống nội dung tin nhắn
+const MAX_DRAFT_LENGTH: usize = 5000;
+
+/// `update_draft` được gửi theo từng lần gõ phím, nên nhớ kết quả kiểm tra thành viên
+/// một lúc thay vì truy vấn DB mỗi lần
+const MEMBERSHIP_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Vượt ngưỡng này thì dọn các mục đã hết hạn khi ghi thêm
+const MEMBERSHIP_CACHE_PRUNE_THRESHOLD: usize = 10_000;
+
+/// Key Redis của bản nháp, dùng chung với MessageService để xoá bản nháp sau khi gửi
+pub(crate) fn draft_key(user_id: Uuid, conversation_id: Uuid) -> String {
+    format!("draft:{user_id}:{conversation_id}")
+}
+
+#[derive(Clone)]
+pub struct DraftService<C, S = RedisCache>
+where
+    C: ConversationRepository + Send + Sync,
+    S: CacheStore + Send + Sync,
+{
+    conversation_repo: Arc<C>,
+    cache: Arc<S>,
+    ws_server: Arc<WebSocketServer>,
+    /// (user_id, conversation_id) -> thời điểm xác nhận là thành viên
+    verified_members: Arc<DashMap<(Uuid, Uuid), Instant>>,
+}
+
+impl<C, S> DraftService<C, S>
+where
+    C: ConversationRepository + Send + Sync,
+    S: CacheStore + Send + Sync,
+{
+    pub fn with_dependencies(
+        conversation_repo: Arc<C>,
+        cache: Arc<S>,
+        ws_server: Arc<WebSocketServer>,
+    ) -> Self {
+        DraftService {
+            conversation_repo,
+            cache,
+            ws_server,
+            verified_members: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Lấy bản nháp của user trong conversation
+    pub async fn get_draft(
+        &self,
+        user_id: Uuid,
+        conversation_id: Uuid,
+    ) -> Result<Option<Draft>, error::SystemError> {
+        self.require_member(user_id, conversation_id).await?;
+
+        self.cache.get(&draft_key(user_id, conversation_id)).await
+    }
+
+    /// Lưu bản nháp và đẩy `DraftUpdated` tới các session khác của user
+    ///
+    /// `origin_session` là session gửi lên (qua WebSocket) để không echo lại chính nó.
+    /// Content rỗng và không reply được coi là xoá bản nháp, khi đó trả về `None`.
+    pub async fn save_draft(
+        &self,
+        user_id: Uuid,
+        conversation_id: Uuid,
+        content: String,
+        reply_to_id: Option<Uuid>,
+        origin_session: Option<Uuid>,
+    ) -> Result<Option<Draft>, error::SystemError> {
+        if content.trim().is_empty() && reply_to_id.is_none() {
+            self.delete_draft(user_id, conversation_id, origin_session)
+                .await?;
+            return Ok(None);
+        }
+
+        if content.chars().count() > MAX_DRAFT_LENGTH {
+            return Err(error::SystemError::bad_request(
+                "Bản nháp không được vượt quá 5000 ký tự",
+            ));
+        }
+
+        self.require_member(user_id, conversation_id).await?;
+
+        let draft = Draft {
+            conversation_id,
+            content,
+            reply_to_id,
+            updated_at: chrono::Utc::now(),
+        };
+
+        self.cache
+            .set(&draft_key(user_id, conversation_id), &draft, DRAFT_TTL)
+            .await?;
+
+        self.notify_draft_updated(user_id, conversation_id, Some(draft.clone()), origin_session);
+
+        Ok(Some(draft))
+    }
+
+    /// Xoá bản nháp (sau khi gửi tin nhắn hoặc user xoá hết nội dung)
+    pub async fn delete_draft(
+        &self,
+        user_id: Uuid,
+        conversation_id: Uuid,
+        origin_session: Option<Uuid>,
+    ) -> Result<(), error::SystemError> {
+        self.require_member(user_id, conversation_id).await?;
+
+        self.cache
+            .delete(&draft_key(user_id, conversation_id))
+            .await?;
+
+        self.notify_draft_updated(user_id, conversation_id, None, origin_session);
+
+        Ok(())
+    }
+
+    /// Helper: Chỉ thành viên của conversation mới có bản nháp
+    ///
+    /// Kết quả hợp lệ được nhớ trong `MEMBERSHIP_CACHE_TTL`; user vừa rời nhóm có thể
+    /// lưu thêm bản nháp của chính mình trong khoảng đó, không ảnh hưởng người khác.
+    async fn require_member(
+        &self,
+        user_id: Uuid,
+        conversation_id: Uuid,
+    ) -> Result<(), error::SystemError> {
+        let key = (user_id, conversation_id);
+        if let Some(verified_at) = self.verified_members.get(&key)
+            && verified_at.elapsed() < MEMBERSHIP_CACHE_TTL
+        {
+            return Ok(());
+        }
+
+        let (conversation, is_member) = self
+            .conversation_repo
+            .get_conversation_and_check_membership(
+                &conversation_id,
+                &user_id,
+                self.conversation_repo.get_pool(),
+            )
+            .await?;
+
+        if conversation.is_none() {
+            return Err(error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"));
+        }
+
+        if !is_member {
+            self.verified_members.remove(&key);
+            return Err(error::SystemError::forbidden(
+                "Bạn không phải thành viên của cuộc trò chuyện này",
+            ));
+        }
+
+        if self.verified_members.len() >= MEMBERSHIP_CACHE_PRUNE_THRESHOLD {
+            self.verified_members
+                .retain(|_, verified_at| verified_at.elapsed() < MEMBERSHIP_CACHE_TTL);
+        }
+        self.verified_members.insert(key, Instant::now());
+
+        Ok(())
+    }
+
+    /// Helper: Đồng bộ bản nháp sang các thiết bị khác, `draft = None` nghĩa là đã xoá
+    fn notify_draft_updated(
+        &self,
+        user_id: Uuid,
+        conversation_id: Uuid,
+        draft: Option<Draft>,
+        origin_session: Option<Uuid>,
+    ) {
+        let message = ServerMessage::DraftUpdated {
+            conversation_id,
+            draft,
+        };
+
+        match origin_session {
+            Some(session_id) => self
+                .ws_server
+                .send_to_other_sessions(&user_id, &session_id, &message),
+            None => self.ws_server.send_to_user(&user_id, &message),
+        }
+    }
+}
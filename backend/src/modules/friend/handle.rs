@@ -6,14 +6,17 @@ use crate::{
     middlewares::get_extensions,
     modules::{
         friend::{
-            model::{FriendRequestBody, FriendRequestResponse, FriendResponse},
+            model::{
+                FriendListQuery, FriendListResponse, FriendRequestBody, FriendRequestListResponse,
+                FriendResponse,
+            },
             repository_pg::FriendRepositoryPg,
             schema::FriendRequestEntity,
             service::FriendService,
         },
         user::repository_pg::UserRepositoryPg,
     },
-    utils::Claims,
+    utils::{Claims, ValidatedQuery},
 };
 
 pub type FriendSvc = FriendService<FriendRepositoryPg, UserRepositoryPg>;
@@ -66,10 +69,13 @@ pub async fn decline_friend_request(
 #[get("/")]
 pub async fn list_friends(
     friend_service: web::Data<FriendSvc>,
+    ValidatedQuery(query): ValidatedQuery<FriendListQuery>,
     req: HttpRequest,
-) -> Result<success::Success<Vec<FriendResponse>>, error::Error> {
+) -> Result<success::Success<FriendListResponse>, error::Error> {
     let user_id = get_extensions::<Claims>(&req)?.sub;
-    let friends = friend_service.get_friends(user_id).await?;
+    let friends = friend_service
+        .get_friends(user_id, query.limit, query.cursor)
+        .await?;
 
     Ok(success::Success::ok(Some(friends)).message("Lấy danh sách bạn bè thành công"))
 }
@@ -78,10 +84,13 @@ pub async fn list_friends(
 #[get("/requests")]
 pub async fn list_friend_requests(
     friend_service: web::Data<FriendSvc>,
+    ValidatedQuery(query): ValidatedQuery<FriendListQuery>,
     req: HttpRequest,
-) -> Result<success::Success<Vec<FriendRequestResponse>>, error::Error> {
+) -> Result<success::Success<FriendRequestListResponse>, error::Error> {
     let user_id = get_extensions::<Claims>(&req)?.sub;
-    let requests = friend_service.get_friend_requests(user_id).await?;
+    let requests = friend_service
+        .get_friend_requests(user_id, query.limit, query.cursor)
+        .await?;
 
     Ok(success::Success::ok(Some(requests)).message("Lấy danh sách lời mời kết bạn thành công"))
 }
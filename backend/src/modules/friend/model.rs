@@ -57,3 +57,37 @@ pub struct FriendRequestBody {
     pub recipient_id: Uuid,
     pub message: Option<String>,
 }
+
+/// Query phân trang danh sách bạn bè / lời mời kết bạn
+#[derive(Debug, Deserialize, Validate)]
+pub struct FriendListQuery {
+    #[serde(default = "default_friend_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: i32,
+    pub cursor: Option<String>,
+}
+
+fn default_friend_limit() -> i32 {
+    30
+}
+
+/// Bạn bè kèm thời điểm kết bạn (mốc để phân trang)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct FriendListItem {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub friend: FriendResponse,
+    pub friends_since: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FriendListResponse {
+    pub friends: Vec<FriendListItem>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FriendRequestListResponse {
+    pub requests: Vec<FriendRequestResponse>,
+    pub cursor: Option<String>,
+}
@@ -1,7 +1,7 @@
 use uuid::Uuid;
 
-use crate::api::error;
-use crate::modules::friend::model::{FriendRequestResponse, FriendResponse};
+use crate::api::{cursor::Cursor, error};
+use crate::modules::friend::model::{FriendListItem, FriendRequestResponse};
 use crate::modules::friend::schema::{FriendEntity, FriendRequestEntity};
 
 #[async_trait::async_trait]
@@ -18,8 +18,10 @@ pub trait FriendRepository {
     async fn find_friends<'e, E>(
         &self,
         user_id: &Uuid,
+        cursor: Option<&Cursor>,
+        limit: i32,
         tx: E,
-    ) -> Result<Vec<FriendResponse>, error::SystemError>
+    ) -> Result<Vec<FriendListItem>, error::SystemError>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 
@@ -64,6 +66,8 @@ pub trait FriendRequestRepository {
     async fn find_friend_request_from_user<'e, E>(
         &self,
         user_id: &Uuid,
+        cursor: Option<&Cursor>,
+        limit: i32,
         tx: E,
     ) -> Result<Vec<FriendRequestResponse>, error::SystemError>
     where
@@ -72,6 +76,8 @@ pub trait FriendRequestRepository {
     async fn find_friend_request_to_user<'e, E>(
         &self,
         user_id: &Uuid,
+        cursor: Option<&Cursor>,
+        limit: i32,
         tx: E,
     ) -> Result<Vec<FriendRequestResponse>, error::SystemError>
     where
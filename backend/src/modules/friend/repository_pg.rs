@@ -1,9 +1,9 @@
 use uuid::Uuid;
 
 use crate::{
-    api::error,
+    api::{cursor::Cursor, error},
     modules::friend::{
-        model::{FriendRequestResponse, FriendResponse, FriendUserRow, IdOrInfo},
+        model::{FriendListItem, FriendRequestResponse, FriendResponse, FriendUserRow, IdOrInfo},
         repository::{FriendRepo, FriendRepository, FriendRequestRepository},
         schema::{FriendEntity, FriendRequestEntity},
     },
@@ -51,30 +51,38 @@ impl FriendRepository for FriendRepositoryPg {
     async fn find_friends<'e, E>(
         &self,
         user_id: &Uuid,
+        cursor: Option<&Cursor>,
+        limit: i32,
         tx: E,
-    ) -> Result<Vec<FriendResponse>, error::SystemError>
+    ) -> Result<Vec<FriendListItem>, error::SystemError>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>,
     {
-        let friends = sqlx::query_as::<_, FriendResponse>(
+        let friends = sqlx::query_as::<_, FriendListItem>(
             r#"
         SELECT
             u.id,
             u.username,
             u.display_name,
             u.avatar_url,
-            u.avatar_id
+            u.avatar_id,
+            f.created_at AS friends_since
         FROM friends f
         JOIN users u
             ON u.id = CASE
                 WHEN f.user_a = $1 THEN f.user_b
                 ELSE f.user_a
             END
-        WHERE f.user_a = $1
-           OR f.user_b = $1
+        WHERE (f.user_a = $1 OR f.user_b = $1)
+          AND ($2::timestamptz IS NULL OR (f.created_at, u.id) < ($2, $3))
+        ORDER BY f.created_at DESC, u.id DESC
+        LIMIT $4
         "#,
         )
         .bind(user_id)
+        .bind(cursor.map(|c| c.timestamp))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit + 1)
         .fetch_all(tx)
         .await?;
 
@@ -178,6 +186,8 @@ impl FriendRequestRepository for FriendRepositoryPg {
     async fn find_friend_request_from_user<'e, E>(
         &self,
         user_id: &Uuid,
+        cursor: Option<&Cursor>,
+        limit: i32,
         tx: E,
     ) -> Result<Vec<FriendRequestResponse>, error::SystemError>
     where
@@ -198,9 +208,15 @@ impl FriendRequestRepository for FriendRepositoryPg {
             JOIN users u
                 ON fr.to_user_id = u.id
             WHERE fr.from_user_id = $1
+              AND ($2::timestamptz IS NULL OR (fr.created_at, fr.id) < ($2, $3))
+            ORDER BY fr.created_at DESC, fr.id DESC
+            LIMIT $4
             "#,
         )
         .bind(user_id)
+        .bind(cursor.map(|c| c.timestamp))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit + 1)
         .fetch_all(tx)
         .await?;
 
@@ -224,6 +240,8 @@ impl FriendRequestRepository for FriendRepositoryPg {
     async fn find_friend_request_to_user<'e, E>(
         &self,
         user_id: &Uuid,
+        cursor: Option<&Cursor>,
+        limit: i32,
         tx: E,
     ) -> Result<Vec<FriendRequestResponse>, error::SystemError>
     where
@@ -244,9 +262,15 @@ impl FriendRequestRepository for FriendRepositoryPg {
             JOIN users u
                 ON fr.from_user_id = u.id
             WHERE fr.to_user_id = $1
+              AND ($2::timestamptz IS NULL OR (fr.created_at, fr.id) < ($2, $3))
+            ORDER BY fr.created_at DESC, fr.id DESC
+            LIMIT $4
             "#,
         )
         .bind(user_id)
+        .bind(cursor.map(|c| c.timestamp))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit + 1)
         .fetch_all(tx)
         .await?;
 
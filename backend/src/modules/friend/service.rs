@@ -3,10 +3,10 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    api::{error, messages},
+    api::{cursor::Cursor, error, messages},
     modules::{
         friend::{
-            model::{FriendRequestResponse, FriendResponse},
+            model::{FriendListResponse, FriendRequestListResponse, FriendResponse},
             repository::FriendRepo,
             schema::{FriendEntity, FriendRequestEntity},
         },
@@ -54,12 +54,29 @@ where
     pub async fn get_friends(
         &self,
         user_id: Uuid,
-    ) -> Result<Vec<FriendResponse>, error::SystemError> {
-        let friends = self
+        limit: i32,
+        cursor: Option<String>,
+    ) -> Result<FriendListResponse, error::SystemError> {
+        let cursor = Cursor::parse(cursor.as_deref())?;
+
+        let mut friends = self
             .friend_repo
-            .find_friends(&user_id, self.friend_repo.get_pool())
+            .find_friends(&user_id, cursor.as_ref(), limit, self.friend_repo.get_pool())
             .await?;
-        Ok(friends)
+
+        let next_cursor = if friends.len() > limit as usize {
+            friends.pop();
+            friends
+                .last()
+                .map(|f| Cursor::new(f.friends_since, f.friend.id).encode())
+        } else {
+            None
+        };
+
+        Ok(FriendListResponse {
+            friends,
+            cursor: next_cursor,
+        })
     }
 
     /// Chấm dứt mối quan hệ bạn bè giữa 2 user
@@ -206,17 +223,35 @@ where
     pub async fn get_friend_requests(
         &self,
         user_id: Uuid,
-    ) -> Result<Vec<FriendRequestResponse>, error::SystemError> {
+        limit: i32,
+        cursor: Option<String>,
+    ) -> Result<FriendRequestListResponse, error::SystemError> {
+        let cursor = Cursor::parse(cursor.as_deref())?;
+
         let pool = self.friend_repo.get_pool();
         let (requests_to, requests_from) = tokio::try_join!(
-            self.friend_repo.find_friend_request_to_user(&user_id, pool),
             self.friend_repo
-                .find_friend_request_from_user(&user_id, pool),
+                .find_friend_request_to_user(&user_id, cursor.as_ref(), limit, pool),
+            self.friend_repo
+                .find_friend_request_from_user(&user_id, cursor.as_ref(), limit, pool),
         )?;
 
+        // Mỗi nhánh đã lấy dư 1 phần tử theo cùng keyset, gộp lại rồi cắt trang chung
         let mut all = Vec::with_capacity(requests_to.len() + requests_from.len());
         all.extend(requests_to);
         all.extend(requests_from);
-        Ok(all)
+        all.sort_by_key(|r| std::cmp::Reverse((r.created_at, r.id)));
+
+        let next_cursor = if all.len() > limit as usize {
+            all.truncate(limit as usize);
+            all.last().map(|r| Cursor::new(r.created_at, r.id).encode())
+        } else {
+            None
+        };
+
+        Ok(FriendRequestListResponse {
+            requests: all,
+            cursor: next_cursor,
+        })
     }
 }
@@ -1,4 +1,4 @@
-use actix_web::{HttpRequest, delete, patch, post, web};
+use actix_web::{HttpRequest, delete, get, patch, post, web};
 use uuid::Uuid;
 
 use crate::{
@@ -15,18 +15,20 @@ use crate::{
         friend::handle::FriendSvc,
         message::{
             model::{
-                EditMessageRequest, SendDirectMessage, SendDirectMessagePayload,
-                SendGroupMessage,
+                AddReactionRequest, CreatePollRequest, EditMessageRequest, ForwardMessageRequest, MessageRevision, PollDetail, ReactionSummary, SearchMessageRequest,
+                SearchMessageResponse, SendDirectMessage, SendDirectMessagePayload,
+                SendGroupMessage, SendGroupMessagePayload, UpdateLiveLocationRequest,
+                VotePollRequest,
             },
             repository_pg::MessageRepositoryPg,
-            schema::MessageEntity,
+            schema::{MessageEntity, MessageLocation},
             service::MessageService,
         },
     },
-    utils::{Claims, ValidatedJson},
+    utils::{Claims, ValidatedJson, ValidatedQuery},
 };
 
-type MessageSvc = MessageService<
+pub type MessageSvc = MessageService<
     MessageRepositoryPg,
     ConversationPgRepository,
     ParticipantPgRepository,
@@ -66,6 +68,8 @@ pub async fn send_direct_message(
                 message_type: body._type.clone(),
                 file_url: body.file_url.clone(),
                 reply_to_id: body.reply_to_id,
+                location: body.location.clone(),
+                contact_user_id: body.contact_user_id,
             },
         )
         .await?;
@@ -97,10 +101,14 @@ pub async fn send_group_message(
         .send_group_message_payload(
             user_id,
             body.conversation_id,
-            body.content.clone(),
-            body._type.clone(),
-            body.file_url.clone(),
-            body.reply_to_id,
+            SendGroupMessagePayload {
+                content: body.content.clone(),
+                message_type: body._type.clone(),
+                file_url: body.file_url.clone(),
+                reply_to_id: body.reply_to_id,
+                location: body.location.clone(),
+                contact_user_id: body.contact_user_id,
+            },
         )
         .await?;
 
@@ -134,3 +142,181 @@ pub async fn edit_message(
         .await?;
     Ok(success::Success::ok(Some(message)).message("Chỉnh sửa tin nhắn thành công"))
 }
+
+/// Thả reaction vào tin nhắn
+#[post("/{message_id}/reactions")]
+pub async fn add_reaction(
+    message_service: web::Data<MessageSvc>,
+    message_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<AddReactionRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<ReactionSummary>>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let reactions = message_service
+        .add_reaction(*message_id, user_id, body.emoji)
+        .await?;
+    Ok(success::Success::ok(Some(reactions)).message("Thả reaction thành công"))
+}
+
+/// Gỡ reaction khỏi tin nhắn
+#[delete("/{message_id}/reactions/{emoji}")]
+pub async fn remove_reaction(
+    message_service: web::Data<MessageSvc>,
+    path: web::Path<(Uuid, String)>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (message_id, emoji) = path.into_inner();
+
+    message_service
+        .remove_reaction(message_id, user_id, emoji)
+        .await?;
+    Ok(success::Success::no_content())
+}
+
+/// Chuyển tiếp tin nhắn sang các cuộc trò chuyện khác
+#[post("/{message_id}/forward")]
+pub async fn forward_message(
+    message_service: web::Data<MessageSvc>,
+    message_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<ForwardMessageRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<MessageEntity>>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let messages = message_service
+        .forward_message(*message_id, user_id, body.conversation_ids)
+        .await?;
+    Ok(success::Success::ok(Some(messages)).message("Chuyển tiếp tin nhắn thành công"))
+}
+
+/// Lấy lịch sử chỉnh sửa của tin nhắn
+#[get("/{message_id}/history")]
+pub async fn get_edit_history(
+    message_service: web::Data<MessageSvc>,
+    message_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<MessageRevision>>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let revisions = message_service
+        .get_edit_history(*message_id, user_id)
+        .await?;
+    Ok(success::Success::ok(Some(revisions)).message("Lấy lịch sử chỉnh sửa thành công"))
+}
+
+/// Lấy danh sách reactions của tin nhắn
+#[get("/{message_id}/reactions")]
+pub async fn get_reactions(
+    message_service: web::Data<MessageSvc>,
+    message_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<ReactionSummary>>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let reactions = message_service.get_reactions(*message_id, user_id).await?;
+    Ok(success::Success::ok(Some(reactions)).message("Lấy danh sách reaction thành công"))
+}
+
+/// Tạo bình chọn trong cuộc trò chuyện
+#[post("/polls")]
+pub async fn create_poll(
+    message_service: web::Data<MessageSvc>,
+    ValidatedJson(body): ValidatedJson<CreatePollRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<PollDetail>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let poll = message_service.create_poll(user_id, body).await?;
+    Ok(success::Success::created(Some(poll)).message("Tạo bình chọn thành công"))
+}
+
+/// Lấy bình chọn kèm kết quả hiện tại
+#[get("/{message_id}/poll")]
+pub async fn get_poll(
+    message_service: web::Data<MessageSvc>,
+    message_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<PollDetail>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let poll = message_service.get_poll(*message_id, user_id).await?;
+    Ok(success::Success::ok(Some(poll)).message("Lấy bình chọn thành công"))
+}
+
+/// Bình chọn một phương án
+#[post("/{message_id}/poll/votes")]
+pub async fn vote_poll(
+    message_service: web::Data<MessageSvc>,
+    message_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<VotePollRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<PollDetail>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let poll = message_service
+        .vote_poll(*message_id, user_id, body.option_index)
+        .await?;
+    Ok(success::Success::ok(Some(poll)).message("Bình chọn thành công"))
+}
+
+/// Rút lại phiếu đã bầu
+#[delete("/{message_id}/poll/votes/{option_index}")]
+pub async fn retract_poll_vote(
+    message_service: web::Data<MessageSvc>,
+    path: web::Path<(Uuid, i16)>,
+    req: HttpRequest,
+) -> Result<success::Success<PollDetail>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+    let (message_id, option_index) = path.into_inner();
+
+    let poll = message_service
+        .retract_poll_vote(message_id, user_id, option_index)
+        .await?;
+    Ok(success::Success::ok(Some(poll)).message("Rút phiếu bình chọn thành công"))
+}
+
+/// Đóng bình chọn
+#[post("/{message_id}/poll/close")]
+pub async fn close_poll(
+    message_service: web::Data<MessageSvc>,
+    message_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<PollDetail>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let poll = message_service.close_poll(*message_id, user_id).await?;
+    Ok(success::Success::ok(Some(poll)).message("Đóng bình chọn thành công"))
+}
+
+/// Cập nhật toạ độ của vị trí trực tiếp đang chia sẻ
+#[patch("/{message_id}/location")]
+pub async fn update_live_location(
+    message_service: web::Data<MessageSvc>,
+    message_id: web::Path<Uuid>,
+    ValidatedJson(body): ValidatedJson<UpdateLiveLocationRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<MessageLocation>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let location = message_service
+        .update_live_location(*message_id, user_id, body.latitude, body.longitude)
+        .await?;
+    Ok(success::Success::ok(Some(location)).message("Cập nhật vị trí thành công"))
+}
+
+/// Tìm kiếm tin nhắn trong các cuộc trò chuyện của User (có phân trang cursor)
+#[get("/search")]
+pub async fn search_messages(
+    message_service: web::Data<MessageSvc>,
+    ValidatedQuery(query): ValidatedQuery<SearchMessageRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<SearchMessageResponse>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let response = message_service
+        .search_messages(user_id, query.q, query.limit, query.cursor)
+        .await?;
+    Ok(success::Success::ok(Some(response)).message("Tìm kiếm tin nhắn thành công"))
+}
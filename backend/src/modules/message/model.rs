@@ -1,6 +1,11 @@
+use crate::api::cursor::Cursor;
 use crate::modules::message::schema::MessageEntity;
+use crate::modules::message::schema::MessageLocation;
 use crate::modules::message::schema::MessageType;
+use crate::modules::message::schema::PollEntity;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use sqlx::prelude::FromRow;
 use uuid::Uuid;
 use validator::Validate;
 
@@ -12,18 +17,108 @@ pub struct InsertMessage {
     pub _type: MessageType,
     pub content: Option<String>,
     pub file_url: Option<String>,
+    pub forwarded_from_message_id: Option<Uuid>,
+    pub location: Option<MessageLocation>,
+    pub contact_user_id: Option<Uuid>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Nội dung tin nhắn sau khi chuẩn hoá, dùng chung cho direct/group
+#[derive(Debug, Clone)]
+pub struct MessageBody {
+    pub message_type: MessageType,
+    pub content: Option<String>,
+    pub file_url: Option<String>,
+    pub location: Option<MessageLocation>,
+    pub contact_user_id: Option<Uuid>,
+}
+
+/// Chiều phân trang so với cursor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageDirection {
+    /// Cũ hơn cursor (mới -> cũ); không có cursor = trang mới nhất
+    Before,
+    /// Mới hơn cursor (cũ -> mới)
+    After,
+}
+
+#[derive(Debug, Clone)]
 pub struct MessageQuery {
     pub conversation_id: Uuid,
-    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub cursor: Option<Cursor>,
+    pub direction: PageDirection,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct GetMessageResponse {
-    pub messages: Vec<MessageEntity>,
+    pub messages: Vec<MessageWithReactions>,
+    /// Cursor lấy trang cũ hơn (truyền vào `before`)
     pub cursor: Option<String>,
+    /// Cursor lấy trang mới hơn (truyền vào `after`)
+    pub newer_cursor: Option<String>,
+}
+
+/// Tin nhắn kèm tổng hợp reactions (trả về inline để client không phải gọi N+1)
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageWithReactions {
+    #[serde(flatten)]
+    pub message: MessageEntity,
+    pub reactions: Vec<ReactionSummary>,
+}
+
+/// Tổng hợp một emoji trên một tin nhắn
+#[derive(Debug, Clone, Serialize)]
+pub struct ReactionSummary {
+    pub emoji: String,
+    pub count: i64,
+    pub user_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ReactionSummaryRow {
+    pub message_id: Uuid,
+    pub emoji: String,
+    pub count: i64,
+    pub user_ids: Vec<Uuid>,
+}
+
+impl From<ReactionSummaryRow> for ReactionSummary {
+    fn from(row: ReactionSummaryRow) -> Self {
+        ReactionSummary {
+            emoji: row.emoji,
+            count: row.count,
+            user_ids: row.user_ids,
+        }
+    }
+}
+
+/// Một phiên bản cũ của tin nhắn đã bị chỉnh sửa
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct MessageRevision {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub content: Option<String>,
+    pub edited_by: Uuid,
+    pub edited_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ForwardMessageRequest {
+    #[validate(length(
+        min = 1,
+        max = 20,
+        message = "Must forward to between 1 and 20 conversations"
+    ))]
+    pub conversation_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct AddReactionRequest {
+    #[validate(length(
+        min = 1,
+        max = 32,
+        message = "Emoji must be between 1 and 32 characters"
+    ))]
+    pub emoji: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -38,6 +133,10 @@ pub struct SendDirectMessage {
     pub file_url: Option<String>,
     #[serde(default)]
     pub reply_to_id: Option<Uuid>,
+    #[serde(default)]
+    pub location: Option<MessageLocation>,
+    #[serde(default)]
+    pub contact_user_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +146,18 @@ pub struct SendDirectMessagePayload {
     pub message_type: Option<MessageType>,
     pub file_url: Option<String>,
     pub reply_to_id: Option<Uuid>,
+    pub location: Option<MessageLocation>,
+    pub contact_user_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SendGroupMessagePayload {
+    pub content: Option<String>,
+    pub message_type: Option<MessageType>,
+    pub file_url: Option<String>,
+    pub reply_to_id: Option<Uuid>,
+    pub location: Option<MessageLocation>,
+    pub contact_user_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -60,6 +171,23 @@ pub struct SendGroupMessage {
     pub file_url: Option<String>,
     #[serde(default)]
     pub reply_to_id: Option<Uuid>,
+    #[serde(default)]
+    pub location: Option<MessageLocation>,
+    #[serde(default)]
+    pub contact_user_id: Option<Uuid>,
+}
+
+/// Cập nhật toạ độ của tin nhắn vị trí trực tiếp
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UpdateLiveLocationRequest {
+    #[validate(range(min = -90.0, max = 90.0, message = "Latitude must be between -90 and 90"))]
+    pub latitude: f64,
+    #[validate(range(
+        min = -180.0,
+        max = 180.0,
+        message = "Longitude must be between -180 and 180"
+    ))]
+    pub longitude: f64,
 }
 
 #[derive(Debug, Clone, Deserialize, Validate)]
@@ -71,3 +199,159 @@ pub struct EditMessageRequest {
     ))]
     pub content: String,
 }
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SearchMessageRequest {
+    #[validate(length(
+        min = 1,
+        max = 200,
+        message = "Query must be between 1 and 200 characters"
+    ))]
+    pub q: String,
+    #[validate(range(min = 1, max = 50))]
+    pub limit: i32,
+    pub cursor: Option<String>,
+}
+
+/// Một kết quả tìm kiếm: tin nhắn kèm đoạn trích đã highlight
+///
+/// `snippet` là HTML đã escape, thẻ duy nhất được tin cậy là `<mark>`.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct MessageSearchResult {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub sender_id: Uuid,
+    #[sqlx(rename = "type")]
+    pub _type: MessageType,
+    pub content: Option<String>,
+    pub snippet: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMessageResponse {
+    pub results: Vec<MessageSearchResult>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MentionListResponse {
+    pub messages: Vec<MessageEntity>,
+    pub cursor: Option<String>,
+}
+
+/// Thống kê thread trên tin nhắn gốc
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ThreadStats {
+    pub root_message_id: Uuid,
+    pub conversation_id: Uuid,
+    pub reply_count: i32,
+    pub last_reply_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadResponse {
+    pub root: MessageWithReactions,
+    pub replies: Vec<MessageWithReactions>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreatePollRequest {
+    pub conversation_id: Uuid,
+    #[validate(length(
+        min = 1,
+        max = 300,
+        message = "Question must be between 1 and 300 characters"
+    ))]
+    pub question: String,
+    #[validate(length(min = 2, max = 10, message = "Poll must have between 2 and 10 options"))]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub multiple_choice: bool,
+    pub closes_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct VotePollRequest {
+    #[validate(range(min = 0, max = 9, message = "Option index must be between 0 and 9"))]
+    pub option_index: i16,
+}
+
+/// Dữ liệu poll đã chuẩn hoá, lưu cùng transaction với tin nhắn
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewPoll {
+    pub question: String,
+    pub options: Vec<String>,
+    pub multiple_choice: bool,
+    pub closes_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct PollTallyRow {
+    pub option_index: i16,
+    pub count: i64,
+    pub user_ids: Vec<Uuid>,
+}
+
+/// Kết quả một phương án của poll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollOptionResult {
+    pub index: i16,
+    pub text: String,
+    pub count: i64,
+    pub user_ids: Vec<Uuid>,
+}
+
+/// Poll kèm kết quả bình chọn hiện tại
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollDetail {
+    pub message_id: Uuid,
+    pub question: String,
+    pub multiple_choice: bool,
+    pub closes_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub closed: bool,
+    pub options: Vec<PollOptionResult>,
+}
+
+impl PollDetail {
+    pub fn from_tallies(
+        poll: PollEntity,
+        tallies: Vec<PollTallyRow>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        let closed = poll.is_closed(now);
+        let mut tally_map: HashMap<i16, PollTallyRow> = tallies
+            .into_iter()
+            .map(|row| (row.option_index, row))
+            .collect();
+
+        let options = poll
+            .options
+            .into_iter()
+            .zip(0i16..)
+            .map(|(text, index)| {
+                let (count, user_ids) = tally_map
+                    .remove(&index)
+                    .map(|row| (row.count, row.user_ids))
+                    .unwrap_or_default();
+
+                PollOptionResult {
+                    index,
+                    text,
+                    count,
+                    user_ids,
+                }
+            })
+            .collect();
+
+        PollDetail {
+            message_id: poll.message_id,
+            question: poll.question,
+            multiple_choice: poll.multiple_choice,
+            closes_at: poll.closes_at,
+            closed,
+            options,
+        }
+    }
+}
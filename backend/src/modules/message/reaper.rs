@@ -0,0 +1,37 @@
+/// Expired Message Reaper
+///
+/// Task chạy nền định kỳ soft delete các tin nhắn tự hủy đã quá `expires_at`
+/// và phát `MessageDeleted` tới participants.
+use std::{sync::Arc, time::Duration};
+
+use crate::modules::message::handle::MessageSvc;
+
+/// Chu kỳ quét tin nhắn hết hạn
+const REAP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Số tin nhắn tối đa xóa trong một lượt quét
+const REAP_BATCH_SIZE: i64 = 200;
+
+pub fn spawn_reaper(message_service: MessageSvc) {
+    let message_service = Arc::new(message_service);
+
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(REAP_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+
+            // Xóa liên tục khi còn tồn đọng, tránh chờ cả chu kỳ giữa các lô đầy
+            loop {
+                match message_service.reap_expired_messages(REAP_BATCH_SIZE).await {
+                    Ok(reaped) if reaped as i64 == REAP_BATCH_SIZE => continue,
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::error!("Failed to reap expired messages: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
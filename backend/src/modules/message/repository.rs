@@ -1,5 +1,13 @@
-use crate::modules::message::model::{InsertMessage, MessageQuery};
-use crate::{api::error, modules::message::schema::MessageEntity};
+use crate::modules::message::model::{
+    InsertMessage, MessageQuery, MessageRevision, MessageSearchResult, NewPoll, PollTallyRow,
+    ReactionSummaryRow, ThreadStats,
+};
+use crate::{
+    api::{cursor::Cursor, error},
+    modules::{
+        message::schema::{ContactCard, MessageEntity, PollEntity},
+    },
+};
 
 #[async_trait::async_trait]
 pub trait MessageRepository {
@@ -21,6 +29,7 @@ pub trait MessageRepository {
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 
+    /// Lấy tối đa limit + 1 tin nhắn theo chiều của query (Before: mới -> cũ, After: cũ -> mới)
     async fn find_by_query<'e, E>(
         &self,
         query: &MessageQuery,
@@ -41,6 +50,8 @@ pub trait MessageRepository {
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 
     /// Edit a message by ID (only content can be edited)
+    ///
+    /// Nội dung cũ được lưu vào message_edits trong cùng câu lệnh
     async fn edit_message<'e, E>(
         &self,
         message_id: &uuid::Uuid,
@@ -51,6 +62,15 @@ pub trait MessageRepository {
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 
+    /// Lịch sử chỉnh sửa của tin nhắn (cũ -> mới)
+    async fn find_edit_history<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Vec<MessageRevision>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
     /// Get the last message of a conversation
     async fn get_last_message_by_conversation<'e, E>(
         &self,
@@ -59,4 +79,199 @@ pub trait MessageRepository {
     ) -> Result<Option<MessageEntity>, error::SystemError>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Thêm reaction (idempotent), trả về false nếu reaction đã tồn tại
+    async fn add_reaction<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        emoji: &str,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Gỡ reaction, trả về false nếu không có gì để xóa
+    async fn remove_reaction<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        emoji: &str,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Tổng hợp reactions theo (message_id, emoji) cho một lô tin nhắn
+    async fn get_reaction_summaries<'e, E>(
+        &self,
+        message_ids: &[uuid::Uuid],
+        tx: E,
+    ) -> Result<Vec<ReactionSummaryRow>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Full-text search trong các cuộc trò chuyện mà user còn là thành viên
+    async fn search_messages<'e, E>(
+        &self,
+        user_id: &uuid::Uuid,
+        query: &str,
+        before: Option<&Cursor>,
+        limit: i32,
+        tx: E,
+    ) -> Result<Vec<MessageSearchResult>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Tính lại reply_count/last_reply_at của tin nhắn gốc từ các reply còn tồn tại
+    ///
+    /// Khóa dòng tin nhắn gốc trước khi đếm để các reply song song không ghi đè số liệu của nhau.
+    async fn refresh_thread_stats<'e>(
+        &self,
+        root_message_id: &uuid::Uuid,
+        tx: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<ThreadStats>, error::SystemError>;
+
+    /// Lấy các reply của một thread theo thứ tự cũ -> mới
+    async fn find_thread_replies<'e, E>(
+        &self,
+        root_message_id: &uuid::Uuid,
+        after: Option<&Cursor>,
+        limit: i32,
+        tx: E,
+    ) -> Result<Vec<MessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn create_mentions<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_ids: &[uuid::Uuid],
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Các tin nhắn nhắc tới user (mới -> cũ), chỉ trong conversation user còn tham gia
+    async fn find_mentions_for_user<'e, E>(
+        &self,
+        user_id: &uuid::Uuid,
+        before: Option<&Cursor>,
+        limit: i32,
+        tx: E,
+    ) -> Result<Vec<MessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Soft delete một lô tin nhắn đã quá expires_at, trả về các tin nhắn vừa bị xóa
+    async fn delete_expired_messages<'e, E>(
+        &self,
+        limit: i64,
+        tx: E,
+    ) -> Result<Vec<MessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn create_poll<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        poll: &NewPoll,
+        tx: E,
+    ) -> Result<PollEntity, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn find_poll<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Option<PollEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Khoá poll (FOR UPDATE) để các lượt vote của cùng user không chạy chồng lên nhau
+    async fn lock_poll<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Option<PollEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn add_poll_vote<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        option_index: i16,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn remove_poll_vote<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        option_index: i16,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Xoá mọi phiếu của user trong poll (dùng khi đổi lựa chọn ở poll một đáp án)
+    async fn clear_poll_votes<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn get_poll_tallies<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Vec<PollTallyRow>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn close_poll<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Cập nhật toạ độ của tin nhắn vị trí trực tiếp còn hạn, chỉ người gửi mới cập nhật được
+    async fn update_live_location<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        sender_id: &uuid::Uuid,
+        latitude: f64,
+        longitude: f64,
+        tx: E,
+    ) -> Result<Option<MessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Lấy thông tin danh thiếp của các user được chia sẻ (bỏ qua tài khoản đã xoá)
+    async fn find_contact_cards<'e, E>(
+        &self,
+        user_ids: &[uuid::Uuid],
+        tx: E,
+    ) -> Result<Vec<ContactCard>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Lấy danh thiếp mà sender được phép chia sẻ: của chính mình hoặc của bạn bè hiện tại
+    async fn find_shareable_contact_card<'e, E>(
+        &self,
+        sender_id: &uuid::Uuid,
+        contact_user_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Option<ContactCard>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
 }
@@ -1,7 +1,13 @@
 use crate::{
-    api::error,
+    api::{cursor::Cursor, error},
     modules::message::{
-        self, model::InsertMessage, repository::MessageRepository, schema::MessageEntity,
+        self,
+        model::{
+            InsertMessage, MessageRevision, MessageSearchResult, NewPoll, PageDirection,
+            PollTallyRow, ReactionSummaryRow, ThreadStats,
+        },
+        repository::MessageRepository,
+        schema::{ContactCard, MessageEntity, PollEntity},
     },
 };
 
@@ -48,7 +54,22 @@ impl MessageRepository for MessageRepositoryPg {
         E: sqlx::Executor<'e, Database = sqlx::Postgres>,
     {
         let message = sqlx::query_as::<_, MessageEntity>(
-            "INSERT INTO messages (conversation_id, sender_id, reply_to_id, type, content, file_url) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+            r#"
+            INSERT INTO messages (
+                conversation_id, sender_id, reply_to_id, type, content, file_url,
+                forwarded_from_message_id, location_latitude, location_longitude,
+                location_label, location_live_until, contact_user_id, expires_at
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12,
+                (
+                    SELECT NOW() + make_interval(secs => c.message_ttl_seconds)
+                    FROM conversations c
+                    WHERE c.id = $1
+                )
+            )
+            RETURNING *
+            "#,
         )
         .bind(message.conversation_id)
         .bind(message.sender_id)
@@ -56,6 +77,12 @@ impl MessageRepository for MessageRepositoryPg {
         .bind(&message._type)
         .bind(&message.content)
         .bind(&message.file_url)
+        .bind(message.forwarded_from_message_id)
+        .bind(message.location.as_ref().map(|l| l.latitude))
+        .bind(message.location.as_ref().map(|l| l.longitude))
+        .bind(message.location.as_ref().and_then(|l| l.label.as_deref()))
+        .bind(message.location.as_ref().and_then(|l| l.live_until))
+        .bind(message.contact_user_id)
         .fetch_one(tx)
         .await?;
 
@@ -71,24 +98,39 @@ impl MessageRepository for MessageRepositoryPg {
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>,
     {
-        // has index on (conversation_id, created_at DESC NULLS LAST) where deleted_at IS NULL
+        // has index on (conversation_id, created_at DESC, id DESC) where deleted_at IS NULL
+        let sql = match query.direction {
+            PageDirection::Before => {
+                r#"
+                SELECT *
+                FROM messages
+                WHERE conversation_id = $1
+                  AND deleted_at IS NULL
+                  AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3))
+                ORDER BY created_at DESC, id DESC
+                LIMIT $4
+                "#
+            }
+            PageDirection::After => {
+                r#"
+                SELECT *
+                FROM messages
+                WHERE conversation_id = $1
+                  AND deleted_at IS NULL
+                  AND ($2::timestamptz IS NULL OR (created_at, id) > ($2, $3))
+                ORDER BY created_at ASC, id ASC
+                LIMIT $4
+                "#
+            }
+        };
 
-        let messages = sqlx::query_as::<_, MessageEntity>(
-            r#"
-            SELECT *
-            FROM messages
-            WHERE conversation_id = $1
-              AND deleted_at IS NULL
-              AND ($2::timestamptz IS NULL OR created_at < $2)
-            ORDER BY created_at DESC
-            LIMIT $3
-            "#,
-        )
-        .bind(query.conversation_id)
-        .bind(query.created_at)
-        .bind(limit + 1)
-        .fetch_all(tx)
-        .await?;
+        let messages = sqlx::query_as::<_, MessageEntity>(sql)
+            .bind(query.conversation_id)
+            .bind(query.cursor.as_ref().map(|c| c.timestamp))
+            .bind(query.cursor.as_ref().map(|c| c.id))
+            .bind(limit + 1)
+            .fetch_all(tx)
+            .await?;
 
         Ok(messages)
     }
@@ -131,16 +173,29 @@ impl MessageRepository for MessageRepositoryPg {
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>,
     {
-        // Edit message: chỉ cho phép sửa tin nhắn của chính mình
+        // Edit message: chỉ cho phép sửa tin nhắn của chính mình.
+        // Khóa dòng rồi lưu nội dung cũ vào message_edits trước khi ghi đè.
         let message = sqlx::query_as::<_, MessageEntity>(
             r#"
-            UPDATE messages
+            WITH previous AS (
+                SELECT id, content
+                FROM messages
+                WHERE id = $2
+                  AND sender_id = $3
+                  AND deleted_at IS NULL
+                FOR UPDATE
+            ),
+            revision AS (
+                INSERT INTO message_edits (message_id, content, edited_by)
+                SELECT id, content, $3 FROM previous
+            )
+            UPDATE messages m
             SET content = $1,
+                is_edited = TRUE,
                 updated_at = NOW()
-            WHERE id = $2
-              AND sender_id = $3
-              AND deleted_at IS NULL
-            RETURNING *
+            FROM previous
+            WHERE m.id = previous.id
+            RETURNING m.*
             "#,
         )
         .bind(new_content)
@@ -152,6 +207,29 @@ impl MessageRepository for MessageRepositoryPg {
         Ok(message)
     }
 
+    async fn find_edit_history<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Vec<MessageRevision>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let revisions = sqlx::query_as::<_, MessageRevision>(
+            r#"
+            SELECT id, message_id, content, edited_by, edited_at
+            FROM message_edits
+            WHERE message_id = $1
+            ORDER BY edited_at ASC
+            "#,
+        )
+        .bind(message_id)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(revisions)
+    }
+
     async fn get_last_message_by_conversation<'e, E>(
         &self,
         conversation_id: &uuid::Uuid,
@@ -176,4 +254,574 @@ impl MessageRepository for MessageRepositoryPg {
 
         Ok(message)
     }
+
+    async fn add_reaction<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        emoji: &str,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query(
+            r#"
+            INSERT INTO message_reactions (message_id, user_id, emoji)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (message_id, user_id, emoji) DO NOTHING
+            "#,
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(emoji)
+        .execute(tx)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn remove_reaction<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        emoji: &str,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query(
+            r#"
+            DELETE FROM message_reactions
+            WHERE message_id = $1
+              AND user_id = $2
+              AND emoji = $3
+            "#,
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(emoji)
+        .execute(tx)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn get_reaction_summaries<'e, E>(
+        &self,
+        message_ids: &[uuid::Uuid],
+        tx: E,
+    ) -> Result<Vec<ReactionSummaryRow>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // primary key (message_id, user_id, emoji) phục vụ luôn cho lookup theo message_id
+        let rows = sqlx::query_as::<_, ReactionSummaryRow>(
+            r#"
+            SELECT message_id,
+                   emoji,
+                   COUNT(*) AS count,
+                   ARRAY_AGG(user_id ORDER BY created_at) AS user_ids
+            FROM message_reactions
+            WHERE message_id = ANY($1)
+            GROUP BY message_id, emoji
+            ORDER BY message_id, MIN(created_at)
+            "#,
+        )
+        .bind(message_ids)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn search_messages<'e, E>(
+        &self,
+        user_id: &uuid::Uuid,
+        query: &str,
+        before: Option<&Cursor>,
+        limit: i32,
+        tx: E,
+    ) -> Result<Vec<MessageSearchResult>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // expression phải khớp với idx_messages_content_search để dùng được GIN index.
+        // Nội dung được escape HTML trước khi highlight: snippet chỉ chứa thẻ <mark> do server chèn
+        let results = sqlx::query_as::<_, MessageSearchResult>(
+            r#"
+            SELECT m.id,
+                   m.conversation_id,
+                   m.sender_id,
+                   m.type,
+                   m.content,
+                   ts_headline(
+                       'simple',
+                       replace(replace(replace(replace(
+                           COALESCE(m.content, ''),
+                           '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), '"', '&quot;'),
+                       q.query,
+                       'StartSel=<mark>, StopSel=</mark>, MaxWords=30, MinWords=10, MaxFragments=2'
+                   ) AS snippet,
+                   m.created_at
+            FROM messages m
+            JOIN participants p
+              ON p.conversation_id = m.conversation_id
+             AND p.user_id = $1
+             AND p.deleted_at IS NULL
+            CROSS JOIN websearch_to_tsquery('simple', $2) AS q(query)
+            WHERE m.deleted_at IS NULL
+              AND to_tsvector('simple', COALESCE(m.content, '')) @@ q.query
+              AND ($3::timestamptz IS NULL OR (m.created_at, m.id) < ($3, $4))
+            ORDER BY m.created_at DESC, m.id DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(user_id)
+        .bind(query)
+        .bind(before.map(|c| c.timestamp))
+        .bind(before.map(|c| c.id))
+        .bind(limit + 1)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(results)
+    }
+
+    async fn refresh_thread_stats<'e>(
+        &self,
+        root_message_id: &uuid::Uuid,
+        tx: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<ThreadStats>, error::SystemError> {
+        // Khóa dòng gốc ở câu lệnh riêng: câu đếm phía sau lấy snapshot mới nên thấy được reply
+        // của transaction vừa giữ khóa trước đó. NO KEY UPDATE không chặn FK của reply mới.
+        let locked = sqlx::query_scalar::<_, uuid::Uuid>(
+            "SELECT id FROM messages WHERE id = $1 FOR NO KEY UPDATE",
+        )
+        .bind(root_message_id)
+        .fetch_optional(tx.as_mut())
+        .await?;
+        if locked.is_none() {
+            return Ok(None);
+        }
+
+        // Đếm lại thay vì +1/-1 để luôn đúng kể cả khi reply bị xóa
+        let stats = sqlx::query_as::<_, ThreadStats>(
+            r#"
+            UPDATE messages root
+            SET reply_count = stats.reply_count,
+                last_reply_at = stats.last_reply_at
+            FROM (
+                SELECT COUNT(*)::int AS reply_count, MAX(created_at) AS last_reply_at
+                FROM messages
+                WHERE reply_to_id = $1
+                  AND deleted_at IS NULL
+            ) stats
+            WHERE root.id = $1
+            RETURNING root.id AS root_message_id,
+                      root.conversation_id,
+                      root.reply_count,
+                      root.last_reply_at
+            "#,
+        )
+        .bind(root_message_id)
+        .fetch_optional(tx.as_mut())
+        .await?;
+
+        Ok(stats)
+    }
+
+    async fn find_thread_replies<'e, E>(
+        &self,
+        root_message_id: &uuid::Uuid,
+        after: Option<&Cursor>,
+        limit: i32,
+        tx: E,
+    ) -> Result<Vec<MessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // has index on (reply_to_id, created_at) where deleted_at IS NULL
+        let messages = sqlx::query_as::<_, MessageEntity>(
+            r#"
+            SELECT *
+            FROM messages
+            WHERE reply_to_id = $1
+              AND deleted_at IS NULL
+              AND ($2::timestamptz IS NULL OR (created_at, id) > ($2, $3))
+            ORDER BY created_at ASC, id ASC
+            LIMIT $4
+            "#,
+        )
+        .bind(root_message_id)
+        .bind(after.map(|c| c.timestamp))
+        .bind(after.map(|c| c.id))
+        .bind(limit + 1)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(messages)
+    }
+
+    async fn create_mentions<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_ids: &[uuid::Uuid],
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO message_mentions (message_id, user_id)
+            SELECT $1, unnest($2::uuid[])
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(message_id)
+        .bind(user_ids)
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_mentions_for_user<'e, E>(
+        &self,
+        user_id: &uuid::Uuid,
+        before: Option<&Cursor>,
+        limit: i32,
+        tx: E,
+    ) -> Result<Vec<MessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let messages = sqlx::query_as::<_, MessageEntity>(
+            r#"
+            SELECT m.*
+            FROM message_mentions mm
+            JOIN messages m ON m.id = mm.message_id
+            JOIN participants p
+              ON p.conversation_id = m.conversation_id
+             AND p.user_id = mm.user_id
+             AND p.deleted_at IS NULL
+            WHERE mm.user_id = $1
+              AND m.deleted_at IS NULL
+              AND ($2::timestamptz IS NULL OR (m.created_at, m.id) < ($2, $3))
+            ORDER BY m.created_at DESC, m.id DESC
+            LIMIT $4
+            "#,
+        )
+        .bind(user_id)
+        .bind(before.map(|c| c.timestamp))
+        .bind(before.map(|c| c.id))
+        .bind(limit + 1)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(messages)
+    }
+
+    async fn delete_expired_messages<'e, E>(
+        &self,
+        limit: i64,
+        tx: E,
+    ) -> Result<Vec<MessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // SKIP LOCKED để nhiều instance chạy reaper song song không giẫm lên nhau
+        let messages = sqlx::query_as::<_, MessageEntity>(
+            r#"
+            UPDATE messages
+            SET deleted_at = NOW()
+            WHERE id IN (
+                SELECT id
+                FROM messages
+                WHERE expires_at <= NOW()
+                  AND deleted_at IS NULL
+                ORDER BY expires_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(messages)
+    }
+
+    async fn create_poll<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        poll: &NewPoll,
+        tx: E,
+    ) -> Result<PollEntity, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let poll = sqlx::query_as::<_, PollEntity>(
+            r#"
+            INSERT INTO polls (message_id, question, options, multiple_choice, closes_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(message_id)
+        .bind(&poll.question)
+        .bind(&poll.options)
+        .bind(poll.multiple_choice)
+        .bind(poll.closes_at)
+        .fetch_one(tx)
+        .await?;
+
+        Ok(poll)
+    }
+
+    async fn find_poll<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Option<PollEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let poll = sqlx::query_as::<_, PollEntity>("SELECT * FROM polls WHERE message_id = $1")
+            .bind(message_id)
+            .fetch_optional(tx)
+            .await?;
+
+        Ok(poll)
+    }
+
+    async fn lock_poll<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Option<PollEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let poll = sqlx::query_as::<_, PollEntity>(
+            "SELECT * FROM polls WHERE message_id = $1 FOR UPDATE",
+        )
+        .bind(message_id)
+        .fetch_optional(tx)
+        .await?;
+
+        Ok(poll)
+    }
+
+    async fn add_poll_vote<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        option_index: i16,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query(
+            r#"
+            INSERT INTO poll_votes (message_id, user_id, option_index)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (message_id, user_id, option_index) DO NOTHING
+            "#,
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(option_index)
+        .execute(tx)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn remove_poll_vote<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        option_index: i16,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query(
+            r#"
+            DELETE FROM poll_votes
+            WHERE message_id = $1
+              AND user_id = $2
+              AND option_index = $3
+            "#,
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(option_index)
+        .execute(tx)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn clear_poll_votes<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        user_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        sqlx::query("DELETE FROM poll_votes WHERE message_id = $1 AND user_id = $2")
+            .bind(message_id)
+            .bind(user_id)
+            .execute(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_poll_tallies<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Vec<PollTallyRow>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query_as::<_, PollTallyRow>(
+            r#"
+            SELECT option_index,
+                   COUNT(*) AS count,
+                   ARRAY_AGG(user_id ORDER BY created_at) AS user_ids
+            FROM poll_votes
+            WHERE message_id = $1
+            GROUP BY option_index
+            "#,
+        )
+        .bind(message_id)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn close_poll<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query(
+            "UPDATE polls SET closed_at = NOW() WHERE message_id = $1 AND closed_at IS NULL",
+        )
+        .bind(message_id)
+        .execute(tx)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn update_live_location<'e, E>(
+        &self,
+        message_id: &uuid::Uuid,
+        sender_id: &uuid::Uuid,
+        latitude: f64,
+        longitude: f64,
+        tx: E,
+    ) -> Result<Option<MessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let message = sqlx::query_as::<_, MessageEntity>(
+            r#"
+            UPDATE messages
+            SET location_latitude = $3, location_longitude = $4, updated_at = NOW()
+            WHERE id = $1
+              AND sender_id = $2
+              AND type = 'location'
+              AND location_live_until > NOW()
+              AND deleted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(message_id)
+        .bind(sender_id)
+        .bind(latitude)
+        .bind(longitude)
+        .fetch_optional(tx)
+        .await?;
+
+        Ok(message)
+    }
+
+    async fn find_contact_cards<'e, E>(
+        &self,
+        user_ids: &[uuid::Uuid],
+        tx: E,
+    ) -> Result<Vec<ContactCard>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let cards = sqlx::query_as::<_, ContactCard>(
+            r#"
+            SELECT id, username, display_name, avatar_url
+            FROM users
+            WHERE id = ANY($1) AND deleted_at IS NULL
+            "#,
+        )
+        .bind(user_ids)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(cards)
+    }
+
+    async fn find_shareable_contact_card<'e, E>(
+        &self,
+        sender_id: &uuid::Uuid,
+        contact_user_id: &uuid::Uuid,
+        tx: E,
+    ) -> Result<Option<ContactCard>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let card = sqlx::query_as::<_, ContactCard>(
+            r#"
+            SELECT u.id, u.username, u.display_name, u.avatar_url
+            FROM users u
+            WHERE u.id = $2
+              AND u.deleted_at IS NULL
+              AND (
+                u.id = $1
+                OR EXISTS (
+                    SELECT 1 FROM friends f
+                    WHERE f.user_a = LEAST($1, $2)
+                      AND f.user_b = GREATEST($1, $2)
+                      AND f.deleted_at IS NULL
+                )
+              )
+            "#,
+        )
+        .bind(sender_id)
+        .bind(contact_user_id)
+        .fetch_optional(tx)
+        .await?;
+
+        Ok(card)
+    }
 }
@@ -10,7 +10,19 @@ pub fn configure(cfg: &mut ServiceConfig) {
         scope("/messages")
             .service(scope("/direct").service(send_direct_message))
             .service(scope("/group").service(send_group_message))
+            .service(search_messages)
+            .service(create_poll)
             .service(delete_message)
-            .service(edit_message),
+            .service(edit_message)
+            .service(get_edit_history)
+            .service(forward_message)
+            .service(add_reaction)
+            .service(remove_reaction)
+            .service(get_reactions)
+            .service(get_poll)
+            .service(vote_poll)
+            .service(retract_poll_vote)
+            .service(close_poll)
+            .service(update_live_location),
     );
 }
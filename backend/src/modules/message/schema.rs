@@ -17,6 +17,9 @@ pub enum MessageType {
     CallEnd,
     CallCancel,
     CallSignaling,
+    Poll,
+    Location,
+    Contact,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize)]
@@ -30,7 +33,117 @@ pub struct MessageEntity {
     pub content: Option<String>,
     pub file_url: Option<String>,
     pub is_edited: bool,
+    /// Chỉ trả về cờ `is_forwarded` cho client, không lộ tin nhắn/conversation gốc
+    #[serde(rename = "is_forwarded", serialize_with = "serialize_is_some")]
+    pub forwarded_from_message_id: Option<Uuid>,
+    pub reply_count: i32,
+    pub last_reply_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Vị trí của tin nhắn `location`, trả về dạng object `location` (null với loại khác)
+    #[sqlx(flatten)]
+    #[serde(serialize_with = "serialize_location")]
+    pub location: LocationColumns,
+    /// User được chia sẻ trong tin nhắn `contact` (null nếu tài khoản đã bị xoá)
+    pub contact_user_id: Option<Uuid>,
+    /// Danh thiếp đã resolve từ `contact_user_id`, service điền vào trước khi trả cho client
+    #[sqlx(skip)]
+    pub contact: Option<ContactCard>,
     pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
+
+/// Danh thiếp đính kèm tin nhắn `contact`, chỉ gồm thông tin hiển thị công khai
+/// (không có email, số điện thoại hay trạng thái bảo mật của tài khoản)
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct ContactCard {
+    pub id: Uuid,
+    pub username: String,
+    pub display_name: String,
+    pub avatar_url: Option<String>,
+}
+
+/// Vị trí được chia sẻ, `live_until` có giá trị nghĩa là đang chia sẻ trực tiếp tới thời điểm đó
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub live_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl MessageLocation {
+    /// Vị trí trực tiếp còn được cập nhật
+    pub fn is_live(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.live_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Các cột `location_*` của bảng messages
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct LocationColumns {
+    pub location_latitude: Option<f64>,
+    pub location_longitude: Option<f64>,
+    pub location_label: Option<String>,
+    pub location_live_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl LocationColumns {
+    pub fn get(&self) -> Option<MessageLocation> {
+        Some(MessageLocation {
+            latitude: self.location_latitude?,
+            longitude: self.location_longitude?,
+            label: self.location_label.clone(),
+            live_until: self.location_live_until,
+        })
+    }
+}
+
+impl From<Option<MessageLocation>> for LocationColumns {
+    fn from(location: Option<MessageLocation>) -> Self {
+        match location {
+            Some(location) => LocationColumns {
+                location_latitude: Some(location.latitude),
+                location_longitude: Some(location.longitude),
+                location_label: location.label,
+                location_live_until: location.live_until,
+            },
+            None => LocationColumns::default(),
+        }
+    }
+}
+
+/// Bình chọn gắn với một tin nhắn loại `poll`
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct PollEntity {
+    pub message_id: Uuid,
+    pub question: String,
+    pub options: Vec<String>,
+    pub multiple_choice: bool,
+    pub closes_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub closed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PollEntity {
+    /// Poll đã bị đóng thủ công hoặc đã quá hạn `closes_at`
+    pub fn is_closed(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.closed_at.is_some() || self.closes_at.is_some_and(|at| at <= now)
+    }
+}
+
+fn serialize_is_some<S, T>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_bool(value.is_some())
+}
+
+fn serialize_location<S>(value: &LocationColumns, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    value.get().serialize(serializer)
+}
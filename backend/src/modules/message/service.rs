@@ -3,12 +3,18 @@
 /// Service layer xử lý business logic cho messages, bao gồm:
 /// - Gửi tin nhắn (direct và group)
 /// - Xóa và chỉnh sửa tin nhắn
+/// - Chuyển tiếp tin nhắn
+/// - Mentions (@username, @all)
+/// - Thả/gỡ reaction
+/// - Tìm kiếm tin nhắn
+/// - Dọn tin nhắn tự hủy đã hết hạn
 /// - Broadcast real-time qua WebSocket
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Instant;
 use uuid::Uuid;
 
+use crate::api::cursor::Cursor;
 use crate::api::error;
 use crate::configs::RedisCache;
 use crate::METRICS;
@@ -17,18 +23,55 @@ use crate::modules::conversation::repository::{
     ConversationRepository, LastMessageRepository, ParticipantRepository,
 };
 use crate::modules::conversation::schema::ConversationType;
-use crate::modules::message::model::{InsertMessage, SendDirectMessagePayload};
+use crate::modules::conversation::schema::ParticipantRole;
+use crate::modules::draft::service::draft_key;
+use crate::modules::message::model::{
+    CreatePollRequest, InsertMessage, MentionListResponse, MessageBody, MessageRevision, NewPoll,
+    PollDetail, ReactionSummary, SearchMessageResponse, SendDirectMessagePayload,
+    SendGroupMessagePayload, ThreadStats,
+};
 use crate::modules::message::repository::MessageRepository;
-use crate::modules::message::schema::{MessageEntity, MessageType};
+use crate::modules::message::schema::{
+    ContactCard, MessageEntity, MessageLocation, MessageType, PollEntity,
+};
+use crate::modules::user::model::is_username_char;
 use crate::modules::websocket::message::{LastMessageInfo, SenderInfo, ServerMessage};
 use crate::modules::websocket::server::WebSocketServer;
 
+/// Số phương án tối đa của một bình chọn
+const MAX_POLL_OPTIONS: usize = 10;
+
+/// Độ dài tối đa của một phương án bình chọn
+const MAX_POLL_OPTION_LENGTH: usize = 100;
+
+/// Độ dài tối đa của nhãn địa điểm
+const MAX_LOCATION_LABEL_LENGTH: usize = 200;
+
+/// Thời gian tối đa chia sẻ vị trí trực tiếp (giờ)
+const MAX_LIVE_LOCATION_HOURS: i64 = 8;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum MessageRoute {
     Group,
     Direct { recipient_id: Uuid },
 }
 
+/// Tin nhắn đã ghi trong transaction của caller, chờ commit rồi mới fan-out qua WebSocket
+pub(crate) struct PendingMessage {
+    message: MessageEntity,
+    unread_counts: HashMap<Uuid, i32>,
+    mentioned_user_ids: Vec<Uuid>,
+    muted_user_ids: Vec<Uuid>,
+    thread_stats: Option<ThreadStats>,
+    started_at: Instant,
+}
+
+impl PendingMessage {
+    pub(crate) fn message(&self) -> &MessageEntity {
+        &self.message
+    }
+}
+
 /// Message service với generic repositories để dễ testing
 #[derive(Clone)]
 pub struct MessageService<M, C, P, L>
@@ -84,6 +127,25 @@ where
         conversation_id: Uuid,
         content: String,
     ) -> Result<MessageEntity, error::SystemError> {
+        let route = self
+            .resolve_conversation_route(sender_id, conversation_id)
+            .await?;
+
+        match route {
+            MessageRoute::Group => self.send_group_message(sender_id, content, conversation_id).await,
+            MessageRoute::Direct { recipient_id } => {
+                self.send_direct_message(sender_id, recipient_id, content, Some(conversation_id))
+                    .await
+            }
+        }
+    }
+
+    /// Kiểm tra sender là thành viên và xác định route (direct/group) của conversation
+    pub(crate) async fn resolve_conversation_route(
+        &self,
+        sender_id: Uuid,
+        conversation_id: Uuid,
+    ) -> Result<MessageRoute, error::SystemError> {
         let (conversation, is_member) = self
             .conversation_repo
             .get_conversation_and_check_membership(
@@ -102,8 +164,8 @@ where
             ));
         }
 
-        let route = match conversation._type {
-            ConversationType::Group => MessageRoute::Group,
+        match conversation._type {
+            ConversationType::Group => Ok(MessageRoute::Group),
             ConversationType::Direct => {
                 let participants = self
                     .participant_repo
@@ -117,15 +179,7 @@ where
                     &ConversationType::Direct,
                     sender_id,
                     participants.iter().map(|participant| participant.user_id),
-                )?
-            }
-        };
-
-        match route {
-            MessageRoute::Group => self.send_group_message(sender_id, content, conversation_id).await,
-            MessageRoute::Direct { recipient_id } => {
-                self.send_direct_message(sender_id, recipient_id, content, Some(conversation_id))
-                    .await
+                )
             }
         }
     }
@@ -176,6 +230,8 @@ where
                 message_type: None,
                 file_url: None,
                 reply_to_id: None,
+                location: None,
+                contact_user_id: None,
             },
         )
         .await
@@ -187,11 +243,16 @@ where
         recipient_id: Uuid,
         payload: SendDirectMessagePayload,
     ) -> Result<MessageEntity, error::SystemError> {
-        let started_at = Instant::now();
         let mut tx = self.conversation_repo.get_pool().begin().await?;
 
-        let (message_type, content, file_url) =
-            Self::normalize_message_input(payload.content, payload.message_type, payload.file_url)?;
+        let body = Self::normalize_message_body(
+            payload.content,
+            payload.message_type,
+            payload.file_url,
+            payload.location,
+            payload.contact_user_id,
+            chrono::Utc::now(),
+        )?;
 
         let conversation = match payload.conversation_id {
             Some(conv_id) => self
@@ -210,68 +271,29 @@ where
                 ),
         };
 
-        self.validate_reply_target(payload.reply_to_id, conversation.id, tx.as_mut())
-            .await?;
-
-        let message = self
-            .message_repo
-            .create(
-                &InsertMessage {
+        let pending = self
+            .insert_message(
+                InsertMessage {
                     conversation_id: conversation.id,
                     sender_id,
                     reply_to_id: payload.reply_to_id,
-                    _type: message_type,
-                    content: content.clone(),
-                    file_url: file_url.clone(),
-                },
-                tx.as_mut(),
-            )
-            .await?;
-
-        self.participant_repo
-            .increment_unread_count(&conversation.id, &recipient_id, tx.as_mut())
-            .await?;
-
-        self.last_message_repo
-            .upsert_last_message(
-                &NewLastMessage {
-                    conversation_id: conversation.id,
-                    sender_id,
-                    content: content.clone(),
-                    created_at: message.created_at,
+                    _type: body.message_type,
+                    content: body.content,
+                    file_url: body.file_url,
+                    forwarded_from_message_id: None,
+                    location: body.location,
+                    contact_user_id: body.contact_user_id,
                 },
-                tx.as_mut(),
+                None,
+                &mut tx,
             )
             .await?;
 
-        self.conversation_repo
-            .update_timestamp(&conversation.id, tx.as_mut())
-            .await?;
-
-        // Get unread counts for all participants
-        let unread_counts = self
-            .participant_repo
-            .get_unread_counts(&conversation.id, tx.as_mut())
-            .await?;
-
         tx.commit().await?;
 
-        let sender_info = self
-            .build_sender_info(conversation.id, sender_id)
-            .await
-            .unwrap_or(SenderInfo {
-                _id: sender_id,
-                display_name: String::new(),
-                avatar_url: None,
-            });
-
-        // Build and broadcast new message
-        let server_message = self.build_new_message_event(&message, &unread_counts, sender_info);
-        let participant_ids = vec![sender_id, recipient_id];
-        self.ws_server
-            .send_to_users(&participant_ids, &server_message);
+        let message = self.publish_message(pending).await;
 
-        METRICS.record_message_send_latency(started_at.elapsed());
+        self.clear_draft(sender_id, conversation.id);
 
         Ok(message)
     }
@@ -292,10 +314,10 @@ where
         self.send_group_message_payload(
             sender_id,
             conversation_id,
-            Some(content),
-            None,
-            None,
-            None,
+            SendGroupMessagePayload {
+                content: Some(content),
+                ..Default::default()
+            },
         )
         .await
     }
@@ -304,35 +326,203 @@ where
         &self,
         sender_id: Uuid,
         conversation_id: Uuid,
-        content: Option<String>,
-        message_type: Option<MessageType>,
-        file_url: Option<String>,
-        reply_to_id: Option<Uuid>,
+        payload: SendGroupMessagePayload,
     ) -> Result<MessageEntity, error::SystemError> {
-        let started_at = Instant::now();
-        let mut tx = self.conversation_repo.get_pool().begin().await?;
-
-        let (message_type, content, file_url) =
-            Self::normalize_message_input(content, message_type, file_url)?;
-
-        self.validate_reply_target(reply_to_id, conversation_id, tx.as_mut())
-            .await?;
+        let body = Self::normalize_message_body(
+            payload.content,
+            payload.message_type,
+            payload.file_url,
+            payload.location,
+            payload.contact_user_id,
+            chrono::Utc::now(),
+        )?;
 
         let message = self
-            .message_repo
-            .create(
-                &InsertMessage {
+            .send_to_conversation(
+                InsertMessage {
                     conversation_id,
                     sender_id,
-                    reply_to_id,
-                    _type: message_type,
-                    content: content.clone(),
-                    file_url: file_url.clone(),
+                    reply_to_id: payload.reply_to_id,
+                    _type: body.message_type,
+                    content: body.content,
+                    file_url: body.file_url,
+                    forwarded_from_message_id: None,
+                    location: body.location,
+                    contact_user_id: body.contact_user_id,
                 },
-                tx.as_mut(),
+                None,
             )
             .await?;
 
+        self.clear_draft(sender_id, conversation_id);
+
+        Ok(message)
+    }
+
+    /// Helper: Xoá bản nháp của sender sau khi gửi tin và báo các thiết bị xoá ô soạn tin
+    ///
+    /// Tin nhắn đã commit nên lỗi Redis chỉ được ghi log, không làm hỏng lượt gửi.
+    fn clear_draft(&self, user_id: Uuid, conversation_id: Uuid) {
+        self.ws_server.send_to_user(
+            &user_id,
+            &ServerMessage::DraftUpdated {
+                conversation_id,
+                draft: None,
+            },
+        );
+
+        let cache = self.cache.clone();
+        tokio::spawn(async move {
+            if let Err(e) = cache.delete(&draft_key(user_id, conversation_id)).await {
+                tracing::warn!(
+                    "Không xoá được bản nháp của user {} trong conversation {}: {}",
+                    user_id,
+                    conversation_id,
+                    e
+                );
+            }
+        });
+    }
+
+    /// Chuyển tiếp tin nhắn sang các conversation mà user là thành viên
+    ///
+    /// Tất cả đích được ghi trong một transaction: chỉ cần một đích không hợp lệ là từ chối cả lượt.
+    pub async fn forward_message(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        target_conversation_ids: Vec<Uuid>,
+    ) -> Result<Vec<MessageEntity>, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+        let source = self.find_message_for_member(message_id, user_id, &mut tx).await?;
+
+        if !matches!(
+            source._type,
+            MessageType::Text | MessageType::Image | MessageType::Video | MessageType::File
+        ) {
+            return Err(error::SystemError::bad_request(
+                "Không thể chuyển tiếp loại tin nhắn này",
+            ));
+        }
+
+        // Luôn trỏ về tin nhắn gốc ban đầu khi chuyển tiếp lại một tin đã chuyển tiếp
+        let forwarded_from = source.forwarded_from_message_id.unwrap_or(source.id);
+
+        let mut seen = HashSet::new();
+        let target_conversation_ids: Vec<Uuid> = target_conversation_ids
+            .into_iter()
+            .filter(|id| seen.insert(*id))
+            .collect();
+
+        // Kiểm tra toàn bộ đích trước khi ghi để không chuyển tiếp dở dang
+        for conversation_id in &target_conversation_ids {
+            let (conversation, is_member) = self
+                .conversation_repo
+                .get_conversation_and_check_membership(conversation_id, &user_id, tx.as_mut())
+                .await?;
+
+            if conversation.is_none() || !is_member {
+                return Err(error::SystemError::forbidden(
+                    "Bạn không phải thành viên của một hoặc nhiều cuộc trò chuyện đích",
+                ));
+            }
+        }
+
+        // Ghi theo thứ tự id cố định để hai lượt chuyển tiếp song song không khóa chéo nhau
+        let mut insert_order = target_conversation_ids.clone();
+        insert_order.sort();
+
+        let mut pending_by_conversation = HashMap::with_capacity(insert_order.len());
+        for conversation_id in insert_order {
+            let pending = self
+                .insert_message(
+                    InsertMessage {
+                        conversation_id,
+                        sender_id: user_id,
+                        reply_to_id: None,
+                        _type: source._type.clone(),
+                        content: source.content.clone(),
+                        file_url: source.file_url.clone(),
+                        forwarded_from_message_id: Some(forwarded_from),
+                        location: None,
+                        contact_user_id: None,
+                    },
+                    None,
+                    &mut tx,
+                )
+                .await?;
+            pending_by_conversation.insert(conversation_id, pending);
+        }
+
+        tx.commit().await?;
+
+        let mut forwarded = Vec::with_capacity(target_conversation_ids.len());
+        for conversation_id in target_conversation_ids {
+            if let Some(pending) = pending_by_conversation.remove(&conversation_id) {
+                forwarded.push(self.publish_message(pending).await);
+            }
+        }
+
+        Ok(forwarded)
+    }
+
+    /// Helper: Lưu tin nhắn vào conversation có sẵn, cập nhật unread/last message và fan-out
+    async fn send_to_conversation(
+        &self,
+        insert: InsertMessage,
+        poll: Option<&NewPoll>,
+    ) -> Result<MessageEntity, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+        let pending = self.insert_message(insert, poll, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(self.publish_message(pending).await)
+    }
+
+    /// Ghi tin nhắn vào conversation có sẵn trong transaction của caller
+    ///
+    /// Caller commit rồi gọi `publish_message`, nhờ vậy tin nhắn có thể đi cùng
+    /// thay đổi khác (cài đặt nhóm, trạng thái tin hẹn giờ...) trong một transaction.
+    pub(crate) async fn insert_message(
+        &self,
+        insert: InsertMessage,
+        poll: Option<&NewPoll>,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<PendingMessage, error::SystemError> {
+        let started_at = Instant::now();
+
+        let conversation_id = insert.conversation_id;
+        let sender_id = insert.sender_id;
+        let reply_to_id = insert.reply_to_id;
+
+        self.validate_reply_target(reply_to_id, conversation_id, tx.as_mut())
+            .await?;
+
+        let contact = self
+            .load_contact_card(sender_id, insert.contact_user_id, tx.as_mut())
+            .await?;
+
+        let mut message = self.message_repo.create(&insert, tx.as_mut()).await?;
+        message.contact = contact;
+
+        if let Some(poll) = poll {
+            self.message_repo
+                .create_poll(&message.id, poll, tx.as_mut())
+                .await?;
+        }
+
+        // Tin nhắn chuyển tiếp không ping lại những người được nhắc trong bản gốc
+        let mentioned_user_ids =
+            if message._type == MessageType::Text && insert.forwarded_from_message_id.is_none() {
+                self.record_mentions(&message, tx).await?
+            } else {
+                vec![]
+            };
+
+        let thread_stats = self
+            .refresh_thread_stats(reply_to_id, tx)
+            .await?;
+
         self.participant_repo
             .increment_unread_count_for_others(&conversation_id, &sender_id, tx.as_mut())
             .await?;
@@ -342,7 +532,7 @@ where
                 &NewLastMessage {
                     conversation_id,
                     sender_id,
-                    content,
+                    content: insert.content,
                     created_at: message.created_at,
                 },
                 tx.as_mut(),
@@ -358,27 +548,58 @@ where
             .participant_repo
             .get_unread_counts(&conversation_id, tx.as_mut())
             .await?;
+        let muted_user_ids = self
+            .participant_repo
+            .find_muted_user_ids(&conversation_id, tx.as_mut())
+            .await?;
 
-        tx.commit().await?;
+        Ok(PendingMessage {
+            message,
+            unread_counts,
+            mentioned_user_ids,
+            muted_user_ids,
+            thread_stats,
+            started_at,
+        })
+    }
+
+    /// Fan-out tin nhắn đã commit tới các participants
+    pub(crate) async fn publish_message(&self, pending: PendingMessage) -> MessageEntity {
+        let PendingMessage {
+            message,
+            unread_counts,
+            mentioned_user_ids,
+            muted_user_ids,
+            thread_stats,
+            started_at,
+        } = pending;
 
         let sender_info = self
-            .build_sender_info(conversation_id, sender_id)
+            .build_sender_info(message.conversation_id, message.sender_id)
             .await
             .unwrap_or(SenderInfo {
-                _id: sender_id,
+                _id: message.sender_id,
                 display_name: String::new(),
                 avatar_url: None,
             });
 
-        // Build and broadcast new message
-        let server_message = self.build_new_message_event(&message, &unread_counts, sender_info);
         let participant_ids: Vec<Uuid> = unread_counts.keys().copied().collect();
-        self.ws_server
-            .send_to_users(&participant_ids, &server_message);
+        self.broadcast_new_message(
+            &message,
+            &unread_counts,
+            sender_info,
+            mentioned_user_ids,
+            &muted_user_ids,
+            &participant_ids,
+        );
+
+        if let Some(stats) = thread_stats {
+            self.notify_thread_updated(stats);
+        }
 
         METRICS.record_message_send_latency(started_at.elapsed());
 
-        Ok(message)
+        message
     }
 
     /// Xóa message (soft delete)
@@ -414,6 +635,10 @@ where
             ));
         }
 
+        let thread_stats = self
+            .refresh_thread_stats(message.reply_to_id, &mut tx)
+            .await?;
+
         let participants = self
             .participant_repo
             .find_participants_by_conversation_id(&[message.conversation_id], tx.as_mut())
@@ -430,9 +655,109 @@ where
             },
         );
 
+        if let Some(stats) = thread_stats {
+            self.notify_thread_updated(stats);
+        }
+
         Ok(())
     }
 
+    /// Ghi system message (thông báo thay đổi cài đặt, thành viên mới...) trong transaction của
+    /// caller để thông báo và thay đổi được commit cùng nhau
+    pub(crate) async fn insert_system_message(
+        &self,
+        sender_id: Uuid,
+        conversation_id: Uuid,
+        content: String,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<PendingMessage, error::SystemError> {
+        self.insert_message(
+            InsertMessage {
+                conversation_id,
+                sender_id,
+                reply_to_id: None,
+                _type: MessageType::System,
+                content: Some(content),
+                file_url: None,
+                forwarded_from_message_id: None,
+                location: None,
+                contact_user_id: None,
+            },
+            None,
+            tx,
+        )
+        .await
+    }
+
+    /// Soft delete một lô tin nhắn tự hủy đã hết hạn và báo MessageDeleted tới participants
+    ///
+    /// Trả về số tin nhắn đã xóa trong lượt này.
+    pub async fn reap_expired_messages(&self, limit: i64) -> Result<usize, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let expired = self
+            .message_repo
+            .delete_expired_messages(limit, tx.as_mut())
+            .await?;
+
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        let root_ids: HashSet<Uuid> = expired.iter().filter_map(|m| m.reply_to_id).collect();
+        let mut thread_stats = Vec::with_capacity(root_ids.len());
+        for root_id in root_ids {
+            if let Some(stats) = self.refresh_thread_stats(Some(root_id), &mut tx).await? {
+                thread_stats.push(stats);
+            }
+        }
+
+        let conversation_ids: Vec<Uuid> = expired
+            .iter()
+            .map(|m| m.conversation_id)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        // Preview của conversation không được giữ lại nội dung đã hủy
+        self.last_message_repo
+            .refresh_last_messages(&conversation_ids, tx.as_mut())
+            .await?;
+
+        let participants = self
+            .participant_repo
+            .find_participants_by_conversation_id(&conversation_ids, tx.as_mut())
+            .await?;
+
+        tx.commit().await?;
+
+        let mut participants_by_conversation: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for participant in participants {
+            participants_by_conversation
+                .entry(participant.conversation_id)
+                .or_default()
+                .push(participant.user_id);
+        }
+
+        for message in &expired {
+            if let Some(participant_ids) = participants_by_conversation.get(&message.conversation_id) {
+                self.ws_server.send_to_users(
+                    participant_ids,
+                    &ServerMessage::MessageDeleted {
+                        conversation_id: message.conversation_id,
+                        message_id: message.id,
+                    },
+                );
+            }
+        }
+
+        for stats in thread_stats {
+            self.notify_thread_updated(stats);
+        }
+
+        Ok(expired.len())
+    }
+
     /// Chỉnh sửa message
     ///
     /// Chỉ sender mới có thể edit message của mình
@@ -456,6 +781,28 @@ where
             ));
         }
 
+        if message._type == MessageType::Poll {
+            return Err(error::SystemError::bad_request(
+                "Không thể chỉnh sửa bình chọn",
+            ));
+        }
+
+        if matches!(message._type, MessageType::Location | MessageType::Contact) {
+            return Err(error::SystemError::bad_request(
+                "Không thể chỉnh sửa tin nhắn vị trí hoặc danh thiếp",
+            ));
+        }
+
+        if !Self::is_within_edit_window(
+            message.created_at,
+            chrono::Utc::now(),
+            crate::ENV.message_edit_window,
+        ) {
+            return Err(error::SystemError::forbidden(
+                "Đã quá thời gian cho phép chỉnh sửa tin nhắn",
+            ));
+        }
+
         let edited_message = self
             .message_repo
             .edit_message(&message_id, &user_id, &new_content, tx.as_mut())
@@ -482,30 +829,720 @@ where
         Ok(edited_message)
     }
 
-    /// Helper: Build new-message event với format tương thích Socket.IO
-    async fn build_sender_info(
+    /// Ghi nhận tin nhắn đã tới session của người nhận và báo cho người gửi
+    pub async fn acknowledge_delivery(
         &self,
+        user_id: Uuid,
         conversation_id: Uuid,
-        sender_id: Uuid,
-    ) -> Result<SenderInfo, error::SystemError> {
-        let participants = self
-            .participant_repo
-            .find_participants_by_conversation_id(
-                &[conversation_id],
-                self.conversation_repo.get_pool(),
-            )
+        message_id: Uuid,
+    ) -> Result<(), error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let message = self
+            .find_message_for_member(message_id, user_id, &mut tx)
             .await?;
 
-        let sender = participants
-            .into_iter()
-            .find(|participant| participant.user_id == sender_id);
+        tx.commit().await?;
 
-        Ok(match sender {
-            Some(participant) => SenderInfo {
-                _id: sender_id,
-                display_name: participant.display_name,
-                avatar_url: participant.avatar_url,
-            },
+        if message.conversation_id != conversation_id {
+            return Err(error::SystemError::bad_request(
+                "Tin nhắn không thuộc cuộc trò chuyện này",
+            ));
+        }
+
+        if message.sender_id == user_id {
+            return Ok(());
+        }
+
+        self.ws_server.send_to_user(
+            &message.sender_id,
+            &ServerMessage::MessageDelivered {
+                conversation_id,
+                message_id,
+                user_id,
+                delivered_at: chrono::Utc::now().to_rfc3339(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Thả reaction vào tin nhắn
+    ///
+    /// Idempotent: thả lại cùng emoji không tạo bản ghi mới và không broadcast lại
+    pub async fn add_reaction(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String,
+    ) -> Result<Vec<ReactionSummary>, error::SystemError> {
+        let emoji = Self::normalize_emoji(emoji)?;
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let message = self
+            .find_message_for_member(message_id, user_id, &mut tx)
+            .await?;
+
+        let added = self
+            .message_repo
+            .add_reaction(&message_id, &user_id, &emoji, tx.as_mut())
+            .await?;
+
+        let summaries = self
+            .message_repo
+            .get_reaction_summaries(&[message_id], tx.as_mut())
+            .await?;
+
+        let participants = self
+            .participant_repo
+            .find_participants_by_conversation_id(&[message.conversation_id], tx.as_mut())
+            .await?;
+        let participant_ids: Vec<Uuid> = participants.into_iter().map(|p| p.user_id).collect();
+
+        tx.commit().await?;
+
+        if added {
+            self.ws_server.send_to_users(
+                &participant_ids,
+                &ServerMessage::ReactionAdded {
+                    conversation_id: message.conversation_id,
+                    message_id,
+                    user_id,
+                    emoji,
+                },
+            );
+        }
+
+        Ok(summaries.into_iter().map(ReactionSummary::from).collect())
+    }
+
+    /// Gỡ reaction của chính mình khỏi tin nhắn
+    pub async fn remove_reaction(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String,
+    ) -> Result<(), error::SystemError> {
+        let emoji = Self::normalize_emoji(emoji)?;
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let message = self
+            .find_message_for_member(message_id, user_id, &mut tx)
+            .await?;
+
+        let removed = self
+            .message_repo
+            .remove_reaction(&message_id, &user_id, &emoji, tx.as_mut())
+            .await?;
+
+        if !removed {
+            return Err(error::SystemError::not_found("Không tìm thấy reaction"));
+        }
+
+        let participants = self
+            .participant_repo
+            .find_participants_by_conversation_id(&[message.conversation_id], tx.as_mut())
+            .await?;
+        let participant_ids: Vec<Uuid> = participants.into_iter().map(|p| p.user_id).collect();
+
+        tx.commit().await?;
+
+        self.ws_server.send_to_users(
+            &participant_ids,
+            &ServerMessage::ReactionRemoved {
+                conversation_id: message.conversation_id,
+                message_id,
+                user_id,
+                emoji,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Tạo bình chọn: tin nhắn loại poll (content = câu hỏi) và bản ghi polls trong cùng transaction
+    pub async fn create_poll(
+        &self,
+        sender_id: Uuid,
+        request: CreatePollRequest,
+    ) -> Result<PollDetail, error::SystemError> {
+        let now = chrono::Utc::now();
+        let poll = Self::normalize_poll_input(
+            request.question,
+            request.options,
+            request.multiple_choice,
+            request.closes_at,
+            now,
+        )?;
+
+        self.resolve_conversation_route(sender_id, request.conversation_id)
+            .await?;
+
+        let message = self
+            .send_to_conversation(
+                InsertMessage {
+                    conversation_id: request.conversation_id,
+                    sender_id,
+                    reply_to_id: None,
+                    _type: MessageType::Poll,
+                    content: Some(poll.question.clone()),
+                    file_url: None,
+                    forwarded_from_message_id: None,
+                    location: None,
+                    contact_user_id: None,
+                },
+                Some(&poll),
+            )
+            .await?;
+
+        let entity = PollEntity {
+            message_id: message.id,
+            question: poll.question,
+            options: poll.options,
+            multiple_choice: poll.multiple_choice,
+            closes_at: poll.closes_at,
+            closed_at: None,
+            created_at: message.created_at,
+        };
+
+        Ok(PollDetail::from_tallies(entity, vec![], now))
+    }
+
+    /// Lấy poll kèm kết quả hiện tại
+    pub async fn get_poll(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PollDetail, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.find_message_for_member(message_id, user_id, &mut tx)
+            .await?;
+
+        let poll = self
+            .message_repo
+            .find_poll(&message_id, tx.as_mut())
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy bình chọn"))?;
+
+        let tallies = self
+            .message_repo
+            .get_poll_tallies(&message_id, tx.as_mut())
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(PollDetail::from_tallies(poll, tallies, chrono::Utc::now()))
+    }
+
+    /// Bình chọn một phương án, poll một đáp án sẽ thay lựa chọn cũ của user
+    pub async fn vote_poll(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        option_index: i16,
+    ) -> Result<PollDetail, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let (message, poll) = self
+            .find_open_poll_for_member(message_id, user_id, &mut tx)
+            .await?;
+
+        if option_index < 0 || option_index as usize >= poll.options.len() {
+            return Err(error::SystemError::bad_request("Phương án bình chọn không hợp lệ"));
+        }
+
+        if !poll.multiple_choice {
+            self.message_repo
+                .clear_poll_votes(&message_id, &user_id, tx.as_mut())
+                .await?;
+        }
+
+        self.message_repo
+            .add_poll_vote(&message_id, &user_id, option_index, tx.as_mut())
+            .await?;
+
+        self.finish_poll_update(tx, message.conversation_id, poll)
+            .await
+    }
+
+    /// Rút lại phiếu đã bầu cho một phương án
+    pub async fn retract_poll_vote(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        option_index: i16,
+    ) -> Result<PollDetail, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let (message, poll) = self
+            .find_open_poll_for_member(message_id, user_id, &mut tx)
+            .await?;
+
+        let removed = self
+            .message_repo
+            .remove_poll_vote(&message_id, &user_id, option_index, tx.as_mut())
+            .await?;
+
+        if !removed {
+            return Err(error::SystemError::not_found(
+                "Bạn chưa bình chọn phương án này",
+            ));
+        }
+
+        self.finish_poll_update(tx, message.conversation_id, poll)
+            .await
+    }
+
+    /// Đóng bình chọn (người tạo poll hoặc owner/admin của nhóm)
+    pub async fn close_poll(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PollDetail, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let (message, mut poll) = self
+            .find_open_poll_for_member(message_id, user_id, &mut tx)
+            .await?;
+
+        if message.sender_id != user_id {
+            let role = self
+                .conversation_repo
+                .get_participant_role(&message.conversation_id, &user_id, tx.as_mut())
+                .await?;
+
+            if role < Some(ParticipantRole::Admin) {
+                return Err(error::SystemError::forbidden(
+                    "Chỉ người tạo bình chọn hoặc quản trị viên mới có thể đóng bình chọn",
+                ));
+            }
+        }
+
+        self.message_repo
+            .close_poll(&message_id, tx.as_mut())
+            .await?;
+        poll.closed_at = Some(chrono::Utc::now());
+
+        self.finish_poll_update(tx, message.conversation_id, poll)
+            .await
+    }
+
+    /// Helper: Tìm poll còn mở của tin nhắn mà user là thành viên (khoá poll tới hết transaction)
+    async fn find_open_poll_for_member(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(MessageEntity, PollEntity), error::SystemError> {
+        let message = self
+            .find_message_for_member(message_id, user_id, tx)
+            .await?;
+
+        let poll = self
+            .message_repo
+            .lock_poll(&message_id, tx.as_mut())
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy bình chọn"))?;
+
+        if poll.is_closed(chrono::Utc::now()) {
+            return Err(error::SystemError::bad_request("Bình chọn đã kết thúc"));
+        }
+
+        Ok((message, poll))
+    }
+
+    /// Helper: Tính lại kết quả, commit và broadcast PollUpdated tới participants
+    async fn finish_poll_update(
+        &self,
+        mut tx: sqlx::Transaction<'_, sqlx::Postgres>,
+        conversation_id: Uuid,
+        poll: PollEntity,
+    ) -> Result<PollDetail, error::SystemError> {
+        let tallies = self
+            .message_repo
+            .get_poll_tallies(&poll.message_id, tx.as_mut())
+            .await?;
+
+        let participants = self
+            .participant_repo
+            .find_participants_by_conversation_id(&[conversation_id], tx.as_mut())
+            .await?;
+        let participant_ids: Vec<Uuid> = participants.into_iter().map(|p| p.user_id).collect();
+
+        tx.commit().await?;
+
+        let detail = PollDetail::from_tallies(poll, tallies, chrono::Utc::now());
+
+        self.ws_server.send_to_users(
+            &participant_ids,
+            &ServerMessage::PollUpdated {
+                conversation_id,
+                poll: detail.clone(),
+            },
+        );
+
+        Ok(detail)
+    }
+
+    /// Cập nhật toạ độ của vị trí trực tiếp và broadcast LiveLocationUpdated tới participants
+    pub async fn update_live_location(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<MessageLocation, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        let message = self
+            .find_message_for_member(message_id, user_id, &mut tx)
+            .await?;
+
+        if message.sender_id != user_id {
+            return Err(error::SystemError::forbidden(
+                "Chỉ người chia sẻ mới có thể cập nhật vị trí",
+            ));
+        }
+
+        if !message
+            .location
+            .get()
+            .is_some_and(|location| location.is_live(chrono::Utc::now()))
+        {
+            return Err(error::SystemError::bad_request(
+                "Tin nhắn không phải vị trí trực tiếp đang chia sẻ",
+            ));
+        }
+
+        let updated = self
+            .message_repo
+            .update_live_location(&message_id, &user_id, latitude, longitude, tx.as_mut())
+            .await?
+            .and_then(|message| message.location.get())
+            .ok_or_else(|| {
+                error::SystemError::bad_request("Chia sẻ vị trí trực tiếp đã kết thúc")
+            })?;
+
+        let participants = self
+            .participant_repo
+            .find_participants_by_conversation_id(&[message.conversation_id], tx.as_mut())
+            .await?;
+        let participant_ids: Vec<Uuid> = participants.into_iter().map(|p| p.user_id).collect();
+
+        tx.commit().await?;
+
+        self.ws_server.send_to_users(
+            &participant_ids,
+            &ServerMessage::LiveLocationUpdated {
+                conversation_id: message.conversation_id,
+                message_id,
+                location: updated.clone(),
+            },
+        );
+
+        Ok(updated)
+    }
+
+    /// Helper: Lấy danh thiếp sender muốn chia sẻ
+    ///
+    /// Chỉ chia sẻ được danh thiếp của chính mình hoặc bạn bè; tài khoản không tồn tại
+    /// cũng trả cùng lỗi để không dò được user id nào có thật.
+    async fn load_contact_card<'e, E>(
+        &self,
+        sender_id: Uuid,
+        contact_user_id: Option<Uuid>,
+        tx: E,
+    ) -> Result<Option<ContactCard>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let Some(contact_user_id) = contact_user_id else {
+            return Ok(None);
+        };
+
+        self.message_repo
+            .find_shareable_contact_card(&sender_id, &contact_user_id, tx)
+            .await?
+            .map(Some)
+            .ok_or_else(|| {
+                error::SystemError::forbidden(
+                    "Chỉ có thể chia sẻ danh thiếp của chính bạn hoặc bạn bè",
+                )
+            })
+    }
+
+    /// Lấy danh sách reactions (đã tổng hợp theo emoji) của một tin nhắn
+    pub async fn get_reactions(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<ReactionSummary>, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.find_message_for_member(message_id, user_id, &mut tx)
+            .await?;
+
+        let summaries = self
+            .message_repo
+            .get_reaction_summaries(&[message_id], tx.as_mut())
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(summaries.into_iter().map(ReactionSummary::from).collect())
+    }
+
+    /// Lịch sử chỉnh sửa của tin nhắn (chỉ thành viên conversation)
+    pub async fn get_edit_history(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<MessageRevision>, error::SystemError> {
+        let mut tx = self.conversation_repo.get_pool().begin().await?;
+
+        self.find_message_for_member(message_id, user_id, &mut tx)
+            .await?;
+
+        let revisions = self
+            .message_repo
+            .find_edit_history(&message_id, tx.as_mut())
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(revisions)
+    }
+
+    /// Tìm kiếm tin nhắn (full-text) trong mọi cuộc trò chuyện user đang tham gia
+    pub async fn search_messages(
+        &self,
+        user_id: Uuid,
+        query: String,
+        limit: i32,
+        cursor: Option<String>,
+    ) -> Result<SearchMessageResponse, error::SystemError> {
+        let query = query.trim().to_owned();
+        if query.is_empty() {
+            return Err(error::SystemError::bad_request(
+                "Từ khóa tìm kiếm không được để trống",
+            ));
+        }
+
+        let before = Cursor::parse(cursor.as_deref())?;
+
+        let mut results = self
+            .message_repo
+            .search_messages(
+                &user_id,
+                &query,
+                before.as_ref(),
+                limit,
+                self.message_repo.get_pool(),
+            )
+            .await?;
+
+        let next_cursor = if results.len() > limit as usize {
+            results.pop();
+            results.last().map(|r| Cursor::new(r.created_at, r.id).encode())
+        } else {
+            None
+        };
+
+        Ok(SearchMessageResponse {
+            results,
+            cursor: next_cursor,
+        })
+    }
+
+    /// Danh sách tin nhắn nhắc tới user (có phân trang cursor)
+    pub async fn get_mentions(
+        &self,
+        user_id: Uuid,
+        limit: i32,
+        cursor: Option<String>,
+    ) -> Result<MentionListResponse, error::SystemError> {
+        let before = Cursor::parse(cursor.as_deref())?;
+
+        let mut messages = self
+            .message_repo
+            .find_mentions_for_user(&user_id, before.as_ref(), limit, self.message_repo.get_pool())
+            .await?;
+
+        let next_cursor = if messages.len() > limit as usize {
+            messages.pop();
+            messages.last().map(|m| Cursor::new(m.created_at, m.id).encode())
+        } else {
+            None
+        };
+
+        Ok(MentionListResponse {
+            messages,
+            cursor: next_cursor,
+        })
+    }
+
+    /// Helper: Resolve và lưu mentions của tin nhắn, tăng mention_count cho người được nhắc
+    async fn record_mentions(
+        &self,
+        message: &MessageEntity,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<Uuid>, error::SystemError> {
+        let Some(content) = message.content.as_deref() else {
+            return Ok(vec![]);
+        };
+
+        let (usernames, mention_all) = Self::parse_mentions(content);
+        if usernames.is_empty() && !mention_all {
+            return Ok(vec![]);
+        }
+
+        // Mentions chỉ có trong nhóm, tin direct không ghi dù đi qua chuyển tiếp hay hẹn giờ
+        let is_group = self
+            .conversation_repo
+            .find_by_id(&message.conversation_id, tx.as_mut())
+            .await?
+            .is_some_and(|conversation| conversation._type == ConversationType::Group);
+        if !is_group {
+            return Ok(vec![]);
+        }
+
+        let mentioned = self
+            .participant_repo
+            .find_mentioned_user_ids(
+                &message.conversation_id,
+                &message.sender_id,
+                &usernames,
+                mention_all,
+                tx.as_mut(),
+            )
+            .await?;
+
+        if mentioned.is_empty() {
+            return Ok(mentioned);
+        }
+
+        self.message_repo
+            .create_mentions(&message.id, &mentioned, tx.as_mut())
+            .await?;
+        self.participant_repo
+            .increment_mention_count(&message.conversation_id, &mentioned, tx.as_mut())
+            .await?;
+
+        Ok(mentioned)
+    }
+
+    /// Tách các token `@username` / `@all` trong nội dung tin nhắn
+    ///
+    /// Username được lowercase và loại trùng; token phải đứng đầu chuỗi hoặc sau khoảng trắng.
+    /// Bộ ký tự khớp với ràng buộc username lúc đăng ký/đổi tên.
+    pub(crate) fn parse_mentions(content: &str) -> (Vec<String>, bool) {
+        let mut usernames: Vec<String> = Vec::new();
+        let mut mention_all = false;
+
+        for word in content.split_whitespace() {
+            let Some(rest) = word.strip_prefix('@') else {
+                continue;
+            };
+
+            let name: String = rest
+                .chars()
+                .take_while(|c| is_username_char(*c))
+                .collect();
+            let name = name.trim_end_matches(['.', '-']).to_ascii_lowercase();
+
+            if name.is_empty() {
+                continue;
+            }
+
+            if name == "all" {
+                mention_all = true;
+            } else if !usernames.contains(&name) {
+                usernames.push(name);
+            }
+        }
+
+        (usernames, mention_all)
+    }
+
+    /// Helper: Lấy tin nhắn và đảm bảo user là thành viên của cuộc trò chuyện chứa nó
+    async fn find_message_for_member(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<MessageEntity, error::SystemError> {
+        let message = self
+            .message_repo
+            .find_by_id(&message_id, tx.as_mut())
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy tin nhắn"))?;
+
+        let (_, is_member) = self
+            .conversation_repo
+            .get_conversation_and_check_membership(&message.conversation_id, &user_id, tx.as_mut())
+            .await?;
+
+        if !is_member {
+            return Err(error::SystemError::forbidden(
+                "Bạn không phải thành viên của cuộc trò chuyện này",
+            ));
+        }
+
+        Ok(message)
+    }
+
+    /// Helper: Tính lại thống kê thread của tin nhắn gốc (nếu có) trong cùng transaction
+    async fn refresh_thread_stats<'e>(
+        &self,
+        root_message_id: Option<Uuid>,
+        tx: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<ThreadStats>, error::SystemError> {
+        let Some(root_id) = root_message_id else {
+            return Ok(None);
+        };
+
+        self.message_repo.refresh_thread_stats(&root_id, tx).await
+    }
+
+    /// Helper: Thông báo thread thay đổi tới các thành viên trong room
+    fn notify_thread_updated(&self, stats: ThreadStats) {
+        self.ws_server.broadcast_to_room(
+            stats.conversation_id,
+            &ServerMessage::ThreadUpdated {
+                conversation_id: stats.conversation_id,
+                root_message_id: stats.root_message_id,
+                reply_count: stats.reply_count,
+                last_reply_at: stats.last_reply_at.map(|at| at.to_rfc3339()),
+            },
+            None,
+        );
+    }
+
+    /// Helper: Build new-message event với format tương thích Socket.IO
+    async fn build_sender_info(
+        &self,
+        conversation_id: Uuid,
+        sender_id: Uuid,
+    ) -> Result<SenderInfo, error::SystemError> {
+        let participants = self
+            .participant_repo
+            .find_participants_by_conversation_id(
+                &[conversation_id],
+                self.conversation_repo.get_pool(),
+            )
+            .await?;
+
+        let sender = participants
+            .into_iter()
+            .find(|participant| participant.user_id == sender_id);
+
+        Ok(match sender {
+            Some(participant) => SenderInfo {
+                _id: sender_id,
+                display_name: participant.display_name,
+                avatar_url: participant.avatar_url,
+            },
             None => SenderInfo {
                 _id: sender_id,
                 display_name: String::new(),
@@ -514,12 +1551,46 @@ where
         })
     }
 
+    /// Helper: Gửi new-message tới người nhận
+    ///
+    /// Danh sách tắt thông báo là riêng tư: mỗi người nhận chỉ biết cờ `muted` của chính mình.
+    fn broadcast_new_message(
+        &self,
+        message: &MessageEntity,
+        unread_counts: &HashMap<Uuid, i32>,
+        sender_info: SenderInfo,
+        mentioned_user_ids: Vec<Uuid>,
+        muted_user_ids: &[Uuid],
+        recipient_ids: &[Uuid],
+    ) {
+        let muted_user_ids: HashSet<&Uuid> = muted_user_ids.iter().collect();
+        let (muted, unmuted): (Vec<Uuid>, Vec<Uuid>) = recipient_ids
+            .iter()
+            .partition(|id| muted_user_ids.contains(id));
+
+        for (recipients, is_muted) in [(unmuted, false), (muted, true)] {
+            if recipients.is_empty() {
+                continue;
+            }
+            let server_message = self.build_new_message_event(
+                message,
+                unread_counts,
+                sender_info.clone(),
+                mentioned_user_ids.clone(),
+                is_muted,
+            );
+            self.ws_server.send_to_users(&recipients, &server_message);
+        }
+    }
+
     /// Helper: Build new-message event với format tương thích Socket.IO
     fn build_new_message_event(
         &self,
         message: &MessageEntity,
         unread_counts: &HashMap<Uuid, i32>,
         sender_info: SenderInfo,
+        mentioned_user_ids: Vec<Uuid>,
+        muted: bool,
     ) -> ServerMessage {
         let message_json = serde_json::to_value(message).unwrap_or_default();
 
@@ -542,10 +1613,12 @@ where
             last_message,
             message.created_at.to_rfc3339(),
             unread_counts_json,
+            mentioned_user_ids,
+            muted,
         )
     }
 
-    async fn validate_reply_target<'e, E>(
+    pub(crate) async fn validate_reply_target<'e, E>(
         &self,
         reply_to_id: Option<Uuid>,
         conversation_id: Uuid,
@@ -573,48 +1646,274 @@ where
         Ok(())
     }
 
-    pub(crate) fn normalize_message_input(
+    /// Tin nhắn còn trong thời gian được phép chỉnh sửa (window = 0: không giới hạn)
+    pub(crate) fn is_within_edit_window(
+        created_at: chrono::DateTime<chrono::Utc>,
+        now: chrono::DateTime<chrono::Utc>,
+        window_secs: u64,
+    ) -> bool {
+        if window_secs == 0 {
+            return true;
+        }
+
+        let window = chrono::Duration::seconds(i64::try_from(window_secs).unwrap_or(i64::MAX));
+        now.signed_duration_since(created_at) <= window
+    }
+
+    /// Chuẩn hoá tin nhắn gửi lên, kể cả dữ liệu có cấu trúc của tin nhắn vị trí/danh thiếp
+    ///
+    /// Không truyền `type` thì suy ra từ `location` / `contact_user_id` trước, sau đó tới file/text.
+    pub(crate) fn normalize_message_body(
         content: Option<String>,
         message_type: Option<MessageType>,
         file_url: Option<String>,
-    ) -> Result<(MessageType, Option<String>, Option<String>), error::SystemError> {
-        let normalized_content = content
-            .map(|value| value.trim().to_owned())
-            .filter(|value| !value.is_empty());
-        let normalized_file_url = file_url
-            .map(|value| value.trim().to_owned())
-            .filter(|value| !value.is_empty());
-
-        if normalized_content.is_none() && normalized_file_url.is_none() {
+        location: Option<MessageLocation>,
+        contact_user_id: Option<Uuid>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<MessageBody, error::SystemError> {
+        let message_type = message_type.or(match (&location, contact_user_id) {
+            (Some(_), _) => Some(MessageType::Location),
+            (None, Some(_)) => Some(MessageType::Contact),
+            (None, None) => None,
+        });
+
+        if !matches!(
+            message_type,
+            Some(MessageType::Location) | Some(MessageType::Contact)
+        ) {
+            if location.is_some() || contact_user_id.is_some() {
+                return Err(error::SystemError::bad_request(
+                    "Chỉ tin nhắn vị trí/danh thiếp mới được kèm location hoặc contact_user_id",
+                ));
+            }
+
+            let (message_type, content, file_url) =
+                normalize_message_input(content, message_type, file_url)?;
+
+            return Ok(MessageBody {
+                message_type,
+                content,
+                file_url,
+                location: None,
+                contact_user_id: None,
+            });
+        }
+
+        let has_text =
+            |value: &Option<String>| value.as_deref().is_some_and(|v| !v.trim().is_empty());
+        if has_text(&content) || has_text(&file_url) {
             return Err(error::SystemError::bad_request(
-                "Tin nhắn phải có nội dung hoặc tệp đính kèm",
+                "Tin nhắn vị trí/danh thiếp không kèm nội dung hoặc tệp",
             ));
         }
 
-        let resolved_type = message_type.unwrap_or_else(|| {
-            if normalized_file_url.is_some() {
-                MessageType::File
-            } else {
-                MessageType::Text
+        if message_type == Some(MessageType::Contact) {
+            let contact_user_id = contact_user_id.ok_or_else(|| {
+                error::SystemError::bad_request("Tin nhắn danh thiếp yêu cầu contact_user_id")
+            })?;
+
+            if location.is_some() {
+                return Err(error::SystemError::bad_request(
+                    "Tin nhắn danh thiếp không kèm vị trí",
+                ));
             }
-        });
 
-        if matches!(resolved_type, MessageType::Image | MessageType::Video | MessageType::File)
-            && normalized_file_url.is_none()
+            return Ok(MessageBody {
+                message_type: MessageType::Contact,
+                content: None,
+                file_url: None,
+                location: None,
+                contact_user_id: Some(contact_user_id),
+            });
+        }
+
+        let location = location.ok_or_else(|| {
+            error::SystemError::bad_request("Tin nhắn vị trí yêu cầu location")
+        })?;
+
+        if contact_user_id.is_some() {
+            return Err(error::SystemError::bad_request(
+                "Tin nhắn vị trí không kèm danh thiếp",
+            ));
+        }
+
+        Ok(MessageBody {
+            message_type: MessageType::Location,
+            content: None,
+            file_url: None,
+            location: Some(Self::normalize_location(location, now)?),
+            contact_user_id: None,
+        })
+    }
+
+    /// Kiểm tra toạ độ, nhãn và hạn chia sẻ của vị trí
+    fn normalize_location(
+        location: MessageLocation,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<MessageLocation, error::SystemError> {
+        if !(-90.0..=90.0).contains(&location.latitude)
+            || !(-180.0..=180.0).contains(&location.longitude)
+        {
+            return Err(error::SystemError::bad_request("Toạ độ vị trí không hợp lệ"));
+        }
+
+        let label = location
+            .label
+            .map(|label| label.trim().to_owned())
+            .filter(|label| !label.is_empty());
+
+        if label
+            .as_ref()
+            .is_some_and(|label| label.chars().count() > MAX_LOCATION_LABEL_LENGTH)
         {
             return Err(error::SystemError::bad_request(
-                "Loại tin nhắn này yêu cầu file_url",
+                "Tên địa điểm không được vượt quá 200 ký tự",
             ));
         }
 
-        if matches!(resolved_type, MessageType::Text | MessageType::System)
-            && normalized_content.is_none()
+        let max_live_until = now + chrono::Duration::hours(MAX_LIVE_LOCATION_HOURS);
+        if location
+            .live_until
+            .is_some_and(|until| until <= now || until > max_live_until)
         {
             return Err(error::SystemError::bad_request(
-                "Tin nhắn văn bản yêu cầu nội dung",
+                "Thời gian chia sẻ vị trí trực tiếp phải trong vòng 8 giờ tới",
             ));
         }
 
-        Ok((resolved_type, normalized_content, normalized_file_url))
+        Ok(MessageLocation {
+            latitude: location.latitude,
+            longitude: location.longitude,
+            label,
+            live_until: location.live_until,
+        })
     }
+
+    /// Chuẩn hoá câu hỏi/phương án của poll (trim, không rỗng, không trùng) và kiểm tra hạn đóng
+    pub(crate) fn normalize_poll_input(
+        question: String,
+        options: Vec<String>,
+        multiple_choice: bool,
+        closes_at: Option<chrono::DateTime<chrono::Utc>>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<NewPoll, error::SystemError> {
+        let question = question.trim().to_owned();
+        if question.is_empty() {
+            return Err(error::SystemError::bad_request(
+                "Câu hỏi bình chọn không được để trống",
+            ));
+        }
+
+        let options: Vec<String> = options
+            .into_iter()
+            .map(|option| option.trim().to_owned())
+            .collect();
+
+        if options.len() < 2 || options.len() > MAX_POLL_OPTIONS {
+            return Err(error::SystemError::bad_request(
+                "Bình chọn phải có từ 2 đến 10 phương án",
+            ));
+        }
+
+        if options
+            .iter()
+            .any(|option| option.is_empty() || option.chars().count() > MAX_POLL_OPTION_LENGTH)
+        {
+            return Err(error::SystemError::bad_request(
+                "Mỗi phương án phải có từ 1 đến 100 ký tự",
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        if !options.iter().all(|option| seen.insert(option.to_lowercase())) {
+            return Err(error::SystemError::bad_request(
+                "Các phương án bình chọn không được trùng nhau",
+            ));
+        }
+
+        if closes_at.is_some_and(|at| at <= now) {
+            return Err(error::SystemError::bad_request(
+                "Thời điểm đóng bình chọn phải ở tương lai",
+            ));
+        }
+
+        Ok(NewPoll {
+            question,
+            options,
+            multiple_choice,
+            closes_at,
+        })
+    }
+
+    pub(crate) fn normalize_emoji(emoji: String) -> Result<String, error::SystemError> {
+        let emoji = emoji.trim().to_owned();
+
+        if emoji.is_empty() || emoji.chars().count() > 32 {
+            return Err(error::SystemError::bad_request("Emoji không hợp lệ"));
+        }
+
+        if emoji.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(error::SystemError::bad_request("Emoji không hợp lệ"));
+        }
+
+        Ok(emoji)
+    }
+}
+
+/// Chuẩn hóa nội dung/tệp của tin nhắn thường và suy ra `type` khi client không truyền
+pub(crate) fn normalize_message_input(
+    content: Option<String>,
+    message_type: Option<MessageType>,
+    file_url: Option<String>,
+) -> Result<(MessageType, Option<String>, Option<String>), error::SystemError> {
+    let normalized_content = content
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty());
+    let normalized_file_url = file_url
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty());
+
+    if normalized_content.is_none() && normalized_file_url.is_none() {
+        return Err(error::SystemError::bad_request(
+            "Tin nhắn phải có nội dung hoặc tệp đính kèm",
+        ));
+    }
+
+    let resolved_type = message_type.unwrap_or_else(|| {
+        if normalized_file_url.is_some() {
+            MessageType::File
+        } else {
+            MessageType::Text
+        }
+    });
+
+    if resolved_type == MessageType::Poll {
+        return Err(error::SystemError::bad_request(
+            "Bình chọn phải được tạo qua API tạo bình chọn",
+        ));
+    }
+
+    if matches!(resolved_type, MessageType::Location | MessageType::Contact) {
+        return Err(error::SystemError::bad_request(
+            "Tin nhắn vị trí/danh thiếp yêu cầu dữ liệu location hoặc contact_user_id",
+        ));
+    }
+
+    if matches!(resolved_type, MessageType::Image | MessageType::Video | MessageType::File)
+        && normalized_file_url.is_none()
+    {
+        return Err(error::SystemError::bad_request(
+            "Loại tin nhắn này yêu cầu file_url",
+        ));
+    }
+
+    if matches!(resolved_type, MessageType::Text | MessageType::System)
+        && normalized_content.is_none()
+    {
+        return Err(error::SystemError::bad_request(
+            "Tin nhắn văn bản yêu cầu nội dung",
+        ));
+    }
+
+    Ok((resolved_type, normalized_content, normalized_file_url))
 }
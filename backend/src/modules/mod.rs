@@ -8,6 +8,7 @@ pub mod user {
     pub mod route;
     pub mod schema;
     pub mod service;
+    pub mod totp;
 }
 
 pub mod friend {
@@ -24,6 +25,7 @@ pub mod friend {
 pub mod message {
     pub mod handle;
     pub mod model;
+    pub mod reaper;
     pub mod repository;
     pub mod repository_pg;
     pub mod route;
@@ -61,4 +63,22 @@ pub mod call {
     pub mod route;
 }
 
+pub mod draft {
+    pub mod handle;
+    pub mod model;
+    pub mod route;
+    pub mod service;
+}
+
+pub mod scheduled_message {
+    pub mod dispatcher;
+    pub mod handle;
+    pub mod model;
+    pub mod repository;
+    pub mod repository_pg;
+    pub mod route;
+    pub mod schema;
+    pub mod service;
+}
+
 pub mod websocket;
\ No newline at end of file
@@ -0,0 +1,76 @@
+/// Scheduled Message Dispatcher
+///
+/// Task chạy nền định kỳ claim các tin nhắn hẹn giờ đã đến hạn và gửi chúng
+/// qua `MessageService` như tin nhắn thường (fan-out WebSocket, last message,
+/// unread count...). Việc claim dùng `FOR UPDATE SKIP LOCKED` nên nhiều instance
+/// có thể chạy song song mà không gửi trùng; lỗi tạm thời được thử lại với backoff.
+use std::{sync::Arc, time::Duration};
+
+use crate::modules::{
+    message::handle::MessageSvc,
+    scheduled_message::{handle::ScheduledMessageSvc, schema::ScheduledMessageEntity},
+};
+
+/// Chu kỳ quét tin nhắn đến hạn
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Số tin nhắn tối đa xử lý trong một lượt quét
+const DISPATCH_BATCH_SIZE: i64 = 50;
+
+pub fn spawn_dispatcher(scheduled_service: ScheduledMessageSvc, message_service: MessageSvc) {
+    let scheduled_service = Arc::new(scheduled_service);
+    let message_service = Arc::new(message_service);
+
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(DISPATCH_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+
+            let due = match scheduled_service.claim_due(DISPATCH_BATCH_SIZE).await {
+                Ok(due) => due,
+                Err(e) => {
+                    tracing::error!("Failed to claim scheduled messages: {}", e);
+                    continue;
+                }
+            };
+
+            for scheduled in due {
+                dispatch_one(&scheduled_service, &message_service, scheduled).await;
+            }
+        }
+    });
+}
+
+async fn dispatch_one(
+    scheduled_service: &ScheduledMessageSvc,
+    message_service: &MessageSvc,
+    scheduled: ScheduledMessageEntity,
+) {
+    let failure = match scheduled_service.deliver(&scheduled, message_service).await {
+        Ok(Some(_)) => return,
+        Ok(None) => {
+            tracing::info!(
+                "Scheduled message {} was already handled by another worker",
+                scheduled.id
+            );
+            return;
+        }
+        Err(e) => e,
+    };
+
+    tracing::warn!(
+        "Scheduled message {} failed (attempt {}): {}",
+        scheduled.id,
+        scheduled.attempts + 1,
+        failure
+    );
+
+    if let Err(e) = scheduled_service.record_failure(&scheduled, &failure).await {
+        tracing::error!(
+            "Failed to update scheduled message {} status: {}",
+            scheduled.id,
+            e
+        );
+    }
+}
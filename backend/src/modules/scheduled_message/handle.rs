@@ -0,0 +1,65 @@
+use actix_web::{HttpRequest, delete, get, post, web};
+use uuid::Uuid;
+
+use crate::{
+    api::{error, success},
+    middlewares::get_extensions,
+    modules::{
+        conversation::repository_pg::ConversationPgRepository,
+        message::handle::MessageSvc,
+        scheduled_message::{
+            model::{CreateScheduledMessageRequest, ScheduledMessageQuery},
+            repository_pg::ScheduledMessagePgRepository,
+            schema::ScheduledMessageEntity,
+            service::ScheduledMessageService,
+        },
+    },
+    utils::{Claims, ValidatedJson, ValidatedQuery},
+};
+
+pub type ScheduledMessageSvc =
+    ScheduledMessageService<ScheduledMessagePgRepository, ConversationPgRepository>;
+
+/// Hẹn giờ gửi tin nhắn
+#[post("")]
+pub async fn create_scheduled_message(
+    scheduled_service: web::Data<ScheduledMessageSvc>,
+    message_service: web::Data<MessageSvc>,
+    ValidatedJson(body): ValidatedJson<CreateScheduledMessageRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<ScheduledMessageEntity>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let scheduled = scheduled_service
+        .schedule(user_id, body, &message_service)
+        .await?;
+    Ok(success::Success::created(Some(scheduled)).message("Hẹn giờ gửi tin nhắn thành công"))
+}
+
+/// Danh sách tin nhắn hẹn giờ đang chờ gửi
+#[get("")]
+pub async fn get_scheduled_messages(
+    scheduled_service: web::Data<ScheduledMessageSvc>,
+    ValidatedQuery(query): ValidatedQuery<ScheduledMessageQuery>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<ScheduledMessageEntity>>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let scheduled = scheduled_service
+        .list_pending(user_id, query.conversation_id)
+        .await?;
+    Ok(success::Success::ok(Some(scheduled)).message("Lấy danh sách tin nhắn hẹn giờ thành công"))
+}
+
+/// Hủy tin nhắn hẹn giờ
+#[delete("/{scheduled_id}")]
+pub async fn cancel_scheduled_message(
+    scheduled_service: web::Data<ScheduledMessageSvc>,
+    scheduled_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    scheduled_service.cancel(user_id, *scheduled_id).await?;
+    Ok(success::Success::no_content())
+}
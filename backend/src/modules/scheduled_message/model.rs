@@ -0,0 +1,36 @@
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::modules::message::schema::MessageType;
+
+#[derive(Debug, Clone)]
+pub struct InsertScheduledMessage {
+    pub conversation_id: Uuid,
+    pub sender_id: Uuid,
+    pub _type: MessageType,
+    pub content: Option<String>,
+    pub file_url: Option<String>,
+    pub reply_to_id: Option<Uuid>,
+    pub scheduled_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateScheduledMessageRequest {
+    pub conversation_id: Uuid,
+    #[serde(default)]
+    #[validate(length(max = 5000, message = "Content must be at most 5000 characters"))]
+    pub content: Option<String>,
+    #[serde(rename = "type", default)]
+    pub _type: Option<MessageType>,
+    #[serde(default)]
+    pub file_url: Option<String>,
+    #[serde(default)]
+    pub reply_to_id: Option<Uuid>,
+    pub scheduled_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ScheduledMessageQuery {
+    pub conversation_id: Option<Uuid>,
+}
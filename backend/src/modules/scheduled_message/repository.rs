@@ -0,0 +1,84 @@
+use uuid::Uuid;
+
+use crate::{
+    api::error,
+    modules::scheduled_message::{model::InsertScheduledMessage, schema::ScheduledMessageEntity},
+};
+
+#[async_trait::async_trait]
+pub trait ScheduledMessageRepository {
+    fn get_pool(&self) -> &sqlx::PgPool;
+
+    async fn create<'e, E>(
+        &self,
+        scheduled: &InsertScheduledMessage,
+        tx: E,
+    ) -> Result<ScheduledMessageEntity, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Danh sách tin nhắn hẹn giờ đang chờ gửi của user (lọc theo conversation nếu có)
+    async fn find_pending_by_sender<'e, E>(
+        &self,
+        sender_id: &Uuid,
+        conversation_id: Option<Uuid>,
+        tx: E,
+    ) -> Result<Vec<ScheduledMessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Hủy tin nhắn hẹn giờ còn pending, trả về false nếu không có gì để hủy
+    async fn cancel<'e, E>(
+        &self,
+        scheduled_id: &Uuid,
+        sender_id: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Nhận (claim) một lô tin nhắn đến hạn bằng FOR UPDATE SKIP LOCKED
+    ///
+    /// Các bản ghi `processing` bị kẹt quá `stale_after_secs` (instance chết giữa chừng)
+    /// cũng được nhận lại.
+    async fn claim_due<'e, E>(
+        &self,
+        limit: i64,
+        stale_after_secs: f64,
+        tx: E,
+    ) -> Result<Vec<ScheduledMessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Đánh dấu đã gửi (cùng transaction với tin nhắn vừa tạo)
+    ///
+    /// Trả về false nếu bản ghi không còn `processing` (đã được instance khác xử lý).
+    async fn mark_sent<'e, E>(
+        &self,
+        scheduled_id: &Uuid,
+        message_id: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    /// Trả bản ghi về `pending` để thử lại sau `delay_secs` giây
+    async fn schedule_retry<'e, E>(
+        &self,
+        scheduled_id: &Uuid,
+        reason: &str,
+        delay_secs: f64,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+
+    async fn mark_failed<'e, E>(
+        &self,
+        scheduled_id: &Uuid,
+        reason: &str,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>;
+}
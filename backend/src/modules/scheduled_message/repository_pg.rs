@@ -0,0 +1,234 @@
+use uuid::Uuid;
+
+use crate::{
+    api::error,
+    modules::scheduled_message::{
+        model::InsertScheduledMessage, repository::ScheduledMessageRepository,
+        schema::ScheduledMessageEntity,
+    },
+};
+
+#[derive(Clone)]
+pub struct ScheduledMessagePgRepository {
+    pool: sqlx::PgPool,
+}
+
+impl ScheduledMessagePgRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ScheduledMessageRepository for ScheduledMessagePgRepository {
+    fn get_pool(&self) -> &sqlx::PgPool {
+        &self.pool
+    }
+
+    async fn create<'e, E>(
+        &self,
+        scheduled: &InsertScheduledMessage,
+        tx: E,
+    ) -> Result<ScheduledMessageEntity, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let scheduled = sqlx::query_as::<_, ScheduledMessageEntity>(
+            r#"
+            INSERT INTO scheduled_messages
+                (conversation_id, sender_id, type, content, file_url, reply_to_id, scheduled_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(scheduled.conversation_id)
+        .bind(scheduled.sender_id)
+        .bind(&scheduled._type)
+        .bind(&scheduled.content)
+        .bind(&scheduled.file_url)
+        .bind(scheduled.reply_to_id)
+        .bind(scheduled.scheduled_at)
+        .fetch_one(tx)
+        .await?;
+
+        Ok(scheduled)
+    }
+
+    async fn find_pending_by_sender<'e, E>(
+        &self,
+        sender_id: &Uuid,
+        conversation_id: Option<Uuid>,
+        tx: E,
+    ) -> Result<Vec<ScheduledMessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let scheduled = sqlx::query_as::<_, ScheduledMessageEntity>(
+            r#"
+            SELECT *
+            FROM scheduled_messages
+            WHERE sender_id = $1
+              AND status = 'pending'
+              AND ($2::uuid IS NULL OR conversation_id = $2)
+            ORDER BY scheduled_at ASC
+            "#,
+        )
+        .bind(sender_id)
+        .bind(conversation_id)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(scheduled)
+    }
+
+    async fn cancel<'e, E>(
+        &self,
+        scheduled_id: &Uuid,
+        sender_id: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // Bản ghi đang được dispatcher xử lý (processing) sẽ không bị hủy
+        let rows = sqlx::query(
+            r#"
+            UPDATE scheduled_messages
+            SET status = 'canceled'
+            WHERE id = $1
+              AND sender_id = $2
+              AND status = 'pending'
+            "#,
+        )
+        .bind(scheduled_id)
+        .bind(sender_id)
+        .execute(tx)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn claim_due<'e, E>(
+        &self,
+        limit: i64,
+        stale_after_secs: f64,
+        tx: E,
+    ) -> Result<Vec<ScheduledMessageEntity>, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        // SKIP LOCKED cho phép nhiều instance chạy dispatcher song song mà không gửi trùng
+        let claimed = sqlx::query_as::<_, ScheduledMessageEntity>(
+            r#"
+            UPDATE scheduled_messages
+            SET status = 'processing',
+                claimed_at = NOW()
+            WHERE id IN (
+                SELECT id
+                FROM scheduled_messages
+                WHERE (status = 'pending'
+                       AND scheduled_at <= NOW()
+                       AND (next_attempt_at IS NULL OR next_attempt_at <= NOW()))
+                   OR (status = 'processing' AND claimed_at < NOW() - make_interval(secs => $2))
+                ORDER BY scheduled_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .bind(stale_after_secs)
+        .fetch_all(tx)
+        .await?;
+
+        Ok(claimed)
+    }
+
+    async fn mark_sent<'e, E>(
+        &self,
+        scheduled_id: &Uuid,
+        message_id: &Uuid,
+        tx: E,
+    ) -> Result<bool, error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query(
+            r#"
+            UPDATE scheduled_messages
+            SET status = 'sent',
+                message_id = $2,
+                error = NULL
+            WHERE id = $1
+              AND status = 'processing'
+            "#,
+        )
+        .bind(scheduled_id)
+        .bind(message_id)
+        .execute(tx)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn schedule_retry<'e, E>(
+        &self,
+        scheduled_id: &Uuid,
+        reason: &str,
+        delay_secs: f64,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE scheduled_messages
+            SET status = 'pending',
+                attempts = attempts + 1,
+                next_attempt_at = NOW() + make_interval(secs => $3),
+                claimed_at = NULL,
+                error = $2
+            WHERE id = $1
+              AND status = 'processing'
+            "#,
+        )
+        .bind(scheduled_id)
+        .bind(reason)
+        .bind(delay_secs)
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn mark_failed<'e, E>(
+        &self,
+        scheduled_id: &Uuid,
+        reason: &str,
+        tx: E,
+    ) -> Result<(), error::SystemError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE scheduled_messages
+            SET status = 'failed',
+                attempts = attempts + 1,
+                error = $2
+            WHERE id = $1
+              AND status = 'processing'
+            "#,
+        )
+        .bind(scheduled_id)
+        .bind(reason)
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,12 @@
+use actix_web::web::{ServiceConfig, scope};
+
+use crate::modules::scheduled_message::handle::*;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/scheduled-messages")
+            .service(create_scheduled_message)
+            .service(get_scheduled_messages)
+            .service(cancel_scheduled_message),
+    );
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::{FromRow, Type};
+use uuid::Uuid;
+
+use crate::modules::message::schema::MessageType;
+
+#[derive(Debug, PartialEq, Clone, Type, Serialize, Deserialize)]
+#[sqlx(type_name = "scheduled_message_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduledMessageStatus {
+    Pending,
+    Processing,
+    Sent,
+    Canceled,
+    Failed,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ScheduledMessageEntity {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub sender_id: Uuid,
+    #[sqlx(rename = "type")]
+    pub _type: MessageType,
+    pub content: Option<String>,
+    pub file_url: Option<String>,
+    pub reply_to_id: Option<Uuid>,
+    pub scheduled_at: chrono::DateTime<chrono::Utc>,
+    pub status: ScheduledMessageStatus,
+    pub claimed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub message_id: Option<Uuid>,
+    pub error: Option<String>,
+    /// Số lần gửi thất bại do lỗi tạm thời
+    pub attempts: i32,
+    /// Chưa tới thời điểm này thì dispatcher chưa thử gửi lại
+    pub next_attempt_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,257 @@
+/// Scheduled Message Service
+///
+/// Service layer cho tin nhắn hẹn giờ: tạo/liệt kê/hủy và các bước
+/// claim/đánh dấu kết quả được dispatcher chạy nền sử dụng.
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::{
+    api::error,
+    modules::{
+        conversation::repository::{
+            ConversationRepository, LastMessageRepository, ParticipantRepository,
+        },
+        message::{
+            model::InsertMessage,
+            repository::MessageRepository,
+            schema::MessageEntity,
+            service::{MessageService, normalize_message_input},
+        },
+        scheduled_message::{
+            model::{CreateScheduledMessageRequest, InsertScheduledMessage},
+            repository::ScheduledMessageRepository,
+            schema::ScheduledMessageEntity,
+        },
+    },
+};
+
+/// Khoảng thời gian tối đa được phép hẹn trước
+const MAX_SCHEDULE_AHEAD_DAYS: i64 = 365;
+
+/// Sau khoảng này, bản ghi `processing` được coi là bị kẹt và có thể claim lại
+const STALE_CLAIM_SECS: f64 = 300.0;
+
+/// Số lần gửi tối đa trước khi đánh dấu failed khi gặp lỗi tạm thời
+const MAX_DISPATCH_ATTEMPTS: i32 = 5;
+
+/// Backoff giữa các lần thử lại: 15s, 30s, 60s... tối đa 10 phút
+const RETRY_BASE_SECS: f64 = 15.0;
+const RETRY_MAX_SECS: f64 = 600.0;
+
+#[derive(Clone)]
+pub struct ScheduledMessageService<S, C>
+where
+    S: ScheduledMessageRepository + Send + Sync,
+    C: ConversationRepository + Send + Sync,
+{
+    scheduled_repo: Arc<S>,
+    conversation_repo: Arc<C>,
+}
+
+impl<S, C> ScheduledMessageService<S, C>
+where
+    S: ScheduledMessageRepository + Send + Sync,
+    C: ConversationRepository + Send + Sync,
+{
+    pub fn with_dependencies(scheduled_repo: Arc<S>, conversation_repo: Arc<C>) -> Self {
+        ScheduledMessageService {
+            scheduled_repo,
+            conversation_repo,
+        }
+    }
+
+    /// Hẹn giờ gửi tin nhắn vào một conversation mà user đang là thành viên
+    ///
+    /// Tin được trả lời được kiểm tra ngay lúc hẹn giống như khi gửi trực tiếp.
+    pub async fn schedule<M, MC, P, L>(
+        &self,
+        sender_id: Uuid,
+        request: CreateScheduledMessageRequest,
+        message_service: &MessageService<M, MC, P, L>,
+    ) -> Result<ScheduledMessageEntity, error::SystemError>
+    where
+        M: MessageRepository + Send + Sync,
+        MC: ConversationRepository + Send + Sync,
+        P: ParticipantRepository + Send + Sync,
+        L: LastMessageRepository + Send + Sync,
+    {
+        Self::validate_scheduled_at(request.scheduled_at, chrono::Utc::now())?;
+
+        let (message_type, content, file_url) =
+            normalize_message_input(request.content, request._type, request.file_url)?;
+
+        let pool = self.scheduled_repo.get_pool();
+
+        let (conversation, is_member) = self
+            .conversation_repo
+            .get_conversation_and_check_membership(&request.conversation_id, &sender_id, pool)
+            .await?;
+
+        if conversation.is_none() {
+            return Err(error::SystemError::not_found("Không tìm thấy cuộc trò chuyện"));
+        }
+
+        if !is_member {
+            return Err(error::SystemError::forbidden(
+                "Bạn không phải thành viên của cuộc trò chuyện này",
+            ));
+        }
+
+        message_service
+            .validate_reply_target(request.reply_to_id, request.conversation_id, pool)
+            .await?;
+
+        self.scheduled_repo
+            .create(
+                &InsertScheduledMessage {
+                    conversation_id: request.conversation_id,
+                    sender_id,
+                    _type: message_type,
+                    content,
+                    file_url,
+                    reply_to_id: request.reply_to_id,
+                    scheduled_at: request.scheduled_at,
+                },
+                pool,
+            )
+            .await
+    }
+
+    /// Danh sách tin nhắn hẹn giờ đang chờ gửi của user
+    pub async fn list_pending(
+        &self,
+        sender_id: Uuid,
+        conversation_id: Option<Uuid>,
+    ) -> Result<Vec<ScheduledMessageEntity>, error::SystemError> {
+        self.scheduled_repo
+            .find_pending_by_sender(&sender_id, conversation_id, self.scheduled_repo.get_pool())
+            .await
+    }
+
+    /// Hủy tin nhắn hẹn giờ (chỉ khi còn pending)
+    pub async fn cancel(&self, sender_id: Uuid, scheduled_id: Uuid) -> Result<(), error::SystemError> {
+        let canceled = self
+            .scheduled_repo
+            .cancel(&scheduled_id, &sender_id, self.scheduled_repo.get_pool())
+            .await?;
+
+        if !canceled {
+            return Err(error::SystemError::not_found(
+                "Không tìm thấy tin nhắn hẹn giờ hoặc tin nhắn đã được gửi",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Claim một lô tin nhắn đến hạn cho dispatcher
+    pub async fn claim_due(&self, limit: i64) -> Result<Vec<ScheduledMessageEntity>, error::SystemError> {
+        self.scheduled_repo
+            .claim_due(limit, STALE_CLAIM_SECS, self.scheduled_repo.get_pool())
+            .await
+    }
+
+    /// Gửi một tin nhắn đã claim
+    ///
+    /// Đi qua cùng pipeline với tin gửi trực tiếp (`insert_message` → `publish_message`), nhưng
+    /// không xoá bản nháp: nháp là nội dung user đang soạn, không liên quan tới tin đã hẹn.
+    /// Tin nhắn và trạng thái `sent` được ghi trong cùng transaction, nên claim lại một bản ghi
+    /// bị kẹt không thể gửi trùng. Trả về None nếu bản ghi đã được instance khác xử lý.
+    pub async fn deliver<M, MC, P, L>(
+        &self,
+        scheduled: &ScheduledMessageEntity,
+        message_service: &MessageService<M, MC, P, L>,
+    ) -> Result<Option<MessageEntity>, error::SystemError>
+    where
+        M: MessageRepository + Send + Sync,
+        MC: ConversationRepository + Send + Sync,
+        P: ParticipantRepository + Send + Sync,
+        L: LastMessageRepository + Send + Sync,
+    {
+        // Sender có thể đã rời conversation kể từ lúc hẹn giờ
+        message_service
+            .resolve_conversation_route(scheduled.sender_id, scheduled.conversation_id)
+            .await?;
+
+        let mut tx = self.scheduled_repo.get_pool().begin().await?;
+
+        let pending = message_service
+            .insert_message(
+                InsertMessage {
+                    conversation_id: scheduled.conversation_id,
+                    sender_id: scheduled.sender_id,
+                    reply_to_id: scheduled.reply_to_id,
+                    _type: scheduled._type.clone(),
+                    content: scheduled.content.clone(),
+                    file_url: scheduled.file_url.clone(),
+                    forwarded_from_message_id: None,
+                    location: None,
+                    contact_user_id: None,
+                },
+                None,
+                &mut tx,
+            )
+            .await?;
+
+        if !self
+            .scheduled_repo
+            .mark_sent(&scheduled.id, &pending.message().id, tx.as_mut())
+            .await?
+        {
+            tx.rollback().await?;
+            return Ok(None);
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(message_service.publish_message(pending).await))
+    }
+
+    /// Ghi nhận lần gửi thất bại: lỗi tạm thời được thử lại với backoff, lỗi nghiệp vụ
+    /// (sender rời nhóm, tin được trả lời đã mất...) hoặc hết lượt thử thì đánh dấu failed
+    pub async fn record_failure(
+        &self,
+        scheduled: &ScheduledMessageEntity,
+        failure: &error::SystemError,
+    ) -> Result<(), error::SystemError> {
+        let pool = self.scheduled_repo.get_pool();
+        let reason = failure.to_string();
+
+        if failure.is_transient() && scheduled.attempts + 1 < MAX_DISPATCH_ATTEMPTS {
+            let delay = Self::retry_delay_secs(scheduled.attempts);
+            return self
+                .scheduled_repo
+                .schedule_retry(&scheduled.id, &reason, delay, pool)
+                .await;
+        }
+
+        self.scheduled_repo
+            .mark_failed(&scheduled.id, &reason, pool)
+            .await
+    }
+
+    /// Thời gian chờ trước lần thử tiếp theo khi đã thất bại `attempts` lần
+    pub(crate) fn retry_delay_secs(attempts: i32) -> f64 {
+        (RETRY_BASE_SECS * 2f64.powi(attempts.clamp(0, 16))).min(RETRY_MAX_SECS)
+    }
+
+    pub(crate) fn validate_scheduled_at(
+        scheduled_at: chrono::DateTime<chrono::Utc>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(), error::SystemError> {
+        if scheduled_at <= now {
+            return Err(error::SystemError::bad_request(
+                "Thời điểm hẹn giờ phải ở tương lai",
+            ));
+        }
+
+        if scheduled_at > now + chrono::Duration::days(MAX_SCHEDULE_AHEAD_DAYS) {
+            return Err(error::SystemError::bad_request(
+                "Chỉ có thể hẹn giờ tối đa 365 ngày",
+            ));
+        }
+
+        Ok(())
+    }
+}
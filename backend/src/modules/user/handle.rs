@@ -1,16 +1,20 @@
 use actix_web::{
     HttpRequest,
     cookie::{self, Cookie, time},
-    delete, get, patch, post, web,
+    delete, get,
+    http::header,
+    patch, post, web,
 };
 use uuid::Uuid;
 
+use crate::modules::conversation::model::MessageQueryRequest;
+use crate::modules::message::{handle::MessageSvc, model::MentionListResponse};
 use crate::modules::user::{model, service::UserService};
 use crate::modules::websocket::presence::{PresenceInfo, PresenceService};
 use crate::{ENV, middlewares::get_extensions};
 use crate::{
     api::{error, success},
-    utils::{ValidatedJson, ValidatedQuery},
+    utils::{ValidatedJson, ValidatedQuery, client_ip},
 };
 use crate::{
     modules::user::{model::SignUpResponse, repository_pg::UserRepositoryPg},
@@ -19,6 +23,46 @@ use crate::{
 
 pub type UserSvc = UserService<UserRepositoryPg>;
 
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// Helper: Lấy User-Agent và IP của client để gắn vào phiên đăng nhập
+fn device_info(req: &HttpRequest) -> model::DeviceInfo {
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+    let ip_address = client_ip(req).map(|ip| ip.to_string());
+
+    model::DeviceInfo {
+        user_agent,
+        ip_address,
+    }
+}
+
+/// Helper: Cookie httpOnly chứa refresh token
+fn refresh_cookie(refresh_token: String) -> Cookie<'static> {
+    Cookie::build("refresh_token", refresh_token)
+        .path("/")
+        .http_only(true)
+        .same_site(cookie::SameSite::Strict)
+        .secure(ENV.cookie_secure)
+        .max_age(time::Duration::seconds(ENV.refresh_token_expiration as i64))
+        .finish()
+}
+
+/// Helper: Cookie xoá refresh token phía client
+fn expired_refresh_cookie() -> Cookie<'static> {
+    Cookie::build("refresh_token", "")
+        .path("/")
+        .http_only(true)
+        .same_site(cookie::SameSite::Strict)
+        .secure(ENV.cookie_secure)
+        .max_age(time::Duration::seconds(0))
+        .expires(time::OffsetDateTime::UNIX_EPOCH)
+        .finish()
+}
+
 /// Tiện ích lấy thông tin Profile của chính mình
 #[get("/profile")]
 pub async fn get_profile(
@@ -35,9 +79,9 @@ pub async fn get_profile(
 pub async fn get_user(
     user_service: web::Data<UserSvc>,
     user_id: web::Path<Uuid>,
-) -> Result<success::Success<model::UserResponse>, error::Error> {
+) -> Result<success::Success<model::PublicUserResponse>, error::Error> {
     let user = user_service.get_by_id(user_id.into_inner()).await?;
-    Ok(success::Success::ok(Some(user)).message("Lấy thông tin người dùng thành công"))
+    Ok(success::Success::ok(Some(user.into())).message("Lấy thông tin người dùng thành công"))
 }
 
 /// Tiện ích cập nhật thông tin cá nhân hiện tại
@@ -91,24 +135,55 @@ pub async fn sign_up(
 }
 
 /// Đăng nhập (Login)
+///
+/// Tài khoản bật 2FA nhận về `challenge_token` thay vì access token, gửi kèm mã tới `/signin/2fa`.
 #[post("/signin")]
 pub async fn sign_in(
     user_service: web::Data<UserSvc>,
+    req: HttpRequest,
     ValidatedJson(user_data): ValidatedJson<model::SignInModel>,
+) -> Result<success::Success<model::SignInResult>, error::Error> {
+    let outcome = user_service
+        .sign_in(user_data, device_info(&req))
+        .await?;
+
+    match outcome {
+        model::SignInOutcome::Authenticated {
+            access_token,
+            refresh_token,
+        } => {
+            let response =
+                model::SignInResult::Authenticated(model::SignInResponse { access_token });
+            Ok(success::Success::ok(Some(response))
+                .message("Đăng nhập thành công")
+                .cookies(vec![refresh_cookie(refresh_token)]))
+        }
+        model::SignInOutcome::TwoFactorRequired { challenge_token } => {
+            let response =
+                model::SignInResult::TwoFactorRequired(model::TwoFactorChallengeResponse {
+                    two_factor_required: true,
+                    challenge_token,
+                });
+            Ok(success::Success::ok(Some(response)).message("Vui lòng nhập mã xác thực hai lớp"))
+        }
+    }
+}
+
+/// Đăng nhập bước 2 (tài khoản bật 2FA): mã TOTP hoặc mã khôi phục
+#[post("/signin/2fa")]
+pub async fn sign_in_two_factor(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+    ValidatedJson(body): ValidatedJson<model::TwoFactorSignInRequest>,
 ) -> Result<success::Success<model::SignInResponse>, error::Error> {
-    let (access_token, refresh_token) = user_service.sign_in(user_data).await?;
+    let (access_token, refresh_token) = user_service
+        .complete_two_factor_sign_in(&body.challenge_token, &body.code, device_info(&req))
+        .await?;
     let response = model::SignInResponse { access_token };
-    let refresh_cookie = Cookie::build("refresh_token", refresh_token)
-        .path("/")
-        .http_only(true)
-        .same_site(cookie::SameSite::Strict)
-        .secure(ENV.cookie_secure)
-        .max_age(time::Duration::seconds(ENV.refresh_token_expiration as i64))
-        .finish();
 
     Ok(success::Success::ok(Some(response))
         .message("Đăng nhập thành công")
-        .cookies(vec![refresh_cookie]))
+        .cookies(vec![refresh_cookie(refresh_token)]))
 }
 
 /// Đăng xuất (Gỡ bỏ refresh token cookie)
@@ -119,16 +194,66 @@ pub async fn sign_out(
 ) -> Result<success::Success<()>, error::Error> {
     let refresh_token = req.cookie("refresh_token").map(|c| c.value().to_string());
     user_service.sign_out(refresh_token).await?;
-    let refresh_cookie = Cookie::build("refresh_token", "")
-        .path("/")
-        .http_only(true)
-        .same_site(cookie::SameSite::Strict)
-        .secure(ENV.cookie_secure)
-        .max_age(time::Duration::seconds(0))
-        .expires(time::OffsetDateTime::UNIX_EPOCH)
-        .finish();
 
-    Ok(success::Success::no_content().cookies(vec![refresh_cookie]))
+    Ok(success::Success::no_content().cookies(vec![expired_refresh_cookie()]))
+}
+
+/// Quên mật khẩu: gửi link đặt lại mật khẩu qua email
+#[post("/forgot-password")]
+pub async fn forgot_password(
+    user_service: web::Data<UserSvc>,
+    ValidatedJson(body): ValidatedJson<model::EmailRequest>,
+) -> Result<success::Success<()>, error::Error> {
+    user_service.request_password_reset(body.email);
+    Ok(success::Success::ok(None)
+        .message("Nếu email đã đăng ký, liên kết đặt lại mật khẩu sẽ được gửi tới hộp thư"))
+}
+
+/// Đặt lại mật khẩu bằng token trong email
+#[post("/reset-password")]
+pub async fn reset_password(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+    ValidatedJson(body): ValidatedJson<model::ResetPasswordRequest>,
+) -> Result<success::Success<()>, error::Error> {
+    user_service
+        .reset_password(&body.token, body.new_password, device_info(&req))
+        .await?;
+    Ok(success::Success::ok(None).message("Đặt lại mật khẩu thành công, vui lòng đăng nhập lại"))
+}
+
+/// Xác thực email bằng token trong email
+#[post("/verify-email")]
+pub async fn verify_email(
+    user_service: web::Data<UserSvc>,
+    ValidatedJson(body): ValidatedJson<model::VerifyEmailRequest>,
+) -> Result<success::Success<()>, error::Error> {
+    user_service.verify_email(&body.token).await?;
+    Ok(success::Success::ok(None).message("Xác thực email thành công"))
+}
+
+/// Xác nhận đổi email bằng token gửi tới địa chỉ mới
+#[post("/confirm-email-change")]
+pub async fn confirm_email_change(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+    ValidatedJson(body): ValidatedJson<model::VerifyEmailRequest>,
+) -> Result<success::Success<()>, error::Error> {
+    user_service
+        .confirm_email_change(&body.token, device_info(&req))
+        .await?;
+    Ok(success::Success::ok(None).message("Đổi email thành công"))
+}
+
+/// Gửi lại email xác thực
+#[post("/resend-verification")]
+pub async fn resend_verification(
+    user_service: web::Data<UserSvc>,
+    ValidatedJson(body): ValidatedJson<model::EmailRequest>,
+) -> Result<success::Success<()>, error::Error> {
+    user_service.resend_verification_email(body.email);
+    Ok(success::Success::ok(None)
+        .message("Nếu email chưa được xác thực, email xác thực mới sẽ được gửi tới hộp thư"))
 }
 
 /// Lấy Access Token mới thông qua Refresh Token cookie
@@ -138,18 +263,13 @@ pub async fn refresh(
     req: HttpRequest,
 ) -> Result<success::Success<model::SignInResponse>, error::Error> {
     let refresh_token = req.cookie("refresh_token").map(|c| c.value().to_string());
-    let (access_token, refresh_token) = user_service.refresh(refresh_token).await?;
+    let (access_token, refresh_token) = user_service
+        .refresh(refresh_token, device_info(&req))
+        .await?;
     let response = model::SignInResponse { access_token };
-    let refresh_cookie = Cookie::build("refresh_token", refresh_token)
-        .path("/")
-        .http_only(true)
-        .same_site(cookie::SameSite::Strict)
-        .secure(ENV.cookie_secure)
-        .max_age(time::Duration::seconds(ENV.refresh_token_expiration as i64))
-        .finish();
     Ok(success::Success::ok(Some(response))
         .message("Làm mới phiên truy cập thành công")
-        .cookies(vec![refresh_cookie]))
+        .cookies(vec![refresh_cookie(refresh_token)]))
 }
 
 /// Tính năng tìm kiếm Users
@@ -157,13 +277,150 @@ pub async fn refresh(
 pub async fn search_users(
     user_service: web::Data<UserSvc>,
     ValidatedQuery(query): ValidatedQuery<model::UserSearchQuery>,
-) -> Result<success::Success<Vec<model::UserResponse>>, error::Error> {
+) -> Result<success::Success<model::UserSearchResponse>, error::Error> {
     let users = user_service
-        .search_users(&query.q, query.limit.unwrap_or(10))
+        .search_users(&query.q, query.limit.unwrap_or(10), query.cursor)
         .await?;
     Ok(success::Success::ok(Some(users)).message("Tìm kiếm người dùng thành công"))
 }
 
+/// Danh sách tin nhắn mà User được nhắc tới (@username / @all)
+#[get("/me/mentions")]
+pub async fn get_my_mentions(
+    message_service: web::Data<MessageSvc>,
+    ValidatedQuery(query): ValidatedQuery<MessageQueryRequest>,
+    req: HttpRequest,
+) -> Result<success::Success<MentionListResponse>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let mentions = message_service
+        .get_mentions(user_id, query.limit, query.cursor)
+        .await?;
+    Ok(success::Success::ok(Some(mentions)).message("Lấy danh sách nhắc tới thành công"))
+}
+
+/// Danh sách thiết bị đang đăng nhập của User
+#[get("/me/sessions")]
+pub async fn get_my_sessions(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+) -> Result<success::Success<Vec<model::SessionResponse>>, error::Error> {
+    let claims = get_extensions::<Claims>(&req)?;
+
+    let sessions = user_service.list_sessions(claims.sub, claims.sid).await?;
+    Ok(success::Success::ok(Some(sessions)).message("Lấy danh sách phiên đăng nhập thành công"))
+}
+
+/// Đăng xuất một thiết bị, đóng luôn các kết nối WebSocket của thiết bị đó
+#[delete("/me/sessions/{session_id}")]
+pub async fn revoke_my_session(
+    user_service: web::Data<UserSvc>,
+    session_id: web::Path<Uuid>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    user_service
+        .revoke_session(user_id, session_id.into_inner())
+        .await?;
+    Ok(success::Success::no_content())
+}
+
+/// Đăng xuất khỏi mọi thiết bị (kể cả thiết bị hiện tại)
+#[delete("/me/sessions")]
+pub async fn revoke_all_my_sessions(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    user_service.revoke_all_sessions(user_id, None).await?;
+    Ok(success::Success::no_content().cookies(vec![expired_refresh_cookie()]))
+}
+
+/// Bắt đầu bật 2FA: trả về secret và otpauth URI để quét bằng app authenticator
+#[post("/me/2fa/setup")]
+pub async fn setup_two_factor(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+) -> Result<success::Success<model::TwoFactorSetupResponse>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let setup = user_service.setup_two_factor(user_id).await?;
+    Ok(success::Success::ok(Some(setup)).message("Quét mã QR rồi nhập mã để hoàn tất"))
+}
+
+/// Xác nhận mã TOTP đầu tiên để bật 2FA, trả về mã khôi phục (chỉ một lần)
+#[post("/me/2fa/confirm")]
+pub async fn confirm_two_factor(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+    ValidatedJson(body): ValidatedJson<model::ConfirmTwoFactorRequest>,
+) -> Result<success::Success<model::RecoveryCodesResponse>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    let recovery_codes = user_service
+        .confirm_two_factor(user_id, &body.code, device_info(&req))
+        .await?;
+    Ok(
+        success::Success::ok(Some(model::RecoveryCodesResponse { recovery_codes }))
+            .message("Bật xác thực hai lớp thành công, hãy lưu lại các mã khôi phục"),
+    )
+}
+
+/// Tắt 2FA (yêu cầu nhập lại mật khẩu)
+#[post("/me/2fa/disable")]
+pub async fn disable_two_factor(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+    ValidatedJson(body): ValidatedJson<model::DisableTwoFactorRequest>,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    user_service
+        .disable_two_factor(user_id, body.password, device_info(&req))
+        .await?;
+    Ok(success::Success::ok(None).message("Đã tắt xác thực hai lớp"))
+}
+
+/// Đổi mật khẩu (yêu cầu mật khẩu hiện tại), đăng xuất mọi thiết bị khác
+#[post("/me/password")]
+pub async fn change_password(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+    ValidatedJson(body): ValidatedJson<model::ChangePasswordRequest>,
+) -> Result<success::Success<()>, error::Error> {
+    let claims = get_extensions::<Claims>(&req)?;
+
+    user_service
+        .change_password(
+            claims.sub,
+            claims.sid,
+            body.current_password,
+            body.new_password,
+            device_info(&req),
+        )
+        .await?;
+    Ok(success::Success::ok(None)
+        .message("Đổi mật khẩu thành công, các thiết bị khác đã được đăng xuất"))
+}
+
+/// Yêu cầu đổi email (yêu cầu mật khẩu), gửi link xác nhận tới email mới
+#[post("/me/email")]
+pub async fn change_email(
+    user_service: web::Data<UserSvc>,
+    req: HttpRequest,
+    ValidatedJson(body): ValidatedJson<model::ChangeEmailRequest>,
+) -> Result<success::Success<()>, error::Error> {
+    let user_id = get_extensions::<Claims>(&req)?.sub;
+
+    user_service
+        .request_email_change(user_id, body.new_email, body.password)
+        .await?;
+    Ok(success::Success::ok(None)
+        .message("Đã gửi liên kết xác nhận tới email mới, email chỉ thay đổi sau khi xác nhận"))
+}
+
 /// Batch query presence status cho nhiều users
 ///
 /// POST /users/presence
@@ -1,12 +1,39 @@
 use core::str;
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
-use crate::modules::user::schema::UserEntity;
+use crate::modules::user::schema::{SecurityEventType, UserEntity, UserTokenPurpose};
+
+/// Ký tự được phép trong username, dùng chung với parser `@mention`
+pub(crate) fn is_username_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')
+}
+
+/// Username phải gõ được thành `@mention`: chỉ gồm [`is_username_char`], không kết thúc bằng
+/// `.`/`-` (bị coi là dấu câu) và không trùng từ khóa `@all`
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if !username.chars().all(is_username_char) {
+        return Err(ValidationError::new("username_charset").with_message(
+            "Username may only contain letters, digits, '_', '.' and '-'".into(),
+        ));
+    }
+    if username.ends_with(['.', '-']) {
+        return Err(ValidationError::new("username_trailing")
+            .with_message("Username cannot end with '.' or '-'".into()));
+    }
+    if username.eq_ignore_ascii_case("all") {
+        return Err(ValidationError::new("username_reserved")
+            .with_message("Username is reserved".into()));
+    }
+    Ok(())
+}
 
 #[derive(Deserialize, Validate)]
 pub struct SignUpModel {
-    #[validate(length(min = 3, message = "Username must be at least 3 characters long"))]
+    #[validate(
+        length(min = 3, message = "Username must be at least 3 characters long"),
+        custom(function = "validate_username")
+    )]
     pub username: String,
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
@@ -35,9 +62,12 @@ use crate::utils::double_option;
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct UpdateUserModel {
-    #[validate(length(min = 3, message = "Username must be at least 3 characters long"))]
+    #[validate(
+        length(min = 3, message = "Username must be at least 3 characters long"),
+        custom(function = "validate_username")
+    )]
     pub username: Option<String>,
-    #[validate(email(message = "Invalid email format"))]
+    /// Không đổi được qua đây, chỉ để báo lỗi hướng dẫn dùng luồng đổi email
     pub email: Option<String>,
     #[validate(length(min = 1, message = "Display name cannot be empty"))]
     pub display_name: Option<String>,
@@ -71,13 +101,92 @@ pub struct InsertUser {
 #[allow(unused)]
 pub struct UpdateUser {
     pub username: Option<String>,
-    pub email: Option<String>,
     pub display_name: Option<String>,
     pub avatar_url: Option<Option<String>>,
     pub bio: Option<Option<String>>,
     pub phone: Option<Option<String>>,
 }
 
+/// Thông tin thiết bị lấy từ request đăng nhập / refresh
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+pub struct NewUserSession {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub current_jti: uuid::Uuid,
+    pub device: DeviceInfo,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub struct NewUserToken {
+    pub user_id: uuid::Uuid,
+    pub purpose: UserTokenPurpose,
+    pub token_hash: String,
+    pub email: Option<String>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Body cho quên mật khẩu / gửi lại email xác thực
+#[derive(Deserialize, Validate)]
+pub struct EmailRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1, max = 128, message = "Token is required"))]
+    pub token: String,
+    #[validate(length(min = 6, message = "Password must be at least 6 characters long"))]
+    pub new_password: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "Current password is required"))]
+    pub current_password: String,
+    #[validate(length(min = 6, message = "Password must be at least 6 characters long"))]
+    pub new_password: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ChangeEmailRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub new_email: String,
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+}
+
+/// Token trong email (xác thực email, xác nhận đổi email)
+#[derive(Deserialize, Validate)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1, max = 128, message = "Token is required"))]
+    pub token: String,
+}
+
+pub struct NewSecurityEvent {
+    pub user_id: uuid::Uuid,
+    pub event_type: SecurityEventType,
+    pub session_id: Option<uuid::Uuid>,
+    pub device: DeviceInfo,
+}
+
+/// Phiên đăng nhập hiển thị trong danh sách thiết bị
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub id: uuid::Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: chrono::DateTime<chrono::Utc>,
+    /// Phiên đang gọi API
+    pub current: bool,
+}
+
 #[derive(Serialize)]
 pub struct SignUpResponse {
     pub id: uuid::Uuid,
@@ -89,15 +198,74 @@ pub struct SignInResponse {
     pub access_token: String,
 }
 
+/// Kết quả bước 1 đăng nhập: có token ngay, hoặc cần thêm mã 2FA
+pub enum SignInOutcome {
+    Authenticated {
+        access_token: String,
+        refresh_token: String,
+    },
+    TwoFactorRequired {
+        challenge_token: String,
+    },
+}
+
+#[derive(Serialize)]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum SignInResult {
+    Authenticated(SignInResponse),
+    TwoFactorRequired(TwoFactorChallengeResponse),
+}
+
+/// Bước 2 đăng nhập: mã TOTP 6 chữ số hoặc một mã khôi phục
+#[derive(Deserialize, Validate)]
+pub struct TwoFactorSignInRequest {
+    #[validate(length(min = 1, max = 1024, message = "Challenge token is required"))]
+    pub challenge_token: String,
+    #[validate(length(min = 6, max = 32, message = "Code must be between 6 and 32 characters"))]
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct TwoFactorSetupResponse {
+    /// Secret base32 để nhập tay nếu không quét được QR
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ConfirmTwoFactorRequest {
+    #[validate(length(equal = 6, message = "Code must be 6 digits"))]
+    pub code: String,
+}
+
+/// Mã khôi phục chỉ được trả về một lần khi bật 2FA
+#[derive(Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct DisableTwoFactorRequest {
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+}
+
 #[derive(Deserialize, Validate)]
 pub struct UserSearchQuery {
     #[validate(length(min = 2, message = "Search query must be at least 2 characters"))]
     pub q: String,
     #[validate(range(min = 1, max = 50, message = "Limit must be between 1 and 50"))]
     pub limit: Option<i32>,
+    pub cursor: Option<String>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 
 pub struct UserResponse {
     pub id: uuid::Uuid,
@@ -107,6 +275,31 @@ pub struct UserResponse {
     pub avatar_url: Option<String>,
     pub bio: Option<String>,
     pub phone: Option<String>,
+    /// false nếu user chưa xác thực email hiện tại
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub two_factor_enabled: bool,
+}
+
+/// Thông tin user cho người khác xem (`GET /users/{id}`, tìm kiếm)
+///
+/// Trạng thái xác thực email / 2FA chỉ có trong profile của chính chủ (`UserResponse`).
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicUserResponse {
+    pub id: uuid::Uuid,
+    pub username: String,
+    pub email: String,
+    pub display_name: String,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+    pub phone: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UserSearchResponse {
+    pub users: Vec<PublicUserResponse>,
+    pub cursor: Option<String>,
 }
 
 impl From<UserEntity> for UserResponse {
@@ -119,6 +312,22 @@ impl From<UserEntity> for UserResponse {
             avatar_url: entity.avatar_url,
             bio: entity.bio,
             phone: entity.phone,
+            email_verified: entity.email_verified_at.is_some(),
+            two_factor_enabled: entity.totp_enabled_at.is_some(),
+        }
+    }
+}
+
+impl From<UserResponse> for PublicUserResponse {
+    fn from(user: UserResponse) -> Self {
+        PublicUserResponse {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
+            bio: user.bio,
+            phone: user.phone,
         }
     }
 }
@@ -1,8 +1,13 @@
 use uuid::Uuid;
 
 use crate::{
-    api::error, modules::user::model::InsertUser, modules::user::model::UpdateUser,
-    modules::user::schema::UserEntity,
+    api::{cursor::Cursor, error},
+    modules::user::model::{
+        DeviceInfo, InsertUser, NewSecurityEvent, NewUserSession, NewUserToken, UpdateUser,
+    },
+    modules::user::schema::{
+        RecoveryCodeEntity, UserEntity, UserSessionEntity, UserTokenEntity, UserTokenPurpose,
+    },
 };
 
 #[async_trait::async_trait]
@@ -21,6 +26,119 @@ pub trait UserRepository {
     async fn search_users(
         &self,
         query: &str,
+        cursor: Option<&Cursor>,
         limit: i32,
     ) -> Result<Vec<UserEntity>, error::SystemError>;
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<UserEntity>, error::SystemError>;
+
+    async fn update_password(
+        &self,
+        user_id: &Uuid,
+        hash_password: &str,
+    ) -> Result<bool, error::SystemError>;
+
+    /// Đổi email (đã xác nhận qua địa chỉ mới) và vô hiệu hóa token đặt lại mật khẩu còn treo
+    ///
+    /// Trả về Conflict nếu email đã thuộc user khác
+    async fn update_email(&self, user_id: &Uuid, email: &str) -> Result<bool, error::SystemError>;
+
+    /// Đánh dấu đã xác thực, chỉ khi email của user vẫn là `email` mà token được gửi tới
+    async fn mark_email_verified(
+        &self,
+        user_id: &Uuid,
+        email: &str,
+    ) -> Result<bool, error::SystemError>;
+
+    /// Tạo token mới, các token chưa dùng cùng mục đích của user bị vô hiệu hoá
+    async fn create_user_token(&self, token: &NewUserToken) -> Result<(), error::SystemError>;
+
+    /// Dùng token (một lần): chỉ thành công khi token chưa dùng và chưa hết hạn
+    async fn consume_user_token(
+        &self,
+        token_hash: &str,
+        purpose: UserTokenPurpose,
+    ) -> Result<Option<UserTokenEntity>, error::SystemError>;
+
+    /// Lưu secret TOTP đang chờ xác nhận (chỉ khi 2FA chưa bật)
+    async fn set_pending_totp_secret(
+        &self,
+        user_id: &Uuid,
+        secret: &str,
+    ) -> Result<bool, error::SystemError>;
+
+    /// Bật 2FA và thay toàn bộ mã khôi phục bằng `recovery_code_hashes`
+    async fn enable_totp(
+        &self,
+        user_id: &Uuid,
+        recovery_code_hashes: &[String],
+    ) -> Result<bool, error::SystemError>;
+
+    /// Tắt 2FA, xoá secret và mã khôi phục
+    async fn disable_totp(&self, user_id: &Uuid) -> Result<bool, error::SystemError>;
+
+    /// Ghi nhận bước TOTP vừa dùng, trả về false nếu bước đó (hoặc bước mới hơn) đã được dùng
+    async fn record_totp_step(&self, user_id: &Uuid, step: i64)
+    -> Result<bool, error::SystemError>;
+
+    async fn find_unused_recovery_codes(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<RecoveryCodeEntity>, error::SystemError>;
+
+    /// Đánh dấu mã khôi phục đã dùng, trả về false nếu đã bị dùng trước đó
+    async fn use_recovery_code(&self, code_id: &Uuid) -> Result<bool, error::SystemError>;
+
+    async fn create_session(&self, session: &NewUserSession) -> Result<(), error::SystemError>;
+
+    /// Đổi refresh token của phiên (chỉ khi `old_jti` vẫn là token mới nhất và phiên còn hiệu lực)
+    async fn rotate_session(
+        &self,
+        session_id: &Uuid,
+        user_id: &Uuid,
+        old_jti: &Uuid,
+        new_jti: &Uuid,
+        device: &DeviceInfo,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool, error::SystemError>;
+
+    async fn find_active_sessions(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<UserSessionEntity>, error::SystemError>;
+
+    async fn revoke_session(
+        &self,
+        user_id: &Uuid,
+        session_id: &Uuid,
+    ) -> Result<Option<UserSessionEntity>, error::SystemError>;
+
+    /// Lấy phiên vừa xoay vòng từ `presented_jti` sau thời điểm `rotated_after` (refresh đồng thời)
+    async fn find_recently_rotated_session(
+        &self,
+        session_id: &Uuid,
+        user_id: &Uuid,
+        presented_jti: &Uuid,
+        rotated_after: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<UserSessionEntity>, error::SystemError>;
+
+    /// Thu hồi phiên nếu `presented_jti` không còn là token mới nhất (token cũ bị dùng lại)
+    async fn revoke_reused_session(
+        &self,
+        session_id: &Uuid,
+        user_id: &Uuid,
+        presented_jti: &Uuid,
+    ) -> Result<Option<UserSessionEntity>, error::SystemError>;
+
+    async fn create_security_event(
+        &self,
+        event: &NewSecurityEvent,
+    ) -> Result<(), error::SystemError>;
+
+    /// Thu hồi mọi phiên còn hiệu lực của user, trừ `except` (phiên hiện tại) nếu có
+    async fn revoke_sessions(
+        &self,
+        user_id: &Uuid,
+        except: Option<&Uuid>,
+    ) -> Result<Vec<UserSessionEntity>, error::SystemError>;
 }
@@ -1,11 +1,15 @@
 use uuid::Uuid;
 
 use crate::{
-    api::error,
+    api::{cursor::Cursor, error},
     modules::user::{
-        model::{InsertUser, UpdateUser},
+        model::{
+            DeviceInfo, InsertUser, NewSecurityEvent, NewUserSession, NewUserToken, UpdateUser,
+        },
         repository::UserRepository,
-        schema::UserEntity,
+        schema::{
+            RecoveryCodeEntity, UserEntity, UserSessionEntity, UserTokenEntity, UserTokenPurpose,
+        },
     },
 };
 
@@ -66,25 +70,23 @@ impl UserRepository for UserRepositoryPg {
         UPDATE users
         SET
             username     = COALESCE($2, username),
-            email        = COALESCE($3, email),
-            display_name = COALESCE($4, display_name),
-            avatar_url   = CASE WHEN $5::boolean THEN $6 ELSE avatar_url END,
-            bio          = CASE WHEN $7::boolean THEN $8 ELSE bio END,
-            phone        = CASE WHEN $9::boolean THEN $10 ELSE phone END
+            display_name = COALESCE($3, display_name),
+            avatar_url   = CASE WHEN $4::boolean THEN $5 ELSE avatar_url END,
+            bio          = CASE WHEN $6::boolean THEN $7 ELSE bio END,
+            phone        = CASE WHEN $8::boolean THEN $9 ELSE phone END
         WHERE id = $1
         RETURNING *
         "#,
         )
         .bind(id)
         .bind(&user.username) // $2: Option<String>
-        .bind(&user.email) // $3: Option<String>
-        .bind(&user.display_name) // $4: Option<String>
-        .bind(user.avatar_url.is_some()) // $5: bool - was avatar_url provided?
-        .bind(user.avatar_url.as_ref().and_then(|v| v.as_ref())) // $6: Option<&String>
-        .bind(user.bio.is_some()) // $7: bool - was bio provided?
-        .bind(user.bio.as_ref().and_then(|v| v.as_ref())) // $8: Option<&String>
-        .bind(user.phone.is_some()) // $9: bool - was phone provided?
-        .bind(user.phone.as_ref().and_then(|v| v.as_ref())) // $10: Option<&String>
+        .bind(&user.display_name) // $3: Option<String>
+        .bind(user.avatar_url.is_some()) // $4: bool - was avatar_url provided?
+        .bind(user.avatar_url.as_ref().and_then(|v| v.as_ref())) // $5: Option<&String>
+        .bind(user.bio.is_some()) // $6: bool - was bio provided?
+        .bind(user.bio.as_ref().and_then(|v| v.as_ref())) // $7: Option<&String>
+        .bind(user.phone.is_some()) // $8: bool - was phone provided?
+        .bind(user.phone.as_ref().and_then(|v| v.as_ref())) // $9: Option<&String>
         .fetch_optional(&self.pool)
         .await?
         .ok_or_else(|| error::SystemError::not_found("Không tìm thấy người dùng"))?;
@@ -106,6 +108,7 @@ impl UserRepository for UserRepositoryPg {
     async fn search_users(
         &self,
         query: &str,
+        cursor: Option<&Cursor>,
         limit: i32,
     ) -> Result<Vec<UserEntity>, error::SystemError> {
         let search_pattern = format!("%{}%", query.replace('%', "\\%").replace('_', "\\_"));
@@ -117,14 +120,456 @@ impl UserRepository for UserRepositoryPg {
                 lower(username) LIKE lower($1)
                 OR lower(display_name) LIKE lower($1)
             )
-            ORDER BY display_name
-            LIMIT $2
+            AND (
+                $2::text IS NULL
+                OR (lower(display_name), created_at, id) > (lower($2), $3, $4)
+            )
+            ORDER BY lower(display_name), created_at, id
+            LIMIT $5
             "#,
         )
         .bind(&search_pattern)
-        .bind(limit)
+        .bind(cursor.and_then(|c| c.rank.as_deref()))
+        .bind(cursor.map(|c| c.timestamp))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit + 1)
         .fetch_all(&self.pool)
         .await?;
         Ok(users)
     }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<UserEntity>, error::SystemError> {
+        let user = sqlx::query_as::<_, UserEntity>(
+            "SELECT * FROM users WHERE lower(email) = lower($1) AND deleted_at IS NULL",
+        )
+        .bind(email)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(user)
+    }
+
+    async fn update_password(
+        &self,
+        user_id: &Uuid,
+        hash_password: &str,
+    ) -> Result<bool, error::SystemError> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE users SET hash_password = $2, updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(hash_password)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn update_email(&self, user_id: &Uuid, email: &str) -> Result<bool, error::SystemError> {
+        // Link đặt lại mật khẩu đã gửi tới email cũ hết hiệu lực cùng lúc
+        let rows = sqlx::query(
+            r#"
+            WITH invalidated AS (
+                UPDATE user_tokens SET used_at = NOW()
+                WHERE user_id = $1 AND purpose = $3 AND used_at IS NULL
+            )
+            UPDATE users SET email = $2, email_verified_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(email)
+        .bind(UserTokenPurpose::PasswordReset)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn mark_email_verified(
+        &self,
+        user_id: &Uuid,
+        email: &str,
+    ) -> Result<bool, error::SystemError> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW())
+            WHERE id = $1 AND lower(email) = lower($2) AND deleted_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(email)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn create_user_token(&self, token: &NewUserToken) -> Result<(), error::SystemError> {
+        sqlx::query(
+            r#"
+            WITH invalidated AS (
+                UPDATE user_tokens SET used_at = NOW()
+                WHERE user_id = $2 AND purpose = $3 AND used_at IS NULL
+            )
+            INSERT INTO user_tokens (id, user_id, purpose, token_hash, email, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)))
+        .bind(token.user_id)
+        .bind(token.purpose)
+        .bind(&token.token_hash)
+        .bind(&token.email)
+        .bind(token.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn consume_user_token(
+        &self,
+        token_hash: &str,
+        purpose: UserTokenPurpose,
+    ) -> Result<Option<UserTokenEntity>, error::SystemError> {
+        let token = sqlx::query_as::<_, UserTokenEntity>(
+            r#"
+            UPDATE user_tokens SET used_at = NOW()
+            WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(token_hash)
+        .bind(purpose)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(token)
+    }
+
+    async fn set_pending_totp_secret(
+        &self,
+        user_id: &Uuid,
+        secret: &str,
+    ) -> Result<bool, error::SystemError> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE users SET totp_secret = $2, totp_last_step = NULL
+            WHERE id = $1 AND totp_enabled_at IS NULL AND deleted_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(secret)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn enable_totp(
+        &self,
+        user_id: &Uuid,
+        recovery_code_hashes: &[String],
+    ) -> Result<bool, error::SystemError> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query(
+            r#"
+            UPDATE users SET totp_enabled_at = NOW()
+            WHERE id = $1 AND totp_secret IS NOT NULL AND totp_enabled_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if rows == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let ids: Vec<Uuid> = recovery_code_hashes
+            .iter()
+            .map(|_| Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)))
+            .collect();
+        sqlx::query(
+            r#"
+            INSERT INTO user_recovery_codes (id, user_id, code_hash)
+            SELECT id, $2, code_hash FROM UNNEST($1::uuid[], $3::text[]) AS t(id, code_hash)
+            "#,
+        )
+        .bind(&ids)
+        .bind(user_id)
+        .bind(recovery_code_hashes)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn disable_totp(&self, user_id: &Uuid) -> Result<bool, error::SystemError> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query(
+            r#"
+            UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL
+            WHERE id = $1 AND totp_enabled_at IS NOT NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows > 0)
+    }
+
+    async fn record_totp_step(
+        &self,
+        user_id: &Uuid,
+        step: i64,
+    ) -> Result<bool, error::SystemError> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE users SET totp_last_step = $2
+            WHERE id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)
+            "#,
+        )
+        .bind(user_id)
+        .bind(step)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn find_unused_recovery_codes(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<RecoveryCodeEntity>, error::SystemError> {
+        let codes = sqlx::query_as::<_, RecoveryCodeEntity>(
+            "SELECT id, code_hash FROM user_recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(codes)
+    }
+
+    async fn use_recovery_code(&self, code_id: &Uuid) -> Result<bool, error::SystemError> {
+        let rows = sqlx::query(
+            "UPDATE user_recovery_codes SET used_at = NOW() WHERE id = $1 AND used_at IS NULL",
+        )
+        .bind(code_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn create_session(&self, session: &NewUserSession) -> Result<(), error::SystemError> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_sessions (id, user_id, current_jti, user_agent, ip_address, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(session.id)
+        .bind(session.user_id)
+        .bind(session.current_jti)
+        .bind(&session.device.user_agent)
+        .bind(&session.device.ip_address)
+        .bind(session.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn rotate_session(
+        &self,
+        session_id: &Uuid,
+        user_id: &Uuid,
+        old_jti: &Uuid,
+        new_jti: &Uuid,
+        device: &DeviceInfo,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool, error::SystemError> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE user_sessions
+            SET previous_jti = current_jti,
+                rotated_at = NOW(),
+                current_jti = $4,
+                user_agent = COALESCE($5, user_agent),
+                ip_address = COALESCE($6, ip_address),
+                last_used_at = NOW(),
+                expires_at = $7
+            WHERE id = $1
+              AND user_id = $2
+              AND current_jti = $3
+              AND revoked_at IS NULL
+              AND expires_at > NOW()
+            "#,
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .bind(old_jti)
+        .bind(new_jti)
+        .bind(&device.user_agent)
+        .bind(&device.ip_address)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows > 0)
+    }
+
+    async fn find_active_sessions(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<UserSessionEntity>, error::SystemError> {
+        let sessions = sqlx::query_as::<_, UserSessionEntity>(
+            r#"
+            SELECT * FROM user_sessions
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            ORDER BY last_used_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(sessions)
+    }
+
+    async fn revoke_session(
+        &self,
+        user_id: &Uuid,
+        session_id: &Uuid,
+    ) -> Result<Option<UserSessionEntity>, error::SystemError> {
+        let session = sqlx::query_as::<_, UserSessionEntity>(
+            r#"
+            UPDATE user_sessions
+            SET revoked_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(session)
+    }
+
+    async fn find_recently_rotated_session(
+        &self,
+        session_id: &Uuid,
+        user_id: &Uuid,
+        presented_jti: &Uuid,
+        rotated_after: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<UserSessionEntity>, error::SystemError> {
+        let session = sqlx::query_as::<_, UserSessionEntity>(
+            r#"
+            SELECT * FROM user_sessions
+            WHERE id = $1
+              AND user_id = $2
+              AND previous_jti = $3
+              AND rotated_at > $4
+              AND revoked_at IS NULL
+              AND expires_at > NOW()
+            "#,
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .bind(presented_jti)
+        .bind(rotated_after)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(session)
+    }
+
+    async fn revoke_reused_session(
+        &self,
+        session_id: &Uuid,
+        user_id: &Uuid,
+        presented_jti: &Uuid,
+    ) -> Result<Option<UserSessionEntity>, error::SystemError> {
+        let session = sqlx::query_as::<_, UserSessionEntity>(
+            r#"
+            UPDATE user_sessions
+            SET revoked_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND current_jti <> $3
+            RETURNING *
+            "#,
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .bind(presented_jti)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(session)
+    }
+
+    async fn create_security_event(
+        &self,
+        event: &NewSecurityEvent,
+    ) -> Result<(), error::SystemError> {
+        sqlx::query(
+            r#"
+            INSERT INTO security_events (id, user_id, event_type, session_id, user_agent, ip_address)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)))
+        .bind(event.user_id)
+        .bind(event.event_type)
+        .bind(event.session_id)
+        .bind(&event.device.user_agent)
+        .bind(&event.device.ip_address)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn revoke_sessions(
+        &self,
+        user_id: &Uuid,
+        except: Option<&Uuid>,
+    ) -> Result<Vec<UserSessionEntity>, error::SystemError> {
+        let sessions = sqlx::query_as::<_, UserSessionEntity>(
+            r#"
+            UPDATE user_sessions
+            SET revoked_at = NOW()
+            WHERE user_id = $1
+              AND revoked_at IS NULL
+              AND ($2::uuid IS NULL OR id <> $2)
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(except)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(sessions)
+    }
 }
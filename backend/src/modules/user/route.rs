@@ -6,8 +6,14 @@ pub fn public_api_configure(cfg: &mut ServiceConfig) {
         scope("/auth")
             .service(sign_up)
             .service(sign_in)
+            .service(sign_in_two_factor)
             .service(sign_out)
-            .service(refresh),
+            .service(refresh)
+            .service(forgot_password)
+            .service(reset_password)
+            .service(verify_email)
+            .service(confirm_email_change)
+            .service(resend_verification),
     );
 }
 
@@ -19,6 +25,15 @@ pub fn configure(cfg: &mut ServiceConfig) {
             .service(get_user)
             .service(delete_user)
             .service(search_users)
+            .service(get_my_mentions)
+            .service(get_my_sessions)
+            .service(revoke_my_session)
+            .service(revoke_all_my_sessions)
+            .service(setup_two_factor)
+            .service(confirm_two_factor)
+            .service(disable_two_factor)
+            .service(change_password)
+            .service(change_email)
             .service(get_presence),
     );
 }
@@ -26,4 +26,76 @@ pub struct UserEntity {
     pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub email_verified_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub totp_last_step: Option<i64>,
+}
+
+/// Loại sự kiện bảo mật được ghi vào nhật ký tài khoản
+#[derive(Debug, PartialEq, Clone, Copy, Type, Serialize, Deserialize)]
+#[sqlx(type_name = "security_event_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventType {
+    /// Refresh token đã bị xoay vòng lại được dùng (nghi bị đánh cắp)
+    RefreshTokenReuse,
+    /// Refresh token vừa xoay vòng được dùng lại trong thời gian ân hạn từ cùng thiết bị
+    RefreshTokenGraceUse,
+    /// Mật khẩu được đặt lại qua email
+    PasswordReset,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    /// Đăng nhập bằng mã khôi phục thay cho mã TOTP
+    RecoveryCodeUsed,
+    PasswordChanged,
+    EmailChanged,
+}
+
+/// Mục đích của token dùng một lần gửi qua email
+#[derive(Debug, PartialEq, Clone, Copy, Type, Serialize, Deserialize)]
+#[sqlx(type_name = "user_token_purpose", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum UserTokenPurpose {
+    PasswordReset,
+    EmailVerification,
+    /// Xác nhận địa chỉ email mới (lưu trong `email` của token)
+    EmailChange,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, FromRow)]
+pub struct UserTokenEntity {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub purpose: UserTokenPurpose,
+    /// Địa chỉ email mà token được gửi tới
+    pub email: Option<String>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Mã khôi phục 2FA (chỉ lưu hash argon2)
+#[derive(Debug, Clone, FromRow)]
+pub struct RecoveryCodeEntity {
+    pub id: Uuid,
+    pub code_hash: String,
+}
+
+/// Một phiên đăng nhập (refresh token family) trên một thiết bị
+#[derive(Debug, Clone, FromRow)]
+pub struct UserSessionEntity {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// jti của refresh token mới nhất trong family
+    pub current_jti: Uuid,
+    /// jti vừa bị thay ở lần xoay vòng gần nhất, còn được chấp nhận trong thời gian ân hạn
+    pub previous_jti: Option<Uuid>,
+    pub rotated_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
 }
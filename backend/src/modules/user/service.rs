@@ -1,16 +1,46 @@
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::ENV;
+use crate::api::cursor::Cursor;
 use crate::api::error;
 use crate::api::messages;
+use crate::configs::mailer::{MailMessage, SharedMailer};
 use crate::configs::{CacheStore, RedisCache};
 use crate::modules::CACHE_TTL;
 use crate::modules::user::model::{
-    SignInModel, SignUpModel, UpdateUser, UpdateUserModel, UserResponse,
+    DeviceInfo, NewSecurityEvent, NewUserSession, NewUserToken, SessionResponse, SignInModel,
+    SignInOutcome, SignUpModel, TwoFactorSetupResponse, UpdateUser, UpdateUserModel, UserResponse,
+    UserSearchResponse,
 };
+use crate::modules::user::schema::{
+    SecurityEventType, UserEntity, UserRole, UserSessionEntity, UserTokenPurpose,
+};
+use crate::modules::user::totp;
 use crate::modules::user::{model::InsertUser, repository::UserRepository};
-use crate::utils::{Claims, TypeClaims, hash_password, verify_password};
+use crate::modules::websocket::server::WebSocketServer;
+use crate::utils::{
+    Claims, TypeClaims, generate_random_token, hash_password, verify_password,
+};
+
+const USER_TOKEN_LENGTH: usize = 48;
+const PASSWORD_RESET_TOKEN_TTL_SECONDS: i64 = 30 * 60;
+const EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: i64 = 24 * 60 * 60;
+const EMAIL_CHANGE_TOKEN_TTL_SECONDS: i64 = 60 * 60;
+/// Khoảng cách tối thiểu giữa hai email cùng loại gửi cho một user
+const MAIL_COOLDOWN_SECONDS: usize = 60;
+const TWO_FACTOR_CHALLENGE_TTL_SECONDS: u64 = 5 * 60;
+/// Thời gian refresh token vừa bị xoay vòng vẫn được chấp nhận (nhiều tab refresh cùng lúc)
+const REFRESH_ROTATION_GRACE_SECONDS: i64 = 10;
+/// Số lần nhập mã 2FA tối đa cho mỗi challenge token
+const TWO_FACTOR_MAX_ATTEMPTS: i64 = 5;
+/// Số lần nhập sai mã 2FA tối đa của một tài khoản (tính trên mọi challenge) trước khi bị khoá
+const TWO_FACTOR_MAX_USER_FAILURES: i64 = 10;
+const TWO_FACTOR_LOCKOUT_SECONDS: usize = 15 * 60;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+const EMAIL_TAKEN: &str = "Email đã được sử dụng bởi tài khoản khác";
 
 /// Dịch vụ quản lý người dùng (Đăng ký, Đăng nhập, Tìm kiếm, Cập nhật thông tin)
 #[derive(Clone)]
@@ -21,6 +51,8 @@ where
 {
     repo: Arc<U>,
     cache: Arc<C>,
+    ws_server: Arc<WebSocketServer>,
+    mailer: SharedMailer,
 }
 
 impl<U, C> UserService<U, C>
@@ -28,8 +60,18 @@ where
     U: UserRepository + Send + Sync,
     C: CacheStore + Send + Sync,
 {
-    pub fn with_dependencies(repo: Arc<U>, cache: Arc<C>) -> Self {
-        UserService { repo, cache }
+    pub fn with_dependencies(
+        repo: Arc<U>,
+        cache: Arc<C>,
+        ws_server: Arc<WebSocketServer>,
+        mailer: SharedMailer,
+    ) -> Self {
+        UserService {
+            repo,
+            cache,
+            ws_server,
+            mailer,
+        }
     }
 
     /// Lấy thông tin người dùng theo ID (có cache qua Redis)
@@ -61,9 +103,14 @@ where
             ));
         }
 
+        if user.email.is_some() {
+            return Err(error::SystemError::bad_request(
+                "Đổi email cần xác nhận mật khẩu, hãy dùng POST /users/me/email",
+            ));
+        }
+
         let update_user = UpdateUser {
             username: user.username,
-            email: user.email,
             display_name: user.display_name,
             avatar_url: user.avatar_url,
             bio: user.bio,
@@ -100,11 +147,297 @@ where
         };
 
         let user_id = self.repo.create(&new_user).await?;
+
+        // Tài khoản vẫn tạo thành công nếu gửi mail lỗi, user có thể yêu cầu gửi lại
+        if let Err(e) = self
+            .send_verification_email(&user_id, &new_user.email)
+            .await
+        {
+            tracing::error!("Không gửi được email xác thực cho user {}: {}", user_id, e);
+        }
+
         Ok(user_id)
     }
 
+    /// Helper: Gửi link đặt lại mật khẩu tới email nếu có tài khoản (chạy trong `request_password_reset`)
+    async fn deliver_password_reset(&self, email: &str) -> Result<(), error::SystemError> {
+        let Some(user) = self.repo.find_by_email(email).await? else {
+            return Ok(());
+        };
+
+        if !self
+            .acquire_mail_cooldown(UserTokenPurpose::PasswordReset, &user.id)
+            .await?
+        {
+            return Ok(());
+        }
+
+        let token = self
+            .issue_user_token(
+                &user.id,
+                UserTokenPurpose::PasswordReset,
+                None,
+                PASSWORD_RESET_TOKEN_TTL_SECONDS,
+            )
+            .await?;
+
+        self.mailer
+            .send(&MailMessage {
+                to: user.email,
+                subject: "Đặt lại mật khẩu AppChat".to_string(),
+                body: format!(
+                    "Xin chào {},\n\nNhấn vào liên kết sau để đặt lại mật khẩu (hiệu lực {} phút):\n{}/reset-password?token={}\n\nNếu bạn không yêu cầu, hãy bỏ qua email này.",
+                    user.display_name,
+                    PASSWORD_RESET_TOKEN_TTL_SECONDS / 60,
+                    ENV.frontend_url,
+                    token
+                ),
+            })
+            .await
+    }
+
+    /// Đặt lại mật khẩu bằng token trong email, đăng xuất mọi thiết bị
+    pub async fn reset_password(
+        &self,
+        token: &str,
+        new_password: String,
+        device: DeviceInfo,
+    ) -> Result<(), error::SystemError> {
+        let user_token = self
+            .repo
+            .consume_user_token(&hash_user_token(token), UserTokenPurpose::PasswordReset)
+            .await?
+            .ok_or_else(|| {
+                error::SystemError::bad_request(
+                    "Liên kết đặt lại mật khẩu không hợp lệ hoặc đã hết hạn",
+                )
+            })?;
+
+        let hash_password = hash_password(new_password).await?;
+        if !self
+            .repo
+            .update_password(&user_token.user_id, &hash_password)
+            .await?
+        {
+            return Err(error::SystemError::not_found(messages::error::USER_NOT_FOUND));
+        }
+
+        self.revoke_all_sessions(user_token.user_id, None).await?;
+
+        self.repo
+            .create_security_event(&NewSecurityEvent {
+                user_id: user_token.user_id,
+                event_type: SecurityEventType::PasswordReset,
+                session_id: None,
+                device,
+            })
+            .await
+    }
+
+    /// Helper: Gửi lại email xác thực, bỏ qua nếu không có tài khoản hoặc email đã xác thực
+    async fn deliver_verification_email(&self, email: &str) -> Result<(), error::SystemError> {
+        let Some(user) = self.repo.find_by_email(email).await? else {
+            return Ok(());
+        };
+
+        if user.email_verified_at.is_some()
+            || !self
+                .acquire_mail_cooldown(UserTokenPurpose::EmailVerification, &user.id)
+                .await?
+        {
+            return Ok(());
+        }
+
+        self.send_verification_email(&user.id, &user.email).await
+    }
+
+    /// Xác thực email bằng token trong email
+    pub async fn verify_email(&self, token: &str) -> Result<(), error::SystemError> {
+        let invalid = || {
+            error::SystemError::bad_request("Liên kết xác thực email không hợp lệ hoặc đã hết hạn")
+        };
+
+        let user_token = self
+            .repo
+            .consume_user_token(&hash_user_token(token), UserTokenPurpose::EmailVerification)
+            .await?
+            .ok_or_else(invalid)?;
+
+        // Token gửi tới email cũ không xác thực được email mới
+        let Some(email) = user_token.email.as_deref() else {
+            return Err(invalid());
+        };
+        if !self
+            .repo
+            .mark_email_verified(&user_token.user_id, email)
+            .await?
+        {
+            return Err(invalid());
+        }
+
+        self.cache
+            .delete(&format!("user:{}", user_token.user_id))
+            .await
+    }
+
+    /// Đổi mật khẩu khi đã đăng nhập, yêu cầu mật khẩu hiện tại
+    ///
+    /// Đăng xuất mọi thiết bị khác, giữ lại `current_session_id` (phiên đang thao tác).
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        current_session_id: Option<Uuid>,
+        current_password: String,
+        new_password: String,
+        device: DeviceInfo,
+    ) -> Result<(), error::SystemError> {
+        let user = self.find_user(&user_id).await?;
+
+        if current_password == new_password {
+            return Err(error::SystemError::bad_request(
+                "Mật khẩu mới phải khác mật khẩu hiện tại",
+            ));
+        }
+        if !verify_password(user.hash_password, current_password).await? {
+            return Err(error::SystemError::bad_request("Mật khẩu hiện tại không chính xác"));
+        }
+
+        let hash_password = hash_password(new_password).await?;
+        if !self.repo.update_password(&user_id, &hash_password).await? {
+            return Err(error::SystemError::not_found(messages::error::USER_NOT_FOUND));
+        }
+
+        self.revoke_all_sessions(user_id, current_session_id).await?;
+
+        self.repo
+            .create_security_event(&NewSecurityEvent {
+                user_id,
+                event_type: SecurityEventType::PasswordChanged,
+                session_id: current_session_id,
+                device,
+            })
+            .await
+    }
+
+    /// Yêu cầu đổi email: kiểm tra mật khẩu rồi gửi link xác nhận tới địa chỉ mới
+    ///
+    /// Email chỉ thực sự đổi khi chủ địa chỉ mới mở link (`confirm_email_change`).
+    pub async fn request_email_change(
+        &self,
+        user_id: Uuid,
+        new_email: String,
+        password: String,
+    ) -> Result<(), error::SystemError> {
+        let user = self.find_user(&user_id).await?;
+
+        if !verify_password(user.hash_password, password).await? {
+            return Err(error::SystemError::bad_request("Mật khẩu không chính xác"));
+        }
+        if new_email.eq_ignore_ascii_case(&user.email) {
+            return Err(error::SystemError::bad_request("Email mới trùng với email hiện tại"));
+        }
+        if self.repo.find_by_email(&new_email).await?.is_some() {
+            return Err(error::SystemError::bad_request(EMAIL_TAKEN));
+        }
+
+        if !self
+            .acquire_mail_cooldown(UserTokenPurpose::EmailChange, &user_id)
+            .await?
+        {
+            return Err(error::SystemError::bad_request(
+                "Vừa gửi email xác nhận, vui lòng thử lại sau ít phút",
+            ));
+        }
+
+        let token = self
+            .issue_user_token(
+                &user_id,
+                UserTokenPurpose::EmailChange,
+                Some(new_email.clone()),
+                EMAIL_CHANGE_TOKEN_TTL_SECONDS,
+            )
+            .await?;
+
+        self.mailer
+            .send(&MailMessage {
+                to: new_email,
+                subject: "Xác nhận đổi email AppChat".to_string(),
+                body: format!(
+                    "Xin chào {},\n\nNhấn vào liên kết sau để dùng địa chỉ này cho tài khoản AppChat (hiệu lực {} phút):\n{}/confirm-email-change?token={}\n\nNếu bạn không yêu cầu, hãy bỏ qua email này.",
+                    user.display_name,
+                    EMAIL_CHANGE_TOKEN_TTL_SECONDS / 60,
+                    ENV.frontend_url,
+                    token
+                ),
+            })
+            .await
+    }
+
+    /// Xác nhận đổi email bằng token gửi tới địa chỉ mới, báo cho địa chỉ cũ
+    pub async fn confirm_email_change(
+        &self,
+        token: &str,
+        device: DeviceInfo,
+    ) -> Result<(), error::SystemError> {
+        let invalid = || {
+            error::SystemError::bad_request("Liên kết đổi email không hợp lệ hoặc đã hết hạn")
+        };
+
+        let user_token = self
+            .repo
+            .consume_user_token(&hash_user_token(token), UserTokenPurpose::EmailChange)
+            .await?
+            .ok_or_else(invalid)?;
+        let Some(new_email) = user_token.email else {
+            return Err(invalid());
+        };
+        let user = self.find_user(&user_token.user_id).await?;
+
+        // Email có thể đã bị tài khoản khác chiếm trong lúc chờ xác nhận (idx_user_email)
+        let updated = match self.repo.update_email(&user.id, &new_email).await {
+            Err(error::SystemError::Conflict(_)) => {
+                return Err(error::SystemError::bad_request(EMAIL_TAKEN));
+            }
+            result => result?,
+        };
+        if !updated {
+            return Err(error::SystemError::not_found(messages::error::USER_NOT_FOUND));
+        }
+
+        self.repo
+            .create_security_event(&NewSecurityEvent {
+                user_id: user.id,
+                event_type: SecurityEventType::EmailChanged,
+                session_id: None,
+                device,
+            })
+            .await?;
+        self.cache.delete(&format!("user:{}", user.id)).await?;
+
+        let notice = MailMessage {
+            to: user.email,
+            subject: "Email tài khoản AppChat đã thay đổi".to_string(),
+            body: format!(
+                "Xin chào {},\n\nEmail đăng nhập của tài khoản đã được đổi thành {}.\nNếu không phải bạn thực hiện, hãy đặt lại mật khẩu ngay.",
+                user.display_name, new_email
+            ),
+        };
+        if let Err(e) = self.mailer.send(&notice).await {
+            tracing::error!("Không gửi được thông báo đổi email cho user {}: {}", user.id, e);
+        }
+
+        Ok(())
+    }
+
     /// Đăng nhập (Sign in) và trả về Access Token + Refresh Token
-    pub async fn sign_in(&self, user: SignInModel) -> Result<(String, String), error::SystemError> {
+    ///
+    /// Mỗi lần đăng nhập mở một phiên mới (refresh token family) gắn với thiết bị.
+    /// Tài khoản bật 2FA chỉ nhận challenge token, gửi tiếp mã qua `complete_two_factor_sign_in`.
+    pub async fn sign_in(
+        &self,
+        user: SignInModel,
+        device: DeviceInfo,
+    ) -> Result<SignInOutcome, error::SystemError> {
         let user_entity = self
             .repo
             .find_by_username(&user.username)
@@ -120,35 +453,208 @@ where
             ));
         }
 
-        let access_token = Claims::new(
-            &user_entity.id,
-            &user_entity.role,
-            ENV.access_token_expiration,
-        )
-        .with_type(TypeClaims::AccessToken)
-        .encode(ENV.jwt_secret.as_ref())?;
+        if user_entity.totp_enabled_at.is_some() {
+            self.ensure_two_factor_not_locked(&user_entity.id).await?;
 
-        let jti = Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext));
+            let jti = Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext));
+            let challenge_token = Claims::new(
+                &user_entity.id,
+                &user_entity.role,
+                TWO_FACTOR_CHALLENGE_TTL_SECONDS,
+            )
+            .with_jti(jti)
+            .with_type(TypeClaims::TwoFactorChallenge)
+            .encode(ENV.jwt_secret.as_ref())?;
+
+            self.cache
+                .set(
+                    &format!("two_factor_challenge:{jti}"),
+                    &true,
+                    TWO_FACTOR_CHALLENGE_TTL_SECONDS as usize,
+                )
+                .await?;
+
+            return Ok(SignInOutcome::TwoFactorRequired { challenge_token });
+        }
+
+        let (access_token, refresh_token) = self
+            .start_session(&user_entity.id, &user_entity.role, device)
+            .await?;
+        Ok(SignInOutcome::Authenticated {
+            access_token,
+            refresh_token,
+        })
+    }
+
+    /// Bước 2 đăng nhập tài khoản bật 2FA: đổi challenge token + mã TOTP/mã khôi phục lấy token
+    pub async fn complete_two_factor_sign_in(
+        &self,
+        challenge_token: &str,
+        code: &str,
+        device: DeviceInfo,
+    ) -> Result<(String, String), error::SystemError> {
+        let invalid = || {
+            error::SystemError::unauthorized("Phiên xác thực hai lớp không hợp lệ hoặc đã hết hạn")
+        };
+
+        let payload = Claims::decode(challenge_token, ENV.jwt_secret.as_ref())?;
+        let (Some(TypeClaims::TwoFactorChallenge), Some(jti)) = (&payload._type, payload.jti)
+        else {
+            return Err(invalid());
+        };
+
+        let key = format!("two_factor_challenge:{jti}");
+        if self.cache.get::<bool>(&key).await?.is_none() {
+            return Err(invalid());
+        }
+
+        let user = self
+            .repo
+            .find_by_id(&payload.sub)
+            .await?
+            .filter(|user| user.totp_enabled_at.is_some())
+            .ok_or_else(invalid)?;
+
+        // Giữ chỗ lượt thử bằng INCR trước khi kiểm tra mã, nên các request song song
+        // không thể vượt giới hạn của challenge lẫn của tài khoản
+        let failures_key = Self::two_factor_failures_key(&user.id);
+        if self
+            .cache
+            .incr(&failures_key, TWO_FACTOR_LOCKOUT_SECONDS)
+            .await?
+            > TWO_FACTOR_MAX_USER_FAILURES
+        {
+            self.cache.delete(&key).await?;
+            return Err(Self::two_factor_locked());
+        }
+
+        let attempts_key = format!("two_factor_attempts:{jti}");
+        let attempt = self
+            .cache
+            .incr(&attempts_key, TWO_FACTOR_CHALLENGE_TTL_SECONDS as usize)
+            .await?;
+        if attempt > TWO_FACTOR_MAX_ATTEMPTS {
+            self.cache.delete(&key).await?;
+            return Err(invalid());
+        }
+
+        if !self.verify_second_factor(&user, code, &device).await? {
+            if attempt == TWO_FACTOR_MAX_ATTEMPTS {
+                self.cache.delete(&key).await?;
+            }
+            return Err(error::SystemError::unauthorized("Mã xác thực không chính xác"));
+        }
+
+        self.cache.delete(&key).await?;
+        self.cache.delete(&attempts_key).await?;
+        self.cache.delete(&failures_key).await?;
+        self.start_session(&user.id, &user.role, device).await
+    }
+
+    /// Bắt đầu bật 2FA: sinh secret mới (chưa có hiệu lực cho tới khi xác nhận bằng mã)
+    pub async fn setup_two_factor(
+        &self,
+        user_id: Uuid,
+    ) -> Result<TwoFactorSetupResponse, error::SystemError> {
+        let user = self.find_user(&user_id).await?;
+        if user.totp_enabled_at.is_some() {
+            return Err(error::SystemError::bad_request("Xác thực hai lớp đã được bật"));
+        }
+
+        let secret = totp::generate_secret();
+        if !self.repo.set_pending_totp_secret(&user_id, &secret).await? {
+            return Err(error::SystemError::bad_request("Xác thực hai lớp đã được bật"));
+        }
 
-        let refresh_token = Claims::new(
-            &user_entity.id,
-            &user_entity.role,
-            ENV.refresh_token_expiration,
+        Ok(TwoFactorSetupResponse {
+            otpauth_uri: totp::otpauth_uri(&secret, &user.username),
+            secret,
+        })
+    }
+
+    /// Xác nhận mã từ app authenticator để bật 2FA, trả về các mã khôi phục (chỉ hiện một lần)
+    pub async fn confirm_two_factor(
+        &self,
+        user_id: Uuid,
+        code: &str,
+        device: DeviceInfo,
+    ) -> Result<Vec<String>, error::SystemError> {
+        let user = self.find_user(&user_id).await?;
+        if user.totp_enabled_at.is_some() {
+            return Err(error::SystemError::bad_request("Xác thực hai lớp đã được bật"));
+        }
+        let Some(secret) = user.totp_secret.as_deref() else {
+            return Err(error::SystemError::bad_request(
+                "Chưa bắt đầu thiết lập xác thực hai lớp",
+            ));
+        };
+
+        let step = totp::verify(secret, code, chrono::Utc::now().timestamp())
+            .ok_or_else(|| error::SystemError::bad_request("Mã xác thực không chính xác"))?;
+        if !self.repo.record_totp_step(&user_id, step).await? {
+            return Err(error::SystemError::bad_request("Mã xác thực không chính xác"));
+        }
+
+        let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let code = generate_random_token(RECOVERY_CODE_LENGTH).to_ascii_lowercase();
+                let (head, tail) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+                format!("{head}-{tail}")
+            })
+            .collect();
+        let hashes = futures_util::future::try_join_all(
+            recovery_codes
+                .iter()
+                .map(|code| hash_password(normalize_recovery_code(code))),
         )
-        .with_jti(jti)
-        .with_type(TypeClaims::RefreshToken)
-        .encode(ENV.jwt_secret.as_ref())?;
+        .await?;
 
-        let refresh_key = format!("refresh_token:{jti}");
-        self.cache
-            .set(
-                &refresh_key,
-                &user_entity.id,
-                ENV.refresh_token_expiration as usize,
-            )
+        if !self.repo.enable_totp(&user_id, &hashes).await? {
+            return Err(error::SystemError::bad_request("Xác thực hai lớp đã được bật"));
+        }
+
+        self.repo
+            .create_security_event(&NewSecurityEvent {
+                user_id,
+                event_type: SecurityEventType::TwoFactorEnabled,
+                session_id: None,
+                device,
+            })
             .await?;
+        self.cache.delete(&format!("user:{user_id}")).await?;
 
-        Ok((access_token, refresh_token))
+        Ok(recovery_codes)
+    }
+
+    /// Tắt 2FA, yêu cầu nhập lại mật khẩu
+    pub async fn disable_two_factor(
+        &self,
+        user_id: Uuid,
+        password: String,
+        device: DeviceInfo,
+    ) -> Result<(), error::SystemError> {
+        let user = self.find_user(&user_id).await?;
+        if user.totp_enabled_at.is_none() {
+            return Err(error::SystemError::bad_request("Xác thực hai lớp chưa được bật"));
+        }
+
+        if !verify_password(user.hash_password, password).await? {
+            return Err(error::SystemError::bad_request("Mật khẩu không chính xác"));
+        }
+
+        if !self.repo.disable_totp(&user_id).await? {
+            return Err(error::SystemError::bad_request("Xác thực hai lớp chưa được bật"));
+        }
+
+        self.repo
+            .create_security_event(&NewSecurityEvent {
+                user_id,
+                event_type: SecurityEventType::TwoFactorDisabled,
+                session_id: None,
+                device,
+            })
+            .await?;
+        self.cache.delete(&format!("user:{user_id}")).await
     }
 
     /// Đăng xuất tài khoản (Xóa Refresh Token)
@@ -170,6 +676,16 @@ where
         let refresh_key = format!("refresh_token:{jti}");
         self.cache.delete(&refresh_key).await?;
 
+        if let Some(session_id) = payload.sid
+            && self
+                .repo
+                .revoke_session(&payload.sub, &session_id)
+                .await?
+                .is_some()
+        {
+            self.ws_server.revoke_auth_sessions(&[session_id]);
+        }
+
         Ok(())
     }
 
@@ -177,6 +693,7 @@ where
     pub async fn refresh(
         &self,
         old_refresh_token: Option<String>,
+        device: DeviceInfo,
     ) -> Result<(String, String), error::SystemError> {
         let invalid = || error::SystemError::unauthorized(messages::error::INVALID_TOKEN);
 
@@ -195,36 +712,370 @@ where
         };
 
         let old_key = format!("refresh_token:{jti}");
+        let is_current = self.cache.get::<String>(&old_key).await?.is_some();
 
-        if self.cache.get::<String>(&old_key).await?.is_none() {
-            return Err(invalid());
+        if is_current {
+            self.cache.delete(&old_key).await?;
         }
 
-        self.cache.delete(&old_key).await?;
+        // Token phát hành trước khi có bảng phiên: mở phiên mới thay vì xoay vòng
+        let Some(session_id) = payload.sid else {
+            if !is_current {
+                return Err(invalid());
+            }
+            return self.start_session(&payload.sub, &payload.role, device).await;
+        };
 
         let new_jti = Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext));
-        let new_key = format!("refresh_token:{new_jti}");
 
-        let new_access_token =
-            Claims::new(&payload.sub, &payload.role, ENV.access_token_expiration)
-                .with_type(TypeClaims::AccessToken)
-                .encode(ENV.jwt_secret.as_ref())?;
+        let rotated = self
+            .repo
+            .rotate_session(
+                &session_id,
+                &payload.sub,
+                &jti,
+                &new_jti,
+                &device,
+                Self::session_expires_at(),
+            )
+            .await?;
 
-        let new_refresh_token =
-            Claims::new(&payload.sub, &payload.role, ENV.refresh_token_expiration)
-                .with_jti(new_jti)
-                .with_type(TypeClaims::RefreshToken)
-                .encode(ENV.jwt_secret.as_ref())?;
+        if !rotated {
+            // Hai tab gửi cùng cookie gần như đồng thời: request đến sau nhận lại token hiện tại
+            // của phiên thay vì bị coi là đánh cắp. Chỉ ân hạn khi cùng user agent/IP với lần
+            // xoay vòng, và vẫn ghi nhật ký để replay trong cửa sổ này không bị bỏ sót
+            let rotated_after = chrono::Utc::now()
+                - chrono::Duration::seconds(REFRESH_ROTATION_GRACE_SECONDS);
+            let grace_session = self
+                .repo
+                .find_recently_rotated_session(&session_id, &payload.sub, &jti, rotated_after)
+                .await?
+                .filter(|session| {
+                    session.user_agent == device.user_agent
+                        && session.ip_address == device.ip_address
+                });
+            if let Some(session) = grace_session {
+                self.repo
+                    .create_security_event(&NewSecurityEvent {
+                        user_id: payload.sub,
+                        event_type: SecurityEventType::RefreshTokenGraceUse,
+                        session_id: Some(session_id),
+                        device,
+                    })
+                    .await?;
+                return self
+                    .issue_tokens(&payload.sub, &payload.role, session_id, session.current_jti)
+                    .await;
+            }
 
-        self.cache
-            .set(
-                &new_key,
-                &payload.sub,
-                ENV.refresh_token_expiration as usize,
+            self.detect_token_reuse(&payload.sub, &session_id, &jti, device)
+                .await?;
+            return Err(invalid());
+        }
+
+        self.issue_tokens(&payload.sub, &payload.role, session_id, new_jti)
+            .await
+    }
+
+    /// Danh sách phiên đăng nhập còn hiệu lực của user, đánh dấu phiên đang gọi
+    pub async fn list_sessions(
+        &self,
+        user_id: Uuid,
+        current_session_id: Option<Uuid>,
+    ) -> Result<Vec<SessionResponse>, error::SystemError> {
+        let sessions = self.repo.find_active_sessions(&user_id).await?;
+
+        Ok(sessions
+            .into_iter()
+            .map(|session| SessionResponse {
+                current: Some(session.id) == current_session_id,
+                id: session.id,
+                user_agent: session.user_agent,
+                ip_address: session.ip_address,
+                created_at: session.created_at,
+                last_used_at: session.last_used_at,
+            })
+            .collect())
+    }
+
+    /// Đăng xuất một thiết bị: thu hồi refresh token và đóng WebSocket của phiên đó
+    pub async fn revoke_session(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<(), error::SystemError> {
+        let session = self
+            .repo
+            .revoke_session(&user_id, &session_id)
+            .await?
+            .ok_or_else(|| error::SystemError::not_found("Không tìm thấy phiên đăng nhập"))?;
+
+        self.drop_sessions(vec![session]).await
+    }
+
+    /// Đăng xuất mọi thiết bị, giữ lại `except` (phiên hiện tại) nếu có
+    ///
+    /// Trả về số phiên đã thu hồi.
+    pub async fn revoke_all_sessions(
+        &self,
+        user_id: Uuid,
+        except: Option<Uuid>,
+    ) -> Result<usize, error::SystemError> {
+        let sessions = self
+            .repo
+            .revoke_sessions(&user_id, except.as_ref())
+            .await?;
+        let count = sessions.len();
+
+        self.drop_sessions(sessions).await?;
+        Ok(count)
+    }
+
+    /// Helper: Refresh token không xoay vòng được vì không còn là token mới nhất của family
+    ///
+    /// Ngoài thời gian ân hạn, token cũ chỉ có thể đến từ kẻ đã đánh cắp nó (hoặc chủ thật sau khi kẻ gian đã dùng trước),
+    /// nên thu hồi cả family để cả hai bên phải đăng nhập lại và ghi nhận sự kiện bảo mật.
+    async fn detect_token_reuse(
+        &self,
+        user_id: &Uuid,
+        session_id: &Uuid,
+        presented_jti: &Uuid,
+        device: DeviceInfo,
+    ) -> Result<(), error::SystemError> {
+        let Some(session) = self
+            .repo
+            .revoke_reused_session(session_id, user_id, presented_jti)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        tracing::warn!(
+            "Refresh token reuse detected for user {} on session {}, family revoked",
+            user_id,
+            session_id
+        );
+
+        self.repo
+            .create_security_event(&NewSecurityEvent {
+                user_id: *user_id,
+                event_type: SecurityEventType::RefreshTokenReuse,
+                session_id: Some(*session_id),
+                device,
+            })
+            .await?;
+
+        self.drop_sessions(vec![session]).await
+    }
+
+    /// Helper: Từ chối đăng nhập 2FA khi tài khoản đã nhập sai mã quá nhiều lần gần đây
+    async fn ensure_two_factor_not_locked(&self, user_id: &Uuid) -> Result<(), error::SystemError> {
+        let failures = self
+            .cache
+            .get::<i64>(&Self::two_factor_failures_key(user_id))
+            .await?
+            .unwrap_or(0);
+
+        if failures >= TWO_FACTOR_MAX_USER_FAILURES {
+            return Err(Self::two_factor_locked());
+        }
+
+        Ok(())
+    }
+
+    fn two_factor_failures_key(user_id: &Uuid) -> String {
+        format!("two_factor_failures:{user_id}")
+    }
+
+    fn two_factor_locked() -> error::SystemError {
+        error::SystemError::forbidden(
+            "Tài khoản tạm thời bị khoá đăng nhập do nhập sai mã xác thực quá nhiều lần, vui lòng thử lại sau",
+        )
+    }
+
+    /// Helper: Kiểm tra mã TOTP (mỗi mã dùng một lần), nếu không khớp thì thử như mã khôi phục
+    async fn verify_second_factor(
+        &self,
+        user: &UserEntity,
+        code: &str,
+        device: &DeviceInfo,
+    ) -> Result<bool, error::SystemError> {
+        let Some(secret) = user.totp_secret.as_deref() else {
+            return Ok(false);
+        };
+
+        if let Some(step) = totp::verify(secret, code, chrono::Utc::now().timestamp()) {
+            return self.repo.record_totp_step(&user.id, step).await;
+        }
+
+        let code = normalize_recovery_code(code);
+        if code.len() != RECOVERY_CODE_LENGTH {
+            return Ok(false);
+        }
+
+        for recovery_code in self.repo.find_unused_recovery_codes(&user.id).await? {
+            if !verify_password(recovery_code.code_hash, code.clone()).await? {
+                continue;
+            }
+            if !self.repo.use_recovery_code(&recovery_code.id).await? {
+                return Ok(false);
+            }
+
+            self.repo
+                .create_security_event(&NewSecurityEvent {
+                    user_id: user.id,
+                    event_type: SecurityEventType::RecoveryCodeUsed,
+                    session_id: None,
+                    device: device.clone(),
+                })
+                .await?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Helper: Lấy user từ DB (không qua cache), lỗi not_found nếu không có
+    async fn find_user(&self, user_id: &Uuid) -> Result<UserEntity, error::SystemError> {
+        self.repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| error::SystemError::not_found(messages::error::USER_NOT_FOUND))
+    }
+
+    /// Helper: Gửi email chứa link xác thực tới `email`
+    async fn send_verification_email(
+        &self,
+        user_id: &Uuid,
+        email: &str,
+    ) -> Result<(), error::SystemError> {
+        let token = self
+            .issue_user_token(
+                user_id,
+                UserTokenPurpose::EmailVerification,
+                Some(email.to_string()),
+                EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
             )
             .await?;
 
-        Ok((new_access_token, new_refresh_token))
+        self.mailer
+            .send(&MailMessage {
+                to: email.to_string(),
+                subject: "Xác thực email AppChat".to_string(),
+                body: format!(
+                    "Nhấn vào liên kết sau để xác thực email của bạn (hiệu lực {} giờ):\n{}/verify-email?token={}",
+                    EMAIL_VERIFICATION_TOKEN_TTL_SECONDS / 3600,
+                    ENV.frontend_url,
+                    token
+                ),
+            })
+            .await
+    }
+
+    /// Helper: Sinh token dùng một lần, chỉ lưu hash của nó. Trả về token gốc để gửi đi.
+    async fn issue_user_token(
+        &self,
+        user_id: &Uuid,
+        purpose: UserTokenPurpose,
+        email: Option<String>,
+        ttl_seconds: i64,
+    ) -> Result<String, error::SystemError> {
+        let token = generate_random_token(USER_TOKEN_LENGTH);
+
+        self.repo
+            .create_user_token(&NewUserToken {
+                user_id: *user_id,
+                purpose,
+                token_hash: hash_user_token(&token),
+                email,
+                expires_at: chrono::Utc::now() + chrono::Duration::seconds(ttl_seconds),
+            })
+            .await?;
+
+        Ok(token)
+    }
+
+    /// Helper: Trả về false nếu vừa gửi email cùng loại cho user trong `MAIL_COOLDOWN_SECONDS`
+    ///
+    /// Dùng bộ đếm nguyên tử nên nhiều request đồng thời chỉ có một request được gửi.
+    async fn acquire_mail_cooldown(
+        &self,
+        purpose: UserTokenPurpose,
+        user_id: &Uuid,
+    ) -> Result<bool, error::SystemError> {
+        let key = format!("mail_cooldown:{purpose:?}:{user_id}");
+        Ok(self.cache.incr(&key, MAIL_COOLDOWN_SECONDS).await? == 1)
+    }
+
+    /// Helper: Mở phiên mới cho user và cấp cặp token đầu tiên
+    async fn start_session(
+        &self,
+        user_id: &Uuid,
+        role: &UserRole,
+        device: DeviceInfo,
+    ) -> Result<(String, String), error::SystemError> {
+        let session_id = Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext));
+        let jti = Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext));
+
+        self.repo
+            .create_session(&NewUserSession {
+                id: session_id,
+                user_id: *user_id,
+                current_jti: jti,
+                device,
+                expires_at: Self::session_expires_at(),
+            })
+            .await?;
+
+        self.issue_tokens(user_id, role, session_id, jti).await
+    }
+
+    /// Helper: Ký access/refresh token cho phiên và lưu jti hợp lệ lên Redis
+    async fn issue_tokens(
+        &self,
+        user_id: &Uuid,
+        role: &UserRole,
+        session_id: Uuid,
+        jti: Uuid,
+    ) -> Result<(String, String), error::SystemError> {
+        let access_token = Claims::new(user_id, role, ENV.access_token_expiration)
+            .with_sid(session_id)
+            .with_type(TypeClaims::AccessToken)
+            .encode(ENV.jwt_secret.as_ref())?;
+
+        let refresh_token = Claims::new(user_id, role, ENV.refresh_token_expiration)
+            .with_jti(jti)
+            .with_sid(session_id)
+            .with_type(TypeClaims::RefreshToken)
+            .encode(ENV.jwt_secret.as_ref())?;
+
+        let refresh_key = format!("refresh_token:{jti}");
+        self.cache
+            .set(&refresh_key, user_id, ENV.refresh_token_expiration as usize)
+            .await?;
+
+        Ok((access_token, refresh_token))
+    }
+
+    /// Helper: Xoá refresh token đang hiệu lực của các phiên đã thu hồi và ngắt WebSocket của chúng
+    async fn drop_sessions(
+        &self,
+        sessions: Vec<UserSessionEntity>,
+    ) -> Result<(), error::SystemError> {
+        let mut session_ids = Vec::with_capacity(sessions.len());
+        for session in sessions {
+            self.cache
+                .delete(&format!("refresh_token:{}", session.current_jti))
+                .await?;
+            session_ids.push(session.id);
+        }
+
+        self.ws_server.revoke_auth_sessions(&session_ids);
+        Ok(())
+    }
+
+    fn session_expires_at() -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc::now() + chrono::Duration::seconds(ENV.refresh_token_expiration as i64)
     }
 
     /// Tìm kiếm user theo username hoặc tên hiển thị
@@ -232,7 +1083,8 @@ where
         &self,
         query: &str,
         limit: i32,
-    ) -> Result<Vec<UserResponse>, error::SystemError> {
+        cursor: Option<String>,
+    ) -> Result<UserSearchResponse, error::SystemError> {
         // Validate query length
         if query.trim().is_empty() {
             return Err(error::SystemError::bad_request(
@@ -249,10 +1101,68 @@ where
         // Validate limit
         let limit = limit.clamp(1, 50); // Limit between 1 and 50
 
-        let users = self.repo.search_users(query, limit).await?;
+        // Sắp theo tên hiển thị; tên trùng thì theo thời điểm tạo rồi id
+        let cursor = Cursor::parse_ranked(cursor.as_deref())?;
+        let mut users = self.repo.search_users(query, cursor.as_ref(), limit).await?;
+
+        let next_cursor = if users.len() > limit as usize {
+            users.pop();
+            users
+                .last()
+                .map(|u| Cursor::ranked(u.display_name.clone(), u.created_at, u.id).encode())
+        } else {
+            None
+        };
+
+        Ok(UserSearchResponse {
+            users: users
+                .into_iter()
+                .map(|user| UserResponse::from(user).into())
+                .collect(),
+            cursor: next_cursor,
+        })
+    }
+}
 
-        let responses: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
+/// Các luồng gửi email theo địa chỉ do client nhập chạy nền, ngoài request
+///
+/// Tra cứu tài khoản, tạo token và gửi mail đều nằm trong task nên thời gian phản hồi và
+/// kết quả như nhau dù email có đăng ký hay không. Handler bỏ qua `JoinHandle`, test thì chờ nó.
+impl<U, C> UserService<U, C>
+where
+    U: UserRepository + Clone + Send + Sync + 'static,
+    C: CacheStore + Clone + Send + Sync + 'static,
+{
+    /// Quên mật khẩu: gửi link đặt lại mật khẩu tới email (nếu có tài khoản)
+    pub fn request_password_reset(&self, email: String) -> tokio::task::JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = service.deliver_password_reset(&email).await {
+                tracing::error!("Không gửi được email đặt lại mật khẩu: {}", e);
+            }
+        })
+    }
 
-        Ok(responses)
+    /// Gửi lại email xác thực (bỏ qua nếu không có tài khoản hoặc email đã xác thực)
+    pub fn resend_verification_email(&self, email: String) -> tokio::task::JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = service.deliver_verification_email(&email).await {
+                tracing::error!("Không gửi lại được email xác thực: {}", e);
+            }
+        })
     }
 }
+
+/// SHA-256 (hex) của token dùng một lần, chỉ giá trị này được lưu trong DB
+fn hash_user_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Mã khôi phục được hiển thị dạng `abcde-12345`, người dùng có thể gõ thiếu dấu gạch hoặc viết hoa
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
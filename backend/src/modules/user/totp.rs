@@ -0,0 +1,143 @@
+//! TOTP (RFC 6238): HMAC-SHA1, 6 chữ số, chu kỳ 30 giây - tương thích Google Authenticator, Authy...
+use hmac::{Hmac, Mac};
+use rand::{RngCore, rngs::OsRng};
+use sha1::Sha1;
+
+pub const TOTP_ISSUER: &str = "AppChat";
+const TOTP_DIGITS: u32 = 6;
+const TOTP_PERIOD_SECONDS: i64 = 30;
+const SECRET_LENGTH: usize = 20;
+/// Số bước lệch cho phép mỗi phía để bù sai lệch đồng hồ của điện thoại
+const ALLOWED_SKEW_STEPS: i64 = 1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Sinh secret ngẫu nhiên 160 bit, mã hoá base32 (không padding)
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+    base32_encode(&bytes)
+}
+
+/// URI để app authenticator quét qua QR code
+pub fn otpauth_uri(secret: &str, account: &str) -> String {
+    let label: String =
+        url::form_urlencoded::byte_serialize(format!("{TOTP_ISSUER}:{account}").as_bytes())
+            .collect();
+    format!(
+        "otpauth://totp/{label}?secret={secret}&issuer={TOTP_ISSUER}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_PERIOD_SECONDS}"
+    )
+}
+
+/// Kiểm tra mã người dùng nhập, trả về bước thời gian (time step) khớp nếu hợp lệ
+///
+/// Caller lưu lại bước này để mỗi mã chỉ dùng được một lần.
+pub fn verify(secret: &str, code: &str, unix_time: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let key = base32_decode(secret)?;
+    let current_step = unix_time.div_euclid(TOTP_PERIOD_SECONDS);
+
+    (current_step - ALLOWED_SKEW_STEPS..=current_step + ALLOWED_SKEW_STEPS)
+        .find(|&step| step >= 0 && generate(&key, step as u64) == code)
+}
+
+#[cfg(test)]
+pub fn code_at(secret: &str, unix_time: i64) -> String {
+    let key = base32_decode(secret).expect("valid base32 secret");
+    generate(&key, unix_time.div_euclid(TOTP_PERIOD_SECONDS) as u64)
+}
+
+/// Mã TOTP tại bước `step` (HOTP với counter = step)
+fn generate(key: &[u8], step: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    output
+}
+
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.bytes().filter(|&c| c != b'=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc6238_sha1_vectors() {
+        // Secret "12345678901234567890" của RFC 6238, lấy 6 chữ số cuối của các vector 8 chữ số
+        let secret = base32_encode(b"12345678901234567890");
+        assert_eq!(secret, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+
+        let key = base32_decode(&secret).unwrap();
+        assert_eq!(generate(&key, 59 / 30), "287082");
+        assert_eq!(generate(&key, 1111111109 / 30), "081804");
+        assert_eq!(generate(&key, 1234567890 / 30), "005924");
+        assert_eq!(generate(&key, 20000000000 / 30), "353130");
+    }
+
+    #[test]
+    fn test_verify_accepts_adjacent_step_only() {
+        let secret = base32_encode(b"12345678901234567890");
+        let now = 1111111109;
+
+        assert_eq!(verify(&secret, "081804", now), Some(now / 30));
+        assert_eq!(verify(&secret, " 081804 ", now + 30), Some(now / 30));
+        assert_eq!(verify(&secret, "081804", now + 90), None);
+        assert_eq!(verify(&secret, "08180", now), None);
+        assert_eq!(verify(&secret, "abcdef", now), None);
+    }
+}
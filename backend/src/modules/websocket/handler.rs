@@ -2,7 +2,7 @@
 ///
 /// Xử lý HTTP upgrade thành WebSocket và chia ra 2 tasks đọc/ghi
 use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse, web};
-use actix_ws::Message;
+use actix_ws::{CloseCode, CloseReason, Message};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
@@ -12,6 +12,7 @@ use super::message::ClientMessage;
 use super::presence::PresenceService;
 use super::server::WebSocketServer;
 use super::session::{MessageSvc, WebSocketSessionImpl};
+use crate::modules::draft::handle::DraftSvc;
 use crate::modules::friend::repository_pg::FriendRepositoryPg;
 use crate::observability::{RequestContext, WsCloseReason};
 use crate::METRICS;
@@ -28,6 +29,7 @@ pub async fn websocket_handler(
     message_service: web::Data<MessageSvc>,
     presence_service: web::Data<PresenceService>,
     friend_repo: web::Data<FriendRepositoryPg>,
+    draft_service: web::Data<DraftSvc>,
 ) -> Result<HttpResponse, Error> {
     tracing::debug!("WebSocket upgrade request từ {:?}", req.peer_addr());
 
@@ -54,12 +56,14 @@ pub async fn websocket_handler(
         Some(Arc::new(message_service.into_inner().as_ref().clone())),
         Some(Arc::new(presence_service.into_inner().as_ref().clone())),
         Some(Arc::new(friend_repo.into_inner().as_ref().clone())),
+        Some(draft_service.into_inner()),
     );
 
     let session_id = ws_session.id;
 
     // Đăng ký session với Server
     server.connect(session_id, tx);
+    let close_signal = server.close_signal(session_id);
 
     // Spawn task gửi (Writer Task)
     let mut session_writer = session.clone();
@@ -76,6 +80,12 @@ pub async fn websocket_handler(
         let mut heartbeat = actix_web::rt::time::interval(HEARTBEAT_INTERVAL);
         let close_reason = loop {
             tokio::select! {
+                // Phiên đăng nhập bị thu hồi từ thiết bị khác
+                _ = close_signal.notified() => {
+                    tracing::info!("Phiên đăng nhập của session {} đã bị thu hồi", session_id);
+                    break WsCloseReason::SessionRevoked;
+                }
+
                 // Heartbeat / ping mechanism
                 _ = heartbeat.tick() => {
                     // Cập nhật TTL presence trên Redis
@@ -146,7 +156,12 @@ pub async fn websocket_handler(
         METRICS.inc_ws_disconnect();
 
         // Cleanup: Xóa trạng thái của session và đóng kết nối
-        let _ = session.close(None).await;
+        let close_frame =
+            matches!(close_reason, WsCloseReason::SessionRevoked).then(|| CloseReason {
+                code: CloseCode::Policy,
+                description: Some("session_revoked".to_string()),
+            });
+        let _ = session.close(close_frame).await;
 
         let fully_disconnected_user = server.disconnect(session_id);
 
@@ -6,6 +6,11 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::modules::conversation::schema::ParticipantRole;
+use crate::modules::draft::model::Draft;
+use crate::modules::message::model::PollDetail;
+use crate::modules::message::schema::MessageLocation;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CallSignalingType {
@@ -60,6 +65,20 @@ pub enum ClientMessage {
     /// Dừng typing trong conversation
     TypingStop { conversation_id: Uuid },
 
+    /// Cập nhật bản nháp, content rỗng = xoá bản nháp
+    UpdateDraft {
+        conversation_id: Uuid,
+        content: String,
+        #[serde(default)]
+        reply_to_id: Option<Uuid>,
+    },
+
+    /// Xác nhận session đã nhận được tin nhắn (delivery receipt)
+    MessageReceived {
+        conversation_id: Uuid,
+        message_id: Uuid,
+    },
+
     /// Ping để giữ connection alive
     Ping,
 
@@ -112,6 +131,12 @@ pub struct NewMessagePayload {
     pub conversation: ConversationInfo,
     /// Unread counts theo user ID
     pub unread_counts: serde_json::Value,
+    /// Các user được nhắc tới (@username/@all) trong tin nhắn
+    #[serde(default)]
+    pub mentioned_user_ids: Vec<Uuid>,
+    /// Người nhận đang tắt thông báo conversation (vẫn tăng unread, client không hiện alert)
+    #[serde(default)]
+    pub muted: bool,
 }
 
 /// Payload cho event read-message (format tương thích Socket.IO)
@@ -150,6 +175,52 @@ pub enum ServerMessage {
         message_id: Uuid,
     },
 
+    /// Tin nhắn đã tới được thiết bị của người nhận (gửi cho người gửi)
+    MessageDelivered {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        user_id: Uuid,
+        delivered_at: String,
+    },
+
+    /// Thread (các reply của một tin nhắn gốc) vừa thay đổi
+    ThreadUpdated {
+        conversation_id: Uuid,
+        root_message_id: Uuid,
+        reply_count: i32,
+        last_reply_at: Option<String>,
+    },
+
+    /// Có user vừa thả reaction vào tin nhắn
+    ReactionAdded {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String,
+    },
+
+    /// Có user vừa gỡ reaction khỏi tin nhắn
+    ReactionRemoved {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String,
+    },
+
+    /// Tin nhắn vừa được ghim
+    MessagePinned {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        pinned_by: Uuid,
+    },
+
+    /// Tin nhắn vừa được bỏ ghim
+    MessageUnpinned {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        unpinned_by: Uuid,
+    },
+
     /// User đã đọc messages (read receipt) - format tương thích Socket.IO
     ReadMessage(ReadMessagePayload),
 
@@ -190,6 +261,42 @@ pub enum ServerMessage {
         avatar_url: Option<String>,
     },
 
+    /// Có yêu cầu tham gia nhóm mới (gửi tới owner/admin)
+    JoinRequestReceived {
+        conversation_id: Uuid,
+        request_id: Uuid,
+        user_id: Uuid,
+        display_name: String,
+        avatar_url: Option<String>,
+        message: Option<String>,
+    },
+
+    /// Bản nháp thay đổi trên thiết bị khác của chính user, `draft = None` nghĩa là đã xoá
+    DraftUpdated {
+        conversation_id: Uuid,
+        draft: Option<Draft>,
+    },
+
+    /// Kết quả bình chọn thay đổi (có phiếu mới, rút phiếu hoặc poll bị đóng)
+    PollUpdated {
+        conversation_id: Uuid,
+        poll: PollDetail,
+    },
+
+    /// Người chia sẻ vị trí trực tiếp vừa cập nhật toạ độ
+    LiveLocationUpdated {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        location: MessageLocation,
+    },
+
+    /// Vai trò của thành viên trong nhóm thay đổi (owner/admin/member)
+    MemberRoleChanged {
+        conversation_id: Uuid,
+        user_id: Uuid,
+        role: ParticipantRole,
+    },
+
     /// Thành viên rời/bị kick khỏi nhóm
     MemberRemoved {
         conversation_id: Uuid,
@@ -263,6 +370,8 @@ impl ServerMessage {
         last_message: LastMessageInfo,
         last_message_at: String,
         unread_counts: serde_json::Value,
+        mentioned_user_ids: Vec<Uuid>,
+        muted: bool,
     ) -> Self {
         Self::NewMessage(NewMessagePayload {
             message,
@@ -272,6 +381,8 @@ impl ServerMessage {
                 last_message_at,
             },
             unread_counts,
+            mentioned_user_ids,
+            muted,
         })
     }
 
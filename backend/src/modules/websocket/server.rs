@@ -1,11 +1,12 @@
 use dashmap::{DashMap, DashSet};
 use rayon::prelude::*;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
+use tokio::sync::{Notify, mpsc};
 use uuid::Uuid;
 
 use super::message::ServerMessage;
-use crate::METRICS;
+use crate::{ENV, METRICS};
 
 const RECONNECT_WINDOW: Duration = Duration::from_secs(120);
 
@@ -27,6 +28,20 @@ pub struct WebSocketServer {
 
     /// Map: user_id -> last fully-disconnected instant
     last_disconnect_at: DashMap<Uuid, Instant>,
+
+    /// Map: auth session id (phiên đăng nhập / refresh family) -> set of session_ids
+    auth_sessions: DashMap<Uuid, DashSet<Uuid>>,
+
+    /// Map: session_id -> auth session id
+    session_auth: DashMap<Uuid, Uuid>,
+
+    /// Map: session_id -> tín hiệu buộc đóng kết nối
+    close_signals: DashMap<Uuid, Arc<Notify>>,
+
+    /// Map: auth session id đã bị thu hồi -> thời điểm thu hồi
+    ///
+    /// Access token của phiên đó vẫn còn hạn thêm một lúc, giữ lại để chặn kết nối lại.
+    revoked_auth_sessions: DashMap<Uuid, Instant>,
 }
 
 impl WebSocketServer {
@@ -38,6 +53,10 @@ impl WebSocketServer {
             rooms: DashMap::new(),
             user_rooms: DashMap::new(),
             last_disconnect_at: DashMap::new(),
+            auth_sessions: DashMap::new(),
+            session_auth: DashMap::new(),
+            close_signals: DashMap::new(),
+            revoked_auth_sessions: DashMap::new(),
         }
     }
 
@@ -78,6 +97,24 @@ impl WebSocketServer {
         }
     }
 
+    /// Gửi message tới các session khác của user (đồng bộ giữa các thiết bị, bỏ qua session nguồn)
+    pub fn send_to_other_sessions(
+        &self,
+        user_id: &Uuid,
+        origin_session: &Uuid,
+        message: &ServerMessage,
+    ) {
+        if let Some(sessions) = self.users.get(user_id)
+            && let Ok(json) = serde_json::to_string(message)
+        {
+            for session_id in sessions.iter().filter(|id| **id != *origin_session) {
+                if let Some(tx) = self.sessions.get(&*session_id) {
+                    let _ = tx.send(json.clone());
+                }
+            }
+        }
+    }
+
     /// Gửi message tới nhiều users
     pub fn send_to_users(&self, user_ids: &[Uuid], message: &ServerMessage) {
         if let Ok(json) = serde_json::to_string(message) {
@@ -103,6 +140,15 @@ impl WebSocketServer {
     pub fn disconnect(&self, session_id: Uuid) -> Option<Uuid> {
         tracing::debug!("WebSocket session disconnected: {}", session_id);
         self.sessions.remove(&session_id);
+        self.close_signals.remove(&session_id);
+
+        if let Some((_, auth_session_id)) = self.session_auth.remove(&session_id) {
+            self.auth_sessions
+                .remove_if(&auth_session_id, |_, sessions| {
+                    sessions.remove(&session_id);
+                    sessions.is_empty()
+                });
+        }
 
         let mut user_fully_disconnected = None;
 
@@ -162,6 +208,62 @@ impl WebSocketServer {
         sessions.insert(session_id);
     }
 
+    /// Tín hiệu để handler đóng kết nối khi phiên đăng nhập bị thu hồi
+    pub fn close_signal(&self, session_id: Uuid) -> Arc<Notify> {
+        self.close_signals.entry(session_id).or_default().clone()
+    }
+
+    /// Gắn session với phiên đăng nhập đã cấp access token. Trả về false nếu phiên đã bị thu hồi.
+    pub fn bind_auth_session(&self, session_id: Uuid, auth_session_id: Uuid) -> bool {
+        if self.is_auth_session_revoked(&auth_session_id) {
+            return false;
+        }
+
+        self.auth_sessions
+            .entry(auth_session_id)
+            .or_default()
+            .insert(session_id);
+        self.session_auth.insert(session_id, auth_session_id);
+        true
+    }
+
+    /// Thu hồi các phiên đăng nhập: đóng mọi kết nối WebSocket đang dùng chúng
+    ///
+    /// Trả về số kết nối bị đóng.
+    pub fn revoke_auth_sessions(&self, auth_session_ids: &[Uuid]) -> usize {
+        let retention = Duration::from_secs(ENV.access_token_expiration);
+        self.revoked_auth_sessions
+            .retain(|_, revoked_at| revoked_at.elapsed() <= retention);
+
+        let mut closed = 0;
+        for auth_session_id in auth_session_ids {
+            self.revoked_auth_sessions
+                .insert(*auth_session_id, Instant::now());
+
+            let Some((_, sessions)) = self.auth_sessions.remove(auth_session_id) else {
+                continue;
+            };
+
+            for session_id in sessions.iter() {
+                self.session_auth.remove(&*session_id);
+                if let Some(signal) = self.close_signals.get(&*session_id) {
+                    signal.notify_one();
+                    closed += 1;
+                }
+            }
+        }
+
+        closed
+    }
+
+    fn is_auth_session_revoked(&self, auth_session_id: &Uuid) -> bool {
+        self.revoked_auth_sessions
+            .get(auth_session_id)
+            .is_some_and(|revoked_at| {
+                revoked_at.elapsed() <= Duration::from_secs(ENV.access_token_expiration)
+            })
+    }
+
     /// Join conversation room
     pub fn join_room(&self, user_id: Uuid, conversation_id: Uuid) {
         self.rooms
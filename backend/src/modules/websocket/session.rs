@@ -11,6 +11,7 @@ use crate::ENV;
 use crate::modules::conversation::repository_pg::{
     ConversationPgRepository, LastMessagePgRepository, ParticipantPgRepository,
 };
+use crate::modules::draft::handle::DraftSvc;
 use crate::modules::friend::repository_pg::FriendRepositoryPg;
 use crate::modules::message::repository_pg::MessageRepositoryPg;
 use crate::modules::message::service::MessageService;
@@ -36,6 +37,7 @@ pub struct WebSocketSessionImpl {
     pub message_service: Option<Arc<MessageSvc>>,
     pub presence_service: Option<Arc<PresenceService>>,
     pub friend_repo: Option<Arc<FriendRepositoryPg>>,
+    pub draft_service: Option<Arc<DraftSvc>>,
     pub friend_ids: Vec<Uuid>,
 }
 
@@ -47,6 +49,7 @@ impl WebSocketSessionImpl {
         message_service: Option<Arc<MessageSvc>>,
         presence_service: Option<Arc<PresenceService>>,
         friend_repo: Option<Arc<FriendRepositoryPg>>,
+        draft_service: Option<Arc<DraftSvc>>,
     ) -> Self {
         Self {
             id: Uuid::now_v7(),
@@ -57,6 +60,7 @@ impl WebSocketSessionImpl {
             message_service,
             presence_service,
             friend_repo,
+            draft_service,
             friend_ids: Vec::new(),
         }
     }
@@ -113,6 +117,21 @@ impl WebSocketSessionImpl {
             ClientMessage::TypingStop { conversation_id } => {
                 self.handle_typing_stop(conversation_id);
             }
+            ClientMessage::UpdateDraft {
+                conversation_id,
+                content,
+                reply_to_id,
+            } => {
+                self.handle_update_draft(conversation_id, content, reply_to_id)
+                    .await;
+            }
+            ClientMessage::MessageReceived {
+                conversation_id,
+                message_id,
+            } => {
+                self.handle_message_received(conversation_id, message_id)
+                    .await;
+            }
             ClientMessage::Ping => {
                 // Heartbeat được quản lý riêng bằng text/ping qua ws frame.
                 // Hàm này chỉ để tương thích với protocol gửi theo text.
@@ -154,6 +173,15 @@ impl WebSocketSessionImpl {
             return;
         }
 
+        if let Some(auth_session_id) = claims.sid
+            && !self.server.bind_auth_session(self.id, auth_session_id)
+        {
+            self.send_to_client(&ServerMessage::AuthFailed {
+                reason: "Phiên đăng nhập đã bị thu hồi".to_string(),
+            });
+            return;
+        }
+
         let user_id = claims.sub;
         self.user_id = Some(user_id);
         self.server.authenticate(self.id, user_id);
@@ -230,6 +258,63 @@ impl WebSocketSessionImpl {
         }
     }
 
+    /// Xử lý delivery receipt từ client, báo lại cho người gửi
+    async fn handle_message_received(&self, conversation_id: Uuid, message_id: Uuid) {
+        let Some(user_id) = self.require_auth() else {
+            return;
+        };
+
+        let Some(service) = &self.message_service else {
+            return;
+        };
+
+        if let Err(e) = service
+            .acknowledge_delivery(user_id, conversation_id, message_id)
+            .await
+        {
+            tracing::warn!(
+                correlation_id = %self.correlation_id,
+                session_id = %self.id,
+                user_id = %user_id,
+                message_id = %message_id,
+                error = %e,
+                "Bỏ qua delivery receipt không hợp lệ"
+            );
+        }
+    }
+
+    /// Lưu bản nháp và đồng bộ sang các session khác của user
+    async fn handle_update_draft(
+        &self,
+        conversation_id: Uuid,
+        content: String,
+        reply_to_id: Option<Uuid>,
+    ) {
+        let Some(user_id) = self.require_auth() else {
+            return;
+        };
+
+        let Some(service) = &self.draft_service else {
+            self.send_error("Draft service không khả dụng");
+            return;
+        };
+
+        if let Err(e) = service
+            .save_draft(user_id, conversation_id, content, reply_to_id, Some(self.id))
+            .await
+        {
+            tracing::warn!(
+                correlation_id = %self.correlation_id,
+                session_id = %self.id,
+                user_id = %user_id,
+                conversation_id = %conversation_id,
+                error = %e,
+                "Lỗi lưu bản nháp"
+            );
+            self.send_error("Không thể lưu bản nháp. Vui lòng thử lại.");
+        }
+    }
+
     fn handle_join_conversation(&self, conversation_id: Uuid) {
         if let Some(user_id) = self.require_auth() {
             self.server.join_room(user_id, conversation_id);
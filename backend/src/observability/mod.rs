@@ -23,6 +23,7 @@ pub struct AppMetrics {
     ws_close_protocol_error_total: AtomicU64,
     ws_close_ping_failure_total: AtomicU64,
     ws_close_stream_end_total: AtomicU64,
+    ws_close_session_revoked_total: AtomicU64,
     message_send_total: AtomicU64,
     message_send_total_ms: AtomicU64,
     message_send_max_ms: AtomicU64,
@@ -47,6 +48,7 @@ impl Default for AppMetrics {
             ws_close_protocol_error_total: AtomicU64::new(0),
             ws_close_ping_failure_total: AtomicU64::new(0),
             ws_close_stream_end_total: AtomicU64::new(0),
+            ws_close_session_revoked_total: AtomicU64::new(0),
             message_send_total: AtomicU64::new(0),
             message_send_total_ms: AtomicU64::new(0),
             message_send_max_ms: AtomicU64::new(0),
@@ -69,6 +71,8 @@ pub enum WsCloseReason {
     ProtocolError,
     PingFailure,
     StreamEnded,
+    /// Phiên đăng nhập bị thu hồi (đăng xuất từ thiết bị khác)
+    SessionRevoked,
 }
 
 #[derive(Debug, Serialize)]
@@ -81,6 +85,7 @@ pub struct MetricsSnapshot {
     pub ws_close_protocol_error_total: u64,
     pub ws_close_ping_failure_total: u64,
     pub ws_close_stream_end_total: u64,
+    pub ws_close_session_revoked_total: u64,
     pub message_send_total: u64,
     pub message_send_avg_ms: f64,
     pub message_send_p50_ms: f64,
@@ -123,6 +128,10 @@ impl AppMetrics {
             WsCloseReason::StreamEnded => {
                 self.ws_close_stream_end_total.fetch_add(1, Ordering::Relaxed);
             }
+            WsCloseReason::SessionRevoked => {
+                self.ws_close_session_revoked_total
+                    .fetch_add(1, Ordering::Relaxed);
+            }
         }
     }
 
@@ -203,6 +212,9 @@ impl AppMetrics {
             ws_close_protocol_error_total: self.ws_close_protocol_error_total.load(Ordering::Relaxed),
             ws_close_ping_failure_total: self.ws_close_ping_failure_total.load(Ordering::Relaxed),
             ws_close_stream_end_total: self.ws_close_stream_end_total.load(Ordering::Relaxed),
+            ws_close_session_revoked_total: self
+                .ws_close_session_revoked_total
+                .load(Ordering::Relaxed),
             message_send_total,
             message_send_avg_ms,
             message_send_p50_ms,
@@ -270,6 +282,7 @@ app_ws_close_total{{reason=\"timeout\"}} {}\n\
 app_ws_close_total{{reason=\"protocol_error\"}} {}\n\
 app_ws_close_total{{reason=\"ping_failure\"}} {}\n\
 app_ws_close_total{{reason=\"stream_end\"}} {}\n\
+app_ws_close_total{{reason=\"session_revoked\"}} {}\n\
 # HELP app_message_send_total Total sent messages\n\
 # TYPE app_message_send_total counter\n\
 app_message_send_total {}\n\
@@ -297,6 +310,7 @@ app_upload_failure_total {}\n",
             snapshot.ws_close_protocol_error_total,
             snapshot.ws_close_ping_failure_total,
             snapshot.ws_close_stream_end_total,
+            snapshot.ws_close_session_revoked_total,
             snapshot.message_send_total,
             b10,
             b25,
@@ -9,6 +9,7 @@ mod tests {
     use tokio::time::{Duration, timeout};
     use uuid::Uuid;
 
+    use crate::api::cursor::Cursor;
     use crate::api::error;
     use crate::modules::call::model::{CallWithDetails, InitiateCallRequest, RespondCallRequest};
     use crate::modules::call::repository::{CallParticipantRepository, CallRepository};
@@ -151,7 +152,7 @@ mod tests {
             &self,
             _user_id: Uuid,
             limit: i64,
-            _cursor: Option<chrono::DateTime<Utc>>,
+            _cursor: Option<&Cursor>,
         ) -> Result<Vec<CallWithDetails>, error::SystemError> {
             let mut state = self.state.lock().expect("call state mutex poisoned");
             state.last_history_limit = Some(limit);
@@ -13,7 +13,9 @@ mod tests {
 
     use crate::configs::connect_database;
     use crate::middlewares::{authentication, authorization};
+    use crate::api::error;
     use crate::modules::conversation::handle::ConversationSvc;
+    use crate::modules::conversation::model::{ConversationListQuery, MessageHistoryRequest};
     use crate::modules::conversation::repository_pg::{
         ConversationPgRepository, ParticipantPgRepository,
     };
@@ -215,4 +217,231 @@ mod tests {
 
         cleanup_users(&pool, &[owner_id]).await;
     }
+
+    /// Tạo nhóm có `updated_at`, tin nhắn cuối và thời điểm ghim lùi về quá khứ theo số phút
+    async fn seed_listed_group(
+        pool: &sqlx::PgPool,
+        user_id: Uuid,
+        updated_minutes_ago: i32,
+        last_message_minutes_ago: Option<i32>,
+        pinned_minutes_ago: Option<i32>,
+    ) -> Uuid {
+        let conversation_id = Uuid::now_v7();
+        sqlx::query(
+            "INSERT INTO conversations (id, type, updated_at) VALUES ($1, 'group', NOW() - make_interval(mins => $2))",
+        )
+        .bind(conversation_id)
+        .bind(updated_minutes_ago)
+        .execute(pool)
+        .await
+        .expect("seed conversation should succeed");
+        sqlx::query("INSERT INTO group_conversations (conversation_id, name, created_by) VALUES ($1, 'List', $2)")
+            .bind(conversation_id)
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .expect("seed group should succeed");
+        sqlx::query(
+            r#"
+            INSERT INTO participants (conversation_id, user_id, unread_count, pinned_at)
+            VALUES ($1, $2, 0, NOW() - make_interval(mins => $3))
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .bind(pinned_minutes_ago)
+        .execute(pool)
+        .await
+        .expect("seed participant should succeed");
+        if let Some(minutes) = last_message_minutes_ago {
+            sqlx::query(
+                "INSERT INTO messages (conversation_id, sender_id, content, created_at) VALUES ($1, $2, 'hi', NOW() - make_interval(mins => $3))",
+            )
+            .bind(conversation_id)
+            .bind(user_id)
+            .bind(minutes)
+            .execute(pool)
+            .await
+            .expect("seed message should succeed");
+        }
+        conversation_id
+    }
+
+    fn list_query(limit: i32, cursor: Option<String>) -> ConversationListQuery {
+        ConversationListQuery {
+            limit,
+            cursor,
+            archived: false,
+            _type: None,
+            unread_only: false,
+            q: None,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_conversation_list_pages_by_last_activity() {
+        let pool = connect_database()
+            .await
+            .expect("database must be available for integration test");
+        let user_id = Uuid::now_v7();
+        seed_user(&pool, user_id, &format!("list{}", user_id.simple()), &format!("list{}@appchat.local", user_id.simple()))
+            .await
+            .expect("seed user should succeed");
+
+        // Nhóm đổi tên gần đây (updated_at mới) nhưng tin nhắn cuối đã cũ thì vẫn nằm dưới
+        let renamed = seed_listed_group(&pool, user_id, 0, Some(60), None).await;
+        let recent = seed_listed_group(&pool, user_id, 120, Some(10), None).await;
+        let pinned = seed_listed_group(&pool, user_id, 300, Some(180), Some(300)).await;
+        let empty = seed_listed_group(&pool, user_id, 30, None, None).await;
+        let older = seed_listed_group(&pool, user_id, 200, Some(20), None).await;
+
+        let service = build_conversation_service(pool.clone());
+        let mut pages = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (conversations, next) = service
+                .get_by_user_id(user_id, list_query(2, cursor))
+                .await
+                .expect("list should succeed");
+            pages.push(conversations.iter().map(|c| c.conversation_id).collect::<Vec<_>>());
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(
+            pages,
+            vec![vec![pinned, recent], vec![older, empty], vec![renamed]]
+        );
+
+        // Trang vừa khít limit không trả cursor thừa
+        let (conversations, next) = service
+            .get_by_user_id(user_id, list_query(5, None))
+            .await
+            .expect("list should succeed");
+        assert_eq!(conversations.len(), 5);
+        assert!(next.is_none());
+
+        let err = service
+            .get_by_user_id(user_id, list_query(2, Some("not-a-cursor".to_string())))
+            .await
+            .expect_err("tampered cursor should be rejected");
+        assert!(matches!(err, error::SystemError::BadRequest(_)));
+
+        let _ = sqlx::query("DELETE FROM conversations WHERE id = ANY($1)")
+            .bind(vec![renamed, recent, pinned, empty, older])
+            .execute(&pool)
+            .await;
+        cleanup_users(&pool, &[user_id]).await;
+    }
+
+    fn history(limit: i32, before: Option<String>, after: Option<String>, around: Option<Uuid>) -> MessageHistoryRequest {
+        MessageHistoryRequest {
+            limit,
+            before,
+            after,
+            around,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_message_history_boundaries_with_identical_timestamps() {
+        let pool = connect_database()
+            .await
+            .expect("database must be available for integration test");
+        let user_id = Uuid::now_v7();
+        seed_user(&pool, user_id, &format!("page{}", user_id.simple()), &format!("page{}@appchat.local", user_id.simple()))
+            .await
+            .expect("seed user should succeed");
+        let conversation_id = seed_listed_group(&pool, user_id, 0, None, None).await;
+        let other_conversation_id = seed_listed_group(&pool, user_id, 0, Some(1), None).await;
+
+        // Cùng created_at: chỉ id phân định thứ tự, không được bỏ sót hay lặp tin nào
+        let created_at = chrono::Utc::now();
+        let mut ids = Vec::new();
+        for i in 0..7 {
+            let id = Uuid::now_v7();
+            sqlx::query("INSERT INTO messages (id, conversation_id, sender_id, content, created_at) VALUES ($1, $2, $3, $4, $5)")
+                .bind(id)
+                .bind(conversation_id)
+                .bind(user_id)
+                .bind(format!("m{i}"))
+                .bind(created_at)
+                .execute(&pool)
+                .await
+                .expect("seed message should succeed");
+            ids.push(id);
+        }
+        let service = build_conversation_service(pool.clone());
+        let page_ids = |page: &crate::modules::message::model::GetMessageResponse| {
+            page.messages.iter().map(|m| m.message.id).collect::<Vec<_>>()
+        };
+
+        // Trang mới nhất: chỉ có cursor cũ hơn
+        let page = service.get_message(conversation_id, history(3, None, None, None)).await.unwrap();
+        assert_eq!(page_ids(&page), ids[4..].to_vec());
+        assert!(page.newer_cursor.is_none());
+
+        // Lùi hết về đầu
+        let mut seen = page_ids(&page);
+        let mut before = page.cursor;
+        while let Some(cursor) = before {
+            let page = service.get_message(conversation_id, history(3, Some(cursor), None, None)).await.unwrap();
+            assert!(page.newer_cursor.is_some());
+            seen.splice(0..0, page_ids(&page));
+            before = page.cursor;
+        }
+        assert_eq!(seen, ids);
+
+        // Tiến từ tin cũ nhất tới cuối
+        let oldest = service.get_message(conversation_id, history(1, None, None, Some(ids[0]))).await.unwrap();
+        assert_eq!(page_ids(&oldest), vec![ids[0]]);
+        assert!(oldest.cursor.is_none());
+        let mut seen = page_ids(&oldest);
+        let mut after = oldest.newer_cursor;
+        while let Some(cursor) = after {
+            let page = service.get_message(conversation_id, history(3, None, Some(cursor), None)).await.unwrap();
+            seen.extend(page_ids(&page));
+            after = page.newer_cursor;
+        }
+        assert_eq!(seen, ids);
+
+        // Trang vừa khít limit không trả cursor thừa
+        let page = service.get_message(conversation_id, history(7, None, None, None)).await.unwrap();
+        assert_eq!(page_ids(&page), ids);
+        assert!(page.cursor.is_none());
+
+        // Cửa sổ quanh tin ở giữa và ở mép
+        let page = service.get_message(conversation_id, history(3, None, None, Some(ids[3]))).await.unwrap();
+        assert_eq!(page_ids(&page), ids[2..5].to_vec());
+        assert!(page.cursor.is_some() && page.newer_cursor.is_some());
+        let page = service.get_message(conversation_id, history(5, None, None, Some(ids[6]))).await.unwrap();
+        assert_eq!(page_ids(&page), ids[4..].to_vec());
+        assert!(page.cursor.is_some());
+        assert!(page.newer_cursor.is_none());
+
+        let err = service
+            .get_message(conversation_id, history(3, Some("x".to_string()), None, Some(ids[0])))
+            .await
+            .expect_err("mixing modes should fail");
+        assert!(matches!(err, error::SystemError::BadRequest(_)));
+        let foreign_id: Uuid = sqlx::query_scalar("SELECT id FROM messages WHERE conversation_id = $1")
+            .bind(other_conversation_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let err = service
+            .get_message(conversation_id, history(3, None, None, Some(foreign_id)))
+            .await
+            .expect_err("anchor from another conversation should not be found");
+        assert!(matches!(err, error::SystemError::NotFound(_)));
+
+        let _ = sqlx::query("DELETE FROM conversations WHERE id = ANY($1)")
+            .bind(vec![conversation_id, other_conversation_id])
+            .execute(&pool)
+            .await;
+        cleanup_users(&pool, &[user_id]).await;
+    }
 }
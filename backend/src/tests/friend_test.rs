@@ -6,13 +6,19 @@ mod tests {
     use chrono::Utc;
     use uuid::Uuid;
 
+    use crate::api::cursor::Cursor;
     use crate::api::error;
     use crate::modules::friend::repository::{FriendRepo, FriendRepository, FriendRequestRepository};
     use crate::modules::friend::schema::{FriendEntity, FriendRequestEntity};
     use crate::modules::friend::service::FriendService;
-    use crate::modules::user::model::{InsertUser, UpdateUser};
+    use crate::modules::user::model::{
+        DeviceInfo, InsertUser, NewSecurityEvent, NewUserSession, NewUserToken, UpdateUser,
+    };
     use crate::modules::user::repository::UserRepository;
-    use crate::modules::user::schema::{UserEntity, UserRole};
+    use crate::modules::user::schema::{
+        RecoveryCodeEntity, UserEntity, UserRole, UserSessionEntity, UserTokenEntity,
+        UserTokenPurpose,
+    };
     use crate::tests::mock::database::MockDatabase;
 
     fn build_user(id: Uuid, username: &str) -> UserEntity {
@@ -29,6 +35,10 @@ mod tests {
             deleted_at: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            email_verified_at: None,
+            totp_secret: None,
+            totp_enabled_at: None,
+            totp_last_step: None,
         }
     }
 
@@ -66,10 +76,151 @@ mod tests {
         async fn search_users(
             &self,
             _query: &str,
+            _cursor: Option<&Cursor>,
             _limit: i32,
         ) -> Result<Vec<UserEntity>, error::SystemError> {
             Ok(vec![])
         }
+
+        async fn find_by_email(&self, _email: &str) -> Result<Option<UserEntity>, error::SystemError> {
+            Ok(None)
+        }
+
+        async fn update_password(
+            &self,
+            _user_id: &Uuid,
+            _hash_password: &str,
+        ) -> Result<bool, error::SystemError> {
+            Ok(false)
+        }
+
+        async fn update_email(&self, _user_id: &Uuid, _email: &str) -> Result<bool, error::SystemError> {
+            Ok(false)
+        }
+
+        async fn mark_email_verified(
+            &self,
+            _user_id: &Uuid,
+            _email: &str,
+        ) -> Result<bool, error::SystemError> {
+            Ok(false)
+        }
+
+        async fn create_user_token(&self, _token: &NewUserToken) -> Result<(), error::SystemError> {
+            Ok(())
+        }
+
+        async fn consume_user_token(
+            &self,
+            _token_hash: &str,
+            _purpose: UserTokenPurpose,
+        ) -> Result<Option<UserTokenEntity>, error::SystemError> {
+            Ok(None)
+        }
+
+        async fn set_pending_totp_secret(
+            &self,
+            _user_id: &Uuid,
+            _secret: &str,
+        ) -> Result<bool, error::SystemError> {
+            Ok(false)
+        }
+
+        async fn enable_totp(
+            &self,
+            _user_id: &Uuid,
+            _recovery_code_hashes: &[String],
+        ) -> Result<bool, error::SystemError> {
+            Ok(false)
+        }
+
+        async fn disable_totp(&self, _user_id: &Uuid) -> Result<bool, error::SystemError> {
+            Ok(false)
+        }
+
+        async fn record_totp_step(
+            &self,
+            _user_id: &Uuid,
+            _step: i64,
+        ) -> Result<bool, error::SystemError> {
+            Ok(false)
+        }
+
+        async fn find_unused_recovery_codes(
+            &self,
+            _user_id: &Uuid,
+        ) -> Result<Vec<RecoveryCodeEntity>, error::SystemError> {
+            Ok(vec![])
+        }
+
+        async fn use_recovery_code(&self, _code_id: &Uuid) -> Result<bool, error::SystemError> {
+            Ok(false)
+        }
+
+        async fn create_session(&self, _session: &NewUserSession) -> Result<(), error::SystemError> {
+            Ok(())
+        }
+
+        async fn rotate_session(
+            &self,
+            _session_id: &Uuid,
+            _user_id: &Uuid,
+            _old_jti: &Uuid,
+            _new_jti: &Uuid,
+            _device: &DeviceInfo,
+            _expires_at: chrono::DateTime<Utc>,
+        ) -> Result<bool, error::SystemError> {
+            Ok(false)
+        }
+
+        async fn find_active_sessions(
+            &self,
+            _user_id: &Uuid,
+        ) -> Result<Vec<UserSessionEntity>, error::SystemError> {
+            Ok(vec![])
+        }
+
+        async fn revoke_session(
+            &self,
+            _user_id: &Uuid,
+            _session_id: &Uuid,
+        ) -> Result<Option<UserSessionEntity>, error::SystemError> {
+            Ok(None)
+        }
+
+        async fn find_recently_rotated_session(
+            &self,
+            _session_id: &Uuid,
+            _user_id: &Uuid,
+            _presented_jti: &Uuid,
+            _rotated_after: chrono::DateTime<Utc>,
+        ) -> Result<Option<UserSessionEntity>, error::SystemError> {
+            Ok(None)
+        }
+
+        async fn revoke_reused_session(
+            &self,
+            _session_id: &Uuid,
+            _user_id: &Uuid,
+            _presented_jti: &Uuid,
+        ) -> Result<Option<UserSessionEntity>, error::SystemError> {
+            Ok(None)
+        }
+
+        async fn create_security_event(
+            &self,
+            _event: &NewSecurityEvent,
+        ) -> Result<(), error::SystemError> {
+            Ok(())
+        }
+
+        async fn revoke_sessions(
+            &self,
+            _user_id: &Uuid,
+            _except: Option<&Uuid>,
+        ) -> Result<Vec<UserSessionEntity>, error::SystemError> {
+            Ok(vec![])
+        }
     }
 
     #[derive(Clone)]
@@ -118,8 +269,10 @@ mod tests {
         async fn find_friends<'e, E>(
             &self,
             _user_id: &Uuid,
+            _cursor: Option<&Cursor>,
+            _limit: i32,
             _tx: E,
-        ) -> Result<Vec<crate::modules::friend::model::FriendResponse>, error::SystemError>
+        ) -> Result<Vec<crate::modules::friend::model::FriendListItem>, error::SystemError>
         where
             E: sqlx::Executor<'e, Database = sqlx::Postgres>,
         {
@@ -192,6 +345,8 @@ mod tests {
         async fn find_friend_request_from_user<'e, E>(
             &self,
             _user_id: &Uuid,
+            _cursor: Option<&Cursor>,
+            _limit: i32,
             _tx: E,
         ) -> Result<Vec<crate::modules::friend::model::FriendRequestResponse>, error::SystemError>
         where
@@ -203,6 +358,8 @@ mod tests {
         async fn find_friend_request_to_user<'e, E>(
             &self,
             _user_id: &Uuid,
+            _cursor: Option<&Cursor>,
+            _limit: i32,
             _tx: E,
         ) -> Result<Vec<crate::modules::friend::model::FriendRequestResponse>, error::SystemError>
         where
@@ -18,6 +18,11 @@ mod tests {
     use crate::modules::friend::repository_pg::FriendRepositoryPg;
     use crate::modules::friend::service::FriendService;
     use crate::modules::user::repository_pg::UserRepositoryPg;
+    use crate::api::error;
+    use crate::configs::RedisCache;
+    use crate::modules::conversation::repository_pg::LastMessagePgRepository;
+    use crate::modules::message::handle::MessageSvc;
+    use crate::modules::message::service::MessageService;
 
     async fn seed_user(pool: &sqlx::PgPool, id: Uuid, username: &str) {
         sqlx::query("INSERT INTO users (id, username, hash_password, email, role, display_name) VALUES ($1, $2, 'hash', $3, 'USER', $2)")
@@ -75,7 +80,7 @@ mod tests {
         // Seed group
         sqlx::query("INSERT INTO conversations (id, type) VALUES ($1, 'group')").bind(group_id).execute(&pool).await.unwrap();
         sqlx::query("INSERT INTO group_conversations (conversation_id, name, created_by) VALUES ($1, 'Old Name', $2)").bind(group_id).bind(creator_id).execute(&pool).await.unwrap();
-        sqlx::query("INSERT INTO participants (conversation_id, user_id, unread_count) VALUES ($1, $2, 0)").bind(group_id).bind(creator_id).execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO participants (conversation_id, user_id, unread_count, role) VALUES ($1, $2, 0, 'owner')").bind(group_id).bind(creator_id).execute(&pool).await.unwrap();
 
         let (conv_svc, friend_svc) = build_services(pool.clone());
         let app = test::init_service(
@@ -148,4 +153,222 @@ mod tests {
         let _ = sqlx::query("DELETE FROM friends WHERE user_id1 = $1").bind(creator_id).execute(&pool).await;
         let _ = sqlx::query("DELETE FROM users WHERE id IN ($1, $2, $3)").bind(creator_id).bind(member_id).bind(outsider_id).execute(&pool).await;
     }
+
+    /// Tạo nhóm với vai trò cho từng user (`left` = đã rời nhóm)
+    async fn seed_group(pool: &sqlx::PgPool, members: &[(Uuid, &str)]) -> Uuid {
+        let group_id = Uuid::now_v7();
+        sqlx::query("INSERT INTO conversations (id, type) VALUES ($1, 'group')").bind(group_id).execute(pool).await.unwrap();
+        sqlx::query("INSERT INTO group_conversations (conversation_id, name, created_by) VALUES ($1, 'Group', $2)").bind(group_id).bind(members[0].0).execute(pool).await.unwrap();
+        for (user_id, role) in members {
+            let (role, left) = if *role == "left" { ("member", true) } else { (*role, false) };
+            sqlx::query(
+                "INSERT INTO participants (conversation_id, user_id, unread_count, role, deleted_at) VALUES ($1, $2, 0, $3::participant_role, CASE WHEN $4 THEN NOW() END)",
+            )
+            .bind(group_id)
+            .bind(user_id)
+            .bind(role)
+            .bind(left)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+        group_id
+    }
+
+    async fn seed_users(pool: &sqlx::PgPool, count: usize) -> Vec<Uuid> {
+        let mut ids = Vec::with_capacity(count);
+        for _ in 0..count {
+            let id = Uuid::now_v7();
+            seed_user(pool, id, &format!("g{}", id.simple())).await;
+            ids.push(id);
+        }
+        ids
+    }
+
+    async fn active_role(pool: &sqlx::PgPool, group_id: Uuid, user_id: Uuid) -> Option<String> {
+        sqlx::query_scalar("SELECT role::text FROM participants WHERE conversation_id = $1 AND user_id = $2 AND deleted_at IS NULL")
+            .bind(group_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn cleanup_group(pool: &sqlx::PgPool, group_id: Uuid, user_ids: &[Uuid]) {
+        let _ = sqlx::query("DELETE FROM conversations WHERE id = $1").bind(group_id).execute(pool).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = ANY($1)").bind(user_ids).execute(pool).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres"]
+    async fn test_group_roles_gate_admin_actions() {
+        let pool = connect_database().await.unwrap();
+        let users = seed_users(&pool, 5).await;
+        let (owner, admin, member, former, newcomer) = (users[0], users[1], users[2], users[3], users[4]);
+        let group_id = seed_group(&pool, &[(owner, "owner"), (admin, "admin"), (member, "member"), (former, "left")]).await;
+        let (conv_svc, _) = build_services(pool.clone());
+
+        // Member thường không có quyền quản trị
+        let err = conv_svc.add_member(group_id, member, newcomer, true).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+        let err = conv_svc.change_member_role(group_id, member, admin, crate::modules::conversation::schema::ParticipantRole::Member).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+
+        // Admin không phân quyền được và không hạ được owner qua add_member
+        let err = conv_svc.change_member_role(group_id, admin, member, crate::modules::conversation::schema::ParticipantRole::Admin).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+        let err = conv_svc.add_member(group_id, admin, owner, true).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::BadRequest(_)));
+        assert_eq!(active_role(&pool, group_id, owner).await.as_deref(), Some("owner"));
+
+        // Người đã rời nhóm được thêm lại với vai trò member
+        conv_svc.add_member(group_id, admin, former, true).await.unwrap();
+        assert_eq!(active_role(&pool, group_id, former).await.as_deref(), Some("member"));
+
+        // Admin chỉ xóa được người có vai trò thấp hơn
+        let err = conv_svc.remove_member(group_id, admin, owner).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+        conv_svc.remove_member(group_id, admin, member).await.unwrap();
+        assert_eq!(active_role(&pool, group_id, member).await, None);
+
+        // Owner hạ admin; admin cũ mất quyền ngay
+        conv_svc.change_member_role(group_id, owner, admin, crate::modules::conversation::schema::ParticipantRole::Member).await.unwrap();
+        let err = conv_svc.add_member(group_id, admin, newcomer, true).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+
+        cleanup_group(&pool, group_id, &users).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres"]
+    async fn test_concurrent_ownership_transfers_leave_single_owner() {
+        let pool = connect_database().await.unwrap();
+        let (conv_svc, _) = build_services(pool.clone());
+
+        // Lặp nhiều nhóm để hai lượt chuyển quyền thực sự chồng lên nhau
+        for _ in 0..10 {
+            let users = seed_users(&pool, 3).await;
+            let group_id = seed_group(&pool, &[(users[0], "owner"), (users[1], "member"), (users[2], "member")]).await;
+
+            let (first, second) = tokio::join!(
+                tokio::spawn({
+                    let conv_svc = conv_svc.clone();
+                    let (owner, target) = (users[0], users[1]);
+                    async move { conv_svc.transfer_ownership(group_id, owner, target).await }
+                }),
+                tokio::spawn({
+                    let conv_svc = conv_svc.clone();
+                    let (owner, target) = (users[0], users[2]);
+                    async move { conv_svc.transfer_ownership(group_id, owner, target).await }
+                }),
+            );
+            assert_eq!([first.unwrap().is_ok(), second.unwrap().is_ok()].iter().filter(|ok| **ok).count(), 1);
+
+            let owners: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM participants WHERE conversation_id = $1 AND role = 'owner' AND deleted_at IS NULL")
+                .bind(group_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(owners, 1);
+            assert_eq!(active_role(&pool, group_id, users[0]).await.as_deref(), Some("admin"));
+
+            cleanup_group(&pool, group_id, &users).await;
+        }
+    }
+
+    async fn build_message_service(pool: sqlx::PgPool) -> MessageSvc {
+        let participant_repo = ParticipantPgRepository::default();
+        MessageService::with_dependencies(
+            Arc::new(ConversationPgRepository::new(pool.clone(), participant_repo.clone())),
+            Arc::new(MessageRepositoryPg::new(pool)),
+            Arc::new(participant_repo),
+            Arc::new(LastMessagePgRepository::default()),
+            Arc::new(RedisCache::new().await.unwrap()),
+            Arc::new(WebSocketServer::new()),
+        )
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres"]
+    async fn test_invite_link_respects_join_approval_and_announces_join() {
+        let pool = connect_database().await.unwrap();
+        let users = seed_users(&pool, 2).await;
+        let (owner, outsider) = (users[0], users[1]);
+        let group_id = seed_group(&pool, &[(owner, "owner")]).await;
+        let (conv_svc, _) = build_services(pool.clone());
+        let message_svc = build_message_service(pool.clone()).await;
+
+        let invite = conv_svc.create_invite_link(group_id, owner, None, Some(5)).await.unwrap();
+        conv_svc.update_join_approval(group_id, owner, true).await.unwrap();
+
+        // Nhóm cần duyệt: link không thêm thành viên và không tốn lượt dùng
+        let err = conv_svc.join_by_invite(&invite.token, outsider, &message_svc).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+        assert_eq!(active_role(&pool, group_id, outsider).await, None);
+        let use_count: i32 = sqlx::query_scalar("SELECT use_count FROM group_invite_links WHERE id = $1").bind(invite.id).fetch_one(&pool).await.unwrap();
+        assert_eq!(use_count, 0);
+
+        conv_svc.update_join_approval(group_id, owner, false).await.unwrap();
+        conv_svc.join_by_invite(&invite.token, outsider, &message_svc).await.unwrap();
+        assert_eq!(active_role(&pool, group_id, outsider).await.as_deref(), Some("member"));
+        let system_messages: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE conversation_id = $1 AND sender_id = $2 AND type = 'system'")
+            .bind(group_id)
+            .bind(outsider)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(system_messages, 1);
+
+        cleanup_group(&pool, group_id, &users).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres"]
+    async fn test_join_request_approval_flow() {
+        let pool = connect_database().await.unwrap();
+        let users = seed_users(&pool, 6).await;
+        let (owner, admin, member, applicant, added_meanwhile, rejected) = (users[0], users[1], users[2], users[3], users[4], users[5]);
+        let group_id = seed_group(&pool, &[(owner, "owner"), (admin, "admin"), (member, "member")]).await;
+        let (conv_svc, _) = build_services(pool.clone());
+
+        // Nhóm chưa bật phê duyệt không nhận yêu cầu
+        let err = conv_svc.submit_join_request(group_id, applicant, None).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+        conv_svc.update_join_approval(group_id, admin, true).await.unwrap();
+
+        let request = conv_svc.submit_join_request(group_id, applicant, Some("hi".to_string())).await.unwrap();
+        let err = conv_svc.submit_join_request(group_id, applicant, None).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::BadRequest(_)));
+        let err = conv_svc.submit_join_request(group_id, member, None).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::BadRequest(_)));
+
+        // Chỉ owner/admin được xem và duyệt
+        let err = conv_svc.get_join_requests(group_id, member).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+        let err = conv_svc.review_join_request(group_id, member, request.id, true).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+        let pending = conv_svc.get_join_requests(group_id, admin).await.unwrap();
+        assert_eq!(pending.iter().map(|r| r.request.user_id).collect::<Vec<_>>(), vec![applicant]);
+
+        conv_svc.review_join_request(group_id, admin, request.id, true).await.unwrap();
+        assert_eq!(active_role(&pool, group_id, applicant).await.as_deref(), Some("member"));
+        let err = conv_svc.review_join_request(group_id, admin, request.id, true).await.unwrap_err();
+        assert!(matches!(err, error::SystemError::NotFound(_)));
+
+        // Đã được thêm trực tiếp trong lúc chờ: duyệt chỉ đóng yêu cầu, không đụng tới participant
+        let request = conv_svc.submit_join_request(group_id, added_meanwhile, None).await.unwrap();
+        conv_svc.add_member(group_id, owner, added_meanwhile, true).await.unwrap();
+        conv_svc.change_member_role(group_id, owner, added_meanwhile, crate::modules::conversation::schema::ParticipantRole::Admin).await.unwrap();
+        conv_svc.review_join_request(group_id, admin, request.id, true).await.unwrap();
+        assert_eq!(active_role(&pool, group_id, added_meanwhile).await.as_deref(), Some("admin"));
+        assert!(conv_svc.get_join_requests(group_id, admin).await.unwrap().is_empty());
+
+        let request = conv_svc.submit_join_request(group_id, rejected, None).await.unwrap();
+        conv_svc.review_join_request(group_id, owner, request.id, false).await.unwrap();
+        assert_eq!(active_role(&pool, group_id, rejected).await, None);
+        let status: String = sqlx::query_scalar("SELECT status::text FROM group_join_requests WHERE id = $1").bind(request.id).fetch_one(&pool).await.unwrap();
+        assert_eq!(status, "rejected");
+
+        cleanup_group(&pool, group_id, &users).await;
+    }
 }
@@ -6,22 +6,42 @@ mod tests {
     use chrono::Utc;
     use uuid::Uuid;
 
+    use crate::api::cursor::Cursor;
     use crate::api::error;
-    use crate::configs::RedisCache;
+    use crate::configs::{RedisCache, connect_database};
     use crate::modules::conversation::model::{
-        ConversationDetail, ConversationRow, NewLastMessage, NewParticipant,
-        ParticipantDetailWithConversation,
+        ConversationDetail, ConversationPreferences, ConversationQuery,
+        ConversationRow, MessageSeenBy, NewLastMessage, NewParticipant,
+        JoinRequestDetail, MessageHistoryRequest, PINNED_RANK, ParticipantDetailWithConversation, PinnedMessage,
     };
     use crate::modules::conversation::repository::{
         ConversationRepository, LastMessageRepository, ParticipantRepository,
     };
     use crate::modules::conversation::schema::{
-        ConversationEntity, ConversationType, LastMessageEntity, ParticipantEntity,
+        ConversationEntity, ConversationType, GroupInviteLinkEntity, GroupJoinRequestEntity,
+        JoinRequestStatus, LastMessageEntity, ParticipantEntity, ParticipantRole,
     };
-    use crate::modules::message::model::{InsertMessage, MessageQuery};
+    use crate::modules::conversation::repository_pg::{
+        ConversationPgRepository, LastMessagePgRepository, ParticipantPgRepository,
+    };
+    use crate::modules::message::handle::MessageSvc;
+    use crate::modules::message::model::{
+        CreatePollRequest, InsertMessage, MessageQuery, MessageRevision, MessageSearchResult,
+        NewPoll, PollDetail, PollTallyRow, ReactionSummaryRow, SendDirectMessagePayload,
+        SendGroupMessagePayload, ThreadStats,
+    };
+    use crate::modules::message::repository_pg::MessageRepositoryPg;
     use crate::modules::message::repository::MessageRepository;
-    use crate::modules::message::schema::{MessageEntity, MessageType};
-    use crate::modules::message::service::{MessageRoute, MessageService};
+    use crate::modules::message::schema::{
+        ContactCard, MessageEntity, MessageLocation, MessageType, PollEntity,
+    };
+    use crate::modules::message::service::{MessageRoute, MessageService, normalize_message_input};
+    use crate::modules::conversation::handle::ConversationSvc;
+    use crate::modules::conversation::service::{ConversationService, MAX_PINNED_MESSAGES};
+    use crate::modules::scheduled_message::handle::ScheduledMessageSvc;
+    use crate::modules::scheduled_message::model::CreateScheduledMessageRequest;
+    use crate::modules::scheduled_message::repository_pg::ScheduledMessagePgRepository;
+    use crate::modules::scheduled_message::service::ScheduledMessageService;
     use crate::modules::websocket::server::WebSocketServer;
     use crate::tests::mock::database::MockDatabase;
 
@@ -35,8 +55,28 @@ mod tests {
     #[async_trait::async_trait]
     impl ConversationRepository for MockConversationRepo {
         async fn update_group_info<'e, E>(&self, _conversation_id: &Uuid, _name: Option<&str>, _avatar_url: Option<Option<&str>>, _tx: E) -> Result<(), error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(()) }
-        async fn get_group_creator<'e, E>(&self, _conversation_id: &Uuid, _tx: E) -> Result<Option<Uuid>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(None) }
-        async fn add_participant<'e, E>(&self, _conversation_id: &Uuid, _user_id: &Uuid, _tx: E) -> Result<(), error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(()) }
+        async fn update_message_ttl<'e, E>(&self, _conversation_id: &Uuid, _message_ttl_seconds: Option<i32>, _tx: E) -> Result<(), error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(()) }
+        async fn pin_message<'e, E>(&self, _conversation_id: &Uuid, _message_id: &Uuid, _pinned_by: &Uuid, _tx: E) -> Result<bool, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(true) }
+        async fn unpin_message<'e, E>(&self, _conversation_id: &Uuid, _message_id: &Uuid, _tx: E) -> Result<bool, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(true) }
+        async fn lock_conversation<'e, E>(&self, _conversation_id: &Uuid, _tx: E) -> Result<(), error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(()) }
+        async fn count_pinned_messages<'e, E>(&self, _conversation_id: &Uuid, _tx: E) -> Result<i64, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(0) }
+        async fn find_pinned_messages<'e, E>(&self, _conversation_id: &Uuid, _tx: E) -> Result<Vec<PinnedMessage>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(vec![]) }
+        async fn get_participant_role<'e, E>(&self, _conversation_id: &Uuid, _user_id: &Uuid, _tx: E) -> Result<Option<ParticipantRole>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(Some(ParticipantRole::Member)) }
+        async fn lock_participant_role<'e, E>(&self, _conversation_id: &Uuid, _user_id: &Uuid, _tx: E) -> Result<Option<ParticipantRole>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(Some(ParticipantRole::Member)) }
+        async fn create_invite_link<'e, E>(&self, _conversation_id: &Uuid, _created_by: &Uuid, _token: &str, _expires_at: Option<chrono::DateTime<Utc>>, _max_uses: Option<i32>, _tx: E) -> Result<GroupInviteLinkEntity, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Err(error::SystemError::internal_error("not used")) }
+        async fn find_invite_links<'e, E>(&self, _conversation_id: &Uuid, _tx: E) -> Result<Vec<GroupInviteLinkEntity>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(vec![]) }
+        async fn revoke_invite_link<'e, E>(&self, _conversation_id: &Uuid, _invite_id: &Uuid, _tx: E) -> Result<bool, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(true) }
+        async fn find_active_invite_link<'e, E>(&self, _token: &str, _tx: E) -> Result<Option<GroupInviteLinkEntity>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(None) }
+        async fn increment_invite_use<'e, E>(&self, _invite_id: &Uuid, _tx: E) -> Result<(), error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(()) }
+        async fn get_join_approval_required<'e, E>(&self, _conversation_id: &Uuid, _tx: E) -> Result<Option<bool>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(Some(false)) }
+        async fn update_join_approval_required<'e, E>(&self, _conversation_id: &Uuid, _join_approval_required: bool, _tx: E) -> Result<(), error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(()) }
+        async fn create_join_request<'e, E>(&self, _conversation_id: &Uuid, _user_id: &Uuid, _message: Option<&str>, _tx: E) -> Result<Option<GroupJoinRequestEntity>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(None) }
+        async fn find_pending_join_requests<'e, E>(&self, _conversation_id: &Uuid, _tx: E) -> Result<Vec<JoinRequestDetail>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(vec![]) }
+        async fn find_pending_join_request<'e, E>(&self, _conversation_id: &Uuid, _request_id: &Uuid, _tx: E) -> Result<Option<GroupJoinRequestEntity>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(None) }
+        async fn update_join_request_status<'e, E>(&self, _request_id: &Uuid, _status: JoinRequestStatus, _reviewed_by: &Uuid, _tx: E) -> Result<(), error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(()) }
+        async fn find_admin_ids<'e, E>(&self, _conversation_id: &Uuid, _tx: E) -> Result<Vec<Uuid>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(vec![]) }
+        async fn update_participant_role<'e, E>(&self, _conversation_id: &Uuid, _user_id: &Uuid, _role: ParticipantRole, _tx: E) -> Result<bool, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(true) }
+        async fn add_participant<'e, E>(&self, _conversation_id: &Uuid, _user_id: &Uuid, _tx: E) -> Result<bool, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(true) }
         async fn remove_participant<'e, E>(&self, _conversation_id: &Uuid, _user_id: &Uuid, _tx: E) -> Result<(), error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(()) }
         async fn get_group_member_ids<'e, E>(&self, _conversation_id: &Uuid, _tx: E) -> Result<Vec<Uuid>, error::SystemError> where E: sqlx::Executor<'e, Database = sqlx::Postgres> { Ok(vec![]) }
         fn get_pool(&self) -> &sqlx::Pool<sqlx::Postgres> {
@@ -54,6 +94,7 @@ mod tests {
             Ok(Some(ConversationEntity {
                 id: *conversation_id,
                 _type: self.conversation_type.clone(),
+                message_ttl_seconds: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             }))
@@ -110,7 +151,8 @@ mod tests {
 
         async fn find_all_conversation_with_details_by_user<'e, E>(
             &self,
-            _user_id: &Uuid,
+            _query: &ConversationQuery,
+            _limit: i32,
             _tx: E,
         ) -> Result<Vec<ConversationRow>, error::SystemError>
         where
@@ -132,6 +174,7 @@ mod tests {
                 Some(ConversationEntity {
                     id: *conversation_id,
                     _type: self.conversation_type.clone(),
+                    message_ttl_seconds: None,
                     created_at: Utc::now(),
                     updated_at: Utc::now(),
                 }),
@@ -247,6 +290,70 @@ mod tests {
             Ok(self.participants.clone())
         }
 
+        async fn find_seen_by<'e, E>(
+            &self,
+            _conversation_id: &Uuid,
+            _message_id: &Uuid,
+            _tx: E,
+        ) -> Result<Vec<MessageSeenBy>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
+
+        async fn find_mentioned_user_ids<'e, E>(
+            &self,
+            _conversation_id: &Uuid,
+            _sender_id: &Uuid,
+            _usernames: &[String],
+            _mention_all: bool,
+            _tx: E,
+        ) -> Result<Vec<Uuid>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
+
+        async fn increment_mention_count<'e, E>(
+            &self,
+            _conversation_id: &Uuid,
+            _user_ids: &[Uuid],
+            _tx: E,
+        ) -> Result<(), error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(())
+        }
+
+        async fn update_preferences<'e, E>(
+            &self,
+            _conversation_id: &Uuid,
+            _user_id: &Uuid,
+            _archived: Option<bool>,
+            _muted_until: Option<Option<chrono::DateTime<Utc>>>,
+            _pinned: Option<bool>,
+            _tx: E,
+        ) -> Result<Option<ConversationPreferences>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(Some(ConversationPreferences::default()))
+        }
+
+        async fn find_muted_user_ids<'e, E>(
+            &self,
+            _conversation_id: &Uuid,
+            _tx: E,
+        ) -> Result<Vec<Uuid>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
+
         async fn get_unread_counts<'e, E>(
             &self,
             _conversation_id: &Uuid,
@@ -279,6 +386,17 @@ mod tests {
                 created_at: last_message.created_at,
             })
         }
+
+        async fn refresh_last_messages<'e, E>(
+            &self,
+            _conversation_ids: &[Uuid],
+            _tx: E,
+        ) -> Result<(), error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(())
+        }
     }
 
     #[derive(Clone)]
@@ -320,6 +438,13 @@ mod tests {
                 content: message.content.clone(),
                 file_url: message.file_url.clone(),
                 is_edited: false,
+                forwarded_from_message_id: message.forwarded_from_message_id,
+                reply_count: 0,
+                last_reply_at: None,
+                expires_at: None,
+                location: message.location.clone().into(),
+                contact_user_id: message.contact_user_id,
+                contact: None,
                 deleted_at: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
@@ -373,113 +498,367 @@ mod tests {
         {
             Ok(None)
         }
-    }
 
-    async fn build_service(
-        conversation_type: ConversationType,
-        is_member: bool,
-    ) -> (
-        MessageService<
-            MockMessageRepo,
-            MockConversationRepo,
-            MockParticipantRepo,
-            MockLastMessageRepo,
-        >,
-        Arc<Mutex<u32>>,
-        Arc<Mutex<u32>>,
-        Uuid,
-        Uuid,
-    ) {
-        let pool = MockDatabase::new().pool();
-        let direct_unread_calls = Arc::new(Mutex::new(0));
-        let group_unread_calls = Arc::new(Mutex::new(0));
+        async fn add_reaction<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _user_id: &Uuid,
+            _emoji: &str,
+            _tx: E,
+        ) -> Result<bool, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(true)
+        }
 
-        let sender_id = Uuid::now_v7();
-        let recipient_id = Uuid::now_v7();
-        let conversation_id = Uuid::now_v7();
+        async fn remove_reaction<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _user_id: &Uuid,
+            _emoji: &str,
+            _tx: E,
+        ) -> Result<bool, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(false)
+        }
 
-        let participant_repo = MockParticipantRepo {
-            direct_unread_calls: direct_unread_calls.clone(),
-            group_unread_calls: group_unread_calls.clone(),
-            participants: vec![
-                ParticipantDetailWithConversation {
-                    user_id: sender_id,
-                    display_name: "Sender".to_string(),
-                    avatar_url: None,
-                    unread_count: 0,
-                    joined_at: Utc::now(),
-                    conversation_id,
-                },
-                ParticipantDetailWithConversation {
-                    user_id: recipient_id,
-                    display_name: "Recipient".to_string(),
-                    avatar_url: None,
-                    unread_count: 0,
-                    joined_at: Utc::now(),
-                    conversation_id,
-                },
-            ],
-        };
+        async fn get_reaction_summaries<'e, E>(
+            &self,
+            _message_ids: &[Uuid],
+            _tx: E,
+        ) -> Result<Vec<ReactionSummaryRow>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
 
-        let service = MessageService::with_dependencies(
-            Arc::new(MockConversationRepo {
-                pool: pool.clone(),
-                conversation_type,
-                is_member,
-            }),
-            Arc::new(MockMessageRepo { pool: pool.clone() }),
-            Arc::new(participant_repo),
-            Arc::new(MockLastMessageRepo),
-            Arc::new(
-                RedisCache::new()
-                    .await
-                    .expect("failed to initialize redis cache pool"),
-            ),
-            Arc::new(WebSocketServer::new()),
-        );
+        async fn search_messages<'e, E>(
+            &self,
+            _user_id: &Uuid,
+            _query: &str,
+            _before: Option<&Cursor>,
+            _limit: i32,
+            _tx: E,
+        ) -> Result<Vec<MessageSearchResult>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
 
-        (
-            service,
-            direct_unread_calls,
-            group_unread_calls,
-            sender_id,
-            conversation_id,
-        )
-    }
+        async fn refresh_thread_stats<'e>(
+            &self,
+            _root_message_id: &Uuid,
+            _tx: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+        ) -> Result<Option<ThreadStats>, error::SystemError> {
+            Ok(None)
+        }
 
-    #[tokio::test]
-    async fn test_send_message_to_conversation_rejects_non_member() {
-        let (service, _direct_calls, _group_calls, _sender_id, _conversation_id) =
-            build_service(ConversationType::Group, false).await;
+        async fn find_thread_replies<'e, E>(
+            &self,
+            _root_message_id: &Uuid,
+            _after: Option<&Cursor>,
+            _limit: i32,
+            _tx: E,
+        ) -> Result<Vec<MessageEntity>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
 
-        let result = service
-            .send_message_to_conversation(Uuid::now_v7(), Uuid::now_v7(), "hello".to_string())
-            .await;
+        async fn find_edit_history<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _tx: E,
+        ) -> Result<Vec<MessageRevision>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
 
-        assert!(matches!(result, Err(error::SystemError::Forbidden(_))));
-    }
+        async fn create_mentions<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _user_ids: &[Uuid],
+            _tx: E,
+        ) -> Result<(), error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(())
+        }
 
-    #[test]
-    fn test_resolve_message_route_group() {
-        let sender_id = Uuid::now_v7();
+        async fn find_mentions_for_user<'e, E>(
+            &self,
+            _user_id: &Uuid,
+            _before: Option<&Cursor>,
+            _limit: i32,
+            _tx: E,
+        ) -> Result<Vec<MessageEntity>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
 
-        let route = MessageService::<
-            MockMessageRepo,
-            MockConversationRepo,
-            MockParticipantRepo,
-            MockLastMessageRepo,
-        >::resolve_message_route(&ConversationType::Group, sender_id, [sender_id]);
+        async fn delete_expired_messages<'e, E>(
+            &self,
+            _limit: i64,
+            _tx: E,
+        ) -> Result<Vec<MessageEntity>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
 
-        assert!(matches!(route, Ok(MessageRoute::Group)));
-    }
+        async fn create_poll<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _poll: &NewPoll,
+            _tx: E,
+        ) -> Result<PollEntity, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Err(error::SystemError::internal_error("not used"))
+        }
 
-    #[test]
-    fn test_resolve_message_route_direct_success() {
-        let sender_id = Uuid::now_v7();
-        let recipient_id = Uuid::now_v7();
+        async fn find_poll<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _tx: E,
+        ) -> Result<Option<PollEntity>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(None)
+        }
 
-        let route = MessageService::<
-            MockMessageRepo,
+        async fn lock_poll<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _tx: E,
+        ) -> Result<Option<PollEntity>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(None)
+        }
+
+        async fn add_poll_vote<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _user_id: &Uuid,
+            _option_index: i16,
+            _tx: E,
+        ) -> Result<bool, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(true)
+        }
+
+        async fn remove_poll_vote<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _user_id: &Uuid,
+            _option_index: i16,
+            _tx: E,
+        ) -> Result<bool, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(true)
+        }
+
+        async fn clear_poll_votes<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _user_id: &Uuid,
+            _tx: E,
+        ) -> Result<(), error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(())
+        }
+
+        async fn get_poll_tallies<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _tx: E,
+        ) -> Result<Vec<PollTallyRow>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
+
+        async fn close_poll<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _tx: E,
+        ) -> Result<bool, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(true)
+        }
+
+        async fn update_live_location<'e, E>(
+            &self,
+            _message_id: &Uuid,
+            _sender_id: &Uuid,
+            _latitude: f64,
+            _longitude: f64,
+            _tx: E,
+        ) -> Result<Option<MessageEntity>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(None)
+        }
+
+        async fn find_contact_cards<'e, E>(
+            &self,
+            _user_ids: &[Uuid],
+            _tx: E,
+        ) -> Result<Vec<ContactCard>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(vec![])
+        }
+
+        async fn find_shareable_contact_card<'e, E>(
+            &self,
+            _sender_id: &Uuid,
+            _contact_user_id: &Uuid,
+            _tx: E,
+        ) -> Result<Option<ContactCard>, error::SystemError>
+        where
+            E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+        {
+            Ok(None)
+        }
+    }
+
+    async fn build_service(
+        conversation_type: ConversationType,
+        is_member: bool,
+    ) -> (
+        MessageService<
+            MockMessageRepo,
+            MockConversationRepo,
+            MockParticipantRepo,
+            MockLastMessageRepo,
+        >,
+        Arc<Mutex<u32>>,
+        Arc<Mutex<u32>>,
+        Uuid,
+        Uuid,
+    ) {
+        let pool = MockDatabase::new().pool();
+        let direct_unread_calls = Arc::new(Mutex::new(0));
+        let group_unread_calls = Arc::new(Mutex::new(0));
+
+        let sender_id = Uuid::now_v7();
+        let recipient_id = Uuid::now_v7();
+        let conversation_id = Uuid::now_v7();
+
+        let participant_repo = MockParticipantRepo {
+            direct_unread_calls: direct_unread_calls.clone(),
+            group_unread_calls: group_unread_calls.clone(),
+            participants: vec![
+                ParticipantDetailWithConversation {
+                    user_id: sender_id,
+                    display_name: "Sender".to_string(),
+                    avatar_url: None,
+                    unread_count: 0,
+                    mention_count: 0,
+                    role: ParticipantRole::Member,
+                    joined_at: Utc::now(),
+                    conversation_id,
+                },
+                ParticipantDetailWithConversation {
+                    user_id: recipient_id,
+                    display_name: "Recipient".to_string(),
+                    avatar_url: None,
+                    unread_count: 0,
+                    mention_count: 0,
+                    role: ParticipantRole::Member,
+                    joined_at: Utc::now(),
+                    conversation_id,
+                },
+            ],
+        };
+
+        let service = MessageService::with_dependencies(
+            Arc::new(MockConversationRepo {
+                pool: pool.clone(),
+                conversation_type,
+                is_member,
+            }),
+            Arc::new(MockMessageRepo { pool: pool.clone() }),
+            Arc::new(participant_repo),
+            Arc::new(MockLastMessageRepo),
+            Arc::new(
+                RedisCache::new()
+                    .await
+                    .expect("failed to initialize redis cache pool"),
+            ),
+            Arc::new(WebSocketServer::new()),
+        );
+
+        (
+            service,
+            direct_unread_calls,
+            group_unread_calls,
+            sender_id,
+            conversation_id,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_send_message_to_conversation_rejects_non_member() {
+        let (service, _direct_calls, _group_calls, _sender_id, _conversation_id) =
+            build_service(ConversationType::Group, false).await;
+
+        let result = service
+            .send_message_to_conversation(Uuid::now_v7(), Uuid::now_v7(), "hello".to_string())
+            .await;
+
+        assert!(matches!(result, Err(error::SystemError::Forbidden(_))));
+    }
+
+    #[test]
+    fn test_resolve_message_route_group() {
+        let sender_id = Uuid::now_v7();
+
+        let route = MessageService::<
+            MockMessageRepo,
+            MockConversationRepo,
+            MockParticipantRepo,
+            MockLastMessageRepo,
+        >::resolve_message_route(&ConversationType::Group, sender_id, [sender_id]);
+
+        assert!(matches!(route, Ok(MessageRoute::Group)));
+    }
+
+    #[test]
+    fn test_resolve_message_route_direct_success() {
+        let sender_id = Uuid::now_v7();
+        let recipient_id = Uuid::now_v7();
+
+        let route = MessageService::<
+            MockMessageRepo,
             MockConversationRepo,
             MockParticipantRepo,
             MockLastMessageRepo,
@@ -509,13 +888,8 @@ mod tests {
 
     #[test]
     fn test_normalize_message_input_defaults_to_text() {
-        let result = MessageService::<
-            MockMessageRepo,
-            MockConversationRepo,
-            MockParticipantRepo,
-            MockLastMessageRepo,
-        >::normalize_message_input(Some(" hello ".to_string()), None, None)
-        .expect("expected valid text payload");
+        let result = normalize_message_input(Some(" hello ".to_string()), None, None)
+            .expect("expected valid text payload");
 
         assert!(matches!(result.0, MessageType::Text));
         assert_eq!(result.1, Some("hello".to_string()));
@@ -524,25 +898,1521 @@ mod tests {
 
     #[test]
     fn test_normalize_message_input_requires_file_url_for_file_type() {
+        let result = normalize_message_input(None, Some(MessageType::File), None);
+
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_normalize_message_input_rejects_empty_payload() {
+        let result = normalize_message_input(Some("   ".to_string()), None, Some(" ".to_string()));
+
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_normalize_emoji_trims_value() {
         let result = MessageService::<
             MockMessageRepo,
             MockConversationRepo,
             MockParticipantRepo,
             MockLastMessageRepo,
-        >::normalize_message_input(None, Some(MessageType::File), None);
+        >::normalize_emoji(" 👍 ".to_string())
+        .expect("expected valid emoji");
+
+        assert_eq!(result, "👍");
+    }
+
+    #[test]
+    fn test_normalize_emoji_rejects_blank_and_whitespace() {
+        for value in ["   ", "👍 👎"] {
+            let result = MessageService::<
+                MockMessageRepo,
+                MockConversationRepo,
+                MockParticipantRepo,
+                MockLastMessageRepo,
+            >::normalize_emoji(value.to_string());
+
+            assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_messages_rejects_blank_query() {
+        let (service, _direct_calls, _group_calls, sender_id, _conversation_id) =
+            build_service(ConversationType::Group, true).await;
+
+        let result = service
+            .search_messages(sender_id, "   ".to_string(), 20, None)
+            .await;
+
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_search_messages_rejects_invalid_cursor() {
+        let (service, _direct_calls, _group_calls, sender_id, _conversation_id) =
+            build_service(ConversationType::Group, true).await;
+
+        let result = service
+            .search_messages(sender_id, "hello".to_string(), 20, Some("not-a-date".to_string()))
+            .await;
 
         assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
     }
 
     #[test]
-    fn test_normalize_message_input_rejects_empty_payload() {
-        let result = MessageService::<
+    fn test_validate_scheduled_at_rejects_past_and_far_future() {
+        let now = Utc::now();
+
+        for scheduled_at in [
+            now,
+            now - chrono::Duration::minutes(1),
+            now + chrono::Duration::days(366),
+        ] {
+            let result = ScheduledMessageSvc::validate_scheduled_at(scheduled_at, now);
+            assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+        }
+
+        assert!(
+            ScheduledMessageSvc::validate_scheduled_at(now + chrono::Duration::hours(1), now)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_scheduled_retry_backoff_doubles_and_caps() {
+        assert_eq!(ScheduledMessageSvc::retry_delay_secs(0), 15.0);
+        assert_eq!(ScheduledMessageSvc::retry_delay_secs(1), 30.0);
+        assert_eq!(ScheduledMessageSvc::retry_delay_secs(3), 120.0);
+        assert_eq!(ScheduledMessageSvc::retry_delay_secs(10), 600.0);
+
+        // Chỉ lỗi hạ tầng mới được thử lại
+        assert!(error::SystemError::DatabaseError("connection reset".into()).is_transient());
+        assert!(!error::SystemError::forbidden("not a member").is_transient());
+        assert!(!error::SystemError::bad_request("reply target gone").is_transient());
+    }
+
+    #[test]
+    fn test_describe_message_ttl_uses_largest_whole_unit() {
+        let cases = [
+            (None, "Đã tắt tin nhắn tự hủy"),
+            (Some(30), "Tin nhắn mới sẽ tự hủy sau 30 giây"),
+            (Some(300), "Tin nhắn mới sẽ tự hủy sau 5 phút"),
+            (Some(7_200), "Tin nhắn mới sẽ tự hủy sau 2 giờ"),
+            (Some(604_800), "Tin nhắn mới sẽ tự hủy sau 7 ngày"),
+        ];
+
+        for (ttl, expected) in cases {
+            assert_eq!(ConversationSvc::describe_message_ttl(ttl), expected);
+        }
+    }
+
+    #[test]
+    fn test_forwarded_message_serializes_flag_without_source_id() {
+        let source_id = Uuid::now_v7();
+        let message = MessageEntity {
+            id: Uuid::now_v7(),
+            conversation_id: Uuid::now_v7(),
+            sender_id: Uuid::now_v7(),
+            reply_to_id: None,
+            _type: MessageType::Text,
+            content: Some("hello".to_string()),
+            file_url: None,
+            is_edited: false,
+            forwarded_from_message_id: Some(source_id),
+            reply_count: 0,
+            last_reply_at: None,
+            expires_at: None,
+            location: Default::default(),
+            contact_user_id: None,
+            contact: None,
+            deleted_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["is_forwarded"], true);
+        assert!(json.get("forwarded_from_message_id").is_none());
+        assert!(!json.to_string().contains(&source_id.to_string()));
+    }
+
+    #[test]
+    fn test_normalize_message_body_validates_location_and_contact() {
+        type Svc = MessageService<
             MockMessageRepo,
             MockConversationRepo,
             MockParticipantRepo,
             MockLastMessageRepo,
-        >::normalize_message_input(Some("   ".to_string()), None, Some(" ".to_string()));
+        >;
+        let now = Utc::now();
+        let location = |latitude: f64, live_until| MessageLocation {
+            latitude,
+            longitude: 105.85,
+            label: Some("  Hồ Gươm ".to_string()),
+            live_until,
+        };
 
-        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+        let body =
+            Svc::normalize_message_body(None, None, None, Some(location(21.03, None)), None, now)
+                .unwrap();
+        assert_eq!(body.message_type, MessageType::Location);
+        assert_eq!(body.location.unwrap().label.as_deref(), Some("Hồ Gươm"));
+
+        let contact_id = Uuid::now_v7();
+        let body =
+            Svc::normalize_message_body(None, None, None, None, Some(contact_id), now).unwrap();
+        assert_eq!(body.message_type, MessageType::Contact);
+        assert_eq!(body.contact_user_id, Some(contact_id));
+
+        for (content, message_type, location, contact_user_id) in [
+            (None, None, Some(location(91.0, None)), None),
+            (None, None, Some(location(21.03, Some(now - chrono::Duration::minutes(1)))), None),
+            (None, None, Some(location(21.03, Some(now + chrono::Duration::hours(9)))), None),
+            (Some("hello".to_string()), None, Some(location(21.03, None)), None),
+            (None, Some(MessageType::Location), None, None),
+            (None, Some(MessageType::Contact), None, None),
+            (Some("hello".to_string()), Some(MessageType::Text), None, Some(contact_id)),
+        ] {
+            let result = Svc::normalize_message_body(
+                content,
+                message_type,
+                None,
+                location,
+                contact_user_id,
+                now,
+            );
+            assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+        }
+    }
+
+    #[test]
+    fn test_location_message_serializes_structured_location() {
+        let live_until = Utc::now() + chrono::Duration::hours(1);
+        let message = MessageEntity {
+            id: Uuid::now_v7(),
+            conversation_id: Uuid::now_v7(),
+            sender_id: Uuid::now_v7(),
+            reply_to_id: None,
+            _type: MessageType::Location,
+            content: None,
+            file_url: None,
+            is_edited: false,
+            forwarded_from_message_id: None,
+            reply_count: 0,
+            last_reply_at: None,
+            expires_at: None,
+            location: Some(MessageLocation {
+                latitude: 21.03,
+                longitude: 105.85,
+                label: None,
+                live_until: Some(live_until),
+            })
+            .into(),
+            contact_user_id: None,
+            contact: None,
+            deleted_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["_type"], "location");
+        assert_eq!(json["location"]["latitude"], 21.03);
+        assert_eq!(json["location"]["longitude"], 105.85);
+        assert!(json.get("location_latitude").is_none());
+        assert!(message.location.get().unwrap().is_live(Utc::now()));
+    }
+
+    #[test]
+    fn test_is_within_edit_window() {
+        type Svc = MessageService<
+            MockMessageRepo,
+            MockConversationRepo,
+            MockParticipantRepo,
+            MockLastMessageRepo,
+        >;
+        let now = Utc::now();
+
+        assert!(Svc::is_within_edit_window(now - chrono::Duration::seconds(899), now, 900));
+        assert!(!Svc::is_within_edit_window(now - chrono::Duration::seconds(901), now, 900));
+        assert!(Svc::is_within_edit_window(now - chrono::Duration::days(365), now, 0));
+    }
+
+    #[test]
+    fn test_normalize_poll_input_rejects_invalid_options() {
+        type Svc = MessageService<
+            MockMessageRepo,
+            MockConversationRepo,
+            MockParticipantRepo,
+            MockLastMessageRepo,
+        >;
+        let now = Utc::now();
+        let options = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        for (question, items, closes_at) in [
+            ("  ", options(&["A", "B"]), None),
+            ("Ăn gì?", options(&["Phở"]), None),
+            ("Ăn gì?", options(&["Phở", "  "]), None),
+            ("Ăn gì?", options(&["Phở", " phở "]), None),
+            ("Ăn gì?", options(&["Phở", "Bún"]), Some(now - chrono::Duration::minutes(1))),
+        ] {
+            let result = Svc::normalize_poll_input(question.to_string(), items, false, closes_at, now);
+            assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+        }
+
+        let poll = Svc::normalize_poll_input(
+            " Ăn gì? ".to_string(),
+            options(&[" Phở", "Bún "]),
+            true,
+            Some(now + chrono::Duration::hours(1)),
+            now,
+        )
+        .unwrap();
+        assert_eq!(poll.question, "Ăn gì?");
+        assert_eq!(poll.options, vec!["Phở".to_string(), "Bún".to_string()]);
+    }
+
+    #[test]
+    fn test_poll_detail_fills_missing_tallies_and_marks_expired() {
+        let now = Utc::now();
+        let voter = Uuid::now_v7();
+        let poll = PollEntity {
+            message_id: Uuid::now_v7(),
+            question: "Ăn gì?".to_string(),
+            options: vec!["Phở".to_string(), "Bún".to_string(), "Cơm".to_string()],
+            multiple_choice: false,
+            closes_at: Some(now - chrono::Duration::seconds(1)),
+            closed_at: None,
+            created_at: now - chrono::Duration::hours(1),
+        };
+
+        let detail = PollDetail::from_tallies(
+            poll,
+            vec![PollTallyRow {
+                option_index: 1,
+                count: 1,
+                user_ids: vec![voter],
+            }],
+            now,
+        );
+
+        assert!(detail.closed);
+        let counts: Vec<i64> = detail.options.iter().map(|o| o.count).collect();
+        assert_eq!(counts, vec![0, 1, 0]);
+        assert_eq!(detail.options[1].user_ids, vec![voter]);
+    }
+
+    #[test]
+    fn test_parse_mentions_extracts_usernames_and_all() {
+        let (usernames, mention_all) = MessageService::<
+            MockMessageRepo,
+            MockConversationRepo,
+            MockParticipantRepo,
+            MockLastMessageRepo,
+        >::parse_mentions("hi @Alice, @bob.smith. and @alice again @all email@x.com @");
+
+        assert_eq!(usernames, vec!["alice".to_string(), "bob.smith".to_string()]);
+        assert!(mention_all);
+    }
+
+    #[test]
+    fn test_parse_mentions_without_tokens() {
+        let (usernames, mention_all) = MessageService::<
+            MockMessageRepo,
+            MockConversationRepo,
+            MockParticipantRepo,
+            MockLastMessageRepo,
+        >::parse_mentions("no mentions here");
+
+        assert!(usernames.is_empty());
+        assert!(!mention_all);
+    }
+
+    #[test]
+    fn test_can_remove_member_requires_higher_role() {
+        type Svc = ConversationService<MockConversationRepo, MockParticipantRepo, MockMessageRepo>;
+
+        assert!(Svc::can_remove_member(ParticipantRole::Owner, ParticipantRole::Admin));
+        assert!(Svc::can_remove_member(ParticipantRole::Admin, ParticipantRole::Member));
+        assert!(!Svc::can_remove_member(ParticipantRole::Admin, ParticipantRole::Admin));
+        assert!(!Svc::can_remove_member(ParticipantRole::Admin, ParticipantRole::Owner));
+        assert!(!Svc::can_remove_member(ParticipantRole::Member, ParticipantRole::Member));
+    }
+
+    #[test]
+    fn test_ranked_cursor_roundtrip_and_rejects_garbage() {
+        let cursor = Cursor::ranked(
+            "Nhóm. Bạn bè",
+            chrono::DateTime::from_timestamp_micros(1_718_000_000_123_456).unwrap(),
+            Uuid::now_v7(),
+        );
+
+        let encoded = cursor.encode();
+        assert_eq!(Cursor::decode_ranked(&encoded).ok(), Some(cursor));
+
+        assert!(Cursor::decode_ranked("2024-01-01T00:00:00Z").is_err());
+        assert!(Cursor::decode_ranked("0.abc.not-a-uuid").is_err());
+        // Cursor không có rank không dùng được cho danh sách cần rank
+        assert!(Cursor::decode_ranked(&Cursor::new(Utc::now(), Uuid::now_v7()).encode()).is_err());
+    }
+
+    #[test]
+    fn test_cursor_distinguishes_same_timestamp() {
+        // Postgres lưu timestamptz tới micro giây
+        let created_at = chrono::DateTime::from_timestamp_micros(Utc::now().timestamp_micros()).unwrap();
+        let a = Cursor::new(created_at, Uuid::now_v7());
+        let b = Cursor::new(created_at, Uuid::now_v7());
+
+        assert_ne!(a.encode(), b.encode());
+        assert_eq!(Cursor::decode(&a.encode()).ok(), Some(a));
+        assert!(Cursor::decode(&created_at.to_rfc3339()).is_err());
+    }
+
+    #[test]
+    fn test_cursor_rejects_tampered_token() {
+        let cursor = Cursor::new(Utc::now(), Uuid::now_v7());
+        let encoded = cursor.encode();
+        let (payload, tag) = encoded.split_once('.').unwrap();
+
+        // Payload hợp lệ nhưng không có chữ ký của server
+        let forged = format!("{}.{}", payload, "A".repeat(tag.len()));
+        assert!(Cursor::decode(&forged).is_err());
+
+        // Ghép payload khác vào chữ ký cũ
+        let other = Cursor::new(Utc::now(), Uuid::now_v7()).encode();
+        let (other_payload, _) = other.split_once('.').unwrap();
+        assert!(Cursor::decode(&format!("{other_payload}.{tag}")).is_err());
+
+        // Cursor của danh sách conversation không dùng được cho danh sách khác
+        let conversation_cursor = Cursor::ranked(PINNED_RANK, Utc::now(), Uuid::now_v7()).encode();
+        assert!(Cursor::decode(&conversation_cursor).is_err());
+    }
+
+    // ===== Các test dưới đây chạy trên Postgres thật (cần schema đã migrate) =====
+
+    async fn seed_pg_user(pool: &sqlx::PgPool) -> Uuid {
+        let id = Uuid::now_v7();
+        let username = format!("u{}", id.simple());
+        sqlx::query(
+            r#"
+            INSERT INTO users (id, username, hash_password, email, role, display_name)
+            VALUES ($1, $2, 'test_hash', $3, 'USER', $2)
+            "#,
+        )
+        .bind(id)
+        .bind(&username)
+        .bind(format!("{username}@test.local"))
+        .execute(pool)
+        .await
+        .expect("should seed user");
+        id
+    }
+
+    /// Tạo nhóm với người đầu tiên là owner, những người còn lại là member
+    async fn seed_pg_group(pool: &sqlx::PgPool, member_ids: &[Uuid]) -> Uuid {
+        let conversation_id = Uuid::now_v7();
+        sqlx::query("INSERT INTO conversations (id, type) VALUES ($1, 'group')")
+            .bind(conversation_id)
+            .execute(pool)
+            .await
+            .expect("should seed conversation");
+        sqlx::query(
+            "INSERT INTO group_conversations (conversation_id, name, created_by) VALUES ($1, 'Test', $2)",
+        )
+        .bind(conversation_id)
+        .bind(member_ids[0])
+        .execute(pool)
+        .await
+        .expect("should seed group");
+        for (i, user_id) in member_ids.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO participants (conversation_id, user_id, unread_count, role)
+                VALUES ($1, $2, 0, $3::participant_role)
+                "#,
+            )
+            .bind(conversation_id)
+            .bind(user_id)
+            .bind(if i == 0 { "owner" } else { "member" })
+            .execute(pool)
+            .await
+            .expect("should seed participant");
+        }
+        conversation_id
+    }
+
+    async fn cleanup_pg(pool: &sqlx::PgPool, conversation_id: Uuid, user_ids: &[Uuid]) {
+        let _ = sqlx::query("DELETE FROM conversations WHERE id = $1")
+            .bind(conversation_id)
+            .execute(pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = ANY($1)")
+            .bind(user_ids)
+            .execute(pool)
+            .await;
+    }
+
+    async fn build_pg_services(pool: sqlx::PgPool) -> (MessageSvc, ConversationSvc) {
+        build_pg_services_with_ws(pool, Arc::new(WebSocketServer::new())).await
+    }
+
+    async fn build_pg_services_with_ws(
+        pool: sqlx::PgPool,
+        ws_server: Arc<WebSocketServer>,
+    ) -> (MessageSvc, ConversationSvc) {
+        let participant_repo = ParticipantPgRepository::default();
+        let conversation_repo = ConversationPgRepository::new(pool.clone(), participant_repo.clone());
+        let message_repo = MessageRepositoryPg::new(pool);
+
+        let message_service = MessageService::with_dependencies(
+            Arc::new(conversation_repo.clone()),
+            Arc::new(message_repo.clone()),
+            Arc::new(participant_repo.clone()),
+            Arc::new(LastMessagePgRepository::default()),
+            Arc::new(
+                RedisCache::new()
+                    .await
+                    .expect("failed to initialize redis cache pool"),
+            ),
+            ws_server.clone(),
+        );
+        let conversation_service = ConversationService::with_dependencies(
+            Arc::new(conversation_repo),
+            Arc::new(participant_repo),
+            Arc::new(message_repo),
+            ws_server,
+        );
+
+        (message_service, conversation_service)
+    }
+
+    fn text_payload(content: &str, reply_to_id: Option<Uuid>) -> SendGroupMessagePayload {
+        SendGroupMessagePayload {
+            content: Some(content.to_string()),
+            reply_to_id,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_concurrent_thread_replies_keep_reply_count_exact() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, member_id]).await;
+        let (message_service, conversation_service) = build_pg_services(pool.clone()).await;
+
+        let root = message_service
+            .send_group_message_payload(owner_id, conversation_id, text_payload("root", None))
+            .await
+            .expect("root message should be sent");
+
+        let replies = futures_util::future::try_join_all((0..8).map(|i| {
+            let sender_id = if i % 2 == 0 { owner_id } else { member_id };
+            message_service.send_group_message_payload(
+                sender_id,
+                conversation_id,
+                text_payload(&format!("reply {i}"), Some(root.id)),
+            )
+        }))
+        .await
+        .expect("concurrent replies should succeed");
+
+        let thread = conversation_service
+            .get_thread(conversation_id, root.id, 50, None)
+            .await
+            .expect("thread should load");
+        assert_eq!(thread.root.message.reply_count, 8);
+        assert_eq!(thread.replies.len(), 8);
+
+        message_service
+            .delete_message(replies[0].id, replies[0].sender_id)
+            .await
+            .expect("reply should be deleted");
+        let thread = conversation_service
+            .get_thread(conversation_id, root.id, 50, None)
+            .await
+            .expect("thread should load");
+        assert_eq!(thread.root.message.reply_count, 7);
+        assert_eq!(
+            thread.root.message.last_reply_at,
+            replies.iter().skip(1).map(|r| r.created_at).max()
+        );
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, member_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_scheduled_message_is_delivered_once_and_retried_on_transient_errors() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, member_id]).await;
+        let (message_service, _) = build_pg_services(pool.clone()).await;
+        let scheduled_service = ScheduledMessageService::with_dependencies(
+            Arc::new(ScheduledMessagePgRepository::new(pool.clone())),
+            Arc::new(ConversationPgRepository::new(
+                pool.clone(),
+                ParticipantPgRepository::default(),
+            )),
+        );
+
+        let seed_due = |content: &'static str| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query(
+                    r#"
+                    INSERT INTO scheduled_messages
+                        (conversation_id, sender_id, content, scheduled_at)
+                    VALUES ($1, $2, $3, NOW() - INTERVAL '1 second')
+                    "#,
+                )
+                .bind(conversation_id)
+                .bind(owner_id)
+                .bind(content)
+                .execute(&pool)
+                .await
+                .expect("should seed scheduled message");
+            }
+        };
+        let claim_ours = || async {
+            scheduled_service
+                .claim_due(100)
+                .await
+                .expect("should claim")
+                .into_iter()
+                .filter(|s| s.conversation_id == conversation_id)
+                .collect::<Vec<_>>()
+        };
+
+        seed_due("hello later").await;
+        let claimed = claim_ours().await;
+        assert_eq!(claimed.len(), 1);
+
+        // Worker thứ hai giữ bản claim cũ (ví dụ claim lại sau khi quá hạn) không gửi trùng
+        let sent = scheduled_service
+            .deliver(&claimed[0], &message_service)
+            .await
+            .expect("should deliver");
+        assert!(sent.is_some());
+        let again = scheduled_service
+            .deliver(&claimed[0], &message_service)
+            .await
+            .expect("duplicate delivery should be a no-op");
+        assert!(again.is_none());
+
+        let message_count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE conversation_id = $1")
+                .bind(conversation_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(message_count, 1);
+
+        // Lỗi tạm thời: quay lại pending và chưa được claim lại trước thời điểm backoff
+        seed_due("retry me").await;
+        let claimed = claim_ours().await;
+        scheduled_service
+            .record_failure(&claimed[0], &error::SystemError::DatabaseError("timeout".into()))
+            .await
+            .expect("should schedule retry");
+        let (status, attempts): (String, i32) = sqlx::query_as(
+            "SELECT status::text, attempts FROM scheduled_messages WHERE id = $1",
+        )
+        .bind(claimed[0].id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((status.as_str(), attempts), ("pending", 1));
+        assert!(claim_ours().await.is_empty());
+
+        // Lỗi nghiệp vụ: failed ngay
+        sqlx::query("UPDATE scheduled_messages SET next_attempt_at = NULL WHERE id = $1")
+            .bind(claimed[0].id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let claimed = claim_ours().await;
+        assert_eq!(claimed.len(), 1);
+        scheduled_service
+            .record_failure(&claimed[0], &error::SystemError::forbidden("left the group"))
+            .await
+            .expect("should mark failed");
+        let status: String =
+            sqlx::query_scalar("SELECT status::text FROM scheduled_messages WHERE id = $1")
+                .bind(claimed[0].id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(status, "failed");
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, member_id]).await;
+    }
+
+    /// Bỏ id/thời gian/unread khỏi event để so sánh hai lượt gửi khác nhau
+    fn strip_volatile(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for key in ["id", "_id", "created_at", "updated_at", "last_message_at", "unread_counts"] {
+                    map.remove(key);
+                }
+                map.values_mut().for_each(strip_volatile);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip_volatile),
+            _ => {}
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_scheduled_direct_message_matches_live_send() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let friend_id = seed_pg_user(&pool).await;
+        let group_id = seed_pg_group(&pool, &[owner_id]).await;
+
+        let ws_server = Arc::new(WebSocketServer::new());
+        let mut receivers = HashMap::new();
+        for user_id in [owner_id, friend_id] {
+            let session_id = Uuid::now_v7();
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            ws_server.connect(session_id, tx);
+            ws_server.authenticate(session_id, user_id);
+            receivers.insert(user_id, rx);
+        }
+        let (message_service, _) = build_pg_services_with_ws(pool.clone(), ws_server).await;
+        let scheduled_service = ScheduledMessageService::with_dependencies(
+            Arc::new(ScheduledMessagePgRepository::new(pool.clone())),
+            Arc::new(ConversationPgRepository::new(
+                pool.clone(),
+                ParticipantPgRepository::default(),
+            )),
+        );
+
+        let root = message_service
+            .send_direct_message(owner_id, friend_id, "root".to_string(), None)
+            .await
+            .expect("should open direct conversation");
+        let direct_id = root.conversation_id;
+        let elsewhere = message_service
+            .send_group_message_payload(owner_id, group_id, text_payload("elsewhere", None))
+            .await
+            .expect("should send group message");
+        let content = format!("reply @u{}", friend_id.simple());
+
+        // Mỗi lượt gửi: số event xoá nháp của sender và event new-message người nhận thấy
+        let mut observe = || {
+            let events = |rx: &mut tokio::sync::mpsc::UnboundedReceiver<String>| {
+                std::iter::from_fn(|| rx.try_recv().ok())
+                    .map(|raw| serde_json::from_str::<serde_json::Value>(&raw).unwrap())
+                    .collect::<Vec<_>>()
+            };
+            let draft_cleared = events(receivers.get_mut(&owner_id).unwrap())
+                .iter()
+                .filter(|event| event["type"] == "draft-updated" && event["draft"].is_null())
+                .count();
+            let new_message = events(receivers.get_mut(&friend_id).unwrap())
+                .into_iter()
+                .rfind(|event| event["type"] == "new-message")
+                .expect("recipient should get new-message");
+            (draft_cleared, new_message)
+        };
+        observe();
+
+        let schedule = |reply_to_id: Uuid| CreateScheduledMessageRequest {
+            conversation_id: direct_id,
+            content: Some(content.clone()),
+            _type: None,
+            file_url: None,
+            reply_to_id: Some(reply_to_id),
+            scheduled_at: Utc::now() + chrono::Duration::minutes(5),
+        };
+
+        // Trả lời tin của conversation khác bị từ chối ngay lúc hẹn
+        let err = scheduled_service
+            .schedule(owner_id, schedule(elsewhere.id), &message_service)
+            .await
+            .expect_err("cross-conversation reply should be rejected");
+        assert!(matches!(err, error::SystemError::BadRequest(_)));
+
+        message_service
+            .send_direct_message_payload(
+                owner_id,
+                friend_id,
+                SendDirectMessagePayload {
+                    conversation_id: Some(direct_id),
+                    content: Some(content.clone()),
+                    message_type: None,
+                    file_url: None,
+                    reply_to_id: Some(root.id),
+                    location: None,
+                    contact_user_id: None,
+                },
+            )
+            .await
+            .expect("should send live");
+        let (live_drafts, mut live_event) = observe();
+
+        let scheduled = scheduled_service
+            .schedule(owner_id, schedule(root.id), &message_service)
+            .await
+            .expect("should schedule");
+        sqlx::query("UPDATE scheduled_messages SET scheduled_at = NOW() - INTERVAL '1 second' WHERE id = $1")
+            .bind(scheduled.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let claimed = scheduled_service
+            .claim_due(100)
+            .await
+            .expect("should claim")
+            .into_iter()
+            .find(|s| s.id == scheduled.id)
+            .expect("scheduled message should be claimed");
+        scheduled_service
+            .deliver(&claimed, &message_service)
+            .await
+            .expect("should deliver")
+            .expect("should not be delivered twice");
+        let (scheduled_drafts, mut scheduled_event) = observe();
+
+        // Chỉ lượt gửi trực tiếp xoá nháp, tin hẹn giờ giữ nguyên nội dung user đang soạn
+        assert_eq!((live_drafts, scheduled_drafts), (1, 0));
+        let friend_key = friend_id.to_string();
+        assert_eq!(live_event["unread_counts"][&friend_key], 2);
+        assert_eq!(scheduled_event["unread_counts"][&friend_key], 3);
+        strip_volatile(&mut live_event);
+        strip_volatile(&mut scheduled_event);
+        assert_eq!(live_event, scheduled_event);
+        assert_eq!(live_event["mentioned_user_ids"], serde_json::json!([]));
+
+        let (reply_count, mention_count): (i32, i32) = sqlx::query_as(
+            r#"
+            SELECT m.reply_count, p.mention_count
+            FROM messages m
+            JOIN participants p ON p.conversation_id = m.conversation_id AND p.user_id = $2
+            WHERE m.id = $1
+            "#,
+        )
+        .bind(root.id)
+        .bind(friend_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((reply_count, mention_count), (2, 0));
+
+        cleanup_pg(&pool, direct_id, &[]).await;
+        cleanup_pg(&pool, group_id, &[owner_id, friend_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_message_ttl_change_writes_system_message_atomically() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, member_id]).await;
+        let (message_service, conversation_service) = build_pg_services(pool.clone()).await;
+        let count_system = || async {
+            sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM messages WHERE conversation_id = $1 AND type = 'system'",
+            )
+            .bind(conversation_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        };
+
+        // Member thường bị từ chối: không đổi TTL và không có system message lẻ
+        let err = conversation_service
+            .update_message_ttl(conversation_id, member_id, Some(3600), &message_service)
+            .await
+            .expect_err("member should not change ttl");
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+        assert_eq!(count_system().await, 0);
+
+        conversation_service
+            .update_message_ttl(conversation_id, owner_id, Some(3600), &message_service)
+            .await
+            .expect("owner should change ttl");
+        assert_eq!(count_system().await, 1);
+        let ttl: Option<i32> =
+            sqlx::query_scalar("SELECT message_ttl_seconds FROM conversations WHERE id = $1")
+                .bind(conversation_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(ttl, Some(3600));
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, member_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_reaper_refreshes_last_message_preview() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, member_id]).await;
+        let (message_service, _) = build_pg_services(pool.clone()).await;
+        let conversation_repo =
+            ConversationPgRepository::new(pool.clone(), ParticipantPgRepository::default());
+        // Preview ở bảng last_messages, header (detail) và danh sách conversation phải khớp nhau
+        let last_content = || async {
+            let stored = sqlx::query_scalar::<_, Option<String>>(
+                "SELECT content FROM last_messages WHERE conversation_id = $1",
+            )
+            .bind(conversation_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            let detail = conversation_repo
+                .find_one_conversation_detail(&conversation_id)
+                .await
+                .unwrap()
+                .expect("conversation should exist")
+                .last_message
+                .and_then(|m| m.content);
+            let listed = conversation_repo
+                .find_all_conversation_with_details_by_user(
+                    &ConversationQuery {
+                        user_id: owner_id,
+                        archived: false,
+                        _type: None,
+                        unread_only: false,
+                        search: None,
+                        cursor: None,
+                    },
+                    50,
+                    &pool,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .find(|row| row.conversation_id == conversation_id)
+                .expect("conversation should be listed")
+                .last_message
+                .and_then(|m| m.content);
+            assert_eq!(detail, stored, "conversation header preview");
+            assert_eq!(listed, stored, "conversation list preview");
+            stored
+        };
+
+        let first = message_service
+            .send_group_message_payload(owner_id, conversation_id, text_payload("keep me", None))
+            .await
+            .expect("should send");
+        let second = message_service
+            .send_group_message_payload(member_id, conversation_id, text_payload("burn me", None))
+            .await
+            .expect("should send");
+        assert_eq!(last_content().await.as_deref(), Some("burn me"));
+
+        let expire = |id: Uuid| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query("UPDATE messages SET expires_at = NOW() - INTERVAL '1 second' WHERE id = $1")
+                    .bind(id)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+        };
+
+        expire(second.id).await;
+        message_service.reap_expired_messages(1000).await.expect("should reap");
+        assert_eq!(last_content().await.as_deref(), Some("keep me"));
+
+        expire(first.id).await;
+        message_service.reap_expired_messages(1000).await.expect("should reap");
+        assert_eq!(last_content().await, None);
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, member_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_concurrent_pins_never_exceed_limit() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id]).await;
+        let (message_service, conversation_service) = build_pg_services(pool.clone()).await;
+
+        let mut message_ids = Vec::new();
+        for i in 0..MAX_PINNED_MESSAGES + 5 {
+            let message = message_service
+                .send_group_message_payload(owner_id, conversation_id, text_payload(&format!("m{i}"), None))
+                .await
+                .expect("should send");
+            message_ids.push(message.id);
+        }
+
+        let results = futures_util::future::join_all(
+            message_ids
+                .iter()
+                .map(|id| conversation_service.pin_message(conversation_id, owner_id, *id)),
+        )
+        .await;
+        assert_eq!(
+            results.iter().filter(|r| r.is_ok()).count() as i64,
+            MAX_PINNED_MESSAGES
+        );
+
+        let pinned: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM pinned_messages WHERE conversation_id = $1")
+                .bind(conversation_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(pinned, MAX_PINNED_MESSAGES);
+
+        cleanup_pg(&pool, conversation_id, &[owner_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_forward_is_all_or_nothing_across_targets() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let outsider_id = seed_pg_user(&pool).await;
+        let source_conversation_id = seed_pg_group(&pool, &[owner_id]).await;
+        let target_a = seed_pg_group(&pool, &[owner_id]).await;
+        let target_b = seed_pg_group(&pool, &[owner_id]).await;
+        let foreign_target = seed_pg_group(&pool, &[outsider_id]).await;
+        let (message_service, _) = build_pg_services(pool.clone()).await;
+        let count_in = |conversation_ids: Vec<Uuid>| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, i64>(
+                    "SELECT COUNT(*) FROM messages WHERE conversation_id = ANY($1)",
+                )
+                .bind(conversation_ids)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+            }
+        };
+
+        let source = message_service
+            .send_group_message_payload(owner_id, source_conversation_id, text_payload("fwd", None))
+            .await
+            .expect("should send");
+
+        // Một đích không hợp lệ: không đích nào nhận tin
+        let err = message_service
+            .forward_message(source.id, owner_id, vec![target_a, foreign_target, target_b])
+            .await
+            .expect_err("forward into a foreign conversation should fail");
+        assert!(matches!(err, error::SystemError::Forbidden(_)));
+        assert_eq!(count_in(vec![target_a, target_b, foreign_target]).await, 0);
+
+        let forwarded = message_service
+            .forward_message(source.id, owner_id, vec![target_b, target_a, target_b])
+            .await
+            .expect("should forward");
+        assert_eq!(
+            forwarded.iter().map(|m| m.conversation_id).collect::<Vec<_>>(),
+            vec![target_b, target_a]
+        );
+        assert!(forwarded.iter().all(|m| m.forwarded_from_message_id == Some(source.id)));
+        assert_eq!(count_in(vec![target_a, target_b]).await, 2);
+
+        for conversation_id in [source_conversation_id, target_a, target_b] {
+            cleanup_pg(&pool, conversation_id, &[]).await;
+        }
+        cleanup_pg(&pool, foreign_target, &[owner_id, outsider_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_new_message_only_tells_each_recipient_its_own_mute_state() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let muted_id = seed_pg_user(&pool).await;
+        let other_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, muted_id, other_id]).await;
+        sqlx::query(
+            "UPDATE participants SET muted_until = NOW() + INTERVAL '1 hour' WHERE conversation_id = $1 AND user_id = $2",
+        )
+        .bind(conversation_id)
+        .bind(muted_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let ws_server = Arc::new(WebSocketServer::new());
+        let mut receivers = HashMap::new();
+        for user_id in [muted_id, other_id] {
+            let session_id = Uuid::now_v7();
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            ws_server.connect(session_id, tx);
+            ws_server.authenticate(session_id, user_id);
+            receivers.insert(user_id, rx);
+        }
+        let (message_service, _) = build_pg_services_with_ws(pool.clone(), ws_server).await;
+
+        message_service
+            .send_group_message_payload(owner_id, conversation_id, text_payload("hello", None))
+            .await
+            .expect("should send");
+
+        for (user_id, expected_muted) in [(muted_id, true), (other_id, false)] {
+            let rx = receivers.get_mut(&user_id).unwrap();
+            let event = std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|raw| serde_json::from_str::<serde_json::Value>(&raw).unwrap())
+                .find(|event| event["type"] == "new-message")
+                .expect("recipient should get new-message");
+            assert_eq!(event["muted"], expected_muted);
+            assert!(event.get("muted_user_ids").is_none());
+        }
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, muted_id, other_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_sending_message_clears_sender_draft_on_all_sessions() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, member_id]).await;
+
+        let ws_server = Arc::new(WebSocketServer::new());
+        let mut receivers = HashMap::new();
+        for user_id in [owner_id, owner_id, member_id] {
+            let session_id = Uuid::now_v7();
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            ws_server.connect(session_id, tx);
+            ws_server.authenticate(session_id, user_id);
+            receivers.entry(user_id).or_insert_with(Vec::new).push(rx);
+        }
+        let (message_service, _) = build_pg_services_with_ws(pool.clone(), ws_server).await;
+
+        message_service
+            .send_group_message_payload(owner_id, conversation_id, text_payload("hello", None))
+            .await
+            .expect("should send");
+
+        let draft_events = |rx: &mut tokio::sync::mpsc::UnboundedReceiver<String>| {
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|raw| serde_json::from_str::<serde_json::Value>(&raw).unwrap())
+                .filter(|event| event["type"] == "draft-updated")
+                .collect::<Vec<_>>()
+        };
+
+        for rx in receivers.get_mut(&owner_id).unwrap() {
+            let events = draft_events(rx);
+            assert_eq!(events.len(), 1, "every sender session should clear its draft");
+            assert_eq!(events[0]["conversation_id"], conversation_id.to_string());
+            assert!(events[0]["draft"].is_null());
+        }
+        for rx in receivers.get_mut(&member_id).unwrap() {
+            assert!(draft_events(rx).is_empty(), "other members keep their drafts");
+        }
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, member_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_seen_by_lists_group_readers_and_rejects_direct_conversations() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let group_id = seed_pg_group(&pool, &[owner_id, member_id]).await;
+        let (message_service, conversation_service) = build_pg_services(pool.clone()).await;
+
+        let first = message_service
+            .send_group_message_payload(owner_id, group_id, text_payload("first", None))
+            .await
+            .expect("should send");
+        conversation_service
+            .mark_as_seen(group_id, member_id)
+            .await
+            .expect("should mark seen");
+        let second = message_service
+            .send_group_message_payload(owner_id, group_id, text_payload("second", None))
+            .await
+            .expect("should send");
+
+        let seen_first = conversation_service
+            .get_seen_by(group_id, owner_id, first.id)
+            .await
+            .expect("should list readers");
+        assert_eq!(
+            seen_first.iter().map(|s| s.user_id).collect::<Vec<_>>(),
+            vec![member_id]
+        );
+        assert!(seen_first[0].last_seen_at.is_some());
+        let seen_second = conversation_service
+            .get_seen_by(group_id, owner_id, second.id)
+            .await
+            .expect("should list readers");
+        assert!(seen_second.is_empty());
+
+        let direct = message_service
+            .send_direct_message(owner_id, member_id, "hi".to_string(), None)
+            .await
+            .expect("should send direct message");
+        let err = conversation_service
+            .get_seen_by(direct.conversation_id, owner_id, direct.id)
+            .await
+            .expect_err("direct conversations have no seen-by list");
+        assert!(matches!(err, error::SystemError::BadRequest(_)));
+
+        cleanup_pg(&pool, direct.conversation_id, &[]).await;
+        cleanup_pg(&pool, group_id, &[owner_id, member_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_mentions_are_only_recorded_in_groups() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let friend_id = seed_pg_user(&pool).await;
+        let group_id = seed_pg_group(&pool, &[owner_id, friend_id]).await;
+        let (message_service, _) = build_pg_services(pool.clone()).await;
+        let scheduled_service = ScheduledMessageService::with_dependencies(
+            Arc::new(ScheduledMessagePgRepository::new(pool.clone())),
+            Arc::new(ConversationPgRepository::new(
+                pool.clone(),
+                ParticipantPgRepository::default(),
+            )),
+        );
+        let content = format!("hi @u{}", friend_id.simple());
+        let mention_count = |conversation_id: Uuid| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, i32>(
+                    "SELECT mention_count FROM participants WHERE conversation_id = $1 AND user_id = $2",
+                )
+                .bind(conversation_id)
+                .bind(friend_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+            }
+        };
+
+        message_service
+            .send_group_message_payload(owner_id, group_id, text_payload(&content, None))
+            .await
+            .expect("should send group message");
+        assert_eq!(mention_count(group_id).await, 1);
+
+        let direct_id = message_service
+            .send_direct_message(owner_id, friend_id, "hello".to_string(), None)
+            .await
+            .expect("should open direct conversation")
+            .conversation_id;
+
+        // Tin hẹn giờ đi qua insert_message chung với nhóm nhưng vẫn không ghi mention trong DM
+        let scheduled_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO scheduled_messages (conversation_id, sender_id, content, scheduled_at)
+            VALUES ($1, $2, $3, NOW() - INTERVAL '1 second')
+            RETURNING id
+            "#,
+        )
+        .bind(direct_id)
+        .bind(owner_id)
+        .bind(&content)
+        .fetch_one(&pool)
+        .await
+        .expect("should seed scheduled message");
+        let claimed = scheduled_service
+            .claim_due(100)
+            .await
+            .expect("should claim")
+            .into_iter()
+            .find(|s| s.id == scheduled_id)
+            .expect("seeded message should be claimed");
+        let delivered = scheduled_service
+            .deliver(&claimed, &message_service)
+            .await
+            .expect("should deliver")
+            .expect("should not be delivered twice");
+
+        assert_eq!(mention_count(direct_id).await, 0);
+        let direct_mentions: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM message_mentions WHERE message_id = $1")
+                .bind(delivered.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(direct_mentions, 0);
+
+        cleanup_pg(&pool, direct_id, &[]).await;
+        cleanup_pg(&pool, group_id, &[owner_id, friend_id]).await;
+    }
+
+    async fn create_pg_poll(
+        message_service: &MessageSvc,
+        creator_id: Uuid,
+        conversation_id: Uuid,
+        multiple_choice: bool,
+    ) -> Uuid {
+        message_service
+            .create_poll(
+                creator_id,
+                CreatePollRequest {
+                    conversation_id,
+                    question: "Ăn gì?".to_string(),
+                    options: vec!["Phở".to_string(), "Bún".to_string(), "Cơm".to_string()],
+                    multiple_choice,
+                    closes_at: None,
+                },
+            )
+            .await
+            .expect("should create poll")
+            .message_id
+    }
+
+    fn poll_counts(detail: &PollDetail) -> Vec<i64> {
+        detail.options.iter().map(|option| option.count).collect()
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_single_choice_vote_replaces_previous_choice_and_can_be_retracted() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let outsider_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, member_id]).await;
+        let (message_service, _) = build_pg_services(pool.clone()).await;
+        let poll_id = create_pg_poll(&message_service, owner_id, conversation_id, false).await;
+
+        let detail = message_service.vote_poll(poll_id, member_id, 0).await.unwrap();
+        assert_eq!(poll_counts(&detail), vec![1, 0, 0]);
+
+        // Đổi lựa chọn thay vì cộng thêm phiếu
+        let detail = message_service.vote_poll(poll_id, member_id, 1).await.unwrap();
+        assert_eq!(poll_counts(&detail), vec![0, 1, 0]);
+        assert_eq!(detail.options[1].user_ids, vec![member_id]);
+
+        // Bầu lại đúng phương án đang chọn không nhân đôi phiếu
+        let detail = message_service.vote_poll(poll_id, member_id, 1).await.unwrap();
+        assert_eq!(poll_counts(&detail), vec![0, 1, 0]);
+
+        let detail = message_service.vote_poll(poll_id, owner_id, 1).await.unwrap();
+        assert_eq!(poll_counts(&detail), vec![0, 2, 0]);
+
+        for index in [-1, 3] {
+            assert!(matches!(
+                message_service.vote_poll(poll_id, member_id, index).await,
+                Err(error::SystemError::BadRequest(_))
+            ));
+        }
+        assert!(matches!(
+            message_service.vote_poll(poll_id, outsider_id, 0).await,
+            Err(error::SystemError::Forbidden(_))
+        ));
+
+        let detail = message_service
+            .retract_poll_vote(poll_id, member_id, 1)
+            .await
+            .unwrap();
+        assert_eq!(poll_counts(&detail), vec![0, 1, 0]);
+        assert_eq!(detail.options[1].user_ids, vec![owner_id]);
+        assert!(matches!(
+            message_service.retract_poll_vote(poll_id, member_id, 1).await,
+            Err(error::SystemError::NotFound(_))
+        ));
+
+        let detail = message_service.get_poll(poll_id, member_id).await.unwrap();
+        assert_eq!(poll_counts(&detail), vec![0, 1, 0]);
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, member_id, outsider_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_multiple_choice_vote_keeps_every_option() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, member_id]).await;
+        let (message_service, _) = build_pg_services(pool.clone()).await;
+        let poll_id = create_pg_poll(&message_service, owner_id, conversation_id, true).await;
+
+        message_service.vote_poll(poll_id, member_id, 0).await.unwrap();
+        message_service.vote_poll(poll_id, member_id, 2).await.unwrap();
+        let detail = message_service.vote_poll(poll_id, owner_id, 2).await.unwrap();
+        assert_eq!(poll_counts(&detail), vec![1, 0, 2]);
+
+        let detail = message_service
+            .retract_poll_vote(poll_id, member_id, 0)
+            .await
+            .unwrap();
+        assert_eq!(poll_counts(&detail), vec![0, 0, 2]);
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, member_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_closed_poll_rejects_votes_and_only_creator_or_admin_can_close() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let creator_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, creator_id, member_id]).await;
+        let (message_service, _) = build_pg_services(pool.clone()).await;
+
+        let poll_id = create_pg_poll(&message_service, creator_id, conversation_id, false).await;
+        message_service.vote_poll(poll_id, member_id, 0).await.unwrap();
+
+        assert!(matches!(
+            message_service.close_poll(poll_id, member_id).await,
+            Err(error::SystemError::Forbidden(_))
+        ));
+
+        // Owner đóng được poll của người khác
+        let detail = message_service.close_poll(poll_id, owner_id).await.unwrap();
+        assert!(detail.closed);
+        assert_eq!(poll_counts(&detail), vec![1, 0, 0]);
+
+        assert!(matches!(
+            message_service.vote_poll(poll_id, member_id, 1).await,
+            Err(error::SystemError::BadRequest(_))
+        ));
+        assert!(matches!(
+            message_service.retract_poll_vote(poll_id, member_id, 0).await,
+            Err(error::SystemError::BadRequest(_))
+        ));
+        assert!(matches!(
+            message_service.close_poll(poll_id, creator_id).await,
+            Err(error::SystemError::BadRequest(_))
+        ));
+
+        // Người tạo tự đóng được poll của mình
+        let own_poll_id = create_pg_poll(&message_service, creator_id, conversation_id, false).await;
+        let detail = message_service.close_poll(own_poll_id, creator_id).await.unwrap();
+        assert!(detail.closed);
+
+        let detail = message_service.get_poll(poll_id, member_id).await.unwrap();
+        assert!(detail.closed);
+        assert_eq!(poll_counts(&detail), vec![1, 0, 0]);
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, creator_id, member_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_concurrent_single_choice_votes_leave_one_vote_per_user() {
+        let pool = connect_database().await.expect("should connect database");
+        let owner_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[owner_id, member_id]).await;
+        let (message_service, _) = build_pg_services(pool.clone()).await;
+        let poll_id = create_pg_poll(&message_service, owner_id, conversation_id, false).await;
+
+        let handles: Vec<_> = (0..12)
+            .map(|i| {
+                let service = message_service.clone();
+                tokio::spawn(async move { service.vote_poll(poll_id, member_id, i % 3).await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap().expect("vote should succeed");
+        }
+
+        let detail = message_service.get_poll(poll_id, member_id).await.unwrap();
+        assert_eq!(poll_counts(&detail).iter().sum::<i64>(), 1);
+
+        cleanup_pg(&pool, conversation_id, &[owner_id, member_id]).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_contact_card_only_shares_self_or_friends_without_private_fields() {
+        let pool = connect_database().await.expect("should connect database");
+        let sender_id = seed_pg_user(&pool).await;
+        let member_id = seed_pg_user(&pool).await;
+        let friend_id = seed_pg_user(&pool).await;
+        let stranger_id = seed_pg_user(&pool).await;
+        let conversation_id = seed_pg_group(&pool, &[sender_id, member_id]).await;
+        sqlx::query("INSERT INTO friends (user_a, user_b) VALUES (LEAST($1, $2), GREATEST($1, $2))")
+            .bind(sender_id)
+            .bind(friend_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (message_service, conversation_service) = build_pg_services(pool.clone()).await;
+
+        let share = |contact_user_id: Uuid| {
+            message_service.send_group_message_payload(
+                sender_id,
+                conversation_id,
+                SendGroupMessagePayload {
+                    contact_user_id: Some(contact_user_id),
+                    ..Default::default()
+                },
+            )
+        };
+
+        for contact_user_id in [sender_id, friend_id] {
+            let message = share(contact_user_id).await.expect("should share contact");
+            let card = message.contact.clone().expect("contact card should be attached");
+            assert_eq!(card.id, contact_user_id);
+
+            let json = serde_json::to_value(&message).unwrap();
+            let keys: Vec<&str> = json["contact"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(String::as_str)
+                .collect();
+            assert_eq!(keys.len(), 4);
+            for private in ["email", "phone", "email_verified", "two_factor_enabled", "bio"] {
+                assert!(!keys.contains(&private), "contact card leaks {private}");
+            }
+        }
+
+        // Người lạ và id không tồn tại bị từ chối như nhau
+        for contact_user_id in [stranger_id, Uuid::now_v7()] {
+            assert!(matches!(
+                share(contact_user_id).await,
+                Err(error::SystemError::Forbidden(_))
+            ));
+        }
+
+        // Lịch sử vẫn hiện danh thiếp đã gửi kể cả khi hết là bạn bè
+        sqlx::query("UPDATE friends SET deleted_at = NOW() WHERE user_a = LEAST($1, $2) AND user_b = GREATEST($1, $2)")
+            .bind(sender_id)
+            .bind(friend_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(matches!(
+            share(friend_id).await,
+            Err(error::SystemError::Forbidden(_))
+        ));
+        let history = conversation_service
+            .get_message(
+                conversation_id,
+                MessageHistoryRequest {
+                    limit: 10,
+                    before: None,
+                    after: None,
+                    around: None,
+                },
+            )
+            .await
+            .expect("should load history");
+        let cards: Vec<Uuid> = history
+            .messages
+            .iter()
+            .filter_map(|m| m.message.contact.as_ref().map(|card| card.id))
+            .collect();
+        assert_eq!(cards.len(), 2);
+        assert!(cards.contains(&friend_id));
+
+        cleanup_pg(&pool, conversation_id, &[sender_id, member_id, friend_id, stranger_id]).await;
     }
 }
@@ -5,13 +5,24 @@ mod tests {
 
     use chrono::Utc;
     use uuid::Uuid;
+    use validator::Validate;
 
+    use crate::api::cursor::Cursor;
     use crate::api::error;
     use crate::configs::CacheStore;
-    use crate::modules::user::model::{SignInModel, UpdateUser, UpdateUserModel};
+    use crate::configs::mailer::FileMailer;
+    use crate::modules::user::model::{
+        DeviceInfo, NewSecurityEvent, NewUserSession, NewUserToken, SignInModel, SignInOutcome,
+        SignUpModel, UpdateUser, UpdateUserModel,
+    };
     use crate::modules::user::repository::UserRepository;
-    use crate::modules::user::schema::{UserEntity, UserRole};
+    use crate::modules::user::schema::{
+        RecoveryCodeEntity, SecurityEventType, UserEntity, UserRole, UserSessionEntity,
+        UserTokenEntity, UserTokenPurpose,
+    };
+    use crate::modules::user::totp;
     use crate::modules::user::service::UserService;
+    use crate::modules::websocket::server::WebSocketServer;
 
     #[derive(Clone, Default)]
     struct InMemoryCache {
@@ -24,9 +35,11 @@ mod tests {
         where
             T: serde::de::DeserializeOwned + Send,
         {
-            let store = self.store.lock().expect("cache mutex poisoned");
-            match store.get(key) {
-                Some(raw) => Ok(Some(serde_json::from_slice(raw)?)),
+            let raw = self.store.lock().expect("cache mutex poisoned").get(key).cloned();
+            // Nhường lượt như lúc chờ phản hồi Redis để các task đồng thời xen kẽ nhau
+            tokio::task::yield_now().await;
+            match raw {
+                Some(raw) => Ok(Some(serde_json::from_slice(&raw)?)),
                 None => Ok(None),
             }
         }
@@ -50,6 +63,16 @@ mod tests {
             store.remove(key);
             Ok(())
         }
+
+        async fn incr(&self, key: &str, _expiration: usize) -> Result<i64, error::SystemError> {
+            let mut store = self.store.lock().expect("cache mutex poisoned");
+            let count = match store.get(key) {
+                Some(raw) => serde_json::from_slice::<i64>(raw)? + 1,
+                None => 1,
+            };
+            store.insert(key.to_string(), serde_json::to_vec(&count)?);
+            Ok(count)
+        }
     }
 
     #[derive(Clone, Default)]
@@ -59,6 +82,37 @@ mod tests {
         search_result: Arc<Mutex<Vec<UserEntity>>>,
         update_result: Arc<Mutex<Option<UserEntity>>>,
         last_search_limit: Arc<Mutex<Option<i32>>>,
+        sessions: Arc<Mutex<Vec<UserSessionEntity>>>,
+        security_events: Arc<Mutex<Vec<SecurityEventType>>>,
+        /// (token_hash, token)
+        tokens: Arc<Mutex<Vec<(String, UserTokenEntity)>>>,
+        /// (id, code_hash, used)
+        recovery_codes: Arc<Mutex<Vec<(Uuid, String, bool)>>>,
+    }
+
+    impl MockUserRepo {
+        fn with_user(user: UserEntity) -> Self {
+            MockUserRepo {
+                users_by_id: Arc::new(Mutex::new(HashMap::from([(user.id, user.clone())]))),
+                users_by_username: Arc::new(Mutex::new(HashMap::from([(
+                    user.username.clone(),
+                    user,
+                )]))),
+                ..Default::default()
+            }
+        }
+
+        fn modify_user(&self, id: &Uuid, mut f: impl FnMut(&mut UserEntity)) -> bool {
+            let mut by_id = self.users_by_id.lock().expect("repo mutex poisoned");
+            let Some(user) = by_id.get_mut(id) else {
+                return false;
+            };
+            f(user);
+
+            let mut by_username = self.users_by_username.lock().expect("repo mutex poisoned");
+            by_username.insert(user.username.clone(), user.clone());
+            true
+        }
     }
 
     #[async_trait::async_trait]
@@ -95,6 +149,7 @@ mod tests {
         async fn search_users(
             &self,
             _query: &str,
+            _cursor: Option<&Cursor>,
             limit: i32,
         ) -> Result<Vec<UserEntity>, error::SystemError> {
             let mut last_limit = self
@@ -105,6 +160,344 @@ mod tests {
 
             Ok(self.search_result.lock().expect("repo mutex poisoned").clone())
         }
+
+        async fn find_by_email(&self, email: &str) -> Result<Option<UserEntity>, error::SystemError> {
+            let users = self.users_by_id.lock().expect("repo mutex poisoned");
+            Ok(users.values().find(|u| u.email.eq_ignore_ascii_case(email)).cloned())
+        }
+
+        async fn update_password(
+            &self,
+            user_id: &Uuid,
+            hash_password: &str,
+        ) -> Result<bool, error::SystemError> {
+            Ok(self.modify_user(user_id, |u| u.hash_password = hash_password.to_string()))
+        }
+
+        async fn update_email(&self, user_id: &Uuid, email: &str) -> Result<bool, error::SystemError> {
+            let taken = self
+                .users_by_id
+                .lock()
+                .expect("repo mutex poisoned")
+                .values()
+                .any(|u| u.id != *user_id && u.email.eq_ignore_ascii_case(email));
+            if taken {
+                return Err(error::SystemError::Conflict(None));
+            }
+
+            let mut tokens = self.tokens.lock().expect("repo mutex poisoned");
+            for (_, token) in tokens.iter_mut() {
+                if token.user_id == *user_id
+                    && token.purpose == UserTokenPurpose::PasswordReset
+                    && token.used_at.is_none()
+                {
+                    token.used_at = Some(Utc::now());
+                }
+            }
+
+            Ok(self.modify_user(user_id, |u| {
+                u.email = email.to_string();
+                u.email_verified_at = Some(Utc::now());
+            }))
+        }
+
+        async fn mark_email_verified(
+            &self,
+            user_id: &Uuid,
+            email: &str,
+        ) -> Result<bool, error::SystemError> {
+            let matches = self
+                .users_by_id
+                .lock()
+                .expect("repo mutex poisoned")
+                .get(user_id)
+                .is_some_and(|u| u.email.eq_ignore_ascii_case(email));
+
+            Ok(matches && self.modify_user(user_id, |u| u.email_verified_at = Some(Utc::now())))
+        }
+
+        async fn create_user_token(&self, token: &NewUserToken) -> Result<(), error::SystemError> {
+            let mut tokens = self.tokens.lock().expect("repo mutex poisoned");
+            for (_, existing) in tokens.iter_mut() {
+                if existing.user_id == token.user_id
+                    && existing.purpose == token.purpose
+                    && existing.used_at.is_none()
+                {
+                    existing.used_at = Some(Utc::now());
+                }
+            }
+
+            tokens.push((
+                token.token_hash.clone(),
+                UserTokenEntity {
+                    id: Uuid::now_v7(),
+                    user_id: token.user_id,
+                    purpose: token.purpose,
+                    email: token.email.clone(),
+                    expires_at: token.expires_at,
+                    used_at: None,
+                    created_at: Utc::now(),
+                },
+            ));
+            Ok(())
+        }
+
+        async fn consume_user_token(
+            &self,
+            token_hash: &str,
+            purpose: UserTokenPurpose,
+        ) -> Result<Option<UserTokenEntity>, error::SystemError> {
+            let mut tokens = self.tokens.lock().expect("repo mutex poisoned");
+            Ok(tokens
+                .iter_mut()
+                .find(|(hash, t)| {
+                    hash == token_hash
+                        && t.purpose == purpose
+                        && t.used_at.is_none()
+                        && t.expires_at > Utc::now()
+                })
+                .map(|(_, t)| {
+                    t.used_at = Some(Utc::now());
+                    t.clone()
+                }))
+        }
+
+        async fn set_pending_totp_secret(
+            &self,
+            user_id: &Uuid,
+            secret: &str,
+        ) -> Result<bool, error::SystemError> {
+            let mut updated = false;
+            self.modify_user(user_id, |u| {
+                if u.totp_enabled_at.is_none() {
+                    u.totp_secret = Some(secret.to_string());
+                    u.totp_last_step = None;
+                    updated = true;
+                }
+            });
+            Ok(updated)
+        }
+
+        async fn enable_totp(
+            &self,
+            user_id: &Uuid,
+            recovery_code_hashes: &[String],
+        ) -> Result<bool, error::SystemError> {
+            let mut updated = false;
+            self.modify_user(user_id, |u| {
+                if u.totp_secret.is_some() && u.totp_enabled_at.is_none() {
+                    u.totp_enabled_at = Some(Utc::now());
+                    updated = true;
+                }
+            });
+
+            if updated {
+                *self.recovery_codes.lock().expect("repo mutex poisoned") = recovery_code_hashes
+                    .iter()
+                    .map(|hash| (Uuid::now_v7(), hash.clone(), false))
+                    .collect();
+            }
+            Ok(updated)
+        }
+
+        async fn disable_totp(&self, user_id: &Uuid) -> Result<bool, error::SystemError> {
+            let mut updated = false;
+            self.modify_user(user_id, |u| {
+                updated = u.totp_enabled_at.is_some();
+                u.totp_secret = None;
+                u.totp_enabled_at = None;
+                u.totp_last_step = None;
+            });
+            self.recovery_codes.lock().expect("repo mutex poisoned").clear();
+            Ok(updated)
+        }
+
+        async fn record_totp_step(
+            &self,
+            user_id: &Uuid,
+            step: i64,
+        ) -> Result<bool, error::SystemError> {
+            let mut updated = false;
+            self.modify_user(user_id, |u| {
+                if u.totp_last_step.is_none_or(|last| last < step) {
+                    u.totp_last_step = Some(step);
+                    updated = true;
+                }
+            });
+            Ok(updated)
+        }
+
+        async fn find_unused_recovery_codes(
+            &self,
+            _user_id: &Uuid,
+        ) -> Result<Vec<RecoveryCodeEntity>, error::SystemError> {
+            let codes = self.recovery_codes.lock().expect("repo mutex poisoned");
+            Ok(codes
+                .iter()
+                .filter(|(_, _, used)| !used)
+                .map(|(id, code_hash, _)| RecoveryCodeEntity {
+                    id: *id,
+                    code_hash: code_hash.clone(),
+                })
+                .collect())
+        }
+
+        async fn use_recovery_code(&self, code_id: &Uuid) -> Result<bool, error::SystemError> {
+            let mut codes = self.recovery_codes.lock().expect("repo mutex poisoned");
+            Ok(codes
+                .iter_mut()
+                .find(|(id, _, used)| id == code_id && !used)
+                .map(|code| code.2 = true)
+                .is_some())
+        }
+
+        async fn create_session(&self, session: &NewUserSession) -> Result<(), error::SystemError> {
+            let now = Utc::now();
+            self.sessions.lock().expect("repo mutex poisoned").push(UserSessionEntity {
+                id: session.id,
+                user_id: session.user_id,
+                current_jti: session.current_jti,
+                previous_jti: None,
+                rotated_at: None,
+                user_agent: session.device.user_agent.clone(),
+                ip_address: session.device.ip_address.clone(),
+                created_at: now,
+                last_used_at: now,
+                expires_at: session.expires_at,
+                revoked_at: None,
+            });
+            Ok(())
+        }
+
+        async fn rotate_session(
+            &self,
+            session_id: &Uuid,
+            user_id: &Uuid,
+            old_jti: &Uuid,
+            new_jti: &Uuid,
+            device: &DeviceInfo,
+            expires_at: chrono::DateTime<Utc>,
+        ) -> Result<bool, error::SystemError> {
+            let mut sessions = self.sessions.lock().expect("repo mutex poisoned");
+            let Some(session) = sessions.iter_mut().find(|s| {
+                s.id == *session_id
+                    && s.user_id == *user_id
+                    && s.current_jti == *old_jti
+                    && s.revoked_at.is_none()
+            }) else {
+                return Ok(false);
+            };
+
+            session.previous_jti = Some(session.current_jti);
+            session.rotated_at = Some(Utc::now());
+            session.current_jti = *new_jti;
+            if device.user_agent.is_some() {
+                session.user_agent = device.user_agent.clone();
+            }
+            if device.ip_address.is_some() {
+                session.ip_address = device.ip_address.clone();
+            }
+            session.expires_at = expires_at;
+            session.last_used_at = Utc::now();
+            Ok(true)
+        }
+
+        async fn find_active_sessions(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Vec<UserSessionEntity>, error::SystemError> {
+            let sessions = self.sessions.lock().expect("repo mutex poisoned");
+            Ok(sessions
+                .iter()
+                .filter(|s| s.user_id == *user_id && s.revoked_at.is_none())
+                .cloned()
+                .collect())
+        }
+
+        async fn revoke_session(
+            &self,
+            user_id: &Uuid,
+            session_id: &Uuid,
+        ) -> Result<Option<UserSessionEntity>, error::SystemError> {
+            let mut sessions = self.sessions.lock().expect("repo mutex poisoned");
+            Ok(sessions
+                .iter_mut()
+                .find(|s| s.id == *session_id && s.user_id == *user_id && s.revoked_at.is_none())
+                .map(|s| {
+                    s.revoked_at = Some(Utc::now());
+                    s.clone()
+                }))
+        }
+
+        async fn find_recently_rotated_session(
+            &self,
+            session_id: &Uuid,
+            user_id: &Uuid,
+            presented_jti: &Uuid,
+            rotated_after: chrono::DateTime<Utc>,
+        ) -> Result<Option<UserSessionEntity>, error::SystemError> {
+            let sessions = self.sessions.lock().expect("repo mutex poisoned");
+            Ok(sessions
+                .iter()
+                .find(|s| {
+                    s.id == *session_id
+                        && s.user_id == *user_id
+                        && s.previous_jti == Some(*presented_jti)
+                        && s.rotated_at.is_some_and(|at| at > rotated_after)
+                        && s.revoked_at.is_none()
+                })
+                .cloned())
+        }
+
+        async fn revoke_reused_session(
+            &self,
+            session_id: &Uuid,
+            user_id: &Uuid,
+            presented_jti: &Uuid,
+        ) -> Result<Option<UserSessionEntity>, error::SystemError> {
+            let mut sessions = self.sessions.lock().expect("repo mutex poisoned");
+            Ok(sessions
+                .iter_mut()
+                .find(|s| {
+                    s.id == *session_id
+                        && s.user_id == *user_id
+                        && s.revoked_at.is_none()
+                        && s.current_jti != *presented_jti
+                })
+                .map(|s| {
+                    s.revoked_at = Some(Utc::now());
+                    s.clone()
+                }))
+        }
+
+        async fn create_security_event(
+            &self,
+            event: &NewSecurityEvent,
+        ) -> Result<(), error::SystemError> {
+            self.security_events
+                .lock()
+                .expect("repo mutex poisoned")
+                .push(event.event_type);
+            Ok(())
+        }
+
+        async fn revoke_sessions(
+            &self,
+            user_id: &Uuid,
+            except: Option<&Uuid>,
+        ) -> Result<Vec<UserSessionEntity>, error::SystemError> {
+            let mut sessions = self.sessions.lock().expect("repo mutex poisoned");
+            Ok(sessions
+                .iter_mut()
+                .filter(|s| {
+                    s.user_id == *user_id && s.revoked_at.is_none() && Some(&s.id) != except
+                })
+                .map(|s| {
+                    s.revoked_at = Some(Utc::now());
+                    s.clone()
+                })
+                .collect())
+        }
     }
 
     fn build_user(id: Uuid, username: &str, hash_password: &str) -> UserEntity {
@@ -121,11 +514,43 @@ mod tests {
             deleted_at: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            email_verified_at: None,
+            totp_secret: None,
+            totp_enabled_at: None,
+            totp_last_step: None,
         }
     }
 
     async fn build_service(repo: MockUserRepo, cache: InMemoryCache) -> UserService<MockUserRepo, InMemoryCache> {
-        UserService::with_dependencies(Arc::new(repo), Arc::new(cache))
+        UserService::with_dependencies(
+            Arc::new(repo),
+            Arc::new(cache),
+            Arc::new(WebSocketServer::new()),
+            Arc::new(FileMailer::new(std::env::temp_dir().join("appchat-test-mails"))),
+        )
+    }
+
+    fn tokens(outcome: SignInOutcome) -> (String, String) {
+        match outcome {
+            SignInOutcome::Authenticated {
+                access_token,
+                refresh_token,
+            } => (access_token, refresh_token),
+            SignInOutcome::TwoFactorRequired { .. } => panic!("unexpected 2FA challenge"),
+        }
+    }
+
+    /// Đọc token trong email duy nhất đã ghi ra thư mục `dir`
+    fn read_mailed_token(dir: &std::path::Path) -> String {
+        let mut entries: Vec<_> = std::fs::read_dir(dir)
+            .expect("mail dir must exist")
+            .map(|entry| entry.expect("readable entry").path())
+            .collect();
+        assert_eq!(entries.len(), 1, "exactly one mail expected");
+
+        let content = std::fs::read_to_string(entries.remove(0)).expect("readable mail");
+        let (_, rest) = content.split_once("token=").expect("mail must contain a token");
+        rest.split_whitespace().next().expect("token value").to_string()
     }
 
     #[tokio::test]
@@ -136,7 +561,7 @@ mod tests {
             .sign_in(SignInModel {
                 username: "missing_user".to_string(),
                 password: "secret123".to_string(),
-            })
+            }, DeviceInfo::default())
             .await;
 
         assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
@@ -165,7 +590,7 @@ mod tests {
             .sign_in(SignInModel {
                 username: "alice".to_string(),
                 password: "wrong_password".to_string(),
-            })
+            }, DeviceInfo::default())
             .await;
 
         assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
@@ -196,8 +621,9 @@ mod tests {
             .sign_in(SignInModel {
                 username: "bob".to_string(),
                 password: "correct_password".to_string(),
-            })
+            }, DeviceInfo::default())
             .await
+            .map(tokens)
             .expect("sign in should succeed");
 
         let keys_after_sign_in: Vec<String> = {
@@ -208,7 +634,7 @@ mod tests {
         let old_key = keys_after_sign_in[0].clone();
 
         let (_new_access, _new_refresh) = service
-            .refresh(Some(old_refresh_token))
+            .refresh(Some(old_refresh_token), DeviceInfo::default())
             .await
             .expect("refresh should succeed");
 
@@ -221,6 +647,736 @@ mod tests {
         assert_ne!(keys_after_refresh[0], old_key);
     }
 
+    #[tokio::test]
+    async fn test_revoke_session_invalidates_refresh_token() {
+        let user_id = Uuid::now_v7();
+        let valid_hash = crate::utils::hash_password("correct_password".to_string())
+            .await
+            .expect("must hash password for test");
+
+        let user = build_user(user_id, "dave", &valid_hash);
+        let repo = MockUserRepo {
+            users_by_id: Arc::new(Mutex::new(HashMap::from([(user_id, user.clone())]))),
+            users_by_username: Arc::new(Mutex::new(HashMap::from([("dave".to_string(), user)]))),
+            ..Default::default()
+        };
+
+        let cache = InMemoryCache::default();
+        let cache_ref = cache.clone();
+        let service = build_service(repo, cache).await;
+
+        let sign_in = || SignInModel {
+            username: "dave".to_string(),
+            password: "correct_password".to_string(),
+        };
+        let laptop = DeviceInfo {
+            user_agent: Some("Firefox".to_string()),
+            ip_address: Some("10.0.0.1".to_string()),
+        };
+        let (_, laptop_refresh) = service
+            .sign_in(sign_in(), laptop)
+            .await
+            .map(tokens)
+            .expect("sign in");
+        let (_, phone_refresh) = service
+            .sign_in(sign_in(), DeviceInfo::default())
+            .await
+            .map(tokens)
+            .expect("sign in");
+
+        let sessions = service.list_sessions(user_id, None).await.expect("list sessions");
+        assert_eq!(sessions.len(), 2);
+        let laptop_session = sessions
+            .iter()
+            .find(|s| s.user_agent.as_deref() == Some("Firefox"))
+            .expect("laptop session must be listed");
+
+        service
+            .revoke_session(user_id, laptop_session.id)
+            .await
+            .expect("revoke should succeed");
+
+        let result = service.refresh(Some(laptop_refresh), DeviceInfo::default()).await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+        assert_eq!(cache_ref.store.lock().expect("cache mutex poisoned").len(), 1);
+
+        service
+            .refresh(Some(phone_refresh), DeviceInfo::default())
+            .await
+            .expect("other devices stay signed in");
+
+        let result = service.revoke_session(user_id, laptop_session.id).await;
+        assert!(matches!(result, Err(error::SystemError::NotFound(_))));
+    }
+
+    fn expire_rotation_grace(repo: &MockUserRepo) {
+        for session in repo.sessions.lock().expect("repo mutex poisoned").iter_mut() {
+            session.rotated_at = session.rotated_at.map(|at| at - chrono::Duration::minutes(1));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refresh_with_previous_token_is_not_treated_as_reuse() {
+        let user_id = Uuid::now_v7();
+        let valid_hash = crate::utils::hash_password("correct_password".to_string())
+            .await
+            .expect("must hash password for test");
+        let repo = MockUserRepo::with_user(build_user(user_id, "tabs", &valid_hash));
+        let repo_ref = repo.clone();
+        let service = build_service(repo, InMemoryCache::default()).await;
+
+        let (_, shared_refresh) = service
+            .sign_in(SignInModel {
+                username: "tabs".to_string(),
+                password: "correct_password".to_string(),
+            }, DeviceInfo::default())
+            .await
+            .map(tokens)
+            .expect("sign in should succeed");
+
+        // Hai tab gửi cùng một cookie: tab đến sau nhận lại token hiện tại của phiên
+        let (_, first_tab) = service
+            .refresh(Some(shared_refresh.clone()), DeviceInfo::default())
+            .await
+            .expect("first tab should refresh");
+        let (_, second_tab) = service
+            .refresh(Some(shared_refresh.clone()), DeviceInfo::default())
+            .await
+            .expect("second tab should refresh within grace window");
+
+        let jti_of = |token: &str| {
+            crate::utils::Claims::decode(token, crate::ENV.jwt_secret.as_ref())
+                .expect("token must decode")
+                .jti
+        };
+        assert_eq!(jti_of(&first_tab), jti_of(&second_tab));
+        assert_eq!(
+            *repo_ref.security_events.lock().expect("repo mutex poisoned"),
+            vec![SecurityEventType::RefreshTokenGraceUse]
+        );
+
+        service
+            .refresh(Some(second_tab), DeviceInfo::default())
+            .await
+            .expect("session stays usable");
+
+        // Token cũ hơn một vòng không còn được ân hạn
+        let result = service.refresh(Some(shared_refresh), DeviceInfo::default()).await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+        assert_eq!(
+            *repo_ref.security_events.lock().expect("repo mutex poisoned"),
+            vec![
+                SecurityEventType::RefreshTokenGraceUse,
+                SecurityEventType::RefreshTokenReuse
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grace_refresh_from_another_device_is_treated_as_reuse() {
+        let user_id = Uuid::now_v7();
+        let valid_hash = crate::utils::hash_password("correct_password".to_string())
+            .await
+            .expect("must hash password for test");
+        let repo = MockUserRepo::with_user(build_user(user_id, "thief", &valid_hash));
+        let repo_ref = repo.clone();
+        let service = build_service(repo, InMemoryCache::default()).await;
+        let device = |ip: &str| DeviceInfo {
+            user_agent: Some("Firefox".to_string()),
+            ip_address: Some(ip.to_string()),
+        };
+
+        let (_, stolen_refresh) = service
+            .sign_in(SignInModel {
+                username: "thief".to_string(),
+                password: "correct_password".to_string(),
+            }, device("10.0.0.1"))
+            .await
+            .map(tokens)
+            .expect("sign in should succeed");
+        let (_, rotated_refresh) = service
+            .refresh(Some(stolen_refresh.clone()), device("10.0.0.1"))
+            .await
+            .expect("owner should refresh");
+
+        // Replay ngay trong thời gian ân hạn nhưng từ IP khác: thu hồi cả phiên
+        let result = service.refresh(Some(stolen_refresh), device("203.0.113.9")).await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+        assert_eq!(
+            *repo_ref.security_events.lock().expect("repo mutex poisoned"),
+            vec![SecurityEventType::RefreshTokenReuse]
+        );
+
+        let result = service.refresh(Some(rotated_refresh), device("10.0.0.1")).await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_reused_refresh_token_revokes_whole_family() {
+        let user_id = Uuid::now_v7();
+        let valid_hash = crate::utils::hash_password("correct_password".to_string())
+            .await
+            .expect("must hash password for test");
+
+        let user = build_user(user_id, "erin", &valid_hash);
+        let repo = MockUserRepo {
+            users_by_id: Arc::new(Mutex::new(HashMap::from([(user_id, user.clone())]))),
+            users_by_username: Arc::new(Mutex::new(HashMap::from([("erin".to_string(), user)]))),
+            ..Default::default()
+        };
+        let repo_ref = repo.clone();
+
+        let cache = InMemoryCache::default();
+        let cache_ref = cache.clone();
+        let service = build_service(repo, cache).await;
+
+        let (_, stolen_refresh) = service
+            .sign_in(SignInModel {
+                username: "erin".to_string(),
+                password: "correct_password".to_string(),
+            }, DeviceInfo::default())
+            .await
+            .map(tokens)
+            .expect("sign in should succeed");
+
+        let (_, rotated_refresh) = service
+            .refresh(Some(stolen_refresh.clone()), DeviceInfo::default())
+            .await
+            .expect("first refresh should succeed");
+
+        // Token đã xoay vòng bị dùng lại sau thời gian ân hạn -> thu hồi cả family
+        expire_rotation_grace(&repo_ref);
+        let result = service.refresh(Some(stolen_refresh), DeviceInfo::default()).await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+
+        assert_eq!(
+            *repo_ref.security_events.lock().expect("repo mutex poisoned"),
+            vec![SecurityEventType::RefreshTokenReuse]
+        );
+        assert!(cache_ref.store.lock().expect("cache mutex poisoned").is_empty());
+
+        // Token mới nhất của family cũng không còn dùng được
+        let result = service.refresh(Some(rotated_refresh), DeviceInfo::default()).await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+        assert_eq!(repo_ref.security_events.lock().expect("repo mutex poisoned").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_password_reset_token_is_single_use_and_signs_out_everywhere() {
+        let user_id = Uuid::now_v7();
+        let old_hash = crate::utils::hash_password("old_password".to_string())
+            .await
+            .expect("must hash password for test");
+        let repo = MockUserRepo::with_user(build_user(user_id, "frank", &old_hash));
+        let repo_ref = repo.clone();
+
+        let mail_dir = std::env::temp_dir().join(format!("appchat-mails-{}", Uuid::now_v7()));
+        let service = UserService::with_dependencies(
+            Arc::new(repo),
+            Arc::new(InMemoryCache::default()),
+            Arc::new(WebSocketServer::new()),
+            Arc::new(FileMailer::new(&mail_dir)),
+        );
+
+        let sign_in = |password: &str| SignInModel {
+            username: "frank".to_string(),
+            password: password.to_string(),
+        };
+        service
+            .sign_in(sign_in("old_password"), DeviceInfo::default())
+            .await
+            .expect("sign in should succeed");
+
+        // Email không tồn tại: vẫn thành công, không gửi gì
+        service
+            .request_password_reset("nobody@appchat.local".to_string())
+            .await
+            .expect("mail task must not panic");
+        assert!(!mail_dir.exists());
+
+        service
+            .request_password_reset("FRANK@appchat.local".to_string())
+            .await
+            .expect("mail task must not panic");
+        let token = read_mailed_token(&mail_dir);
+
+        // Gửi lại ngay lập tức bị bỏ qua (cooldown)
+        service
+            .request_password_reset("frank@appchat.local".to_string())
+            .await
+            .expect("mail task must not panic");
+        assert_eq!(std::fs::read_dir(&mail_dir).unwrap().count(), 1);
+
+        service
+            .reset_password(&token, "new_password".to_string(), DeviceInfo::default())
+            .await
+            .expect("reset should succeed");
+
+        let result = service
+            .reset_password(&token, "another_password".to_string(), DeviceInfo::default())
+            .await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+
+        assert!(service.list_sessions(user_id, None).await.unwrap().is_empty());
+        assert_eq!(
+            *repo_ref.security_events.lock().expect("repo mutex poisoned"),
+            vec![SecurityEventType::PasswordReset]
+        );
+
+        let result = service.sign_in(sign_in("old_password"), DeviceInfo::default()).await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+        service
+            .sign_in(sign_in("new_password"), DeviceInfo::default())
+            .await
+            .expect("new password must work");
+
+        std::fs::remove_dir_all(&mail_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_verify_email_flags_user_as_verified() {
+        let user_id = Uuid::now_v7();
+        let repo = MockUserRepo::with_user(build_user(user_id, "grace", "hash"));
+
+        let mail_dir = std::env::temp_dir().join(format!("appchat-mails-{}", Uuid::now_v7()));
+        let service = UserService::with_dependencies(
+            Arc::new(repo),
+            Arc::new(InMemoryCache::default()),
+            Arc::new(WebSocketServer::new()),
+            Arc::new(FileMailer::new(&mail_dir)),
+        );
+
+        assert!(!service.get_by_id(user_id).await.unwrap().email_verified);
+
+        service
+            .resend_verification_email("grace@appchat.local".to_string())
+            .await
+            .expect("mail task must not panic");
+        let token = read_mailed_token(&mail_dir);
+
+        let result = service.verify_email("not-a-real-token").await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+
+        service.verify_email(&token).await.expect("verify should succeed");
+        assert!(service.get_by_id(user_id).await.unwrap().email_verified);
+
+        let result = service.verify_email(&token).await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+
+        std::fs::remove_dir_all(&mail_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_mail_requests_send_a_single_mail() {
+        let user_id = Uuid::now_v7();
+        let repo = MockUserRepo::with_user(build_user(user_id, "hana", "hash"));
+
+        let mail_dir = std::env::temp_dir().join(format!("appchat-mails-{}", Uuid::now_v7()));
+        let service = UserService::with_dependencies(
+            Arc::new(repo),
+            Arc::new(InMemoryCache::default()),
+            Arc::new(WebSocketServer::new()),
+            Arc::new(FileMailer::new(&mail_dir)),
+        );
+
+        let tasks: Vec<_> = (0..5)
+            .map(|_| service.resend_verification_email("hana@appchat.local".to_string()))
+            .collect();
+        for task in tasks {
+            task.await.expect("mail task must not panic");
+        }
+
+        assert_eq!(std::fs::read_dir(&mail_dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&mail_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_change_password_keeps_current_session_only() {
+        let user_id = Uuid::now_v7();
+        let hash = crate::utils::hash_password("old_password".to_string())
+            .await
+            .expect("must hash password for test");
+        let repo = MockUserRepo::with_user(build_user(user_id, "ivy", &hash));
+        let repo_ref = repo.clone();
+        let service = build_service(repo, InMemoryCache::default()).await;
+
+        let sign_in = |password: &str| SignInModel {
+            username: "ivy".to_string(),
+            password: password.to_string(),
+        };
+        for _ in 0..2 {
+            service
+                .sign_in(sign_in("old_password"), DeviceInfo::default())
+                .await
+                .expect("sign in should succeed");
+        }
+        let sessions = service.list_sessions(user_id, None).await.unwrap();
+        let current_sid = sessions[0].id;
+
+        let result = service
+            .change_password(
+                user_id,
+                Some(current_sid),
+                "wrong_password".to_string(),
+                "new_password".to_string(),
+                DeviceInfo::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+        assert_eq!(service.list_sessions(user_id, None).await.unwrap().len(), 2);
+
+        service
+            .change_password(
+                user_id,
+                Some(current_sid),
+                "old_password".to_string(),
+                "new_password".to_string(),
+                DeviceInfo::default(),
+            )
+            .await
+            .expect("change password should succeed");
+
+        let remaining = service.list_sessions(user_id, None).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, current_sid);
+        assert_eq!(
+            *repo_ref.security_events.lock().expect("repo mutex poisoned"),
+            vec![SecurityEventType::PasswordChanged]
+        );
+
+        let result = service.sign_in(sign_in("old_password"), DeviceInfo::default()).await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+        service
+            .sign_in(sign_in("new_password"), DeviceInfo::default())
+            .await
+            .expect("new password must work");
+    }
+
+    #[tokio::test]
+    async fn test_email_change_requires_password_and_confirmation() {
+        let user_id = Uuid::now_v7();
+        let hash = crate::utils::hash_password("correct_password".to_string())
+            .await
+            .expect("must hash password for test");
+        let repo = MockUserRepo::with_user(build_user(user_id, "jack", &hash));
+        let other = build_user(Uuid::now_v7(), "kate", "hash");
+        repo.users_by_id
+            .lock()
+            .expect("repo mutex poisoned")
+            .insert(other.id, other);
+        let repo_ref = repo.clone();
+
+        let mail_dir = std::env::temp_dir().join(format!("appchat-mails-{}", Uuid::now_v7()));
+        let service = UserService::with_dependencies(
+            Arc::new(repo),
+            Arc::new(InMemoryCache::default()),
+            Arc::new(WebSocketServer::new()),
+            Arc::new(FileMailer::new(&mail_dir)),
+        );
+
+        // Không đổi email được qua cập nhật hồ sơ
+        let result = service
+            .update(
+                user_id,
+                UpdateUserModel {
+                    username: None,
+                    email: Some("jack.new@appchat.local".to_string()),
+                    display_name: None,
+                    avatar_url: None,
+                    bio: None,
+                    phone: None,
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+
+        let request = |email: &str, password: &str| {
+            service.request_email_change(user_id, email.to_string(), password.to_string())
+        };
+        let result = request("jack.new@appchat.local", "wrong_password").await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+        let result = request("KATE@appchat.local", "correct_password").await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+        assert!(!mail_dir.exists());
+
+        // Link đặt lại mật khẩu gửi tới email cũ trước khi đổi
+        service
+            .request_password_reset("jack@appchat.local".to_string())
+            .await
+            .expect("reset task must not panic");
+        let reset_token = read_mailed_token(&mail_dir);
+        std::fs::remove_dir_all(&mail_dir).ok();
+
+        request("jack.new@appchat.local", "correct_password")
+            .await
+            .expect("email change request should succeed");
+        let token = read_mailed_token(&mail_dir);
+        std::fs::remove_dir_all(&mail_dir).ok();
+
+        // Chưa xác nhận thì email vẫn giữ nguyên
+        assert_eq!(service.get_by_id(user_id).await.unwrap().email, "jack@appchat.local");
+
+        service
+            .confirm_email_change(&token, DeviceInfo::default())
+            .await
+            .expect("confirm should succeed");
+        let user = service.get_by_id(user_id).await.unwrap();
+        assert_eq!(user.email, "jack.new@appchat.local");
+        assert!(user.email_verified);
+        assert_eq!(
+            *repo_ref.security_events.lock().expect("repo mutex poisoned"),
+            vec![SecurityEventType::EmailChanged]
+        );
+
+        // Email cũ nhận được thông báo, token chỉ dùng một lần
+        let notice = std::fs::read_dir(&mail_dir)
+            .expect("notice must be mailed")
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(notice.len(), 1);
+        assert!(notice[0].contains("jack@appchat.local"));
+
+        let result = service.confirm_email_change(&token, DeviceInfo::default()).await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+
+        // Link gửi tới email cũ không còn đặt lại được mật khẩu
+        let result = service
+            .reset_password(&reset_token, "new_password".to_string(), DeviceInfo::default())
+            .await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+
+        std::fs::remove_dir_all(&mail_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_two_factor_enrollment_challenge_and_recovery_codes() {
+        let user_id = Uuid::now_v7();
+        let hash = crate::utils::hash_password("correct_password".to_string())
+            .await
+            .expect("must hash password for test");
+        let repo = MockUserRepo::with_user(build_user(user_id, "henry", &hash));
+        let repo_ref = repo.clone();
+        let service = build_service(repo, InMemoryCache::default()).await;
+
+        let sign_in = || SignInModel {
+            username: "henry".to_string(),
+            password: "correct_password".to_string(),
+        };
+        let challenge = |outcome: SignInOutcome| match outcome {
+            SignInOutcome::TwoFactorRequired { challenge_token } => challenge_token,
+            SignInOutcome::Authenticated { .. } => panic!("2FA challenge expected"),
+        };
+
+        let setup = service.setup_two_factor(user_id).await.expect("setup");
+        assert!(setup.otpauth_uri.starts_with("otpauth://totp/AppChat%3Ahenry?secret="));
+
+        let code = totp::code_at(&setup.secret, Utc::now().timestamp());
+        let recovery_codes = service
+            .confirm_two_factor(user_id, &code, DeviceInfo::default())
+            .await
+            .expect("confirm");
+        assert_eq!(recovery_codes.len(), 10);
+        assert!(service.get_by_id(user_id).await.unwrap().two_factor_enabled);
+
+        // Bước 1 chỉ trả challenge token, challenge token không phải access token
+        let outcome = service.sign_in(sign_in(), DeviceInfo::default()).await.unwrap();
+        let challenge_token = challenge(outcome);
+
+        // Mã TOTP đã dùng khi xác nhận không dùng lại được
+        let result = service
+            .complete_two_factor_sign_in(&challenge_token, &code, DeviceInfo::default())
+            .await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+
+        // Mã khôi phục chấp nhận viết hoa / thiếu dấu gạch, nhưng chỉ dùng được một lần
+        let typed = recovery_codes[0].replace('-', "").to_uppercase();
+        service
+            .complete_two_factor_sign_in(&challenge_token, &typed, DeviceInfo::default())
+            .await
+            .expect("recovery code must work");
+        assert!(
+            repo_ref
+                .security_events
+                .lock()
+                .unwrap()
+                .contains(&SecurityEventType::RecoveryCodeUsed)
+        );
+
+        let outcome = service.sign_in(sign_in(), DeviceInfo::default()).await.unwrap();
+        let challenge_token = challenge(outcome);
+        let result = service
+            .complete_two_factor_sign_in(
+                &challenge_token,
+                &recovery_codes[0],
+                DeviceInfo::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+
+        let result = service
+            .disable_two_factor(user_id, "wrong_password".to_string(), DeviceInfo::default())
+            .await;
+        assert!(matches!(result, Err(error::SystemError::BadRequest(_))));
+
+        service
+            .disable_two_factor(user_id, "correct_password".to_string(), DeviceInfo::default())
+            .await
+            .expect("disable");
+        assert!(matches!(
+            service.sign_in(sign_in(), DeviceInfo::default()).await,
+            Ok(SignInOutcome::Authenticated { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_two_factor_challenge_expires_after_max_attempts() {
+        let user_id = Uuid::now_v7();
+        let hash = crate::utils::hash_password("correct_password".to_string())
+            .await
+            .expect("must hash password for test");
+        let repo = MockUserRepo::with_user(build_user(user_id, "ivy", &hash));
+        let service = build_service(repo, InMemoryCache::default()).await;
+
+        let setup = service.setup_two_factor(user_id).await.expect("setup");
+        let now = Utc::now().timestamp();
+        service
+            .confirm_two_factor(user_id, &totp::code_at(&setup.secret, now), DeviceInfo::default())
+            .await
+            .expect("confirm");
+
+        let outcome = service
+            .sign_in(
+                SignInModel {
+                    username: "ivy".to_string(),
+                    password: "correct_password".to_string(),
+                },
+                DeviceInfo::default(),
+            )
+            .await
+            .unwrap();
+        let SignInOutcome::TwoFactorRequired { challenge_token } = outcome else {
+            panic!("2FA challenge expected");
+        };
+
+        for _ in 0..5 {
+            let result = service
+                .complete_two_factor_sign_in(&challenge_token, "not-a-code", DeviceInfo::default())
+                .await;
+            assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+        }
+
+        // Hết lượt: mã đúng của bước kế tiếp cũng bị từ chối
+        let next_code = totp::code_at(&setup.secret, now + 30);
+        let result = service
+            .complete_two_factor_sign_in(&challenge_token, &next_code, DeviceInfo::default())
+            .await;
+        assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+    }
+
+    async fn enroll_two_factor(
+        username: &str,
+    ) -> (UserService<MockUserRepo, InMemoryCache>, String, i64) {
+        let user_id = Uuid::now_v7();
+        let hash = crate::utils::hash_password("correct_password".to_string())
+            .await
+            .expect("must hash password for test");
+        let repo = MockUserRepo::with_user(build_user(user_id, username, &hash));
+        let service = build_service(repo, InMemoryCache::default()).await;
+
+        let setup = service.setup_two_factor(user_id).await.expect("setup");
+        let now = Utc::now().timestamp();
+        service
+            .confirm_two_factor(user_id, &totp::code_at(&setup.secret, now), DeviceInfo::default())
+            .await
+            .expect("confirm");
+
+        (service, setup.secret, now)
+    }
+
+    async fn two_factor_challenge(
+        service: &UserService<MockUserRepo, InMemoryCache>,
+        username: &str,
+    ) -> Result<String, error::SystemError> {
+        let outcome = service
+            .sign_in(
+                SignInModel {
+                    username: username.to_string(),
+                    password: "correct_password".to_string(),
+                },
+                DeviceInfo::default(),
+            )
+            .await?;
+        match outcome {
+            SignInOutcome::TwoFactorRequired { challenge_token } => Ok(challenge_token),
+            SignInOutcome::Authenticated { .. } => panic!("2FA challenge expected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_factor_failures_lock_account_across_challenges() {
+        let (service, secret, now) = enroll_two_factor("jack").await;
+        let early_challenge = two_factor_challenge(&service, "jack").await.unwrap();
+
+        // Lấy challenge mới sau mỗi 5 lần sai không giúp thử thêm quá giới hạn của tài khoản
+        for _ in 0..2 {
+            let challenge_token = two_factor_challenge(&service, "jack").await.unwrap();
+            for _ in 0..5 {
+                let result = service
+                    .complete_two_factor_sign_in(&challenge_token, "not-a-code", DeviceInfo::default())
+                    .await;
+                assert!(matches!(result, Err(error::SystemError::Unauthorized(_))));
+            }
+        }
+
+        // Bước 1 cũng bị chặn khi tài khoản đang bị khoá
+        let result = two_factor_challenge(&service, "jack").await;
+        assert!(matches!(result, Err(error::SystemError::Forbidden(_))));
+
+        // Challenge lấy trước khi khoá cũng không dùng được, kể cả với mã đúng
+        let result = service
+            .complete_two_factor_sign_in(
+                &early_challenge,
+                &totp::code_at(&secret, now + 30),
+                DeviceInfo::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(error::SystemError::Forbidden(_))));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_two_factor_attempts_stay_within_challenge_limit() {
+        let (service, _, _) = enroll_two_factor("kate").await;
+        let challenge_token = two_factor_challenge(&service, "kate").await.unwrap();
+
+        let handles: Vec<_> = (0..20)
+            .map(|_| {
+                let service = service.clone();
+                let challenge_token = challenge_token.clone();
+                tokio::spawn(async move {
+                    service
+                        .complete_two_factor_sign_in(&challenge_token, "not-a-code", DeviceInfo::default())
+                        .await
+                })
+            })
+            .collect();
+
+        let mut checked = 0;
+        for handle in handles {
+            match handle.await.unwrap() {
+                Err(error::SystemError::Unauthorized(message))
+                    if message == "Mã xác thực không chính xác" =>
+                {
+                    checked += 1
+                }
+                Err(error::SystemError::Unauthorized(_)) => {}
+                // Quá 10 lượt sai của tài khoản thì bị khoá trước khi tới giới hạn challenge
+                Err(error::SystemError::Forbidden(_)) => {}
+                other => panic!("unexpected result: {:?}", other.err()),
+            }
+        }
+        assert_eq!(checked, 5, "only 5 codes may be checked per challenge");
+    }
+
     #[tokio::test]
     async fn test_search_users_validates_query_and_clamps_limit() {
         let user_id = Uuid::now_v7();
@@ -234,18 +1390,24 @@ mod tests {
         let repo_ref = repo.clone();
         let service = build_service(repo, InMemoryCache::default()).await;
 
-        let empty_query = service.search_users("   ", 10).await;
+        let empty_query = service.search_users("   ", 10, None).await;
         assert!(matches!(empty_query, Err(error::SystemError::BadRequest(_))));
 
-        let short_query = service.search_users("a", 10).await;
+        let short_query = service.search_users("a", 10, None).await;
         assert!(matches!(short_query, Err(error::SystemError::BadRequest(_))));
 
         let users = service
-            .search_users("car", 999)
+            .search_users("car", 999, None)
             .await
             .expect("search should succeed");
 
-        assert_eq!(users.len(), 1);
+        assert_eq!(users.users.len(), 1);
+        assert_eq!(users.cursor, None);
+
+        // Kết quả tìm kiếm là thông tin công khai, không kèm trạng thái bảo mật của tài khoản
+        let json = serde_json::to_value(&users.users[0]).unwrap();
+        assert!(json.get("email_verified").is_none());
+        assert!(json.get("two_factor_enabled").is_none());
 
         let last_limit = repo_ref
             .last_search_limit
@@ -316,4 +1478,128 @@ mod tests {
 
         assert!(cached_user.is_some());
     }
+
+    #[test]
+    fn test_generate_random_token_is_alphanumeric_and_unique() {
+        let a = crate::utils::generate_random_token(22);
+        let b = crate::utils::generate_random_token(22);
+
+        assert_eq!(a.len(), 22);
+        assert!(a.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_sign_up_username_must_be_mentionable() {
+        let sign_up = |username: &str| SignUpModel {
+            username: username.to_string(),
+            email: "new@appchat.local".to_string(),
+            password: "secret123".to_string(),
+            display_name: "New".to_string(),
+        };
+
+        for valid in ["alice", "bob.smith", "a_b-c", "User01"] {
+            assert!(sign_up(valid).validate().is_ok(), "{valid} should be accepted");
+            let (mentioned, _) = crate::modules::message::service::MessageService::<
+                crate::modules::message::repository_pg::MessageRepositoryPg,
+                crate::modules::conversation::repository_pg::ConversationPgRepository,
+                crate::modules::conversation::repository_pg::ParticipantPgRepository,
+                crate::modules::conversation::repository_pg::LastMessagePgRepository,
+            >::parse_mentions(&format!("hi @{valid}"));
+            assert_eq!(mentioned, vec![valid.to_ascii_lowercase()]);
+        }
+
+        for invalid in ["bảo", "john doe", "bob.", "ann-", "ALL", "a@b"] {
+            assert!(sign_up(invalid).validate().is_err(), "{invalid} should be rejected");
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires postgres running with migrated schema"]
+    async fn test_search_users_pages_by_display_name() {
+        let pool = crate::configs::connect_database()
+            .await
+            .expect("database must be available for integration test");
+        let token = Uuid::now_v7().simple().to_string();
+        let mut seeded = Vec::new();
+        // Tên trùng nhau khác hoa/thường vẫn ra đủ, thứ tự theo tên rồi thời điểm tạo
+        for display_name in ["Zed", "amy", "Bob", "bob", "Carl"] {
+            let id = Uuid::now_v7();
+            sqlx::query(
+                r#"
+                INSERT INTO users (id, username, hash_password, email, role, display_name)
+                VALUES ($1, $2, 'hash', $3, 'USER', $4)
+                "#,
+            )
+            .bind(id)
+            .bind(format!("s{}{token}", id.simple()))
+            .bind(format!("{}@appchat.local", id.simple()))
+            .bind(display_name)
+            .execute(&pool)
+            .await
+            .expect("seed user should succeed");
+            seeded.push(id);
+        }
+
+        let service = UserService::with_dependencies(
+            Arc::new(crate::modules::user::repository_pg::UserRepositoryPg::new(pool.clone())),
+            Arc::new(InMemoryCache::default()),
+            Arc::new(WebSocketServer::new()),
+            Arc::new(FileMailer::new(std::env::temp_dir().join("appchat-test-mails"))),
+        );
+
+        let mut names = Vec::new();
+        let mut ids = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = service
+                .search_users(&token, 2, cursor)
+                .await
+                .expect("search should succeed");
+            assert!(page.users.len() <= 2);
+            names.extend(page.users.iter().map(|u| u.display_name.clone()));
+            ids.extend(page.users.iter().map(|u| u.id));
+            match page.cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(names, vec!["amy", "Bob", "bob", "Carl", "Zed"]);
+        assert_eq!(ids[1..3], seeded[2..4]);
+
+        // Cursor thời gian của danh sách khác không dùng được ở đây
+        let foreign = Cursor::new(Utc::now(), Uuid::now_v7()).encode();
+        assert!(service.search_users(&token, 2, Some(foreign)).await.is_err());
+
+        let _ = sqlx::query("DELETE FROM users WHERE id = ANY($1)")
+            .bind(&seeded)
+            .execute(&pool)
+            .await;
+    }
+
+    #[test]
+    fn test_client_ip_ignores_forwarded_header_from_untrusted_peer() {
+        use crate::utils::resolve_client_ip;
+        use std::net::IpAddr;
+
+        let peer: IpAddr = "203.0.113.7".parse().unwrap();
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+
+        // Client gửi thẳng kèm header giả mạo: dùng địa chỉ TCP peer
+        assert_eq!(resolve_client_ip(Some(peer), Some("1.2.3.4"), &[]), Some(peer));
+        assert_eq!(resolve_client_ip(Some(peer), Some("1.2.3.4"), &[proxy]), Some(peer));
+
+        // Qua proxy tin cậy: lấy hop không tin cậy gần nhất, bỏ qua phần client tự chèn bên trái
+        assert_eq!(
+            resolve_client_ip(Some(proxy), Some("1.2.3.4, 198.51.100.9"), &[proxy]),
+            Some("198.51.100.9".parse().unwrap())
+        );
+        assert_eq!(
+            resolve_client_ip(Some(proxy), Some("198.51.100.9, 10.0.0.1"), &[proxy]),
+            Some("198.51.100.9".parse().unwrap())
+        );
+        assert_eq!(resolve_client_ip(Some(proxy), Some("garbage"), &[proxy]), Some(proxy));
+        assert_eq!(resolve_client_ip(Some(proxy), None, &[proxy]), Some(proxy));
+        assert_eq!(resolve_client_ip(None, Some("1.2.3.4"), &[proxy]), None);
+    }
 }
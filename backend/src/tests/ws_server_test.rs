@@ -1,4 +1,9 @@
-use crate::modules::websocket::{message::ServerMessage, server::WebSocketServer};
+use crate::modules::conversation::schema::ParticipantRole;
+use crate::modules::draft::model::Draft;
+use crate::modules::websocket::{
+    message::{ClientMessage, LastMessageInfo, SenderInfo, ServerMessage},
+    server::WebSocketServer,
+};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
@@ -178,3 +183,179 @@ async fn test_send_to_user_all_sessions_receive_once() {
     assert!(extra1.is_err());
     assert!(extra2.is_err());
 }
+
+#[test]
+fn test_delivery_receipt_protocol_names() {
+    let conversation_id = Uuid::now_v7();
+    let message_id = Uuid::now_v7();
+
+    let raw = format!(
+        r#"{{"type":"message_received","conversation_id":"{conversation_id}","message_id":"{message_id}"}}"#
+    );
+    let parsed: ClientMessage = serde_json::from_str(&raw).unwrap();
+    assert!(matches!(
+        parsed,
+        ClientMessage::MessageReceived { message_id: id, .. } if id == message_id
+    ));
+
+    let event = serde_json::to_value(ServerMessage::MessageDelivered {
+        conversation_id,
+        message_id,
+        user_id: Uuid::now_v7(),
+        delivered_at: chrono::Utc::now().to_rfc3339(),
+    })
+    .unwrap();
+    assert_eq!(event["type"], "message-delivered");
+}
+
+#[test]
+fn test_pin_event_names() {
+    let conversation_id = Uuid::now_v7();
+    let message_id = Uuid::now_v7();
+    let user_id = Uuid::now_v7();
+
+    let pinned = serde_json::to_value(ServerMessage::MessagePinned {
+        conversation_id,
+        message_id,
+        pinned_by: user_id,
+    })
+    .unwrap();
+    assert_eq!(pinned["type"], "message-pinned");
+
+    let unpinned = serde_json::to_value(ServerMessage::MessageUnpinned {
+        conversation_id,
+        message_id,
+        unpinned_by: user_id,
+    })
+    .unwrap();
+    assert_eq!(unpinned["type"], "message-unpinned");
+}
+
+#[test]
+fn test_member_role_changed_event() {
+    let event = serde_json::to_value(ServerMessage::MemberRoleChanged {
+        conversation_id: Uuid::now_v7(),
+        user_id: Uuid::now_v7(),
+        role: ParticipantRole::Admin,
+    })
+    .unwrap();
+    assert_eq!(event["type"], "member-role-changed");
+    assert_eq!(event["role"], "admin");
+}
+
+#[test]
+fn test_join_request_received_event() {
+    let event = serde_json::to_value(ServerMessage::JoinRequestReceived {
+        conversation_id: Uuid::now_v7(),
+        request_id: Uuid::now_v7(),
+        user_id: Uuid::now_v7(),
+        display_name: "Alice".to_string(),
+        avatar_url: None,
+        message: Some("cho mình vào nhóm".to_string()),
+    })
+    .unwrap();
+    assert_eq!(event["type"], "join-request-received");
+    assert_eq!(event["display_name"], "Alice");
+}
+
+#[test]
+fn test_new_message_payload_flags_muted_recipient() {
+    let event = serde_json::to_value(ServerMessage::new_message(
+        serde_json::json!({}),
+        Uuid::now_v7(),
+        LastMessageInfo {
+            _id: Uuid::now_v7(),
+            content: Some("hello".to_string()),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            sender: SenderInfo {
+                _id: Uuid::now_v7(),
+                display_name: "Alice".to_string(),
+                avatar_url: None,
+            },
+        },
+        chrono::Utc::now().to_rfc3339(),
+        serde_json::json!({}),
+        vec![],
+        true,
+    ))
+    .unwrap();
+    assert_eq!(event["type"], "new-message");
+    assert_eq!(event["muted"], true);
+    assert!(event.get("muted_user_ids").is_none());
+}
+
+#[tokio::test]
+async fn test_draft_update_skips_origin_session() {
+    let server = WebSocketServer::new();
+    let user_id = Uuid::now_v7();
+    let desktop = Uuid::now_v7();
+    let phone = Uuid::now_v7();
+
+    let (tx_desktop, mut rx_desktop) = mpsc::unbounded_channel();
+    let (tx_phone, mut rx_phone) = mpsc::unbounded_channel();
+    server.connect(desktop, tx_desktop);
+    server.connect(phone, tx_phone);
+    server.authenticate(desktop, user_id);
+    server.authenticate(phone, user_id);
+
+    let client_msg: ClientMessage = serde_json::from_str(&format!(
+        r#"{{"type":"update_draft","conversation_id":"{}","content":"đang gõ dở"}}"#,
+        Uuid::now_v7()
+    ))
+    .unwrap();
+    let ClientMessage::UpdateDraft {
+        conversation_id,
+        content,
+        reply_to_id,
+    } = client_msg
+    else {
+        panic!("expected update_draft");
+    };
+    assert!(reply_to_id.is_none());
+
+    server.send_to_other_sessions(
+        &user_id,
+        &desktop,
+        &ServerMessage::DraftUpdated {
+            conversation_id,
+            draft: Some(Draft {
+                conversation_id,
+                content,
+                reply_to_id,
+                updated_at: chrono::Utc::now(),
+            }),
+        },
+    );
+
+    let event: serde_json::Value = serde_json::from_str(&rx_phone.recv().await.unwrap()).unwrap();
+    assert_eq!(event["type"], "draft-updated");
+    assert_eq!(event["draft"]["content"], "đang gõ dở");
+    assert!(rx_desktop.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_revoke_auth_session_closes_only_that_device() {
+    let server = WebSocketServer::new();
+    let user_id = Uuid::now_v7();
+    let (laptop, phone) = (Uuid::now_v7(), Uuid::now_v7());
+    let (laptop_auth, phone_auth) = (Uuid::now_v7(), Uuid::now_v7());
+
+    for (session_id, auth_session_id) in [(laptop, laptop_auth), (phone, phone_auth)] {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        server.connect(session_id, tx);
+        server.authenticate(session_id, user_id);
+        assert!(server.bind_auth_session(session_id, auth_session_id));
+    }
+
+    let laptop_signal = server.close_signal(laptop);
+    assert_eq!(server.revoke_auth_sessions(&[laptop_auth]), 1);
+
+    // Notify đã lưu permit nên handler nhận được tín hiệu dù chưa kịp chờ
+    tokio::time::timeout(std::time::Duration::from_millis(50), laptop_signal.notified())
+        .await
+        .expect("laptop session must be told to close");
+
+    // Access token cũ của phiên đã thu hồi không kết nối lại được
+    assert!(!server.bind_auth_session(Uuid::now_v7(), laptop_auth));
+    assert!(server.bind_auth_session(Uuid::now_v7(), phone_auth));
+}
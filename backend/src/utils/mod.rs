@@ -5,16 +5,59 @@ use argon2::{
 };
 use futures_util::future::LocalBoxFuture;
 use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
-use rand::rngs::OsRng;
+use rand::{Rng, distributions::Alphanumeric, rngs::OsRng};
 use serde::{Deserialize, Serialize, de::Deserializer};
 use validator::Validate;
 
+use std::net::IpAddr;
 use std::sync::LazyLock;
 
 use crate::{api::error, modules::user::schema::UserRole};
 
 static ARGON2: LazyLock<Argon2<'static>> = LazyLock::new(Argon2::default);
 
+/// Xác định IP client của request
+///
+/// Mặc định dùng địa chỉ TCP peer vì header `X-Forwarded-For`/`Forwarded` do client tự đặt được.
+/// Chỉ khi peer nằm trong `TRUSTED_PROXIES` mới đọc `X-Forwarded-For`.
+pub fn client_ip(req: &actix_web::HttpRequest) -> Option<IpAddr> {
+    let forwarded_for = req
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok());
+
+    resolve_client_ip(
+        req.peer_addr().map(|addr| addr.ip()),
+        forwarded_for,
+        &crate::ENV.trusted_proxies,
+    )
+}
+
+/// Đi `X-Forwarded-For` từ phải sang trái, bỏ qua các proxy tin cậy; hop đầu tiên
+/// không tin cậy là client (các hop bên trái nó có thể bị giả mạo)
+pub(crate) fn resolve_client_ip(
+    peer: Option<IpAddr>,
+    forwarded_for: Option<&str>,
+    trusted_proxies: &[IpAddr],
+) -> Option<IpAddr> {
+    let mut client = peer?;
+    if !trusted_proxies.contains(&client) {
+        return Some(client);
+    }
+
+    for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !trusted_proxies.contains(&ip) {
+            break;
+        }
+    }
+
+    Some(client)
+}
+
 pub async fn hash_password(password: String) -> Result<String, error::SystemError> {
     let (tx, rx) = tokio::sync::oneshot::channel();
     rayon::spawn(move || {
@@ -51,10 +94,21 @@ pub async fn verify_password(hash: String, password: String) -> Result<bool, err
         .map_err(|_| error::SystemError::internal_error("Lỗi xác thực mật khẩu"))?
 }
 
+/// Sinh token ngẫu nhiên (a-zA-Z0-9) từ OsRng, dùng cho link mời, token xác thực...
+pub fn generate_random_token(len: usize) -> String {
+    OsRng
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum TypeClaims {
     RefreshToken,
     AccessToken,
+    /// Token tạm của bước 1 khi đăng nhập tài khoản bật 2FA, chỉ dùng để gửi mã ở bước 2
+    TwoFactorChallenge,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +117,9 @@ pub struct Claims {
     pub iat: u64,
     pub exp: u64,
     pub jti: Option<uuid::Uuid>,
+    /// Phiên đăng nhập (refresh token family) đã cấp token này
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<uuid::Uuid>,
     pub role: UserRole,
     pub _type: Option<TypeClaims>,
 }
@@ -76,6 +133,7 @@ impl Claims {
             exp: now + exp,
             role: role.clone(),
             jti: None,
+            sid: None,
             _type: None,
         }
     }
@@ -85,6 +143,11 @@ impl Claims {
         self
     }
 
+    pub fn with_sid(mut self, sid: uuid::Uuid) -> Self {
+        self.sid = Some(sid);
+        self
+    }
+
     pub fn with_type(mut self, _type: TypeClaims) -> Self {
         self._type = Some(_type);
         self
@@ -1,6 +1,7 @@
+import type { UIEvent } from 'react'
 import { Badge } from '@/components/ui/badge'
 import type { Conversation } from '@/types/chat'
-import { cn } from '@/lib/utils'
+import { cn, isNearBottom } from '@/lib/utils'
 import { formatTime } from '@/lib/format'
 import { OnlineDot } from '@/components/chat/online-dot'
 import { GroupAvatar } from '@/components/chat/group-avatar'
@@ -11,6 +12,9 @@ type Props = {
   myUserId: string
   onlineMap: Record<string, { isOnline: boolean }>
   onSelectConversation: (conversationId: string) => void
+  hasMore: boolean
+  loadingMore: boolean
+  onLoadMore: () => void
 }
 
 function conversationName(conversation: Conversation, myUserId: string) {
@@ -29,8 +33,13 @@ function unreadOfMe(conversation: Conversation, myUserId: string) {
 }
 
 export function ConversationList(props: Props) {
+  const handleScroll = (event: UIEvent<HTMLDivElement>) => {
+    if (!props.hasMore || props.loadingMore) return
+    if (isNearBottom(event.currentTarget)) props.onLoadMore()
+  }
+
   return (
-    <div className="flex min-h-0 flex-1 flex-col overflow-y-auto">
+    <div className="flex min-h-0 flex-1 flex-col overflow-y-auto" onScroll={handleScroll}>
       <div className="space-y-1 p-2">
         {props.conversations.length === 0 && (
           <div className="flex flex-col items-center gap-2 py-12 text-muted-foreground">
@@ -104,6 +113,9 @@ export function ConversationList(props: Props) {
             </button>
           )
         })}
+        {props.loadingMore && (
+          <p className="py-2 text-center text-xs text-muted-foreground">Đang tải thêm...</p>
+        )}
       </div>
     </div>
   )
@@ -13,7 +13,7 @@ import { useChatStore } from '@/stores/chat.store'
 import { conversationService } from '@/services/conversation.service'
 import { Avatar, AvatarFallback, AvatarImage } from '@/components/ui/avatar'
 import { Check } from 'lucide-react'
-import { cn } from '@/lib/utils'
+import { cn, isNearBottom } from '@/lib/utils'
 import { toast } from 'sonner'
 import { extractErrorMsg } from '@/lib/api'
 
@@ -23,7 +23,7 @@ interface Props {
 }
 
 export function CreateGroupModal({ open, onOpenChange }: Props) {
-  const { friends, loadFriends } = useFriendStore()
+  const { friends, loadFriends, loadMoreFriends } = useFriendStore()
   const { loadConversations, openConversation } = useChatStore()
   
   const [name, setName] = useState('')
@@ -107,7 +107,12 @@ export function CreateGroupModal({ open, onOpenChange }: Props) {
               className="h-9 text-xs"
             />
             
-            <div className="max-h-[300px] overflow-y-auto pr-1 space-y-1">
+            <div
+              className="max-h-[300px] overflow-y-auto pr-1 space-y-1"
+              onScroll={(e) => {
+                if (isNearBottom(e.currentTarget)) void loadMoreFriends()
+              }}
+            >
               {filteredFriends.length === 0 ? (
                 <p className="text-center py-8 text-xs text-muted-foreground">
                   {friends.length === 0 ? 'Bạn chưa có bạn bè nào' : 'Không tìm thấy kết quả'}
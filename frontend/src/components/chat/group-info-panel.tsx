@@ -19,6 +19,7 @@ import {
   Plus
 } from 'lucide-react'
 import { toast } from 'sonner'
+import { isNearBottom } from '@/lib/utils'
 import { ScrollArea } from '@/components/ui/scroll-area'
 import { Separator } from '@/components/ui/separator'
 import { extractErrorMsg } from '@/lib/api'
@@ -31,7 +32,7 @@ interface Props {
 export function GroupInfoPanel({ conversation, onClose }: Props) {
   const myUser = useAuthStore((state) => state.user)
   const { updateConversation, loadConversations, setActiveConversationId } = useChatStore()
-  const { friends, loadFriends } = useFriendStore()
+  const { friends, loadFriends, loadMoreFriends } = useFriendStore()
 
   const [isEditingName, setIsEditingName] = useState(false)
   const [newName, setNewName] = useState(conversation.group_info?.name || '')
@@ -219,7 +220,12 @@ export function GroupInfoPanel({ conversation, onClose }: Props) {
           {showAddMember && (
             <div className="space-y-2 p-2 bg-muted/30 rounded-lg border border-border/40 animate-in fade-in slide-in-from-top-1">
               <p className="text-[10px] font-medium text-muted-foreground uppercase px-1">Chọn bạn bè để thêm</p>
-              <div className="max-h-[150px] overflow-y-auto space-y-1">
+              <div
+                className="max-h-[150px] overflow-y-auto space-y-1"
+                onScroll={(e) => {
+                  if (isNearBottom(e.currentTarget)) void loadMoreFriends()
+                }}
+              >
                 {friends
                   .filter(f => !conversation.participants.some(p => p.user_id === f.id))
                   .map(friend => (
@@ -1,4 +1,4 @@
-import { useState } from 'react'
+import { useEffect, useRef, useState } from 'react'
 import { useNavigate } from 'react-router-dom'
 import { toast } from 'sonner'
 import { MagnifyingGlassIcon, UserPlusIcon } from '@phosphor-icons/react'
@@ -34,6 +34,10 @@ export function FriendManager() {
     friends,
     requests,
     searchResult,
+    hasMoreFriends,
+    hasMoreRequests,
+    loadMoreFriends,
+    loadMoreRequests,
     searchUsers,
     sendRequest,
     acceptRequest,
@@ -169,6 +173,9 @@ export function FriendManager() {
                   </div>
                 ))
               ))}
+            {tab === 'contacts' && hasMoreFriends && (
+              <LoadMoreTrigger onLoadMore={loadMoreFriends} />
+            )}
 
             {/* ── Lời mời nhận được ── */}
             {tab === 'received' &&
@@ -247,6 +254,9 @@ export function FriendManager() {
                   </div>
                 ))
               ))}
+            {(tab === 'received' || tab === 'sent') && hasMoreRequests && (
+              <LoadMoreTrigger onLoadMore={loadMoreRequests} />
+            )}
 
             {/* ── Kết quả tìm kiếm ── */}
             {tab === 'search' &&
@@ -315,3 +325,25 @@ function EmptyState({ icon, text }: { icon: string; text: string }) {
     </div>
   )
 }
+
+/** Tải trang kế tiếp khi cuộn tới cuối danh sách (ScrollArea không đẩy sự kiện scroll ra ngoài) */
+function LoadMoreTrigger({ onLoadMore }: { onLoadMore: () => Promise<void> }) {
+  const ref = useRef<HTMLDivElement>(null)
+
+  useEffect(() => {
+    const element = ref.current
+    if (!element) return
+
+    const observer = new IntersectionObserver((entries) => {
+      if (entries.some((entry) => entry.isIntersecting)) void onLoadMore()
+    })
+    observer.observe(element)
+    return () => observer.disconnect()
+  }, [onLoadMore])
+
+  return (
+    <div ref={ref} className="py-2 text-center text-xs text-muted-foreground">
+      Đang tải thêm...
+    </div>
+  )
+}
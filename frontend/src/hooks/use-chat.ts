@@ -9,7 +9,10 @@ export function useChat() {
       messagesByConversation: state.messagesByConversation,
       typingUsers: state.typingUsers,
       loadingConversations: state.loadingConversations,
+      loadingMoreConversations: state.loadingMoreConversations,
+      hasMoreConversations: state.conversationsCursor !== null,
       loadConversations: state.loadConversations,
+      loadMoreConversations: state.loadMoreConversations,
       openConversation: state.openConversation,
       refreshConversationMessages: state.refreshConversationMessages,
       sendMessage: state.sendMessage,
@@ -8,8 +8,12 @@ export function useFriends() {
       requests: state.requests,
       searchResult: state.searchResult,
       loading: state.loading,
+      hasMoreFriends: state.friendsCursor !== null,
+      hasMoreRequests: state.requestsCursor !== null,
       loadFriends: state.loadFriends,
+      loadMoreFriends: state.loadMoreFriends,
       loadRequests: state.loadRequests,
+      loadMoreRequests: state.loadMoreRequests,
       searchUsers: state.searchUsers,
       sendRequest: state.sendRequest,
       acceptRequest: state.acceptRequest,
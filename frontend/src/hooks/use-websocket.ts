@@ -56,6 +56,14 @@ export function useWebSocketBridge() {
         case 'new-message': {
           const normalized = normalizeMessage(message.message)
           if (normalized) {
+            // Conversation nằm ở trang chưa tải: tải lại trang đầu để nó hiện lên sidebar
+            if (
+              !chatState.conversations.some(
+                (item) => item.conversation_id === normalized.conversation_id,
+              )
+            ) {
+              void chatState.loadConversations()
+            }
             chatState.receiveMessage(normalized)
             chatState.updateConversationLastMessage({
               conversationId: normalized.conversation_id,
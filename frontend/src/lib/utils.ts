@@ -4,3 +4,8 @@ import { twMerge } from "tailwind-merge"
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
 }
+
+// Danh sách cuộn gần tới đáy thì tải trang kế tiếp
+export function isNearBottom(element: HTMLElement, threshold = 120) {
+  return element.scrollHeight - element.scrollTop - element.clientHeight < threshold
+}
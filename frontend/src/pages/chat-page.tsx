@@ -35,7 +35,10 @@ export function ChatPage() {
     messagesByConversation,
     typingUsers,
     loadingConversations,
+    loadingMoreConversations,
+    hasMoreConversations,
     loadConversations,
+    loadMoreConversations,
     openConversation,
     sendMessage,
     markAsSeen,
@@ -127,6 +130,9 @@ export function ChatPage() {
           myUserId={user.id}
           onlineMap={presenceMap}
           onSelectConversation={handleSelect}
+          hasMore={hasMoreConversations}
+          loadingMore={loadingMoreConversations}
+          onLoadMore={() => void loadMoreConversations()}
         />
       </div>
 
@@ -13,7 +13,11 @@ import { extractErrorMsg } from '@/lib/api'
 
 const schema = z
   .object({
-    username: z.string().min(3, 'Tối thiểu 3 ký tự'),
+    username: z
+      .string()
+      .min(3, 'Tối thiểu 3 ký tự')
+      .regex(/^[A-Za-z0-9_.-]*[A-Za-z0-9_]$/, 'Chỉ gồm chữ, số, "_", "." hoặc "-" và không kết thúc bằng "." hoặc "-"')
+      .refine((value) => value.toLowerCase() !== 'all', 'Tên đăng nhập này đã được dành riêng'),
     display_name: z.string().min(1, 'Không được để trống'),
     email: z.email('Email không hợp lệ'),
     password: z.string().min(6, 'Tối thiểu 6 ký tự'),
@@ -1,11 +1,23 @@
 import { http } from '@/lib/http'
 import { unwrapData } from '@/lib/api'
-import type { Conversation, CreateConversationPayload, MessagePage } from '@/types/chat'
+import type {
+  Conversation,
+  ConversationPage,
+  CreateConversationPayload,
+  MessagePage,
+} from '@/types/chat'
+
+const CONVERSATION_PAGE_SIZE = 30
 
 export const conversationService = {
-  async list(): Promise<Conversation[]> {
-    const response = await http.get('/conversations')
-    return unwrapData<Conversation[]>(response)
+  async listPage(params: { limit?: number; cursor?: string | null } = {}): Promise<ConversationPage> {
+    const response = await http.get('/conversations', {
+      params: {
+        limit: params.limit ?? CONVERSATION_PAGE_SIZE,
+        cursor: params.cursor ?? undefined,
+      },
+    })
+    return unwrapData<ConversationPage>(response)
   },
 
   async create(payload: CreateConversationPayload): Promise<Conversation | null> {
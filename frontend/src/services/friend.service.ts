@@ -1,16 +1,22 @@
 import { http } from '@/lib/http'
 import { unwrapData } from '@/lib/api'
-import type { Friend, FriendRequest, FriendRequestPayload } from '@/types/friend'
+import type { FriendPage, FriendRequestPage, FriendRequestPayload } from '@/types/friend'
+
+const FRIEND_PAGE_SIZE = 30
 
 export const friendService = {
-  async listFriends(): Promise<Friend[]> {
-    const response = await http.get('/friends/')
-    return unwrapData<Friend[]>(response)
+  async listFriendsPage(cursor?: string | null): Promise<FriendPage> {
+    const response = await http.get('/friends/', {
+      params: { limit: FRIEND_PAGE_SIZE, cursor: cursor ?? undefined },
+    })
+    return unwrapData<FriendPage>(response)
   },
 
-  async listRequests(): Promise<FriendRequest[]> {
-    const response = await http.get('/friends/requests')
-    return unwrapData<FriendRequest[]>(response)
+  async listRequestsPage(cursor?: string | null): Promise<FriendRequestPage> {
+    const response = await http.get('/friends/requests', {
+      params: { limit: FRIEND_PAGE_SIZE, cursor: cursor ?? undefined },
+    })
+    return unwrapData<FriendRequestPage>(response)
   },
 
   async sendRequest(payload: FriendRequestPayload): Promise<void> {
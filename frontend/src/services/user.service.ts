@@ -1,11 +1,18 @@
 import { http } from '@/lib/http'
 import { unwrapData } from '@/lib/api'
-import type { PresenceItem, UserSearchResult } from '@/types/user'
+import type { PresenceItem, UserSearchPage, UserSearchResult } from '@/types/user'
 
 export const userService = {
+  async searchPage(q: string, limit = 10, cursor?: string | null): Promise<UserSearchPage> {
+    const response = await http.get('/users/search', {
+      params: { q, limit, cursor: cursor ?? undefined },
+    })
+    return unwrapData<UserSearchPage>(response)
+  },
+
   async search(q: string, limit = 10): Promise<UserSearchResult[]> {
-    const response = await http.get('/users/search', { params: { q, limit } })
-    return unwrapData<UserSearchResult[]>(response)
+    const page = await userService.searchPage(q, limit)
+    return page.users
   },
 
   async getPresence(userIds: string[]): Promise<PresenceItem[]> {
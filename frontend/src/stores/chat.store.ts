@@ -10,8 +10,12 @@ type ChatState = {
   activeConversationId: string | null
   messagesByConversation: Record<string, Message[]>
   loadingConversations: boolean
+  // Cursor của trang kế tiếp, null khi đã tải hết
+  conversationsCursor: string | null
+  loadingMoreConversations: boolean
   typingUsers: Record<string, string[]>
   loadConversations: () => Promise<void>
+  loadMoreConversations: () => Promise<void>
   openConversation: (conversationId: string) => Promise<void>
   refreshConversationMessages: (conversationId: string) => Promise<void>
   sendMessage: (payload: {
@@ -50,6 +54,14 @@ function findDirectRecipient(conversation: Conversation, myUserId: string): stri
   return recipient?.user_id ?? null
 }
 
+// Trang sau có thể trùng với conversation vừa được đẩy lên qua websocket
+function mergeConversations(current: Conversation[], incoming: Conversation[]): Conversation[] {
+  const known = new Set(current.map((item) => item.conversation_id))
+  return [...current, ...incoming.filter((item) => !known.has(item.conversation_id))].sort(
+    sortByRecent,
+  )
+}
+
 function sortAndReplace(conversations: Conversation[], updated: Conversation): Conversation[] {
   return conversations
     .map((item) => (item.conversation_id === updated.conversation_id ? updated : item))
@@ -61,18 +73,40 @@ export const useChatStore = create<ChatState>((set, get) => ({
   activeConversationId: null,
   messagesByConversation: {},
   loadingConversations: false,
+  conversationsCursor: null,
+  loadingMoreConversations: false,
   typingUsers: {},
 
+  // Chỉ tải trang đầu, các trang sau được tải khi cuộn sidebar
   loadConversations: async () => {
     set({ loadingConversations: true })
     try {
-      const data = await conversationService.list()
-      set({ conversations: [...data].sort(sortByRecent) })
+      const page = await conversationService.listPage()
+      set({
+        conversations: [...page.conversations].sort(sortByRecent),
+        conversationsCursor: page.cursor,
+      })
     } finally {
       set({ loadingConversations: false })
     }
   },
 
+  loadMoreConversations: async () => {
+    const { conversationsCursor, loadingConversations, loadingMoreConversations } = get()
+    if (!conversationsCursor || loadingConversations || loadingMoreConversations) return
+
+    set({ loadingMoreConversations: true })
+    try {
+      const page = await conversationService.listPage({ cursor: conversationsCursor })
+      set((state) => ({
+        conversations: mergeConversations(state.conversations, page.conversations),
+        conversationsCursor: page.cursor,
+      }))
+    } finally {
+      set({ loadingMoreConversations: false })
+    }
+  },
+
   openConversation: async (conversationId) => {
     const alreadyLoaded = Boolean(get().messagesByConversation[conversationId])
     set({ activeConversationId: conversationId })
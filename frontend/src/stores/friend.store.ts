@@ -9,8 +9,15 @@ type FriendState = {
   requests: FriendRequest[]
   searchResult: UserSearchResult[]
   loading: boolean
+  // Cursor của trang kế tiếp, null khi đã tải hết
+  friendsCursor: string | null
+  requestsCursor: string | null
+  loadingMoreFriends: boolean
+  loadingMoreRequests: boolean
   loadFriends: () => Promise<void>
+  loadMoreFriends: () => Promise<void>
   loadRequests: () => Promise<void>
+  loadMoreRequests: () => Promise<void>
   searchUsers: (keyword: string) => Promise<void>
   sendRequest: (recipientId: string, message?: string) => Promise<void>
   acceptRequest: (requestId: string) => Promise<void>
@@ -18,25 +25,68 @@ type FriendState = {
   removeFriend: (friendId: string) => Promise<void>
 }
 
+// Trang sau có thể trùng với phần tử đã có nếu danh sách thay đổi giữa hai lần tải
+function appendUnique<T extends { id: string }>(current: T[], incoming: T[]): T[] {
+  const known = new Set(current.map((item) => item.id))
+  return [...current, ...incoming.filter((item) => !known.has(item.id))]
+}
+
 export const useFriendStore = create<FriendState>((set, get) => ({
   friends: [],
   requests: [],
   searchResult: [],
   loading: false,
+  friendsCursor: null,
+  requestsCursor: null,
+  loadingMoreFriends: false,
+  loadingMoreRequests: false,
 
+  // Chỉ tải trang đầu, các trang sau được tải khi cuộn danh sách
   loadFriends: async () => {
     set({ loading: true })
     try {
-      const data = await friendService.listFriends()
-      set({ friends: data })
+      const page = await friendService.listFriendsPage()
+      set({ friends: page.friends, friendsCursor: page.cursor })
     } finally {
       set({ loading: false })
     }
   },
 
+  loadMoreFriends: async () => {
+    const { friendsCursor, loading, loadingMoreFriends } = get()
+    if (!friendsCursor || loading || loadingMoreFriends) return
+
+    set({ loadingMoreFriends: true })
+    try {
+      const page = await friendService.listFriendsPage(friendsCursor)
+      set((state) => ({
+        friends: appendUnique(state.friends, page.friends),
+        friendsCursor: page.cursor,
+      }))
+    } finally {
+      set({ loadingMoreFriends: false })
+    }
+  },
+
   loadRequests: async () => {
-    const data = await friendService.listRequests()
-    set({ requests: data })
+    const page = await friendService.listRequestsPage()
+    set({ requests: page.requests, requestsCursor: page.cursor })
+  },
+
+  loadMoreRequests: async () => {
+    const { requestsCursor, loadingMoreRequests } = get()
+    if (!requestsCursor || loadingMoreRequests) return
+
+    set({ loadingMoreRequests: true })
+    try {
+      const page = await friendService.listRequestsPage(requestsCursor)
+      set((state) => ({
+        requests: appendUnique(state.requests, page.requests),
+        requestsCursor: page.cursor,
+      }))
+    } finally {
+      set({ loadingMoreRequests: false })
+    }
   },
 
   searchUsers: async (keyword) => {
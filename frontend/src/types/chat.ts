@@ -64,6 +64,11 @@ export type MessagePage = {
   cursor: string | null
 }
 
+export type ConversationPage = {
+  conversations: Conversation[]
+  cursor: string | null
+}
+
 export type CreateConversationPayload = {
   type: ConversationType
   name: string
@@ -3,6 +3,12 @@ export type Friend = {
   username: string
   display_name: string
   avatar_url: string | null
+  friends_since?: string
+}
+
+export type FriendPage = {
+  friends: Friend[]
+  cursor: string | null
 }
 
 export type FriendRequestPayload = {
@@ -21,3 +27,8 @@ export type FriendRequest = {
   message: string | null
   created_at: string
 }
+
+export type FriendRequestPage = {
+  requests: FriendRequest[]
+  cursor: string | null
+}
@@ -8,6 +8,11 @@ export type UserSearchResult = {
   phone: string | null
 }
 
+export type UserSearchPage = {
+  users: UserSearchResult[]
+  cursor: string | null
+}
+
 export type PresenceItem = {
   user_id: string
   is_online: boolean